@@ -0,0 +1,82 @@
+//! Timings for the parser on large inputs: literals, string escaping and
+//! a generated script. Run with `cargo bench --bench parsing`.
+//!
+//! Each input is timed at two sizes, the second four times the first, and
+//! the run fails if the larger one takes more than eight times as long,
+//! which a quadratic parser (sixteen times) would.
+
+use std::time::{Duration, Instant};
+
+use toyql::literals::parsing::{self as literals, string};
+use toyql::statements::parsing::parse_script;
+
+/// How much longer four times the input may take.
+const MAX_GROWTH: f64 = 8.0;
+
+fn main() {
+    check("literals", |n| {
+        let texts: Vec<String> = (0..n)
+            .map(|i| match i % 4 {
+                0 => i.to_string(),
+                1 => format!("{i}.5e-3"),
+                2 => format!("'name {i}'"),
+                _ => format!("'it\\'s {i}\\n'"),
+            })
+            .collect();
+        (texts.iter().map(|text| text.len()).sum(), move || {
+            for text in &texts {
+                literals::parse(text).unwrap();
+            }
+        })
+    });
+    check("string escaping", |n| {
+        let text = "it's a \"quote\" ".repeat(n);
+        let quoted = string::serialize(&text);
+        (text.len(), move || {
+            let escaped = string::serialize(&text);
+            let (rest, unescaped) = string::apply_grammar(&quoted).unwrap();
+            assert!(rest.is_empty() && unescaped.len() == text.len() && !escaped.is_empty());
+        })
+    });
+    check("script", |n| {
+        let script: String = (0..n)
+            .map(|i| format!("INSERT INTO t VALUES ({i}, 'name {i}', {i}.5);\n"))
+            .collect();
+        (script.len(), move || {
+            assert_eq!(parse_script(&script).filter(|(_, s)| s.is_ok()).count(), n);
+        })
+    });
+}
+
+/// Times `input(n)`'s work at `n` and `4 n` and checks that it grows
+/// linearly. `input` gives the size of its input in bytes and the work.
+fn check<F: FnMut()>(name: &str, mut input: impl FnMut(usize) -> (usize, F)) {
+    let n = 20_000;
+    let mut times = Vec::new();
+    for n in [n, 4 * n] {
+        let (bytes, work) = input(n);
+        let time = best_of(3, work);
+        println!(
+            "{name}: {n} items, {:.1} MB in {time:?} ({:.1} MB/s)",
+            bytes as f64 / 1e6,
+            bytes as f64 / 1e6 / time.as_secs_f64()
+        );
+        times.push(time);
+    }
+    let growth = times[1].as_secs_f64() / times[0].as_secs_f64();
+    assert!(
+        growth < MAX_GROWTH,
+        "{name}: four times the input took {growth:.1} times as long"
+    );
+}
+
+fn best_of(runs: usize, mut work: impl FnMut()) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            work();
+            start.elapsed()
+        })
+        .min()
+        .expect("at least one run")
+}
@@ -0,0 +1,565 @@
+//! Expression syntax trees.
+
+pub mod parsing;
+
+use std::convert::Infallible;
+
+use crate::lexer;
+use crate::literals::LiteralValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+    Plus,
+}
+
+impl UnaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOp::Not => "NOT",
+            UnaryOp::Neg => "-",
+            UnaryOp::Plus => "+",
+        }
+    }
+
+    /// `NOT` binds looser than comparisons, the sign operators tighter than
+    /// any binary operator.
+    pub fn precedence(self) -> u8 {
+        match self {
+            UnaryOp::Not => 3,
+            UnaryOp::Neg | UnaryOp::Plus => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    /// `<<`: the left inet address lies within the right network.
+    ContainedBy,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "OR",
+            BinaryOp::And => "AND",
+            BinaryOp::Eq => "=",
+            BinaryOp::NotEq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::ContainedBy => "<<",
+            BinaryOp::Concat => "||",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+        }
+    }
+
+    /// Binding strength; higher binds tighter. All binary operators are
+    /// left-associative.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::Lt
+            | BinaryOp::LtEq
+            | BinaryOp::Gt
+            | BinaryOp::GtEq
+            | BinaryOp::ContainedBy => 4,
+            BinaryOp::Concat => 5,
+            BinaryOp::Add | BinaryOp::Sub => 6,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 7,
+        }
+    }
+}
+
+/// `IS NULL`, `IN` and `LIKE` bind like comparisons.
+const PREDICATE_PRECEDENCE: u8 = 4;
+
+const PRIMARY_PRECEDENCE: u8 = 9;
+
+/// An expression. Parentheses are not represented: they only steer how the
+/// tree is built, and `serialize` puts back whichever ones are needed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(LiteralValue),
+    Identifier(String),
+    /// `$n`: a value supplied when the statement is executed.
+    Parameter(usize),
+    /// `@name`: a session variable, replaced by its value before the
+    /// statement runs.
+    Variable(String),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// `<expr> IS [NOT] NULL`
+    IsNull {
+        operand: Box<Expr>,
+        negated: bool,
+    },
+    /// `<expr> [NOT] IN (<expr>, ...)`
+    InList {
+        operand: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    /// `<expr> [NOT] LIKE <pattern>`, where `%` in the pattern matches any
+    /// run of characters and `_` any one character.
+    Like {
+        operand: Box<Expr>,
+        pattern: Box<Expr>,
+        negated: bool,
+    },
+}
+
+impl Expr {
+    pub fn unary(op: UnaryOp, operand: Expr) -> Expr {
+        Expr::Unary {
+            op,
+            operand: Box::new(operand),
+        }
+    }
+
+    pub fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// Every identifier the expression refers to, in order of appearance.
+    pub fn identifiers(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_identifiers(&mut out);
+        out
+    }
+
+    fn collect_identifiers<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Expr::Identifier(name) => out.push(name),
+            _ => {
+                for child in self.children() {
+                    child.collect_identifiers(out);
+                }
+            }
+        }
+    }
+
+    /// The direct subexpressions, left to right.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Literal(_) | Expr::Identifier(_) | Expr::Parameter(_) | Expr::Variable(_) => {
+                vec![]
+            }
+            Expr::Unary { operand, .. } | Expr::IsNull { operand, .. } => vec![operand],
+            Expr::Binary { left, right, .. } => vec![left, right],
+            Expr::Call { args, .. } => args.iter().collect(),
+            Expr::InList { operand, list, .. } => {
+                std::iter::once(operand.as_ref()).chain(list).collect()
+            }
+            Expr::Like {
+                operand, pattern, ..
+            } => vec![operand, pattern],
+        }
+    }
+
+    /// How many nodes deep the tree goes: 1 for a leaf.
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(Expr::depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// How many nodes the tree has.
+    pub fn size(&self) -> usize {
+        1 + self.children().into_iter().map(Expr::size).sum::<usize>()
+    }
+
+    /// A copy of the tree in which each subexpression that `f` maps to
+    /// `Some` is replaced by the result; the replaced parts are not looked
+    /// into.
+    pub fn replace(&self, f: &impl Fn(&Expr) -> Option<Expr>) -> Expr {
+        match self.try_replace(&|e| Ok::<_, Infallible>(f(e))) {
+            Ok(expr) => expr,
+            Err(never) => match never {},
+        }
+    }
+
+    /// [`Expr::replace`] with an `f` that may fail, which stops it.
+    pub fn try_replace<E>(&self, f: &impl Fn(&Expr) -> Result<Option<Expr>, E>) -> Result<Expr, E> {
+        if let Some(replacement) = f(self)? {
+            return Ok(replacement);
+        }
+        let replace_all = |exprs: &[Expr]| -> Result<Vec<Expr>, E> {
+            exprs.iter().map(|e| e.try_replace(f)).collect()
+        };
+        Ok(match self {
+            Expr::Literal(_) | Expr::Identifier(_) | Expr::Parameter(_) | Expr::Variable(_) => {
+                self.clone()
+            }
+            Expr::Unary { op, operand } => Expr::unary(*op, operand.try_replace(f)?),
+            Expr::Binary { op, left, right } => {
+                Expr::binary(*op, left.try_replace(f)?, right.try_replace(f)?)
+            }
+            Expr::Call { name, args } => Expr::Call {
+                name: name.clone(),
+                args: replace_all(args)?,
+            },
+            Expr::IsNull { operand, negated } => Expr::IsNull {
+                operand: Box::new(operand.try_replace(f)?),
+                negated: *negated,
+            },
+            Expr::InList {
+                operand,
+                list,
+                negated,
+            } => Expr::InList {
+                operand: Box::new(operand.try_replace(f)?),
+                list: replace_all(list)?,
+                negated: *negated,
+            },
+            Expr::Like {
+                operand,
+                pattern,
+                negated,
+            } => Expr::Like {
+                operand: Box::new(operand.try_replace(f)?),
+                pattern: Box::new(pattern.try_replace(f)?),
+                negated: *negated,
+            },
+        })
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Unary { op, .. } => op.precedence(),
+            Expr::Binary { op, .. } => op.precedence(),
+            Expr::IsNull { .. } | Expr::InList { .. } | Expr::Like { .. } => PREDICATE_PRECEDENCE,
+            _ => PRIMARY_PRECEDENCE,
+        }
+    }
+
+    /// Renders the expression with the minimum parentheses needed for
+    /// `parsing::parse` to rebuild the same tree.
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut String) {
+        match self {
+            Expr::Literal(value) => out.push_str(&value.serialize()),
+            Expr::Identifier(name) => match name.split_once('.') {
+                Some((table, column)) if !table.is_empty() && !column.is_empty() => {
+                    out.push_str(&lexer::quote_identifier(table));
+                    out.push('.');
+                    out.push_str(&lexer::quote_identifier(column));
+                }
+                _ => out.push_str(&lexer::quote_identifier(name)),
+            },
+            Expr::Parameter(n) => out.push_str(&format!("${n}")),
+            Expr::Variable(name) => out.push_str(&format!("@{name}")),
+            Expr::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => {
+                out.push_str("NOT ");
+                write_wrapped(
+                    operand,
+                    operand.precedence() < UnaryOp::Not.precedence(),
+                    out,
+                );
+            }
+            Expr::Unary { op, operand } => {
+                out.push_str(op.symbol());
+                // `-1` would read back as a negative literal, and `--x` as
+                // a comment, so a sign is kept apart from a number or
+                // another sign. A space does that without the parentheses
+                // that would count towards the nesting limit.
+                if matches!(
+                    **operand,
+                    Expr::Literal(LiteralValue::Int(_) | LiteralValue::Float(_))
+                        | Expr::Unary {
+                            op: UnaryOp::Neg | UnaryOp::Plus,
+                            ..
+                        }
+                ) {
+                    out.push(' ');
+                    operand.write(out);
+                } else {
+                    write_wrapped(operand, operand.precedence() <= op.precedence(), out);
+                }
+            }
+            Expr::Binary { op, left, right } => {
+                write_wrapped(left, left.precedence() < op.precedence(), out);
+                out.push(' ');
+                out.push_str(op.symbol());
+                out.push(' ');
+                write_wrapped(right, right.precedence() <= op.precedence(), out);
+            }
+            // `COUNT(*)` is the only call written without arguments.
+            Expr::Call { name, args } if args.is_empty() && name.eq_ignore_ascii_case("count") => {
+                out.push_str(&lexer::quote_identifier(name));
+                out.push_str("(*)");
+            }
+            Expr::Call { name, args } => {
+                out.push_str(&lexer::quote_identifier(name));
+                write_list(args, out);
+            }
+            Expr::IsNull { operand, negated } => {
+                write_wrapped(operand, operand.precedence() < PREDICATE_PRECEDENCE, out);
+                out.push_str(if *negated { " IS NOT NULL" } else { " IS NULL" });
+            }
+            Expr::InList {
+                operand,
+                list,
+                negated,
+            } => {
+                write_wrapped(operand, operand.precedence() < PREDICATE_PRECEDENCE, out);
+                out.push_str(if *negated { " NOT IN " } else { " IN " });
+                write_list(list, out);
+            }
+            Expr::Like {
+                operand,
+                pattern,
+                negated,
+            } => {
+                write_wrapped(operand, operand.precedence() < PREDICATE_PRECEDENCE, out);
+                out.push_str(if *negated { " NOT LIKE " } else { " LIKE " });
+                write_wrapped(pattern, pattern.precedence() <= PREDICATE_PRECEDENCE, out);
+            }
+        }
+    }
+}
+
+/// `(a, b, ...)`
+fn write_list(exprs: &[Expr], out: &mut String) {
+    out.push('(');
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        expr.write(out);
+    }
+    out.push(')');
+}
+
+fn write_wrapped(expr: &Expr, wrap: bool, out: &mut String) {
+    if wrap {
+        out.push('(');
+        expr.write(out);
+        out.push(')');
+    } else {
+        expr.write(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Expr {
+        parsing::parse(text).unwrap()
+    }
+
+    fn int(v: i64) -> Expr {
+        Expr::Literal(LiteralValue::Int(v))
+    }
+
+    fn ident(name: &str) -> Expr {
+        Expr::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(
+            parse("1 + 2 * 3"),
+            Expr::binary(
+                BinaryOp::Add,
+                int(1),
+                Expr::binary(BinaryOp::Mul, int(2), int(3))
+            )
+        );
+        assert_eq!(
+            parse("a - b - c"),
+            Expr::binary(
+                BinaryOp::Sub,
+                Expr::binary(BinaryOp::Sub, ident("a"), ident("b")),
+                ident("c")
+            )
+        );
+        assert_eq!(
+            parse("a || b = c"),
+            Expr::binary(
+                BinaryOp::Eq,
+                Expr::binary(BinaryOp::Concat, ident("a"), ident("b")),
+                ident("c")
+            )
+        );
+    }
+
+    #[test]
+    fn negative_literals_versus_negation() {
+        assert_eq!(parse("-1"), int(-1));
+        assert_eq!(parse("- 1"), Expr::unary(UnaryOp::Neg, int(1)));
+        assert_eq!(parse("2-1"), Expr::binary(BinaryOp::Sub, int(2), int(1)));
+    }
+
+    #[test]
+    fn calls_and_parentheses() {
+        assert_eq!(
+            parse("f( (a + 1) * 2 , g())"),
+            Expr::Call {
+                name: "f".to_string(),
+                args: vec![
+                    Expr::binary(
+                        BinaryOp::Mul,
+                        Expr::binary(BinaryOp::Add, ident("a"), int(1)),
+                        int(2)
+                    ),
+                    Expr::Call {
+                        name: "g".to_string(),
+                        args: vec![],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for text in [
+            "1 + 2 * 3",
+            "(1 + 2) * 3",
+            "a - (b - c)",
+            "a - b - c",
+            "-(1)",
+            "-(-x)",
+            "-(a + b)",
+            "+(2.5)",
+            "x * -1",
+            "name || \" \" || surname",
+            "f(a, g(b), 'c') <> (1 < 2)",
+            "inf_count + inf",
+            "a OR b AND c",
+            "(a OR b) AND c",
+            "NOT a = b OR NOT NOT c",
+            "NOT (a OR b)",
+            "-(NOT a)",
+            "is_null = null AND true <> false",
+            "a << inet '10.0.0.0/8' = (b < c)",
+            "a IS NULL AND b IS NOT NULL = c IS NULL",
+            "(a = b) IS NULL",
+            "x NOT IN (1, 2 + 3, y) OR x IN (null)",
+            "name LIKE 'a%' || suffix AND NOT name NOT LIKE (p = q)",
+            "a LIKE (b LIKE c)",
+            "count(*) + COUNT(x)",
+            "$1 + $12 * -$3",
+            "t.x = `two words`.`y z` AND `a.b` > 1",
+        ] {
+            let expr = parse(text);
+            assert_eq!(parse(&expr.serialize()), expr, "{text}");
+        }
+        assert_eq!(parse("((a))  +(b)").serialize(), "a + b");
+    }
+
+    #[test]
+    fn logical_operators() {
+        assert_eq!(
+            parse("not a = 1 and b or c"),
+            Expr::binary(
+                BinaryOp::Or,
+                Expr::binary(
+                    BinaryOp::And,
+                    Expr::unary(UnaryOp::Not, Expr::binary(BinaryOp::Eq, ident("a"), int(1))),
+                    ident("b")
+                ),
+                ident("c")
+            )
+        );
+        assert_eq!(parse("android"), ident("android"));
+        assert!(parsing::parse("and").is_err());
+    }
+
+    #[test]
+    fn rejects_dangling_operator() {
+        assert!(parsing::parse("1 +").is_err());
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let deep = 10_000;
+        for text in [
+            format!("{}1{}", "(".repeat(deep), ")".repeat(deep)),
+            format!("{}1{}", "f(".repeat(deep), ")".repeat(deep)),
+            format!("{}true", "NOT ".repeat(deep)),
+            format!("{}1", "- ".repeat(deep)),
+            vec!["1"; deep].join(" + "),
+            vec!["a"; deep].join(" OR "),
+            vec!["a"; deep].join(" = "),
+            format!("NOT {}", vec!["a"; parsing::MAX_DEPTH].join(" OR ")),
+            format!("{}a{}", "f(1 + ".repeat(100), ")".repeat(100)),
+        ] {
+            let error = parsing::parse(&text).unwrap_err();
+            assert_eq!(
+                error.message(),
+                "expression nested too deeply",
+                "{}",
+                &text[..20]
+            );
+        }
+
+        // The deepest that parse also parse back once serialized.
+        let nesting = parsing::MAX_NESTING - 1;
+        let parens = format!("{}1{}", "(".repeat(nesting), ")".repeat(nesting));
+        assert_eq!(parse(&parens), int(1));
+        let depth = parsing::MAX_DEPTH;
+        for text in [
+            format!("{}- 1{}", "f(".repeat(nesting), ")".repeat(nesting)),
+            format!("{}1{}", "-(".repeat(nesting), ")".repeat(nesting)),
+            format!("{}1", "- ".repeat(depth - 1)),
+            format!("{}x", "NOT ".repeat(depth - 1)),
+            vec!["1"; depth].join(" + "),
+        ] {
+            let expr = parse(&text);
+            assert!(expr.depth() > nesting, "{}", &text[..20]);
+            assert_eq!(parse(&expr.serialize()), expr, "{}", &text[..20]);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! nom grammar for expressions over tokens, one function per precedence
+//! level.
+//!
+//! Everything that walks an expression recurses once per level of its
+//! tree, and parsing recurses once per level of parentheses, so without
+//! limits a long enough run of `(`, `NOT` or `+ 1` overflows the stack. An
+//! expression's tree may be at most [`MAX_DEPTH`] deep, and its
+//! parentheses, function arguments and `IN` lists may nest at most
+//! [`MAX_NESTING`] deep; parsing refuses anything deeper with "expression
+//! nested too deeply". Runs of `NOT` and signs are read in a loop, so they
+//! only count towards the depth of the tree.
+//!
+//! Serializing an expression never needs more nesting than the text it was
+//! parsed from, so whatever parses also parses back.
+
+use std::cell::Cell;
+
+use nom::branch::alt;
+use nom::combinator::{cut, map, opt, value, verify};
+use nom::error::context;
+use nom::multi::{many0, many0_count, separated_list0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, terminated};
+
+use super::{BinaryOp, Expr, UnaryOp};
+use crate::error::{GrammarError, ParseError};
+use crate::lexer::{
+    self, identifier, keyword, literal, parameter, symbol, variable, TokenResult, Tokens,
+};
+use crate::literals::LiteralValue;
+
+/// How deep an expression's tree may go, as [`Expr::depth`] counts it.
+pub const MAX_DEPTH: usize = 256;
+
+/// How deep parentheses, function arguments and `IN` lists may nest. Each
+/// level costs the grammar far more stack than a level of the tree costs
+/// anything that walks it.
+pub const MAX_NESTING: usize = 64;
+
+thread_local! {
+    /// The levels the expression being parsed is nested in so far.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Parses one expression.
+pub fn apply_grammar(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    let (rest, expr) = nested(input, context("expression", disjunction))?;
+    if expr.depth() > MAX_DEPTH {
+        return Err(too_deep(input));
+    }
+    Ok((rest, expr))
+}
+
+fn too_deep(input: Tokens<'_>) -> nom::Err<GrammarError<'_>> {
+    nom::Err::Failure(GrammarError::refused(
+        input.rest_source(),
+        "expression nested too deeply",
+    ))
+}
+
+/// The depth of `node`, a new node whose first child is `depth` deep;
+/// failing at `input` when that is deeper than [`MAX_DEPTH`]. Chains of
+/// operators check each node as they grow, so that they never build a
+/// tree deeper than that.
+fn grown<'a>(
+    input: Tokens<'a>,
+    depth: usize,
+    node: &Expr,
+) -> Result<usize, nom::Err<GrammarError<'a>>> {
+    let depth = node.children()[1..]
+        .iter()
+        .map(|child| child.depth())
+        .fold(depth, usize::max)
+        + 1;
+    if depth > MAX_DEPTH {
+        return Err(too_deep(input));
+    }
+    Ok(depth)
+}
+
+/// Runs `grammar` one level of nesting deeper, failing outright when that
+/// is deeper than [`MAX_NESTING`].
+fn nested<'a, T>(
+    input: Tokens<'a>,
+    mut grammar: impl FnMut(Tokens<'a>) -> TokenResult<'a, T>,
+) -> TokenResult<'a, T> {
+    /// Leaves the level again however `grammar` returns.
+    struct Level;
+
+    impl Drop for Level {
+        fn drop(&mut self) {
+            NESTING.with(|nesting| nesting.set(nesting.get() - 1));
+        }
+    }
+
+    if NESTING.with(Cell::get) >= MAX_NESTING {
+        return Err(too_deep(input));
+    }
+    NESTING.with(|nesting| nesting.set(nesting.get() + 1));
+    let _level = Level;
+    grammar(input)
+}
+
+/// Parses a whole string as exactly one expression.
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    lexer::parse_all(input, apply_grammar)
+}
+
+/// Folds `operand (op operand)*` into a left-leaning tree.
+fn left_assoc<'a>(
+    input: Tokens<'a>,
+    operand: fn(Tokens<'a>) -> TokenResult<'a, Expr>,
+    operator: fn(Tokens<'a>) -> TokenResult<'a, BinaryOp>,
+) -> TokenResult<'a, Expr> {
+    let (mut input, mut acc) = operand(input)?;
+    let mut depth = acc.depth();
+    loop {
+        match operator(input) {
+            Ok((rest, op)) => {
+                let (rest, right) = cut(context("expression", operand))(rest)?;
+                acc = Expr::binary(op, acc, right);
+                depth = grown(input, depth, &acc)?;
+                input = rest;
+            }
+            Err(nom::Err::Error(_)) => return Ok((input, acc)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn disjunction(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    left_assoc(input, conjunction, |input| {
+        value(BinaryOp::Or, keyword("or"))(input)
+    })
+}
+
+fn conjunction(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    left_assoc(input, negation, |input| {
+        value(BinaryOp::And, keyword("and"))(input)
+    })
+}
+
+fn negation(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    let (rest, nots) = many0_count(keyword("not"))(input)?;
+    if nots == 0 {
+        return comparison(rest);
+    }
+    let (rest, operand) = cut(context("expression", comparison))(rest)?;
+    Ok((rest, prefixed(input, vec![UnaryOp::Not; nots], operand)?))
+}
+
+/// `operand` under the prefix operators `ops`, the first outermost;
+/// failing at `input` when that is deeper than [`MAX_DEPTH`].
+fn prefixed<'a>(
+    input: Tokens<'a>,
+    ops: Vec<UnaryOp>,
+    operand: Expr,
+) -> Result<Expr, nom::Err<GrammarError<'a>>> {
+    if operand.depth() + ops.len() > MAX_DEPTH {
+        return Err(too_deep(input));
+    }
+    Ok(ops
+        .into_iter()
+        .rev()
+        .fold(operand, |operand, op| Expr::unary(op, operand)))
+}
+
+/// Comparison operators and the `IS NULL`, `IN` and `LIKE` predicates,
+/// all left-associative at the same level.
+fn comparison(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    let (mut input, mut acc) = concat(input)?;
+    let mut depth = acc.depth();
+    loop {
+        match predicate_suffix(input) {
+            Ok((rest, suffix)) => {
+                acc = suffix.apply(acc);
+                depth = grown(input, depth, &acc)?;
+                input = rest;
+            }
+            Err(nom::Err::Error(_)) => return Ok((input, acc)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// What follows the left operand of a comparison.
+enum Suffix {
+    Binary(BinaryOp, Expr),
+    IsNull { negated: bool },
+    InList { list: Vec<Expr>, negated: bool },
+    Like { pattern: Expr, negated: bool },
+}
+
+impl Suffix {
+    fn apply(self, operand: Expr) -> Expr {
+        let operand = Box::new(operand);
+        match self {
+            Suffix::Binary(op, right) => Expr::Binary {
+                op,
+                left: operand,
+                right: Box::new(right),
+            },
+            Suffix::IsNull { negated } => Expr::IsNull { operand, negated },
+            Suffix::InList { list, negated } => Expr::InList {
+                operand,
+                list,
+                negated,
+            },
+            Suffix::Like { pattern, negated } => Expr::Like {
+                operand,
+                pattern: Box::new(pattern),
+                negated,
+            },
+        }
+    }
+}
+
+fn predicate_suffix(input: Tokens<'_>) -> TokenResult<'_, Suffix> {
+    let operand = || cut(context("expression", concat));
+    alt((
+        map(pair(comparison_operator, operand()), |(op, right)| {
+            Suffix::Binary(op, right)
+        }),
+        map(
+            preceded(keyword("is"), cut(terminated(opt(keyword("not")), null))),
+            |negated| Suffix::IsNull {
+                negated: negated.is_some(),
+            },
+        ),
+        map(
+            pair(
+                terminated(opt(keyword("not")), keyword("in")),
+                cut(delimited(
+                    symbol("("),
+                    separated_list1(symbol(","), cut(apply_grammar)),
+                    symbol(")"),
+                )),
+            ),
+            |(negated, list)| Suffix::InList {
+                list,
+                negated: negated.is_some(),
+            },
+        ),
+        map(
+            pair(terminated(opt(keyword("not")), keyword("like")), operand()),
+            |(negated, pattern)| Suffix::Like {
+                pattern,
+                negated: negated.is_some(),
+            },
+        ),
+    ))(input)
+}
+
+/// `NULL`, which the lexer reads as a literal rather than a keyword.
+fn null(input: Tokens<'_>) -> TokenResult<'_, LiteralValue> {
+    context(
+        "`NULL`",
+        verify(literal, |value| *value == LiteralValue::Null),
+    )(input)
+}
+
+fn comparison_operator(input: Tokens<'_>) -> TokenResult<'_, BinaryOp> {
+    alt((
+        value(BinaryOp::ContainedBy, symbol("<<")),
+        value(BinaryOp::LtEq, symbol("<=")),
+        value(BinaryOp::GtEq, symbol(">=")),
+        value(BinaryOp::NotEq, symbol("<>")),
+        value(BinaryOp::NotEq, symbol("!=")),
+        value(BinaryOp::Eq, symbol("=")),
+        value(BinaryOp::Lt, symbol("<")),
+        value(BinaryOp::Gt, symbol(">")),
+    ))(input)
+}
+
+fn concat(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    left_assoc(input, additive, |input| {
+        value(BinaryOp::Concat, symbol("||"))(input)
+    })
+}
+
+fn additive(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    left_assoc(input, multiplicative, |input| {
+        alt((
+            value(BinaryOp::Add, symbol("+")),
+            value(BinaryOp::Sub, symbol("-")),
+        ))(input)
+    })
+}
+
+fn multiplicative(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    left_assoc(input, unary, |input| {
+        alt((
+            value(BinaryOp::Mul, symbol("*")),
+            value(BinaryOp::Div, symbol("/")),
+            value(BinaryOp::Mod, symbol("%")),
+        ))(input)
+    })
+}
+
+fn unary(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    let (rest, signs) = many0(alt((
+        value(UnaryOp::Neg, symbol("-")),
+        value(UnaryOp::Plus, symbol("+")),
+    )))(input)?;
+    if signs.is_empty() {
+        return primary(rest);
+    }
+    let (rest, operand) = cut(context("expression", primary))(rest)?;
+    Ok((rest, prefixed(input, signs, operand)?))
+}
+
+fn primary(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    alt((
+        map(literal, Expr::Literal),
+        map(parameter, Expr::Parameter),
+        map(variable, Expr::Variable),
+        call_or_identifier,
+        delimited(symbol("("), cut(apply_grammar), cut(symbol(")"))),
+    ))(input)
+}
+
+fn call_or_identifier(input: Tokens<'_>) -> TokenResult<'_, Expr> {
+    // `f(*)`, as in `COUNT(*)`, is a call without arguments.
+    let args = delimited(
+        symbol("("),
+        alt((
+            value(vec![], symbol("*")),
+            separated_list0(symbol(","), apply_grammar),
+        )),
+        cut(symbol(")")),
+    );
+    // `t.x` names column `x` of table `t`; it is kept as the identifier `t.x`.
+    let name = map(
+        pair(identifier, opt(preceded(symbol("."), cut(identifier)))),
+        |(name, column)| match column {
+            Some(column) => format!("{name}.{column}"),
+            None => name,
+        },
+    );
+    map(pair(name, opt(args)), |(name, args)| match args {
+        Some(args) => Expr::Call { name, args },
+        None => Expr::Identifier(name),
+    })(input)
+}
@@ -0,0 +1,943 @@
+//! Table schemas, and the catalog of named tables.
+//!
+//! Tables created with `CREATE TABLE` are held in memory for the life of the
+//! `Catalog`; other kinds of [`Table`] can be added with
+//! [`Catalog::add_table`]. The catalog also holds the statement
+//! [`Rewrites`] its statements go through.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::Expr;
+use crate::eval::{self, Bindings, EvalError};
+use crate::external::ExternalError;
+use crate::functions;
+use crate::literals::LiteralValue;
+use crate::macros::Macros;
+use crate::statements::{types, Rewrites};
+use crate::table::{MemoryTable, Table, Values};
+use crate::variables::Variables;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Text,
+    Bool,
+    Inet,
+    Point,
+    /// `LIST`: a list of values, as the `SPLIT` function gives.
+    List,
+    /// `ENUM('a', 'b', ...)`: one of a fixed list of labels. Values are
+    /// stored as their label's position in the list, and read back as the
+    /// label; they sort and compare by that position.
+    Enum(Vec<String>),
+}
+
+impl ColumnType {
+    /// Checks a value against the type. NULL fits any column, and ints are
+    /// widened when stored in a FLOAT column.
+    pub fn coerce(&self, value: LiteralValue) -> Result<LiteralValue, LiteralValue> {
+        match (self, value) {
+            (_, LiteralValue::Null) => Ok(LiteralValue::Null),
+            (ColumnType::Int, value @ LiteralValue::Int(_))
+            | (ColumnType::Float, value @ LiteralValue::Float(_))
+            | (ColumnType::Text, value @ LiteralValue::String(_))
+            | (ColumnType::Bool, value @ LiteralValue::Bool(_))
+            | (ColumnType::Inet, value @ LiteralValue::Inet(_))
+            | (ColumnType::Point, value @ LiteralValue::Point(_))
+            | (ColumnType::List, value @ LiteralValue::List(_)) => Ok(value),
+            (ColumnType::Float, LiteralValue::Int(v)) => Ok(LiteralValue::Float(v as f64)),
+            (ColumnType::Enum(labels), LiteralValue::String(label)) if labels.contains(&label) => {
+                Ok(LiteralValue::String(label))
+            }
+            (_, value) => Err(value),
+        }
+    }
+
+    /// Whether values of type `found` can be stored in a column of this
+    /// type. Ints widen to floats, and text may be an enum label.
+    fn accepts(&self, found: &ColumnType) -> bool {
+        match (self, found) {
+            (ColumnType::Float, ColumnType::Int)
+            | (ColumnType::Enum(_) | ColumnType::Text, ColumnType::Enum(_) | ColumnType::Text) => {
+                true
+            }
+            (expected, found) => expected == found,
+        }
+    }
+
+    /// The form a coerced value is kept in: an enum label becomes its
+    /// position.
+    fn store(&self, value: LiteralValue) -> LiteralValue {
+        match (self, value) {
+            (ColumnType::Enum(labels), LiteralValue::String(label)) => {
+                let position = labels.iter().position(|l| *l == label);
+                LiteralValue::Int(position.expect("coerced before storing") as i64)
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// Undoes [`ColumnType::store`].
+    fn load(&self, value: &LiteralValue) -> LiteralValue {
+        match (self, value) {
+            (ColumnType::Enum(labels), LiteralValue::Int(position)) => {
+                LiteralValue::String(labels[*position as usize].clone())
+            }
+            (_, value) => value.clone(),
+        }
+    }
+}
+
+/// The type as written in `CREATE TABLE`.
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnType::Int => f.write_str("INT"),
+            ColumnType::Float => f.write_str("FLOAT"),
+            ColumnType::Text => f.write_str("TEXT"),
+            ColumnType::Bool => f.write_str("BOOL"),
+            ColumnType::Inet => f.write_str("INET"),
+            ColumnType::Point => f.write_str("POINT"),
+            ColumnType::List => f.write_str("LIST"),
+            ColumnType::Enum(labels) => {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|label| LiteralValue::String(label.clone()).serialize())
+                    .collect();
+                write!(f, "ENUM({})", labels.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub ty: ColumnType,
+    /// Set for a column computed from the others instead of inserted.
+    pub generated: Option<Generated>,
+    /// Left out of `*`, though a query can still name it.
+    pub hidden: bool,
+}
+
+impl Column {
+    fn check(&self, value: LiteralValue) -> Result<LiteralValue, CatalogError> {
+        self.ty
+            .coerce(value)
+            .map_err(|found| CatalogError::TypeMismatch {
+                column: self.name.clone(),
+                expected: self.ty.clone(),
+                found,
+            })
+    }
+
+    pub fn new(name: &str, ty: ColumnType) -> Column {
+        Column {
+            name: name.to_string(),
+            ty,
+            generated: None,
+            hidden: false,
+        }
+    }
+}
+
+/// `AS (<expr>) STORED | VIRTUAL`: the expression may refer only to columns
+/// that are not generated themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generated {
+    pub expr: Expr,
+    pub kind: GeneratedKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratedKind {
+    /// Computed when a row is inserted and stored with it.
+    Stored,
+    /// Computed each time the row is read; NULL is stored in its place.
+    Virtual,
+}
+
+impl GeneratedKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            GeneratedKind::Stored => "STORED",
+            GeneratedKind::Virtual => "VIRTUAL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+}
+
+/// The values of one row, looked up by column name.
+struct ColumnValues<'a> {
+    schema: &'a Schema,
+    values: &'a [LiteralValue],
+}
+
+/// The first function `expr` calls that does not exist.
+fn unknown_function(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Call { name, .. } if !functions::exists(name) => Some(name),
+        _ => expr.children().into_iter().find_map(unknown_function),
+    }
+}
+
+/// The first aggregate function `expr` calls.
+fn aggregate_call(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Call { name, .. } if functions::is_aggregate(name) => Some(name),
+        _ => expr.children().into_iter().find_map(aggregate_call),
+    }
+}
+
+impl Bindings for ColumnValues<'_> {
+    fn lookup(&self, name: &str) -> Option<LiteralValue> {
+        Some(self.values[self.schema.index_of(name)?].clone())
+    }
+}
+
+impl Schema {
+    /// Checks that column names and enum labels are distinct, and that
+    /// generated columns refer only to plain columns, call only known
+    /// scalar functions and give values of their declared type, as far as the
+    /// expression settles it.
+    pub fn validate(&self) -> Result<(), CatalogError> {
+        for (i, column) in self.columns.iter().enumerate() {
+            if self.columns[..i].iter().any(|c| c.name == column.name) {
+                return Err(CatalogError::DuplicateColumn(column.name.clone()));
+            }
+            if let ColumnType::Enum(labels) = &column.ty {
+                for (j, label) in labels.iter().enumerate() {
+                    if labels[..j].contains(label) {
+                        return Err(CatalogError::DuplicateLabel {
+                            column: column.name.clone(),
+                            label: label.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        for column in &self.columns {
+            let Some(generated) = &column.generated else {
+                continue;
+            };
+            for name in generated.expr.identifiers() {
+                let plain = self
+                    .columns
+                    .iter()
+                    .any(|c| c.name == name && c.generated.is_none());
+                if !plain {
+                    return Err(CatalogError::GeneratedReference {
+                        column: column.name.clone(),
+                        reference: name.to_string(),
+                    });
+                }
+            }
+            if let Some(function) = aggregate_call(&generated.expr) {
+                return Err(CatalogError::GeneratedAggregate {
+                    column: column.name.clone(),
+                    function: function.to_string(),
+                });
+            }
+            if let Some(function) = unknown_function(&generated.expr) {
+                return Err(CatalogError::GeneratedFunction {
+                    column: column.name.clone(),
+                    function: function.to_string(),
+                });
+            }
+            if let Some(found) = types::expr_type_in(&generated.expr, self).ty {
+                if !column.ty.accepts(&found) {
+                    return Err(CatalogError::GeneratedType {
+                        column: column.name.clone(),
+                        expected: column.ty.clone(),
+                        found,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The columns an INSERT gives values for.
+    pub fn input_columns(&self) -> impl Iterator<Item = &Column> {
+        self.columns.iter().filter(|c| c.generated.is_none())
+    }
+
+    /// Whether stored rows differ from what queries see: they do when there
+    /// are virtual or enum columns.
+    pub fn needs_decoding(&self) -> bool {
+        self.columns.iter().any(|c| {
+            matches!(c.ty, ColumnType::Enum(_))
+                || c.generated
+                    .as_ref()
+                    .is_some_and(|g| g.kind == GeneratedKind::Virtual)
+        })
+    }
+
+    /// Builds a stored row from the values of the input columns: checks and
+    /// coerces them, computes stored columns, leaves NULL for virtual ones,
+    /// and replaces enum labels with their positions.
+    pub fn complete_row(
+        &self,
+        table: &str,
+        input: Vec<LiteralValue>,
+    ) -> Result<Values, CatalogError> {
+        let expected = self.input_columns().count();
+        if input.len() != expected {
+            return Err(CatalogError::ColumnCount {
+                table: table.to_string(),
+                expected,
+                found: input.len(),
+            });
+        }
+        let mut input = input.into_iter();
+        let mut values = Vec::with_capacity(self.columns.len());
+        for column in &self.columns {
+            let value = match column.generated {
+                None => input.next().expect("counted above"),
+                Some(_) => LiteralValue::Null,
+            };
+            values.push(column.check(value)?);
+        }
+        for (i, column) in self.columns.iter().enumerate() {
+            if let Some(Generated {
+                expr,
+                kind: GeneratedKind::Stored,
+            }) = &column.generated
+            {
+                values[i] = self.compute(column, expr, &values)?;
+            }
+        }
+        let values = self
+            .columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| column.ty.store(value));
+        Ok(values.collect())
+    }
+
+    /// A stored row as queries see it: enum labels in place of their
+    /// positions, and virtual columns filled in.
+    pub fn decode(&self, values: &[LiteralValue]) -> Result<Values, CatalogError> {
+        let mut out: Vec<LiteralValue> = self
+            .columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| column.ty.load(value))
+            .collect();
+        for (i, column) in self.columns.iter().enumerate() {
+            if let Some(Generated {
+                expr,
+                kind: GeneratedKind::Virtual,
+            }) = &column.generated
+            {
+                out[i] = self.compute(column, expr, &out)?;
+            }
+        }
+        Ok(Values::from(out))
+    }
+
+    fn compute(
+        &self,
+        column: &Column,
+        expr: &Expr,
+        values: &[LiteralValue],
+    ) -> Result<LiteralValue, CatalogError> {
+        let bindings = ColumnValues {
+            schema: self,
+            values,
+        };
+        let value = eval::evaluate(expr, &bindings).map_err(|error| CatalogError::Generated {
+            column: column.name.clone(),
+            error,
+        })?;
+        column.check(value)
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogError {
+    UnknownTable(String),
+    UnknownColumn {
+        table: String,
+        column: String,
+    },
+    TableExists(String),
+    DuplicateColumn(String),
+    ColumnCount {
+        table: String,
+        expected: usize,
+        found: usize,
+    },
+    TypeMismatch {
+        column: String,
+        expected: ColumnType,
+        found: LiteralValue,
+    },
+    DuplicateLabel {
+        column: String,
+        label: String,
+    },
+    /// Labels can only be added to ENUM columns.
+    NotAnEnum(String),
+    /// A generated column's expression names something other than a plain
+    /// column of the same table.
+    GeneratedReference {
+        column: String,
+        reference: String,
+    },
+    /// A generated column's expression calls a function that does not
+    /// exist.
+    GeneratedFunction {
+        column: String,
+        function: String,
+    },
+    /// A generated column's expression calls an aggregate function.
+    GeneratedAggregate {
+        column: String,
+        function: String,
+    },
+    /// A generated column's expression gives values of another type than
+    /// the column's.
+    GeneratedType {
+        column: String,
+        expected: ColumnType,
+        found: ColumnType,
+    },
+    /// Computing a generated column failed.
+    Generated {
+        column: String,
+        error: EvalError,
+    },
+    /// An external table's file could not be read.
+    External(ExternalError),
+    /// Rows cannot be inserted into an external table.
+    ReadOnly(String),
+    /// A version the table has not reached yet.
+    UnknownVersion {
+        table: String,
+        version: u64,
+        latest: u64,
+    },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::UnknownTable(name) => write!(f, "unknown table `{name}`"),
+            CatalogError::UnknownColumn { table, column } => {
+                write!(f, "table `{table}` has no column `{column}`")
+            }
+            CatalogError::TableExists(name) => write!(f, "table `{name}` already exists"),
+            CatalogError::DuplicateColumn(name) => write!(f, "column `{name}` declared twice"),
+            CatalogError::DuplicateLabel { column, label } => {
+                write!(f, "column `{column}` lists label '{label}' twice")
+            }
+            CatalogError::NotAnEnum(column) => write!(f, "column `{column}` is not an ENUM"),
+            CatalogError::ColumnCount {
+                table,
+                expected,
+                found,
+            } => write!(
+                f,
+                "table `{table}` has {expected} columns but {found} values were given"
+            ),
+            CatalogError::TypeMismatch {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column `{column}` is {expected} but got {} {}",
+                found.type_name(),
+                found.serialize()
+            ),
+            CatalogError::GeneratedReference { column, reference } => write!(
+                f,
+                "generated column `{column}` refers to `{reference}`, which is not a plain column"
+            ),
+            CatalogError::GeneratedFunction { column, function } => write!(
+                f,
+                "generated column `{column}` calls unknown function `{function}`"
+            ),
+            CatalogError::GeneratedAggregate { column, function } => write!(
+                f,
+                "aggregate `{function}` not allowed in generated column `{column}`"
+            ),
+            CatalogError::GeneratedType {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "generated column `{column}` is {expected} but its expression gives {found}"
+            ),
+            CatalogError::Generated { column, error } => {
+                write!(f, "computing column `{column}`: {error}")
+            }
+            CatalogError::External(e) => e.fmt(f),
+            CatalogError::ReadOnly(name) => write!(f, "table `{name}` is read-only"),
+            CatalogError::UnknownVersion {
+                table,
+                version,
+                latest,
+            } => write!(
+                f,
+                "table `{table}` has no version {version}; its latest is {latest}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// The tables of a session, with the rewrites, variables and macros that
+/// apply to its statements.
+///
+/// Each table is versioned: version 0 is the table as it was added, and
+/// each INSERT into it makes the next version. Since rows are only ever
+/// appended, a version is the rows the table had then, which
+/// `SELECT ... FROM t FOR VERSION AS OF n` reads.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    tables: HashMap<String, Box<dyn Table>>,
+    /// For each table, its row count at each version.
+    versions: HashMap<String, Vec<usize>>,
+    rewrites: Rewrites,
+    variables: Variables,
+    macros: Macros,
+}
+
+impl Catalog {
+    pub fn new() -> Catalog {
+        Catalog::default()
+    }
+
+    /// Adds an empty in-memory table.
+    pub fn create_table(&mut self, name: &str, schema: Schema) -> Result<(), CatalogError> {
+        self.add_table(name, Box::new(MemoryTable::new(schema)))
+    }
+
+    /// Adds a table of any kind under `name`.
+    pub fn add_table(&mut self, name: &str, table: Box<dyn Table>) -> Result<(), CatalogError> {
+        if self.tables.contains_key(name) {
+            return Err(CatalogError::TableExists(name.to_string()));
+        }
+        table.schema().validate()?;
+        self.versions.insert(name.to_string(), vec![table.len()]);
+        self.tables.insert(name.to_string(), table);
+        Ok(())
+    }
+
+    pub fn table(&self, name: &str) -> Option<&dyn Table> {
+        self.tables.get(name).map(Box::as_ref)
+    }
+
+    /// The table's current version.
+    pub fn version(&self, name: &str) -> Option<u64> {
+        let versions = self.versions.get(name)?;
+        Some(versions.len() as u64 - 1)
+    }
+
+    /// How many rows the table had at `version`: its first that many rows,
+    /// in insertion order, are the rows of that version.
+    pub fn rows_at_version(&self, name: &str, version: u64) -> Result<usize, CatalogError> {
+        let versions = self
+            .versions
+            .get(name)
+            .ok_or_else(|| CatalogError::UnknownTable(name.to_string()))?;
+        usize::try_from(version)
+            .ok()
+            .and_then(|v| versions.get(v))
+            .copied()
+            .ok_or_else(|| CatalogError::UnknownVersion {
+                table: name.to_string(),
+                version,
+                latest: versions.len() as u64 - 1,
+            })
+    }
+
+    pub fn rewrites(&self) -> &Rewrites {
+        &self.rewrites
+    }
+
+    /// Where rewrites and their tracers are registered.
+    pub fn rewrites_mut(&mut self) -> &mut Rewrites {
+        &mut self.rewrites
+    }
+
+    /// The session variables set so far; see [`Variables`].
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    pub fn variables_mut(&mut self) -> &mut Variables {
+        &mut self.variables
+    }
+
+    /// The macros defined so far; see [`Macros`].
+    pub fn macros(&self) -> &Macros {
+        &self.macros
+    }
+
+    pub fn macros_mut(&mut self) -> &mut Macros {
+        &mut self.macros
+    }
+
+    /// Adds a label to the end of an ENUM column's list. Stored rows keep
+    /// their meaning, since existing labels keep their positions.
+    pub fn add_enum_label(
+        &mut self,
+        table_name: &str,
+        column: &str,
+        label: &str,
+    ) -> Result<(), CatalogError> {
+        let table = self
+            .tables
+            .get_mut(table_name)
+            .ok_or_else(|| CatalogError::UnknownTable(table_name.to_string()))?;
+        let mut schema = Schema::clone(table.schema());
+        let position = schema
+            .index_of(column)
+            .ok_or_else(|| CatalogError::UnknownColumn {
+                table: table_name.to_string(),
+                column: column.to_string(),
+            })?;
+        let ColumnType::Enum(labels) = &mut schema.columns[position].ty else {
+            return Err(CatalogError::NotAnEnum(column.to_string()));
+        };
+        labels.push(label.to_string());
+        schema.validate()?;
+        table.set_schema(schema);
+        Ok(())
+    }
+
+    /// Type-checks every row, and computes its stored columns, before
+    /// appending any, so a bad row leaves the table unchanged. Rows give
+    /// values for the table's input columns only. Values are
+    /// [`normalize`](LiteralValue::normalize)d as the session's
+    /// `negative_zero` setting says, both as given, so that generated
+    /// columns are computed from what is stored, and as computed.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        rows: Vec<Vec<LiteralValue>>,
+    ) -> Result<usize, CatalogError> {
+        let negative_zero = self.variables.negative_zero();
+        let table = self
+            .tables
+            .get_mut(name)
+            .ok_or_else(|| CatalogError::UnknownTable(name.to_string()))?;
+        if table.read_only() {
+            return Err(CatalogError::ReadOnly(name.to_string()));
+        }
+        let schema = table.schema();
+        let checked = rows
+            .into_iter()
+            .map(|row| {
+                let row = row
+                    .into_iter()
+                    .map(|value| value.normalize(negative_zero))
+                    .collect();
+                let mut values = schema.complete_row(name, row)?;
+                for value in Arc::get_mut(&mut values).expect("a new row is not shared") {
+                    *value = std::mem::replace(value, LiteralValue::Null).normalize(negative_zero);
+                }
+                Ok(values)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = checked.len();
+        for values in checked {
+            table.append(values);
+        }
+        let len = table.len();
+        self.versions.entry(name.to_string()).or_default().push(len);
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LiteralValue::{Float, Int, Null};
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::new();
+        catalog
+            .create_table(
+                "t",
+                Schema {
+                    columns: vec![
+                        Column::new("id", ColumnType::Int),
+                        Column::new("score", ColumnType::Float),
+                    ],
+                },
+            )
+            .unwrap();
+        catalog
+    }
+
+    #[test]
+    fn insert_coerces_and_checks_types() {
+        let mut catalog = catalog();
+        assert_eq!(
+            catalog.insert("t", vec![vec![Int(1), Int(2)], vec![Null, Float(0.5)]]),
+            Ok(2)
+        );
+        assert_eq!(
+            catalog.table("t").unwrap().get(0).unwrap().values()[1],
+            Float(2.0)
+        );
+
+        let err = catalog
+            .insert("t", vec![vec![Int(3), Null], vec![Float(1.5), Null]])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "column `id` is INT but got float 1.5");
+        assert_eq!(catalog.table("t").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn insert_normalizes_floats() {
+        let mut catalog = catalog();
+        let bits = |catalog: &Catalog, row| match catalog
+            .table("t")
+            .unwrap()
+            .get(row)
+            .unwrap()
+            .values()[1]
+        {
+            Float(v) => v.to_bits(),
+            ref other => panic!("{other:?}"),
+        };
+        catalog
+            .insert(
+                "t",
+                vec![vec![Null, Float(-f64::NAN)], vec![Null, Float(-0.0)]],
+            )
+            .unwrap();
+        assert_eq!(bits(&catalog, 0), f64::NAN.to_bits());
+        assert_eq!(bits(&catalog, 1), (-0.0f64).to_bits());
+
+        catalog
+            .variables_mut()
+            .set("negative_zero", LiteralValue::String("fold".to_string()))
+            .unwrap();
+        catalog.insert("t", vec![vec![Null, Float(-0.0)]]).unwrap();
+        assert_eq!(bits(&catalog, 2), 0.0f64.to_bits());
+    }
+
+    #[test]
+    fn generated_columns_see_normalized_inputs() {
+        let float = |name, kind| Column {
+            ty: ColumnType::Float,
+            ..generated(name, "1.0 / x", kind)
+        };
+        let mut catalog = Catalog::new();
+        catalog
+            .create_table(
+                "g",
+                Schema {
+                    columns: vec![
+                        Column::new("x", ColumnType::Float),
+                        float("stored", GeneratedKind::Stored),
+                        float("virtual", GeneratedKind::Virtual),
+                    ],
+                },
+            )
+            .unwrap();
+        catalog
+            .variables_mut()
+            .set("negative_zero", LiteralValue::String("fold".to_string()))
+            .unwrap();
+        catalog.insert("g", vec![vec![Float(-0.0)]]).unwrap();
+        let table = catalog.table("g").unwrap();
+        let row = table
+            .schema()
+            .decode(table.get(0).unwrap().values())
+            .unwrap();
+        let bits: Vec<u64> = row
+            .iter()
+            .map(|value| match value {
+                Float(v) => v.to_bits(),
+                other => panic!("{other:?}"),
+            })
+            .collect();
+        assert_eq!(bits, [0.0, f64::INFINITY, f64::INFINITY].map(f64::to_bits));
+    }
+
+    #[test]
+    fn each_insert_makes_a_version() {
+        let mut catalog = catalog();
+        assert_eq!(catalog.version("t"), Some(0));
+        catalog.insert("t", vec![vec![Int(1), Null]]).unwrap();
+        catalog
+            .insert("t", vec![vec![Int(2), Null], vec![Int(3), Null]])
+            .unwrap();
+        // A failed insert changes nothing, so makes no version.
+        assert!(catalog.insert("t", vec![vec![Float(0.5), Null]]).is_err());
+        assert_eq!(catalog.version("t"), Some(2));
+        let counts: Vec<_> = (0..3)
+            .map(|v| catalog.rows_at_version("t", v).unwrap())
+            .collect();
+        assert_eq!(counts, vec![0, 1, 3]);
+        assert_eq!(
+            catalog.rows_at_version("t", 3).unwrap_err().to_string(),
+            "table `t` has no version 3; its latest is 2"
+        );
+        assert_eq!(catalog.version("nobody"), None);
+    }
+
+    #[test]
+    fn schema_errors() {
+        let mut catalog = catalog();
+        assert_eq!(
+            catalog.create_table("t", Schema::default()),
+            Err(CatalogError::TableExists("t".to_string()))
+        );
+        let column = Column::new("a", ColumnType::Bool);
+        assert_eq!(
+            catalog.create_table(
+                "u",
+                Schema {
+                    columns: vec![column.clone(), column]
+                }
+            ),
+            Err(CatalogError::DuplicateColumn("a".to_string()))
+        );
+        assert_eq!(
+            catalog.insert("t", vec![vec![Int(1)]]),
+            Err(CatalogError::ColumnCount {
+                table: "t".to_string(),
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    fn generated(name: &str, expr: &str, kind: GeneratedKind) -> Column {
+        Column {
+            generated: Some(Generated {
+                expr: crate::ast::parsing::parse(expr).unwrap(),
+                kind,
+            }),
+            ..Column::new(name, ColumnType::Int)
+        }
+    }
+
+    #[test]
+    fn generated_columns() {
+        let mut catalog = Catalog::new();
+        let schema = Schema {
+            columns: vec![
+                Column::new("a", ColumnType::Int),
+                generated("twice", "a * 2", GeneratedKind::Stored),
+                generated("next", "a + 1", GeneratedKind::Virtual),
+            ],
+        };
+        catalog.create_table("g", schema).unwrap();
+        assert_eq!(catalog.insert("g", vec![vec![Int(4)], vec![Null]]), Ok(2));
+
+        let table = catalog.table("g").unwrap();
+        let stored = table.get(0).unwrap();
+        assert_eq!(stored.values().as_ref(), [Int(4), Int(8), Null]);
+        assert_eq!(
+            table.schema().decode(stored.values()),
+            Ok(Values::from([Int(4), Int(8), Int(5)]))
+        );
+
+        assert_eq!(
+            catalog.insert("g", vec![vec![Int(i64::MAX)]]),
+            Err(CatalogError::Generated {
+                column: "twice".to_string(),
+                error: EvalError::Overflow(crate::ast::BinaryOp::Mul),
+            })
+        );
+        assert_eq!(catalog.table("g").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn generated_columns_refer_to_plain_columns() {
+        let schema = Schema {
+            columns: vec![
+                Column::new("a", ColumnType::Int),
+                generated("b", "a + 1", GeneratedKind::Virtual),
+                generated("c", "b + 1", GeneratedKind::Stored),
+            ],
+        };
+        assert_eq!(
+            Catalog::new().create_table("t", schema),
+            Err(CatalogError::GeneratedReference {
+                column: "c".to_string(),
+                reference: "b".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn generated_columns_are_checked_when_created() {
+        let table = |column: Column| Schema {
+            columns: vec![Column::new("a", ColumnType::Int), column],
+        };
+        let text = |expr| Column {
+            ty: ColumnType::Text,
+            ..generated("b", expr, GeneratedKind::Virtual)
+        };
+        let mut catalog = Catalog::new();
+        assert_eq!(
+            catalog.create_table("t", table(text("upper(a)"))),
+            Err(CatalogError::GeneratedFunction {
+                column: "b".to_string(),
+                function: "upper".to_string(),
+            })
+        );
+        for kind in [GeneratedKind::Virtual, GeneratedKind::Stored] {
+            let count = Column {
+                ty: ColumnType::Int,
+                ..generated("b", "count(a) + 1", kind)
+            };
+            assert_eq!(
+                catalog
+                    .create_table("t", table(count))
+                    .unwrap_err()
+                    .to_string(),
+                "aggregate `count` not allowed in generated column `b`"
+            );
+        }
+        assert_eq!(
+            catalog.create_table("t", table(text("a + 1"))),
+            Err(CatalogError::GeneratedType {
+                column: "b".to_string(),
+                expected: ColumnType::Text,
+                found: ColumnType::Int,
+            })
+        );
+        assert_eq!(
+            catalog
+                .create_table("t", table(text("a > 1")))
+                .unwrap_err()
+                .to_string(),
+            "generated column `b` is TEXT but its expression gives BOOL"
+        );
+        let float = Column {
+            ty: ColumnType::Float,
+            ..generated("b", "a * 2", GeneratedKind::Stored)
+        };
+        assert_eq!(catalog.create_table("t", table(float)), Ok(()));
+        let unknown = generated("b", "a + regexp_extract('x', 'x')", GeneratedKind::Virtual);
+        assert_eq!(catalog.create_table("u", table(unknown)), Ok(()));
+    }
+}
@@ -0,0 +1,199 @@
+//! A small RFC 4180 CSV reader: comma-separated fields, optional double
+//! quotes around a field, `""` for a quote inside a quoted field, and LF or
+//! CRLF line endings. [`write_record`] writes records the reader reads
+//! back.
+
+use std::fmt;
+
+/// One parsed line, with the 1-based line number it started on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    /// A quoted field was still open at the end of the input.
+    UnterminatedQuote { line: usize },
+    /// Something other than a comma or line end followed a closing quote.
+    TextAfterQuote { line: usize },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::UnterminatedQuote { line } => {
+                write!(f, "line {line}: quoted field is never closed")
+            }
+            CsvError::TextAfterQuote { line } => {
+                write!(f, "line {line}: unexpected text after closing quote")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Iterates over the records of CSV text. Blank lines are skipped.
+pub struct Records<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+pub fn records(text: &str) -> Records<'_> {
+    Records {
+        chars: text.chars().peekable(),
+        line: 1,
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<Record, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.chars.peek() {
+                None => return None,
+                Some('\n') => {
+                    self.chars.next();
+                    self.line += 1;
+                }
+                Some('\r') => {
+                    self.chars.next();
+                }
+                Some(_) => return Some(self.record()),
+            }
+        }
+    }
+}
+
+/// A line of CSV for the fields, without its line ending. A field is
+/// quoted when it has to be: when it holds a comma, a quote or a line
+/// break, or is empty in a record of one field, which would otherwise be a
+/// blank line.
+pub fn write_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) || (field.is_empty() && fields.len() == 1) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    fields.join(",")
+}
+
+impl Records<'_> {
+    fn record(&mut self) -> Result<Record, CsvError> {
+        let start = self.line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        loop {
+            match self.chars.next() {
+                None => {
+                    fields.push(field);
+                    return Ok(Record {
+                        line: start,
+                        fields,
+                    });
+                }
+                Some('\n') => {
+                    self.line += 1;
+                    fields.push(field);
+                    return Ok(Record {
+                        line: start,
+                        fields,
+                    });
+                }
+                Some('\r') if self.chars.peek() == Some(&'\n') => {}
+                Some(',') => fields.push(std::mem::take(&mut field)),
+                Some('"') if field.is_empty() => {
+                    self.quoted(&mut field, start)?;
+                    if !matches!(self.chars.peek(), None | Some(',' | '\n' | '\r')) {
+                        return Err(CsvError::TextAfterQuote { line: self.line });
+                    }
+                }
+                Some(c) => field.push(c),
+            }
+        }
+    }
+
+    fn quoted(&mut self, field: &mut String, start: usize) -> Result<(), CsvError> {
+        loop {
+            match self.chars.next() {
+                None => return Err(CsvError::UnterminatedQuote { line: start }),
+                Some('"') if self.chars.peek() == Some(&'"') => {
+                    self.chars.next();
+                    field.push('"');
+                }
+                Some('"') => return Ok(()),
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Vec<Record>, CsvError> {
+        records(text).collect()
+    }
+
+    fn record(line: usize, fields: &[&str]) -> Record {
+        Record {
+            line,
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn quoting_and_line_numbers() {
+        assert_eq!(
+            parse("a,b\r\n\"x,1\",\"say \"\"hi\"\"\"\n\n\"two\nlines\",\nlast").unwrap(),
+            vec![
+                record(1, &["a", "b"]),
+                record(2, &["x,1", "say \"hi\""]),
+                record(4, &["two\nlines", ""]),
+                record(6, &["last"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn written_records_read_back() {
+        let written: Vec<Vec<&str>> = vec![
+            vec!["a", "b,c", "say \"hi\""],
+            vec!["two\nlines", ""],
+            vec![""],
+            vec!["\r"],
+        ];
+        let text: Vec<String> = written.iter().map(|f| write_record(f)).collect();
+        assert_eq!(text[0], "a,\"b,c\",\"say \"\"hi\"\"\"");
+        let read: Vec<Vec<String>> = parse(&text.join("\n"))
+            .unwrap()
+            .into_iter()
+            .map(|record| record.fields)
+            .collect();
+        assert_eq!(read, written);
+    }
+
+    #[test]
+    fn malformed_quotes() {
+        assert_eq!(
+            parse("a\n\"open"),
+            Err(CsvError::UnterminatedQuote { line: 2 })
+        );
+        assert_eq!(parse("\"a\"b,c"), Err(CsvError::TextAfterQuote { line: 1 }));
+    }
+}
@@ -0,0 +1,46 @@
+//! The sample database behind `toyql demo`: a few cities and the servers
+//! in them, to try queries on without writing any data first.
+
+use crate::catalog::Catalog;
+use crate::error::ToyQlError;
+use crate::statements::{execute, parsing::parse_script};
+
+/// The statements that create and fill the sample tables.
+pub const SCRIPT: &str = include_str!("demo.sql");
+
+/// Queries worth trying on the sample tables.
+pub const QUERIES: [&str; 5] = [
+    "SELECT name, population FROM cities ORDER BY population DESC LIMIT 3",
+    "SELECT country, count(*), sum(population) FROM cities GROUP BY country",
+    "SELECT name, population / area AS density FROM cities WHERE capital",
+    "SELECT host, country FROM servers JOIN cities ON city = name WHERE status = \"up\"",
+    "EXPLAIN SELECT host FROM servers WHERE city IS NULL",
+];
+
+/// Creates the sample tables in `catalog`.
+pub fn load(catalog: &mut Catalog) -> Result<(), ToyQlError> {
+    for (_, statement) in parse_script(SCRIPT) {
+        execute(&statement?, catalog)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::{parsing::parse, Outcome};
+
+    #[test]
+    fn loads_and_every_suggested_query_runs() {
+        let mut catalog = Catalog::new();
+        load(&mut catalog).unwrap();
+        for query in QUERIES {
+            let outcome = execute(&parse(query).unwrap(), &mut catalog);
+            match outcome {
+                Ok(Outcome::Rows(result)) => assert!(!result.rows.is_empty(), "{query}"),
+                Ok(Outcome::Explained(_)) => {}
+                other => panic!("{query} gave {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+-- The sample database loaded by `toyql demo`.
+
+CREATE TABLE cities (
+    name TEXT,
+    country TEXT,
+    population INT,
+    area FLOAT,
+    capital BOOL
+);
+
+INSERT INTO cities VALUES
+    ("Tokyo", "Japan", 13_960_000, 2194.1, true),
+    ("Osaka", "Japan", 2_753_000, 225.2, false),
+    ("Paris", "France", 2_102_000, 105.4, true),
+    ("Lyon", "France", 522_000, 47.9, false),
+    ("Nairobi", "Kenya", 4_397_000, 696.0, true),
+    ("Mombasa", "Kenya", 1_208_000, 219.9, false),
+    ("Lima", "Peru", 10_004_000, 2672.3, true),
+    ("Cusco", "Peru", 428_000, 385.1, false);
+
+CREATE TABLE servers (
+    host TEXT,
+    address INET,
+    city TEXT,
+    status ENUM('up', 'down', 'draining')
+);
+
+INSERT INTO servers VALUES
+    ("edge-1", INET '10.0.0.1', "Tokyo", 'up'),
+    ("edge-2", INET '10.0.0.2', "Tokyo", 'draining'),
+    ("edge-3", INET '10.0.1.1', "Paris", 'up'),
+    ("edge-4", INET '10.0.2.1', "Nairobi", 'down'),
+    ("edge-5", INET '10.0.3.1', "Lima", 'up'),
+    ("backup", INET '192.168.0.10', NULL, 'up');
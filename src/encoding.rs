@@ -0,0 +1,205 @@
+//! Decoding of input files into UTF-8 text.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1: each byte is the code point of the same value.
+    Latin1,
+    /// UTF-16 with the byte order taken from a leading BOM, defaulting to
+    /// little-endian.
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// An `--encoding` name that is not one of the supported encodings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownEncoding(pub String);
+
+impl fmt::Display for UnknownEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown encoding `{}` (expected utf-8, latin-1, utf-16, utf-16le or utf-16be)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownEncoding {}
+
+impl FromStr for Encoding {
+    type Err = UnknownEncoding;
+
+    fn from_str(name: &str) -> Result<Encoding, UnknownEncoding> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "utf-16" | "utf16" => Ok(Encoding::Utf16),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            _ => Err(UnknownEncoding(name.to_string())),
+        }
+    }
+}
+
+/// Where and why the input is not valid in the chosen encoding. Offsets
+/// count bytes from the start of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    InvalidUtf8 {
+        offset: usize,
+    },
+    /// UTF-16 input must have an even number of bytes.
+    TruncatedUtf16 {
+        offset: usize,
+    },
+    UnpairedSurrogate {
+        offset: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidUtf8 { offset } => {
+                write!(f, "invalid UTF-8 sequence at byte {offset}")
+            }
+            DecodeError::TruncatedUtf16 { offset } => {
+                write!(f, "incomplete UTF-16 code unit at byte {offset}")
+            }
+            DecodeError::UnpairedSurrogate { offset } => {
+                write!(f, "unpaired UTF-16 surrogate at byte {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes `bytes`, dropping a leading byte order mark, as files saved on
+/// Windows often have.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<String, DecodeError> {
+    match encoding {
+        Encoding::Utf8 => {
+            let (base, bytes) = match bytes {
+                [0xEF, 0xBB, 0xBF, rest @ ..] => (3, rest),
+                _ => (0, bytes),
+            };
+            match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(text.to_string()),
+                Err(e) => Err(DecodeError::InvalidUtf8 {
+                    offset: base + e.valid_up_to(),
+                }),
+            }
+        }
+        Encoding::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+        Encoding::Utf16 => match bytes {
+            [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, 2, u16::from_be_bytes),
+            [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, 2, u16::from_le_bytes),
+            _ => decode_utf16(bytes, 0, u16::from_le_bytes),
+        },
+        Encoding::Utf16Le => decode_utf16(bytes, 0, u16::from_le_bytes),
+        Encoding::Utf16Be => decode_utf16(bytes, 0, u16::from_be_bytes),
+    }
+}
+
+/// `base` is the offset of `bytes` within the original input, so errors
+/// point past any BOM that was stripped.
+fn decode_utf16(
+    bytes: &[u8],
+    base: usize,
+    unit: fn([u8; 2]) -> u16,
+) -> Result<String, DecodeError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(DecodeError::TruncatedUtf16 {
+            offset: base + bytes.len() - 1,
+        });
+    }
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut offset = base;
+    for decoded in char::decode_utf16(units) {
+        match decoded {
+            Ok(c) => {
+                text.push(c);
+                offset += c.len_utf16() * 2;
+            }
+            Err(_) => return Err(DecodeError::UnpairedSurrogate { offset }),
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin1_maps_bytes_to_code_points() {
+        assert_eq!(decode(b"caf\xe9", Encoding::Latin1).unwrap(), "café");
+    }
+
+    #[test]
+    fn utf8_errors_report_offset() {
+        assert_eq!(
+            decode(b"ok\xff", Encoding::Utf8),
+            Err(DecodeError::InvalidUtf8 { offset: 2 })
+        );
+        assert_eq!(
+            decode(b"\xef\xbb\xbfok\xff", Encoding::Utf8),
+            Err(DecodeError::InvalidUtf8 { offset: 5 })
+        );
+    }
+
+    #[test]
+    fn drops_a_utf8_bom() {
+        assert_eq!(
+            decode(b"\xef\xbb\xbfSELECT 1", Encoding::Utf8).unwrap(),
+            "SELECT 1"
+        );
+        assert_eq!(
+            decode(b"a\xef\xbb\xbf", Encoding::Utf8).unwrap(),
+            "a\u{feff}"
+        );
+    }
+
+    #[test]
+    fn utf16_byte_orders() {
+        assert_eq!(decode(b"h\0i\0", Encoding::Utf16Le).unwrap(), "hi");
+        assert_eq!(decode(b"\0h\0i", Encoding::Utf16Be).unwrap(), "hi");
+        assert_eq!(decode(b"\xfe\xff\0h", Encoding::Utf16).unwrap(), "h");
+        assert_eq!(decode(b"\xff\xfeh\0", Encoding::Utf16).unwrap(), "h");
+        // U+1F600 as a surrogate pair.
+        assert_eq!(
+            decode(b"\x3d\xd8\x00\xde", Encoding::Utf16Le).unwrap(),
+            "\u{1F600}"
+        );
+    }
+
+    #[test]
+    fn utf16_errors_report_offset() {
+        assert_eq!(
+            decode(b"\xff\xfea\0b", Encoding::Utf16),
+            Err(DecodeError::TruncatedUtf16 { offset: 4 })
+        );
+        assert_eq!(
+            decode(b"a\0\x3d\xd8b\0", Encoding::Utf16Le),
+            Err(DecodeError::UnpairedSurrogate { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!("ISO-8859-1".parse(), Ok(Encoding::Latin1));
+        assert_eq!("utf_16be".parse(), Ok(Encoding::Utf16Be));
+        assert_eq!(
+            "ebcdic".parse::<Encoding>(),
+            Err(UnknownEncoding("ebcdic".to_string()))
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! Error types shared across the crate.
+
+use std::fmt;
+
+use nom::error::{ContextError, ErrorKind, FromExternalError};
+use nom::IResult;
+
+use crate::csv::CsvError;
+use crate::encoding::{DecodeError, UnknownEncoding};
+use crate::import::ImportError;
+use crate::repro::Violation;
+use crate::statements::ExecError;
+use crate::template::TemplateError;
+
+/// Result type of every grammar in the crate.
+pub type PResult<'a, T> = IResult<&'a str, T, GrammarError<'a>>;
+
+/// The nom-level error used while parsing: where the furthest failure
+/// happened and what would have been accepted there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarError<'a> {
+    /// The unparsed input at the point of failure.
+    pub input: &'a str,
+    pub expected: Vec<String>,
+    /// Why the input was refused, when that is not down to what was
+    /// expected there, as for an expression nested too deeply.
+    pub reason: Option<&'static str>,
+}
+
+impl<'a> GrammarError<'a> {
+    pub fn expected(input: &'a str, what: impl Into<String>) -> GrammarError<'a> {
+        GrammarError {
+            input,
+            expected: vec![what.into()],
+            reason: None,
+        }
+    }
+
+    /// A failure at `input` for `reason` rather than for what was there.
+    pub fn refused(input: &'a str, reason: &'static str) -> GrammarError<'a> {
+        GrammarError {
+            input,
+            expected: Vec::new(),
+            reason: Some(reason),
+        }
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for GrammarError<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        GrammarError {
+            input,
+            expected: Vec::new(),
+            reason: None,
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a str, c: char) -> Self {
+        GrammarError::expected(input, format!("`{c}`"))
+    }
+
+    /// Keeps whichever alternative got further; alternatives that failed at
+    /// the same place pool what they expected.
+    fn or(mut self, other: Self) -> Self {
+        match self.input.len().cmp(&other.input.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal => {
+                for what in other.expected {
+                    if !self.expected.contains(&what) {
+                        self.expected.push(what);
+                    }
+                }
+                self.reason = self.reason.or(other.reason);
+                self
+            }
+        }
+    }
+}
+
+impl<'a> ContextError<&'a str> for GrammarError<'a> {
+    /// A label describes the construct as a whole, so it only replaces the
+    /// inner expectations when nothing of the construct was consumed.
+    fn add_context(input: &'a str, ctx: &'static str, other: Self) -> Self {
+        if other.input.len() == input.len() && other.reason.is_none() {
+            GrammarError::expected(input, ctx)
+        } else {
+            other
+        }
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for GrammarError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: E) -> Self {
+        <Self as nom::error::ParseError<&'a str>>::from_error_kind(input, kind)
+    }
+}
+
+/// The 1-based line and column, counted in characters, of a byte offset
+/// into `source`.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// A syntax error located in the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the source.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, counted in characters.
+    pub column: usize,
+    /// What would have been accepted, e.g. `expression` or `` `)` ``.
+    pub expected: Vec<String>,
+    /// The text found instead, e.g. `` `,` `` or `end of input`.
+    pub found: String,
+    /// The full source line containing the error.
+    pub source_line: String,
+    /// Why the input was refused, if not for what was expected; it then
+    /// stands as the message instead.
+    pub reason: Option<&'static str>,
+}
+
+impl ParseError {
+    /// Locates `rest`, a suffix of `source`, and describes it.
+    pub fn at(source: &str, rest: &str, expected: Vec<String>) -> ParseError {
+        let offset = source.len() - rest.len();
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let (line, column) = line_column(source, offset);
+        ParseError {
+            offset,
+            line,
+            column,
+            expected,
+            found: describe(rest),
+            source_line: source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            reason: None,
+        }
+    }
+
+    /// Converts the error from a failed grammar over `source`.
+    pub fn from_nom(source: &str, error: nom::Err<GrammarError<'_>>) -> ParseError {
+        match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => ParseError {
+                reason: e.reason,
+                ..ParseError::at(source, e.input, e.expected)
+            },
+            nom::Err::Incomplete(_) => ParseError::at(source, "", Vec::new()),
+        }
+    }
+
+    /// The error, found in the part of `source` from byte `start` on,
+    /// located in the whole of `source`.
+    pub fn relocated(self, source: &str, start: usize) -> ParseError {
+        ParseError {
+            reason: self.reason,
+            ..ParseError::at(source, &source[start + self.offset..], self.expected)
+        }
+    }
+
+    /// Runs `grammar` over all of `source`: a parse that stops early is an
+    /// error at the first unconsumed character.
+    pub fn parse_all<'a, T>(
+        source: &'a str,
+        mut grammar: impl FnMut(&'a str) -> PResult<'a, T>,
+    ) -> Result<T, ParseError> {
+        match grammar(source) {
+            Ok(("", value)) => Ok(value),
+            Ok((rest, _)) => Err(ParseError::at(
+                source,
+                rest,
+                vec!["end of input".to_string()],
+            )),
+            Err(e) => Err(ParseError::from_nom(source, e)),
+        }
+    }
+
+    /// A multi-line diagnostic quoting the offending line with a caret under
+    /// the error position.
+    pub fn render(&self) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let indent: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!(
+            "error: {}\n{gutter}--> line {}, column {}\n{gutter} |\n{number} | {}\n{gutter} | {indent}^",
+            self.message(),
+            self.line,
+            self.column,
+            self.source_line,
+        )
+    }
+
+    /// What went wrong, without the location.
+    pub fn message(&self) -> String {
+        if let Some(reason) = self.reason {
+            return reason.to_string();
+        }
+        match self.expected.as_slice() {
+            [] => format!("unexpected {}", self.found),
+            [only] => format!("expected {only}, found {}", self.found),
+            [init @ .., last] => {
+                format!(
+                    "expected {} or {last}, found {}",
+                    init.join(", "),
+                    self.found
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line,
+            self.column,
+            self.message()
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Any failure a toyql run can report. Modules keep their own error types;
+/// this gathers them, together with the file they came from where there is
+/// one.
+#[derive(Debug)]
+pub enum ToyQlError {
+    /// Bad command-line arguments.
+    Usage(String),
+    UnknownEncoding(UnknownEncoding),
+    Parse(ParseError),
+    Exec(ExecError),
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    Decode {
+        path: String,
+        source: DecodeError,
+    },
+    Csv {
+        path: String,
+        source: CsvError,
+    },
+    Import {
+        path: String,
+        source: ImportError,
+    },
+    /// `toyql repro` found a broken invariant.
+    Repro {
+        path: String,
+        source: Violation,
+    },
+    Template {
+        path: String,
+        source: TemplateError,
+    },
+}
+
+impl fmt::Display for ToyQlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToyQlError::Usage(message) => f.write_str(message),
+            ToyQlError::UnknownEncoding(e) => e.fmt(f),
+            ToyQlError::Parse(e) => e.fmt(f),
+            ToyQlError::Exec(e) => write!(f, "execution error: {e}"),
+            ToyQlError::Io { path, source } => write!(f, "cannot read {path}: {source}"),
+            ToyQlError::Decode { path, source } => write!(f, "{path}: {source}"),
+            ToyQlError::Csv { path, source } => write!(f, "{path}: {source}"),
+            ToyQlError::Import { path, source } => write!(f, "{path}: {source}"),
+            ToyQlError::Repro { path, source } => write!(f, "{path}: {source}"),
+            ToyQlError::Template { path, source } => write!(f, "{path}: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for ToyQlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ToyQlError::Usage(_) => None,
+            ToyQlError::UnknownEncoding(e) => Some(e),
+            ToyQlError::Parse(e) => Some(e),
+            ToyQlError::Exec(e) => Some(e),
+            ToyQlError::Io { source, .. } => Some(source),
+            ToyQlError::Decode { source, .. } => Some(source),
+            ToyQlError::Csv { source, .. } => Some(source),
+            ToyQlError::Import { source, .. } => Some(source),
+            ToyQlError::Repro { source, .. } => Some(source),
+            ToyQlError::Template { source, .. } => Some(source),
+        }
+    }
+}
+
+impl From<UnknownEncoding> for ToyQlError {
+    fn from(e: UnknownEncoding) -> ToyQlError {
+        ToyQlError::UnknownEncoding(e)
+    }
+}
+
+impl From<ParseError> for ToyQlError {
+    fn from(e: ParseError) -> ToyQlError {
+        ToyQlError::Parse(e)
+    }
+}
+
+impl From<ExecError> for ToyQlError {
+    fn from(e: ExecError) -> ToyQlError {
+        ToyQlError::Exec(e)
+    }
+}
+
+/// Names the token at the start of `rest`: a whole word, or one character.
+fn describe(rest: &str) -> String {
+    let Some(first) = rest.chars().next() else {
+        return "end of input".to_string();
+    };
+    let word_len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    if first.is_alphanumeric() || first == '_' {
+        format!("`{}`", &rest[..word_len])
+    } else {
+        format!("`{first}`")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_and_renders() {
+        let source = "SELECT 1\nFROM\t(x";
+        let error = ParseError::at(source, "(x", vec!["identifier".to_string()]);
+        assert_eq!((error.offset, error.line, error.column), (14, 2, 6));
+        assert_eq!(error.found, "`(`");
+        assert_eq!(
+            error.render(),
+            "error: expected identifier, found `(`\n --> line 2, column 6\n  |\n2 | FROM\t(x\n  |     \t^"
+        );
+    }
+
+    #[test]
+    fn lists_alternatives() {
+        let error = ParseError::at(
+            "x",
+            "",
+            vec!["`,`".to_string(), "`)`".to_string(), "`AND`".to_string()],
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 2: expected `,`, `)` or `AND`, found end of input"
+        );
+    }
+
+    #[test]
+    fn wraps_module_errors() {
+        use std::error::Error;
+
+        let error = ToyQlError::from(ExecError::WildcardWithoutTable);
+        assert_eq!(
+            error.to_string(),
+            "execution error: `*` needs a FROM clause"
+        );
+        assert!(error.source().is_some());
+
+        let error = ToyQlError::Csv {
+            path: "a.csv".to_string(),
+            source: CsvError::UnterminatedQuote { line: 3 },
+        };
+        assert!(error.to_string().starts_with("a.csv: "));
+    }
+}
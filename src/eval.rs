@@ -0,0 +1,698 @@
+//! Evaluation of expression trees to literal values.
+//!
+//! Arithmetic rules:
+//! - int op int stays int; overflow and division by zero are errors rather
+//!   than wrapping or panicking.
+//! - int op float promotes the int to float and follows IEEE 754, so
+//!   `1 / 0.0` is `inf`.
+//! - `||` concatenates two strings; it does not coerce numbers.
+//! - Comparisons accept two numbers (compared exactly, even across int and
+//!   float), two strings (compared by code point), two bools (`false`
+//!   first) or two inet addresses (IPv4 first, then by address and prefix
+//!   length), and yield a bool. Any comparison involving NaN is false except
+//!   `<>`.
+//! - `<`, `<=`, `>` and `>=` with an ENUM column on either side compare
+//!   labels by their position in the column's list, not as text; the other
+//!   operand must then be one of its labels.
+//! - `a << b` takes two inet addresses and is true when `b` is a larger
+//!   network that contains `a`.
+//! - NULL propagates: any arithmetic, concatenation or comparison with a NULL
+//!   operand is NULL, whatever the other operand's type.
+//! - `x IS NULL` and `x IS NOT NULL` are always true or false, never NULL.
+//! - `x IN (a, b, ...)` is true if `x = a`, `x = b`, ... holds for any item,
+//!   NULL if none does but some comparison was NULL, and false otherwise;
+//!   the items must be comparable with `x`.
+//! - `s LIKE p` takes two strings and matches `s` against the pattern `p`,
+//!   where `%` matches any run of characters and `_` any single one.
+//! - `NOT IN` and `NOT LIKE` negate the result, keeping NULL as NULL.
+//! - `AND`, `OR` and `NOT` take bools and follow three-valued logic, so
+//!   `false AND NULL` is `false` and `true OR NULL` is `true`.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::functions;
+use crate::literals::{InvalidInet, LiteralValue};
+
+/// Supplies values for identifiers during evaluation.
+pub trait Bindings {
+    fn lookup(&self, name: &str) -> Option<LiteralValue>;
+
+    /// The labels of `name` when it is an ENUM column, whose values order
+    /// by their position in the list.
+    fn labels(&self, _name: &str) -> Option<&[String]> {
+        None
+    }
+}
+
+/// No identifiers are bound.
+impl Bindings for () {
+    fn lookup(&self, _name: &str) -> Option<LiteralValue> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    UnknownIdentifier(String),
+    UnknownFunction(String),
+    UnaryType {
+        op: UnaryOp,
+        operand: &'static str,
+    },
+    BinaryType {
+        op: BinaryOp,
+        left: &'static str,
+        right: &'static str,
+    },
+    /// `IN` or `LIKE` applied to operands of the wrong types.
+    PredicateType {
+        predicate: &'static str,
+        left: &'static str,
+        right: &'static str,
+    },
+    Overflow(BinaryOp),
+    NegationOverflow,
+    DivisionByZero,
+    /// A function was called with the wrong number of arguments.
+    Arity {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A function argument, counted from 1, had the wrong type.
+    ArgumentType {
+        function: String,
+        position: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+    InvalidInet(InvalidInet),
+    /// A latitude outside `[-90, 90]` or a longitude outside `[-180, 180]`.
+    InvalidPoint {
+        lat: f64,
+        lon: f64,
+    },
+    InvalidRegex {
+        pattern: String,
+        message: String,
+    },
+    NoSuchGroup {
+        pattern: String,
+        group: i64,
+    },
+    /// An aggregate such as `COUNT` was called outside a select list or
+    /// ORDER BY, or inside another aggregate.
+    MisplacedAggregate(String),
+    /// A `$n` placeholder was evaluated without a value bound to it.
+    UnboundParameter(usize),
+    /// An `@name` variable was used that has not been SET.
+    UnsetVariable(String),
+    /// A string ordered against an ENUM column is not one of its labels.
+    NotALabel(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::UnknownIdentifier(name) => write!(f, "unknown identifier `{name}`"),
+            EvalError::UnknownFunction(name) => write!(f, "unknown function `{name}`"),
+            EvalError::UnaryType { op, operand } => {
+                write!(f, "cannot apply unary `{}` to {operand}", op.symbol())
+            }
+            EvalError::BinaryType { op, left, right } => {
+                write!(f, "cannot apply `{}` to {left} and {right}", op.symbol())
+            }
+            EvalError::PredicateType {
+                predicate,
+                left,
+                right,
+            } => write!(f, "cannot apply `{predicate}` to {left} and {right}"),
+            EvalError::Overflow(op) => write!(f, "integer overflow in `{}`", op.symbol()),
+            EvalError::NegationOverflow => write!(f, "integer overflow in negation"),
+            EvalError::DivisionByZero => write!(f, "integer division by zero"),
+            EvalError::Arity {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{function}` takes {expected} arguments but {found} were given"
+            ),
+            EvalError::ArgumentType {
+                function,
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "argument {position} of `{function}` must be {expected}, found {found}"
+            ),
+            EvalError::InvalidInet(e) => e.fmt(f),
+            EvalError::InvalidPoint { lat, lon } => write!(
+                f,
+                "invalid point ({lat}, {lon}): latitude must be within [-90, 90] \
+                 and longitude within [-180, 180]"
+            ),
+            EvalError::InvalidRegex { pattern, message } => {
+                write!(f, "invalid regular expression `{pattern}`: {message}")
+            }
+            EvalError::NoSuchGroup { pattern, group } => {
+                write!(f, "regular expression `{pattern}` has no group {group}")
+            }
+            EvalError::MisplacedAggregate(name) => {
+                write!(f, "aggregate `{name}` is not allowed here")
+            }
+            EvalError::UnboundParameter(n) => write!(f, "parameter ${n} has no value"),
+            EvalError::UnsetVariable(name) => write!(f, "variable @{name} is not set"),
+            EvalError::NotALabel(label) => {
+                write!(
+                    f,
+                    "'{label}' is not a label of the ENUM it is compared with"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+pub fn evaluate<B: Bindings + ?Sized>(
+    expr: &Expr,
+    bindings: &B,
+) -> Result<LiteralValue, EvalError> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Identifier(name) => bindings
+            .lookup(name)
+            .ok_or_else(|| EvalError::UnknownIdentifier(name.clone())),
+        Expr::Parameter(n) => Err(EvalError::UnboundParameter(*n)),
+        Expr::Variable(name) => Err(EvalError::UnsetVariable(name.clone())),
+        Expr::Unary { op, operand } => unary(*op, evaluate(operand, bindings)?),
+        Expr::Binary { op, left, right } => {
+            let (left_value, right_value) = (evaluate(left, bindings)?, evaluate(right, bindings)?);
+            let labels = match op {
+                BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq => {
+                    labels(left, bindings).or_else(|| labels(right, bindings))
+                }
+                _ => None,
+            };
+            match labels {
+                Some(labels) if is_text(&left_value) && is_text(&right_value) => binary(
+                    *op,
+                    position(labels, left_value)?,
+                    position(labels, right_value)?,
+                ),
+                _ => binary(*op, left_value, right_value),
+            }
+        }
+        Expr::Call { name, args } => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate(arg, bindings))
+                .collect::<Result<Vec<_>, _>>()?;
+            functions::call(name, args)
+        }
+        Expr::IsNull { operand, negated } => {
+            let is_null = evaluate(operand, bindings)? == LiteralValue::Null;
+            Ok(LiteralValue::Bool(is_null != *negated))
+        }
+        Expr::InList {
+            operand,
+            list,
+            negated,
+        } => {
+            let operand = evaluate(operand, bindings)?;
+            let list = list
+                .iter()
+                .map(|item| evaluate(item, bindings))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(negate(in_list(&operand, &list)?, *negated))
+        }
+        Expr::Like {
+            operand,
+            pattern,
+            negated,
+        } => {
+            let matched = match (evaluate(operand, bindings)?, evaluate(pattern, bindings)?) {
+                (LiteralValue::Null, _) | (_, LiteralValue::Null) => LiteralValue::Null,
+                (LiteralValue::String(text), LiteralValue::String(pattern)) => {
+                    LiteralValue::Bool(like(&text, &pattern))
+                }
+                (left, right) => {
+                    return Err(EvalError::PredicateType {
+                        predicate: "LIKE",
+                        left: left.type_name(),
+                        right: right.type_name(),
+                    })
+                }
+            };
+            Ok(negate(matched, *negated))
+        }
+    }
+}
+
+/// Evaluates an ORDER BY key: an ENUM column gives its label's position,
+/// so that it sorts in the order the labels were declared.
+pub fn evaluate_key<B: Bindings + ?Sized>(
+    expr: &Expr,
+    bindings: &B,
+) -> Result<LiteralValue, EvalError> {
+    let value = evaluate(expr, bindings)?;
+    match labels(expr, bindings) {
+        Some(labels) => position(labels, value),
+        None => Ok(value),
+    }
+}
+
+fn labels<'a, B: Bindings + ?Sized>(expr: &Expr, bindings: &'a B) -> Option<&'a [String]> {
+    match expr {
+        Expr::Identifier(name) => bindings.labels(name),
+        _ => None,
+    }
+}
+
+fn is_text(value: &LiteralValue) -> bool {
+    matches!(value, LiteralValue::String(_) | LiteralValue::Null)
+}
+
+/// A label's position among `labels`; NULL stays NULL.
+fn position(labels: &[String], value: LiteralValue) -> Result<LiteralValue, EvalError> {
+    match value {
+        LiteralValue::String(label) => match labels.iter().position(|l| *l == label) {
+            Some(position) => Ok(LiteralValue::Int(position as i64)),
+            None => Err(EvalError::NotALabel(label)),
+        },
+        value => Ok(value),
+    }
+}
+
+/// `NOT` for a predicate's result, which is a bool or NULL.
+fn negate(value: LiteralValue, negated: bool) -> LiteralValue {
+    match value {
+        LiteralValue::Bool(v) => LiteralValue::Bool(v != negated),
+        other => other,
+    }
+}
+
+fn in_list(operand: &LiteralValue, list: &[LiteralValue]) -> Result<LiteralValue, EvalError> {
+    if *operand == LiteralValue::Null {
+        return Ok(LiteralValue::Null);
+    }
+    let mut saw_null = false;
+    for item in list {
+        if *item == LiteralValue::Null {
+            saw_null = true;
+            continue;
+        }
+        let ordering = compare(operand, item).ok_or_else(|| EvalError::PredicateType {
+            predicate: "IN",
+            left: operand.type_name(),
+            right: item.type_name(),
+        })?;
+        if ordering == Some(Ordering::Equal) {
+            return Ok(LiteralValue::Bool(true));
+        }
+    }
+    Ok(if saw_null {
+        LiteralValue::Null
+    } else {
+        LiteralValue::Bool(false)
+    })
+}
+
+/// Matches `text` against a LIKE pattern.
+fn like(text: &str, pattern: &str) -> bool {
+    wildcard(text, pattern, '%', '_')
+}
+
+/// Matches `text` against a pattern in which `any` stands for any run of
+/// characters and `one` for a single one, character by character. On a
+/// mismatch after an `any`, it is retried one character further on.
+pub(crate) fn wildcard(text: &str, pattern: &str, any: char, one: char) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    // Where the last `any` was, and where in the text it was last tried.
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(&c) if c == any => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == one || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, tried)) => {
+                    p = star + 1;
+                    t = tried + 1;
+                    backtrack = Some((star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == any)
+}
+
+fn unary(op: UnaryOp, operand: LiteralValue) -> Result<LiteralValue, EvalError> {
+    match (op, operand) {
+        (_, LiteralValue::Null) => Ok(LiteralValue::Null),
+        (UnaryOp::Not, LiteralValue::Bool(v)) => Ok(LiteralValue::Bool(!v)),
+        (UnaryOp::Neg, LiteralValue::Int(v)) => v
+            .checked_neg()
+            .map(LiteralValue::Int)
+            .ok_or(EvalError::NegationOverflow),
+        (UnaryOp::Neg, LiteralValue::Float(v)) => Ok(LiteralValue::Float(-v)),
+        (UnaryOp::Plus, value @ (LiteralValue::Int(_) | LiteralValue::Float(_))) => Ok(value),
+        (op, operand) => Err(EvalError::UnaryType {
+            op,
+            operand: operand.type_name(),
+        }),
+    }
+}
+
+/// Applies `op` to two evaluated operands.
+pub fn binary(
+    op: BinaryOp,
+    left: LiteralValue,
+    right: LiteralValue,
+) -> Result<LiteralValue, EvalError> {
+    use LiteralValue::{Float, Int};
+
+    let type_error = |left: &LiteralValue, right: &LiteralValue| EvalError::BinaryType {
+        op,
+        left: left.type_name(),
+        right: right.type_name(),
+    };
+
+    if let BinaryOp::And | BinaryOp::Or = op {
+        return logical(op, &left, &right).ok_or_else(|| type_error(&left, &right));
+    }
+    if left == LiteralValue::Null || right == LiteralValue::Null {
+        return Ok(LiteralValue::Null);
+    }
+
+    match op {
+        BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            match (&left, &right) {
+                (Int(a), Int(b)) => int_arithmetic(op, *a, *b).map(Int),
+                (Int(a), Float(b)) => Ok(Float(float_arithmetic(op, *a as f64, *b))),
+                (Float(a), Int(b)) => Ok(Float(float_arithmetic(op, *a, *b as f64))),
+                (Float(a), Float(b)) => Ok(Float(float_arithmetic(op, *a, *b))),
+                _ => Err(type_error(&left, &right)),
+            }
+        }
+        BinaryOp::Concat => match (&left, &right) {
+            (LiteralValue::String(a), LiteralValue::String(b)) => {
+                Ok(LiteralValue::String(format!("{a}{b}")))
+            }
+            _ => Err(type_error(&left, &right)),
+        },
+        BinaryOp::ContainedBy => match (&left, &right) {
+            (LiteralValue::Inet(a), LiteralValue::Inet(b)) => {
+                Ok(LiteralValue::Bool(b.strictly_contains(*a)))
+            }
+            _ => Err(type_error(&left, &right)),
+        },
+        BinaryOp::Eq
+        | BinaryOp::NotEq
+        | BinaryOp::Lt
+        | BinaryOp::LtEq
+        | BinaryOp::Gt
+        | BinaryOp::GtEq => {
+            let ordering = compare(&left, &right).ok_or_else(|| type_error(&left, &right))?;
+            let holds = match (op, ordering) {
+                (BinaryOp::NotEq, None) => true,
+                (_, None) => false,
+                (BinaryOp::Eq, Some(o)) => o == Ordering::Equal,
+                (BinaryOp::NotEq, Some(o)) => o != Ordering::Equal,
+                (BinaryOp::Lt, Some(o)) => o == Ordering::Less,
+                (BinaryOp::LtEq, Some(o)) => o != Ordering::Greater,
+                (BinaryOp::Gt, Some(o)) => o == Ordering::Greater,
+                (BinaryOp::GtEq, Some(o)) => o != Ordering::Less,
+                _ => unreachable!("not a comparison operator"),
+            };
+            Ok(LiteralValue::Bool(holds))
+        }
+    }
+}
+
+/// Three-valued `AND`/`OR`, with `None` for non-boolean operands.
+fn logical(op: BinaryOp, left: &LiteralValue, right: &LiteralValue) -> Option<LiteralValue> {
+    let truth = |value: &LiteralValue| match value {
+        LiteralValue::Bool(v) => Some(Some(*v)),
+        LiteralValue::Null => Some(None),
+        _ => None,
+    };
+    let (left, right) = (truth(left)?, truth(right)?);
+    // The value that decides the result on its own: false for AND, true for OR.
+    let dominant = op == BinaryOp::Or;
+    let result = if left == Some(dominant) || right == Some(dominant) {
+        Some(dominant)
+    } else if left.is_none() || right.is_none() {
+        None
+    } else {
+        Some(!dominant)
+    };
+    Some(result.map_or(LiteralValue::Null, LiteralValue::Bool))
+}
+
+fn int_arithmetic(op: BinaryOp, a: i64, b: i64) -> Result<i64, EvalError> {
+    if matches!(op, BinaryOp::Div | BinaryOp::Mod) && b == 0 {
+        return Err(EvalError::DivisionByZero);
+    }
+    let result = match op {
+        BinaryOp::Add => a.checked_add(b),
+        BinaryOp::Sub => a.checked_sub(b),
+        BinaryOp::Mul => a.checked_mul(b),
+        BinaryOp::Div => a.checked_div(b),
+        BinaryOp::Mod => a.checked_rem(b),
+        _ => unreachable!("not an arithmetic operator"),
+    };
+    result.ok_or(EvalError::Overflow(op))
+}
+
+fn float_arithmetic(op: BinaryOp, a: f64, b: f64) -> f64 {
+    match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a / b,
+        BinaryOp::Mod => a % b,
+        _ => unreachable!("not an arithmetic operator"),
+    }
+}
+
+/// `None` in the inner option means the values are comparable types but
+/// unordered (a NaN is involved); `None` outside means incomparable types.
+fn compare(left: &LiteralValue, right: &LiteralValue) -> Option<Option<Ordering>> {
+    left.comparable_with(right).then(|| left.compare(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parsing::parse;
+    use LiteralValue::{Bool, Float, Int, Null};
+
+    fn eval(text: &str) -> Result<LiteralValue, EvalError> {
+        evaluate(&parse(text).unwrap(), &())
+    }
+
+    fn string(s: &str) -> LiteralValue {
+        LiteralValue::String(s.to_string())
+    }
+
+    #[test]
+    fn coercion_table() {
+        let cases: Vec<(&str, LiteralValue)> = vec![
+            ("1 + 2", Int(3)),
+            ("7 / 2", Int(3)),
+            ("-7 / 2", Int(-3)),
+            ("-7 % 3", Int(-1)),
+            ("1 + 2.5", Float(3.5)),
+            ("2.5 * 2", Float(5.0)),
+            ("7 / 2.0", Float(3.5)),
+            ("1 / 0.0", Float(f64::INFINITY)),
+            ("-1 / 0.0", Float(f64::NEG_INFINITY)),
+            ("5.5 % 2", Float(1.5)),
+            ("-(3)", Int(-3)),
+            ("-(2.5)", Float(-2.5)),
+            ("'a' || \"b\"", string("ab")),
+            ("1 = 1.0", Bool(true)),
+            ("1 < 1.5", Bool(true)),
+            ("2 > 1.5", Bool(true)),
+            ("9223372036854775807 < 9223372036854775808.0", Bool(true)),
+            ("9007199254740993 > 9007199254740992.0", Bool(true)),
+            ("NaN = NaN", Bool(false)),
+            ("NaN < 1", Bool(false)),
+            ("NaN <> NaN", Bool(true)),
+            ("inf > 9223372036854775807", Bool(true)),
+            ("'abc' < 'abd'", Bool(true)),
+            ("'b' >= 'a'", Bool(true)),
+            ("'a' = 'a'", Bool(true)),
+            ("true = true", Bool(true)),
+            ("false < true", Bool(true)),
+            ("1 + null", Null),
+            ("null || 'a'", Null),
+            ("null = null", Null),
+            ("null <> 1", Null),
+            ("'a' < null", Null),
+            ("-(null)", Null),
+            ("not null", Null),
+            ("not false", Bool(true)),
+            ("true and null", Null),
+            ("false and null", Bool(false)),
+            ("null and false", Bool(false)),
+            ("true or null", Bool(true)),
+            ("null or false", Null),
+            ("null or null", Null),
+            ("1 < 2 and 'a' < 'b'", Bool(true)),
+            ("null is null", Bool(true)),
+            ("1 is null", Bool(false)),
+            ("1 + null is not null", Bool(false)),
+            ("2 in (1, 2.0)", Bool(true)),
+            ("3 in (1, 2)", Bool(false)),
+            ("3 in (1, null)", Null),
+            ("1 in (1, null)", Bool(true)),
+            ("null in (1)", Null),
+            ("3 not in (1, 2)", Bool(true)),
+            ("3 not in (1, null)", Null),
+            ("NaN in (NaN)", Bool(false)),
+            ("'abc' like 'a%'", Bool(true)),
+            ("'abc' like '_b_'", Bool(true)),
+            ("'abc' like 'b%'", Bool(false)),
+            ("'abc' like '%'", Bool(true)),
+            ("'' like '%%'", Bool(true)),
+            ("'aXbXc' like '%b%c'", Bool(true)),
+            ("'mississippi' like '%iss%ppi'", Bool(true)),
+            ("'mississippi' like '%iss%ppix'", Bool(false)),
+            ("'ünï' like '_n_'", Bool(true)),
+            ("'abc' not like 'a%'", Bool(false)),
+            ("null like 'a%'", Null),
+        ];
+        for (text, expected) in cases {
+            let actual = eval(text).unwrap_or_else(|e| panic!("{text}: {e}"));
+            assert!(actual.identical(&expected), "{text} gave {actual:?}");
+        }
+        assert!(matches!(eval("0.0 / 0.0"), Ok(Float(v)) if v.is_nan()));
+    }
+
+    #[test]
+    fn error_table() {
+        let cases: Vec<(&str, EvalError)> = vec![
+            (
+                "9223372036854775807 + 1",
+                EvalError::Overflow(BinaryOp::Add),
+            ),
+            (
+                "-9223372036854775808 - 1",
+                EvalError::Overflow(BinaryOp::Sub),
+            ),
+            (
+                "4611686018427387904 * 2",
+                EvalError::Overflow(BinaryOp::Mul),
+            ),
+            (
+                "-9223372036854775808 / -1",
+                EvalError::Overflow(BinaryOp::Div),
+            ),
+            ("-(-9223372036854775808)", EvalError::NegationOverflow),
+            ("1 / 0", EvalError::DivisionByZero),
+            ("1 % 0", EvalError::DivisionByZero),
+            (
+                "1 + 'a'",
+                EvalError::BinaryType {
+                    op: BinaryOp::Add,
+                    left: "int",
+                    right: "string",
+                },
+            ),
+            (
+                "'a' || 1",
+                EvalError::BinaryType {
+                    op: BinaryOp::Concat,
+                    left: "string",
+                    right: "int",
+                },
+            ),
+            (
+                "1.5 < 'a'",
+                EvalError::BinaryType {
+                    op: BinaryOp::Lt,
+                    left: "float",
+                    right: "string",
+                },
+            ),
+            (
+                "-'a'",
+                EvalError::UnaryType {
+                    op: UnaryOp::Neg,
+                    operand: "string",
+                },
+            ),
+            (
+                "1 and true",
+                EvalError::BinaryType {
+                    op: BinaryOp::And,
+                    left: "int",
+                    right: "bool",
+                },
+            ),
+            (
+                "not 'a'",
+                EvalError::UnaryType {
+                    op: UnaryOp::Not,
+                    operand: "string",
+                },
+            ),
+            (
+                "true + 1",
+                EvalError::BinaryType {
+                    op: BinaryOp::Add,
+                    left: "bool",
+                    right: "int",
+                },
+            ),
+            (
+                "1 in ('a')",
+                EvalError::PredicateType {
+                    predicate: "IN",
+                    left: "int",
+                    right: "string",
+                },
+            ),
+            (
+                "1 like '1'",
+                EvalError::PredicateType {
+                    predicate: "LIKE",
+                    left: "int",
+                    right: "string",
+                },
+            ),
+            ("x + 1", EvalError::UnknownIdentifier("x".to_string())),
+            ("f(1)", EvalError::UnknownFunction("f".to_string())),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(eval(text), Err(expected), "{text}");
+        }
+    }
+
+    #[test]
+    fn identifiers_come_from_bindings() {
+        struct One;
+        impl Bindings for One {
+            fn lookup(&self, name: &str) -> Option<LiteralValue> {
+                (name == "one").then_some(Int(1))
+            }
+        }
+        let expr = parse("one + one").unwrap();
+        assert_eq!(evaluate(&expr, &One), Ok(Int(2)));
+    }
+}
@@ -0,0 +1,499 @@
+//! External tables: `CREATE EXTERNAL TABLE <name> [(<column> <type>, ...)]
+//! USING csv LOCATION '<path>'` names CSV files that stay on disk and are
+//! read afresh by every scan.
+//!
+//! The location is a file, a directory, which stands for the `.csv` files
+//! in it, or a path whose last part is a pattern with `*` for any run of
+//! characters and `?` for one, which stands for the files it matches. The
+//! files are listed again on every scan, so files added later are read;
+//! their rows come in order of file name. A table over a directory or a
+//! pattern has a last column `_filename`, the path each row was read from.
+//! It is hidden: `*` leaves it out, but a query can name it.
+//!
+//! The schema is read on CREATE: the header names the columns, and their
+//! types are inferred over every file as for `toyql infer`. Each file must
+//! have the same header. A column listed in the statement overrides the
+//! type of the header column of the same name; the others keep what was
+//! inferred. The fields themselves are only converted when a query reads
+//! them, so a field that does not fit its column is an error of that
+//! query, with the file and line. A scan reads each file on a thread of its
+//! own, as many at once as the machine runs threads, and lets each get at
+//! most [`READ_AHEAD`] records ahead of it, so a query that stops early
+//! reads little more than it needs.
+//!
+//! External tables are read-only.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+
+use crate::catalog::{CatalogError, Column, ColumnType, Schema};
+use crate::csv::{self, Record};
+use crate::eval;
+use crate::import;
+use crate::infer;
+use crate::literals::LiteralValue;
+use crate::table::{Row, Table, Values};
+
+/// The column of a table over several files that names each row's file.
+pub const FILENAME_COLUMN: &str = "_filename";
+
+/// How many records a file's reader may run ahead of the scan.
+pub const READ_AHEAD: usize = 1024;
+
+/// Reading or converting an external table's file failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalError {
+    pub path: String,
+    /// The line of the record at fault, when there is one.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ExternalError {
+    fn new(path: &str, line: Option<usize>, message: impl Into<String>) -> ExternalError {
+        ExternalError {
+            path: path.to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ExternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} line {line}: {}", self.path, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ExternalError {}
+
+/// A table over one or more CSV files.
+#[derive(Debug, Clone)]
+pub struct CsvTable {
+    location: String,
+    /// The column names of every file's header.
+    header: Vec<String>,
+    /// Whether the location names several files, so rows have a
+    /// [`FILENAME_COLUMN`].
+    several: bool,
+    /// The columns read from the files, without [`FILENAME_COLUMN`].
+    file_schema: Schema,
+    schema: Arc<Schema>,
+}
+
+impl CsvTable {
+    /// Reads the header of the files at `location` and infers their
+    /// columns' types, taking those of `overrides` in place of inferred
+    /// ones.
+    pub fn open(location: &str, overrides: &[Column]) -> Result<CsvTable, ExternalError> {
+        let (files, several) = files(location)?;
+        let mut header: Vec<String> = Vec::new();
+        let mut types: Vec<Option<ColumnType>> = Vec::new();
+        for (i, path) in files.iter().enumerate() {
+            let text = read(path)?;
+            let reports = infer::infer(&text, usize::MAX)
+                .map_err(|e| ExternalError::new(path, None, e.to_string()))?;
+            if reports.is_empty() {
+                return Err(ExternalError::new(path, None, "no header record"));
+            }
+            let names = names(reports.iter().map(|report| report.name.as_str()));
+            if i == 0 {
+                header = names;
+                types = reports.into_iter().map(|report| report.ty).collect();
+                continue;
+            }
+            if names != header {
+                return Err(ExternalError::new(
+                    path,
+                    Some(1),
+                    format!("the header differs from that of {}", files[0]),
+                ));
+            }
+            for (ty, report) in types.iter_mut().zip(reports) {
+                *ty = match (ty.take(), report.ty) {
+                    (Some(a), Some(b)) => Some(infer::widen(a, b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        }
+        let mut columns: Vec<Column> = header
+            .iter()
+            .zip(types)
+            .map(|(name, ty)| Column::new(name, ty.unwrap_or(ColumnType::Text)))
+            .collect();
+        for column in overrides {
+            if column.generated.is_some() {
+                return Err(ExternalError::new(
+                    location,
+                    Some(1),
+                    format!("column `{}` cannot be generated", column.name),
+                ));
+            }
+            let Some(header) = columns.iter_mut().find(|c| c.name == column.name) else {
+                return Err(ExternalError::new(
+                    location,
+                    Some(1),
+                    format!("the header has no column `{}`", column.name),
+                ));
+            };
+            header.ty = column.ty.clone();
+        }
+        let file_schema = Schema { columns };
+        let mut schema = file_schema.clone();
+        if several {
+            schema.columns.push(Column {
+                hidden: true,
+                ..Column::new(FILENAME_COLUMN, ColumnType::Text)
+            });
+        }
+        Ok(CsvTable {
+            location: location.to_string(),
+            header,
+            several,
+            file_schema,
+            schema: Arc::new(schema),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.location
+    }
+
+    /// The data records of the files, each with its file, or why the
+    /// files cannot be listed.
+    fn records(&self) -> Result<Reads, ExternalError> {
+        let (files, _) = files(&self.location)?;
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        let mut reads = Reads {
+            header: Arc::new(self.header.clone()),
+            pending: files.into(),
+            started: VecDeque::new(),
+        };
+        for _ in 0..threads {
+            reads.start();
+        }
+        Ok(reads)
+    }
+
+    /// The record's values, converted to the schema's types.
+    fn row(&self, path: &str, record: &Record) -> Result<Row, ExternalError> {
+        let columns: Vec<&Column> = self.file_schema.input_columns().collect();
+        let values = import::row(&self.file_schema, path, &columns, &record.fields)
+            .and_then(|mut values| {
+                if self.several {
+                    values.push(LiteralValue::String(path.to_string()));
+                }
+                self.schema
+                    .complete_row(path, values)
+                    .map_err(|e| e.to_string())
+            })
+            .map_err(|message| ExternalError::new(path, Some(record.line), message))?;
+        Ok(Row::new(Arc::clone(&self.schema), values))
+    }
+}
+
+/// The records of files read on threads of their own, in order of file.
+struct Reads {
+    header: Arc<Vec<String>>,
+    /// The files not yet started.
+    pending: VecDeque<String>,
+    /// The files being read, in order.
+    started: VecDeque<(String, Receiver<Result<Record, ExternalError>>)>,
+}
+
+impl Reads {
+    /// Starts reading the next pending file, if there is one.
+    fn start(&mut self) {
+        let Some(path) = self.pending.pop_front() else {
+            return;
+        };
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD);
+        let header = Arc::clone(&self.header);
+        let reading = path.clone();
+        std::thread::spawn(move || read_records(&reading, &header, &sender));
+        self.started.push_back((path, receiver));
+    }
+}
+
+impl Iterator for Reads {
+    type Item = (String, Result<Record, ExternalError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, receiver) = self.started.front()?;
+            match receiver.recv() {
+                Ok(record) => return Some((path.clone(), record)),
+                // The file is done; its thread has hung up.
+                Err(_) => {
+                    self.started.pop_front();
+                    self.start();
+                }
+            }
+        }
+    }
+}
+
+/// Sends the data records of the file at `path`, whose header must be
+/// `header`, until one cannot be read or the scan stops listening.
+fn read_records(path: &str, header: &[String], sender: &SyncSender<Result<Record, ExternalError>>) {
+    let text = match read(path) {
+        Ok(text) => text,
+        Err(e) => {
+            let _ = sender.send(Err(e));
+            return;
+        }
+    };
+    let mut records = csv::records(&text);
+    let first = match records.next() {
+        Some(Ok(first)) => first,
+        Some(Err(e)) => {
+            let _ = sender.send(Err(ExternalError::new(path, None, e.to_string())));
+            return;
+        }
+        None => {
+            let _ = sender.send(Err(ExternalError::new(path, None, "no header record")));
+            return;
+        }
+    };
+    if names(first.fields.iter().map(String::as_str)) != header {
+        let error = ExternalError::new(path, Some(1), "the header differs from the table's");
+        let _ = sender.send(Err(error));
+        return;
+    }
+    for record in records {
+        let record = record.map_err(|e| ExternalError::new(path, None, e.to_string()));
+        let failed = record.is_err();
+        if sender.send(record).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// The files a location names, and whether it names several rather than
+/// one: a directory's `.csv` files, or the files matching a pattern, in
+/// order of name.
+fn files(location: &str) -> Result<(Vec<String>, bool), ExternalError> {
+    let path = Path::new(location);
+    let (dir, pattern) = if path.is_dir() {
+        (path, "*.csv".to_string())
+    } else {
+        let name = path.file_name().map(|name| name.to_string_lossy());
+        match name {
+            Some(name) if name.contains(['*', '?']) => {
+                (path.parent().unwrap_or(Path::new("")), name.into_owned())
+            }
+            _ => return Ok((vec![location.to_string()], false)),
+        }
+    };
+    let listed = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let entries =
+        std::fs::read_dir(listed).map_err(|e| ExternalError::new(location, None, e.to_string()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| ExternalError::new(location, None, e.to_string()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.path().is_file() && eval::wildcard(&name, &pattern, '*', '?') {
+            files.push(dir.join(name).to_string_lossy().into_owned());
+        }
+    }
+    if files.is_empty() {
+        return Err(ExternalError::new(location, None, "no files match"));
+    }
+    files.sort();
+    Ok((files, true))
+}
+
+fn read(path: &str) -> Result<String, ExternalError> {
+    std::fs::read_to_string(path).map_err(|e| ExternalError::new(path, None, e.to_string()))
+}
+
+/// The column names for the fields of a header.
+fn names<'a>(header: impl Iterator<Item = &'a str>) -> Vec<String> {
+    header.map(infer::identifier_for).collect()
+}
+
+impl Table for CsvTable {
+    fn schema(&self) -> &Arc<Schema> {
+        &self.schema
+    }
+
+    fn set_schema(&mut self, schema: Schema) {
+        self.file_schema = schema.clone();
+        if self.several {
+            self.file_schema.columns.pop();
+        }
+        self.schema = Arc::new(schema);
+    }
+
+    /// The number of data records, or 0 if the files cannot be read.
+    fn len(&self) -> usize {
+        self.records().map_or(0, |mut reads| {
+            reads
+                .try_fold(0, |n, (_, record)| record.map(|_| n + 1))
+                .unwrap_or(0)
+        })
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn append(&mut self, _values: Values) {
+        unreachable!("`Catalog::insert` does not append to read-only tables")
+    }
+
+    /// The rows up to the first that cannot be read.
+    fn scan(&self) -> Box<dyn Iterator<Item = Row> + '_> {
+        Box::new(self.try_scan().map_while(Result::ok))
+    }
+
+    fn try_scan(&self) -> Box<dyn Iterator<Item = Result<Row, CatalogError>> + '_> {
+        match self.records() {
+            Ok(reads) => Box::new(reads.map(move |(path, record)| {
+                record
+                    .and_then(|record| self.row(&path, &record))
+                    .map_err(CatalogError::External)
+            })),
+            Err(e) => Box::new(std::iter::once(Err(CatalogError::External(e)))),
+        }
+    }
+
+    fn get(&self, position: usize) -> Option<Row> {
+        let (path, record) = self
+            .records()
+            .ok()?
+            .map_while(|(path, record)| Some((path, record.ok()?)))
+            .nth(position)?;
+        self.row(&path, &record).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::literals::LiteralValue::{Float, Int, Null, String as Str};
+
+    fn file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("toyql-{}-{name}", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn infers_and_overrides_column_types() {
+        let path = file("scores.csv", "id,score,note\n1,2,a\n2,,b\n");
+        let table = CsvTable::open(&path, &[Column::new("score", ColumnType::Float)]).unwrap();
+        let types: Vec<String> = table
+            .schema()
+            .columns
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.ty))
+            .collect();
+        assert_eq!(types, vec!["id INT", "score FLOAT", "note TEXT"]);
+        let rows: Vec<_> = table.scan().map(|row| row.values().to_vec()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Int(1), Float(2.0), Str("a".to_string())],
+                vec![Int(2), Null, Str("b".to_string())],
+            ]
+        );
+        assert_eq!(table.len(), 2);
+
+        let error = CsvTable::open(&path, &[Column::new("nope", ColumnType::Int)]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("{path} line 1: the header has no column `nope`")
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn fields_are_converted_as_they_are_read() {
+        let path = file("lazy.csv", "a,b\n1,x\n2,y\n");
+        let table = CsvTable::open(&path, &[Column::new("b", ColumnType::Int)]).unwrap();
+        let mut rows = table.try_scan();
+        assert_eq!(
+            rows.next().unwrap().unwrap_err().to_string(),
+            format!("{path} line 2: column `b` is INT but got `x`")
+        );
+        assert!(table.scan().next().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(table.try_scan().next().unwrap().is_err());
+        assert_eq!(table.len(), 0);
+    }
+
+    #[test]
+    fn unions_the_files_of_a_directory_or_pattern() {
+        let dir = std::env::temp_dir().join(format!("toyql-{}-parts", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let part = |name: &str, text: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, text).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let b = part("b.csv", "id,score\n3,4.5\n");
+        let a = part("a.csv", "id,score\n1,2\n2,\n");
+        part("notes.txt", "not,csv\n");
+
+        let table = CsvTable::open(&dir.to_string_lossy(), &[]).unwrap();
+        let types: Vec<String> = table
+            .schema()
+            .columns
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.ty))
+            .collect();
+        assert_eq!(types, vec!["id INT", "score FLOAT", "_filename TEXT"]);
+        assert!(table.schema().columns[2].hidden);
+        let rows: Vec<_> = table.scan().map(|row| row.values().to_vec()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Int(1), Float(2.0), Str(a.clone())],
+                vec![Int(2), Null, Str(a.clone())],
+                vec![Int(3), Float(4.5), Str(b.clone())],
+            ]
+        );
+
+        let pattern = dir.join("?.csv").to_string_lossy().into_owned();
+        let table = CsvTable::open(&pattern, &[]).unwrap();
+        part("c.csv", "id,score\n5,6\n");
+        assert_eq!(table.len(), 4);
+        part("d.csv", "id,points\n7,8\n");
+        // The files before d.csv stream as usual; the scan fails on reaching it.
+        let rows: Vec<_> = table.try_scan().collect();
+        assert_eq!(rows.len(), 5);
+        assert!(rows[..4].iter().all(Result::is_ok));
+        assert_eq!(
+            rows[4].as_ref().unwrap_err().to_string(),
+            format!(
+                "{} line 1: the header differs from the table's",
+                dir.join("d.csv").display()
+            )
+        );
+        assert_eq!(
+            CsvTable::open(&pattern, &[]).unwrap_err().to_string(),
+            format!(
+                "{} line 1: the header differs from that of {a}",
+                dir.join("d.csv").display()
+            )
+        );
+        let none = dir.join("*.json").to_string_lossy().into_owned();
+        assert_eq!(
+            CsvTable::open(&none, &[]).unwrap_err().to_string(),
+            format!("{none}: no files match")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
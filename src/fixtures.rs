@@ -0,0 +1,157 @@
+//! Test support for crates that embed toyql, built with the `test-fixtures`
+//! feature: a builder of catalogs holding populated tables, so that an
+//! integration test can set up its data without creating and filling each
+//! table by hand.
+//!
+//! `Fixture::table("t").columns([("x", ColumnType::Int)])
+//! .rows_from(example_ints()).build()` gives a catalog with a table `t` of
+//! one INT column holding the example ints. Further tables follow with
+//! `.table(name)`. The tables live in the catalog that is built, in memory,
+//! so they go when it does.
+//!
+//! The example data the crate's own tests use is exported here too.
+
+use crate::catalog::{Catalog, CatalogError, Column, ColumnType, Schema};
+use crate::literals::{IntoValues, LiteralValue};
+
+pub use crate::test_utils::{example_floats, example_ints, example_literals, example_strings};
+
+/// The tables a catalog is to be built with.
+#[derive(Debug, Clone, Default)]
+pub struct Fixture {
+    tables: Vec<TableFixture>,
+}
+
+impl Fixture {
+    /// A fixture that starts with a table called `name`.
+    pub fn table(name: &str) -> TableFixture {
+        TableFixture {
+            fixture: Box::default(),
+            name: name.to_string(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn create(self, catalog: &mut Catalog) -> Result<(), CatalogError> {
+        for table in self.tables {
+            catalog.create_table(
+                &table.name,
+                Schema {
+                    columns: table.columns,
+                },
+            )?;
+            catalog.insert(&table.name, table.rows)?;
+        }
+        Ok(())
+    }
+}
+
+/// A table of a [`Fixture`], with the tables before it.
+#[derive(Debug, Clone)]
+pub struct TableFixture {
+    fixture: Box<Fixture>,
+    name: String,
+    columns: Vec<Column>,
+    rows: Vec<Vec<LiteralValue>>,
+}
+
+impl TableFixture {
+    pub fn column(mut self, name: &str, ty: ColumnType) -> TableFixture {
+        self.columns.push(Column::new(name, ty));
+        self
+    }
+
+    pub fn columns<'a>(
+        self,
+        columns: impl IntoIterator<Item = (&'a str, ColumnType)>,
+    ) -> TableFixture {
+        columns
+            .into_iter()
+            .fold(self, |table, (name, ty)| table.column(name, ty))
+    }
+
+    /// Adds rows, each a tuple or array of values for the table's columns.
+    pub fn rows(mut self, rows: impl IntoIterator<Item = impl IntoValues>) -> TableFixture {
+        self.rows
+            .extend(rows.into_iter().map(IntoValues::into_values));
+        self
+    }
+
+    /// Adds a row of one value for each of `values`, for a table of one
+    /// column.
+    pub fn rows_from(
+        mut self,
+        values: impl IntoIterator<Item = impl Into<LiteralValue>>,
+    ) -> TableFixture {
+        self.rows
+            .extend(values.into_iter().map(|value| vec![value.into()]));
+        self
+    }
+
+    /// Starts another table, called `name`.
+    pub fn table(self, name: &str) -> TableFixture {
+        TableFixture {
+            fixture: Box::new(self.finish()),
+            ..Fixture::table(name)
+        }
+    }
+
+    /// A new catalog with the tables.
+    pub fn build(self) -> Result<Catalog, CatalogError> {
+        let mut catalog = Catalog::new();
+        self.create(&mut catalog)?;
+        Ok(catalog)
+    }
+
+    /// Creates the tables in `catalog`, and fills them. The first one that
+    /// fails stops the others.
+    pub fn create(self, catalog: &mut Catalog) -> Result<(), CatalogError> {
+        self.finish().create(catalog)
+    }
+
+    fn finish(mut self) -> Fixture {
+        let mut fixture = *std::mem::take(&mut self.fixture);
+        fixture.tables.push(self);
+        fixture
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::{execute, parsing::parse, Outcome};
+
+    #[test]
+    fn builds_populated_tables() {
+        let mut catalog = Fixture::table("ints")
+            .columns([("x", ColumnType::Int)])
+            .rows_from(example_ints())
+            .table("people")
+            .column("name", ColumnType::Text)
+            .column("score", ColumnType::Float)
+            .rows([("ann", 3.5), ("bob", 4.0)])
+            .build()
+            .unwrap();
+        assert_eq!(catalog.table("ints").unwrap().len(), example_ints().len());
+        let outcome = execute(
+            &parse("SELECT name FROM people WHERE score > 3.7").unwrap(),
+            &mut catalog,
+        )
+        .unwrap();
+        let Outcome::Rows(result) = outcome else {
+            panic!("{outcome:?}")
+        };
+        assert_eq!(result.rows[0][0], LiteralValue::String("bob".into()));
+
+        assert_eq!(
+            Fixture::table("t")
+                .columns([("a", ColumnType::Int), ("b", ColumnType::Int)])
+                .rows_from([1])
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "table `t` has 2 columns but 1 values were given"
+        );
+    }
+}
@@ -0,0 +1,500 @@
+//! Scalar functions callable from expressions, looked up by name
+//! case-insensitively.
+//!
+//! Every function returns NULL when any argument is NULL.
+//!
+//! - `REGEXP_EXTRACT(s, pattern, group)`: the text of capture `group` (0 for
+//!   the whole match) in the first match of `pattern` in `s`, or NULL when
+//!   there is no match or the group did not take part in it.
+//! - `REGEXP_REPLACE(s, pattern, replacement)`: `s` with every match of
+//!   `pattern` replaced; `$1` or `${name}` in `replacement` insert captures.
+//! - `SPLIT(s, sep)`: the list of the parts of `s` between occurrences of
+//!   the text `sep`; an empty `sep` splits `s` into its characters.
+//! - `INET(s)`: the string `s`, such as `'10.0.0.0/8'`, as an inet address.
+//! - `HOST(i)`: the address of inet `i` as a string, without the prefix
+//!   length.
+//! - `MASKLEN(i)`: the prefix length of inet `i`.
+//! - `NETWORK(i)`: inet `i` with the bits after its prefix cleared.
+//! - `POINT(lat, lon)`: the point at latitude `lat` and longitude `lon`, in
+//!   degrees.
+//! - `ST_DISTANCE(p, q)`: the great-circle distance between points `p` and
+//!   `q` in metres, on a spherical Earth.
+//! - `ST_WITHIN_BOX(p, south, west, north, east)`: whether point `p` lies in
+//!   the box between those latitudes and longitudes, edges included; a box
+//!   with `west > east` crosses the antimeridian.
+//!
+//! The aggregates `COUNT`, `SUM`, `AVG`, `MIN` and `MAX` share the same
+//! namespace but are evaluated by the executor over groups of rows; calling
+//! one here is an error.
+//!
+//! Patterns use the `regex` crate's syntax. A backslash in a pattern must be
+//! doubled inside a string literal: `'\\d+'` is the pattern `\d+`.
+//! Compiled patterns are cached per thread, keyed on the pattern text, so a
+//! pattern in a query is compiled once rather than once per row.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::eval::EvalError;
+use crate::literals::{Inet, LiteralValue, Point};
+
+/// Compiled patterns kept before the cache is emptied and starts again.
+const REGEX_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    static REGEX_CACHE: RefCell<HashMap<String, Regex>> = RefCell::new(HashMap::new());
+}
+
+/// Names of the aggregate functions, in lower case.
+pub const AGGREGATES: [&str; 5] = ["count", "sum", "avg", "min", "max"];
+
+/// The scalar functions [`call`] knows.
+const SCALARS: [&str; 10] = [
+    "regexp_extract",
+    "regexp_replace",
+    "split",
+    "inet",
+    "host",
+    "masklen",
+    "network",
+    "point",
+    "st_distance",
+    "st_within_box",
+];
+
+/// Whether `name` is a built-in function, scalar or aggregate.
+pub fn exists(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SCALARS.contains(&name.as_str()) || AGGREGATES.contains(&name.as_str())
+}
+
+/// Whether `name` is an aggregate function.
+pub fn is_aggregate(name: &str) -> bool {
+    AGGREGATES.contains(&name.to_ascii_lowercase().as_str())
+}
+
+pub fn call(name: &str, args: Vec<LiteralValue>) -> Result<LiteralValue, EvalError> {
+    let function = Function { name, args: &args };
+    if is_aggregate(name) {
+        return Err(EvalError::MisplacedAggregate(name.to_string()));
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "regexp_extract" => regexp_extract(&function),
+        "regexp_replace" => regexp_replace(&function),
+        "split" => split(&function),
+        "inet" => inet(&function),
+        "host" => host(&function),
+        "masklen" => masklen(&function),
+        "network" => network(&function),
+        "point" => point(&function),
+        "st_distance" => st_distance(&function),
+        "st_within_box" => st_within_box(&function),
+        _ => Err(EvalError::UnknownFunction(name.to_string())),
+    }
+}
+
+/// A call being evaluated, for checking its arguments.
+struct Function<'a> {
+    name: &'a str,
+    args: &'a [LiteralValue],
+}
+
+impl Function<'_> {
+    fn arity(&self, expected: usize) -> Result<(), EvalError> {
+        if self.args.len() == expected {
+            Ok(())
+        } else {
+            Err(EvalError::Arity {
+                function: self.name.to_string(),
+                expected,
+                found: self.args.len(),
+            })
+        }
+    }
+
+    fn has_null(&self) -> bool {
+        self.args.contains(&LiteralValue::Null)
+    }
+
+    fn type_error(&self, position: usize, expected: &'static str) -> EvalError {
+        EvalError::ArgumentType {
+            function: self.name.to_string(),
+            position: position + 1,
+            expected,
+            found: self.args[position].type_name(),
+        }
+    }
+
+    fn string(&self, position: usize) -> Result<&str, EvalError> {
+        match &self.args[position] {
+            LiteralValue::String(s) => Ok(s),
+            _ => Err(self.type_error(position, "string")),
+        }
+    }
+
+    fn int(&self, position: usize) -> Result<i64, EvalError> {
+        match self.args[position] {
+            LiteralValue::Int(v) => Ok(v),
+            _ => Err(self.type_error(position, "int")),
+        }
+    }
+
+    fn inet(&self, position: usize) -> Result<Inet, EvalError> {
+        match self.args[position] {
+            LiteralValue::Inet(v) => Ok(v),
+            _ => Err(self.type_error(position, "inet")),
+        }
+    }
+
+    /// An int or float argument, as a float.
+    fn number(&self, position: usize) -> Result<f64, EvalError> {
+        match self.args[position] {
+            LiteralValue::Int(v) => Ok(v as f64),
+            LiteralValue::Float(v) => Ok(v),
+            _ => Err(self.type_error(position, "number")),
+        }
+    }
+
+    fn point(&self, position: usize) -> Result<Point, EvalError> {
+        match self.args[position] {
+            LiteralValue::Point(v) => Ok(v),
+            _ => Err(self.type_error(position, "point")),
+        }
+    }
+
+    fn regex(&self, position: usize) -> Result<Regex, EvalError> {
+        compile(self.string(position)?)
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, EvalError> {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(regex) = cache.get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern).map_err(|e| EvalError::InvalidRegex {
+            pattern: pattern.to_string(),
+            message: e.to_string(),
+        })?;
+        if cache.len() >= REGEX_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    })
+}
+
+fn regexp_extract(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(3)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    let (text, regex, group) = (f.string(0)?, f.regex(1)?, f.int(2)?);
+    let group = usize::try_from(group)
+        .ok()
+        .filter(|&g| g < regex.captures_len())
+        .ok_or_else(|| EvalError::NoSuchGroup {
+            pattern: regex.as_str().to_string(),
+            group,
+        })?;
+    Ok(regex
+        .captures(text)
+        .and_then(|captures| captures.get(group))
+        .map_or(LiteralValue::Null, |m| {
+            LiteralValue::String(m.as_str().to_string())
+        }))
+}
+
+fn regexp_replace(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(3)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    let (text, regex, replacement) = (f.string(0)?, f.regex(1)?, f.string(2)?);
+    Ok(LiteralValue::String(
+        regex.replace_all(text, replacement).into_owned(),
+    ))
+}
+
+fn split(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(2)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    let (text, separator) = (f.string(0)?, f.string(1)?);
+    Ok(LiteralValue::List(if separator.is_empty() {
+        text.chars()
+            .map(|c| LiteralValue::String(c.to_string()))
+            .collect()
+    } else {
+        text.split(separator)
+            .map(|part| LiteralValue::String(part.to_string()))
+            .collect()
+    }))
+}
+
+fn inet(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(1)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    let inet = f.string(0)?.parse().map_err(EvalError::InvalidInet)?;
+    Ok(LiteralValue::Inet(inet))
+}
+
+fn host(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(1)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    Ok(LiteralValue::String(f.inet(0)?.addr().to_string()))
+}
+
+fn masklen(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(1)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    Ok(LiteralValue::Int(i64::from(f.inet(0)?.prefix())))
+}
+
+fn network(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(1)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    Ok(LiteralValue::Inet(f.inet(0)?.network()))
+}
+
+fn point(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(2)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    let (lat, lon) = (f.number(0)?, f.number(1)?);
+    let point = Point::new(lat, lon).ok_or(EvalError::InvalidPoint { lat, lon })?;
+    Ok(LiteralValue::Point(point))
+}
+
+fn st_distance(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(2)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    Ok(LiteralValue::Float(f.point(0)?.distance(f.point(1)?)))
+}
+
+fn st_within_box(f: &Function) -> Result<LiteralValue, EvalError> {
+    f.arity(5)?;
+    if f.has_null() {
+        return Ok(LiteralValue::Null);
+    }
+    let point = f.point(0)?;
+    let (south, west, north, east) = (f.number(1)?, f.number(2)?, f.number(3)?, f.number(4)?);
+    Ok(LiteralValue::Bool(
+        point.within_box(south, west, north, east),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parsing::parse;
+    use crate::eval::evaluate;
+    use LiteralValue::Null;
+
+    fn eval(text: &str) -> Result<LiteralValue, EvalError> {
+        evaluate(&parse(text).unwrap(), &())
+    }
+
+    fn string(s: &str) -> LiteralValue {
+        LiteralValue::String(s.to_string())
+    }
+
+    #[test]
+    fn regexp_extract_returns_groups() {
+        let cases = [
+            (
+                r"regexp_extract('key=42;', '(\\w+)=(\\d+)', 2)",
+                string("42"),
+            ),
+            (
+                r"REGEXP_EXTRACT('key=42;', '(\\w+)=(\\d+)', 0)",
+                string("key=42"),
+            ),
+            ("regexp_extract('abc', 'x', 0)", Null),
+            ("regexp_extract('b', 'a|(b)|(c)', 2)", Null),
+            ("regexp_extract(null, 'x', 0)", Null),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(eval(text), Ok(expected), "{text}");
+        }
+    }
+
+    #[test]
+    fn regexp_replace_replaces_every_match() {
+        assert_eq!(
+            eval(r"regexp_replace('a1b22c', '\\d+', '#')"),
+            Ok(string("a#b#c"))
+        );
+        assert_eq!(
+            eval(r"regexp_replace('john smith', '(\\w+) (\\w+)', '$2, $1')"),
+            Ok(string("smith, john"))
+        );
+        assert_eq!(eval("regexp_replace('a', 'a', null)"), Ok(Null));
+    }
+
+    #[test]
+    fn inet_functions() {
+        let inet = |text: &str| LiteralValue::Inet(text.parse().unwrap());
+        let cases = [
+            ("inet('192.168.7.9/20')", inet("192.168.7.9/20")),
+            ("host(inet '192.168.7.9/20')", string("192.168.7.9")),
+            ("masklen(inet('::1'))", LiteralValue::Int(128)),
+            ("network(inet '192.168.7.9/20')", inet("192.168.0.0/20")),
+            (
+                "inet('10.1.2.3') << network(inet '10.9.9.9/8')",
+                LiteralValue::Bool(true),
+            ),
+            (
+                "inet '10.0.0.0/8' << inet '10.0.0.0/8'",
+                LiteralValue::Bool(false),
+            ),
+            ("masklen(null)", Null),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(eval(text), Ok(expected), "{text}");
+        }
+        assert_eq!(
+            eval("inet('10.0.0.1/40')").unwrap_err().to_string(),
+            "invalid inet address `10.0.0.1/40`"
+        );
+        assert_eq!(
+            eval("inet '10.0.0.1' << '10.0.0.0/8'")
+                .unwrap_err()
+                .to_string(),
+            "cannot apply `<<` to inet and string"
+        );
+    }
+
+    #[test]
+    fn split_gives_lists() {
+        let list = |items: &[&str]| LiteralValue::List(items.iter().map(|s| string(s)).collect());
+        let cases = [
+            ("split('a,b,,c', ',')", list(&["a", "b", "", "c"])),
+            ("SPLIT('a--b', '--')", list(&["a", "b"])),
+            ("split('abc', 'x')", list(&["abc"])),
+            ("split('', ',')", list(&[""])),
+            ("split('héllo', '')", list(&["h", "é", "l", "l", "o"])),
+            ("split('', '')", list(&[])),
+            ("split(null, ',')", Null),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(eval(text), Ok(expected), "{text}");
+        }
+        assert_eq!(
+            eval("split('a b', ' ')").unwrap().serialize(),
+            "LIST(\"a\", \"b\")"
+        );
+        assert_eq!(
+            eval("split(1, ',')").unwrap_err().to_string(),
+            "argument 1 of `split` must be string, found int"
+        );
+    }
+
+    #[test]
+    fn point_functions() {
+        let point = |lat, lon| LiteralValue::Point(Point::new(lat, lon).unwrap());
+        let cases = [
+            ("point(1, -2.5)", point(1.0, -2.5)),
+            ("point(0.5 * 2, 3)", point(1.0, 3.0)),
+            (
+                "st_distance(point(0, 0), point(0, 0))",
+                LiteralValue::Float(0.0),
+            ),
+            (
+                "st_within_box(point(10, 20), 0, 0, 10, 20)",
+                LiteralValue::Bool(true),
+            ),
+            (
+                "ST_WITHIN_BOX(POINT(0, 179), -1, 170, 1, -170)",
+                LiteralValue::Bool(true),
+            ),
+            ("st_distance(point(0, 0), null)", Null),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(eval(text), Ok(expected), "{text}");
+        }
+        let Ok(LiteralValue::Float(metres)) =
+            eval("st_distance(point(48.8566, 2.3522), point(51.5074, -0.1278))")
+        else {
+            panic!("expected a float");
+        };
+        assert!((343_000.0..345_000.0).contains(&metres), "{metres}");
+        assert_eq!(
+            eval("point(91, 0)"),
+            Err(EvalError::InvalidPoint {
+                lat: 91.0,
+                lon: 0.0
+            })
+        );
+        assert_eq!(
+            eval("st_distance(point(0, 0), 1)"),
+            Err(EvalError::ArgumentType {
+                function: "st_distance".to_string(),
+                position: 2,
+                expected: "point",
+                found: "int",
+            })
+        );
+    }
+
+    #[test]
+    fn argument_errors() {
+        let cases = [
+            (
+                "regexp_extract('a', 'a')",
+                EvalError::Arity {
+                    function: "regexp_extract".to_string(),
+                    expected: 3,
+                    found: 2,
+                },
+            ),
+            (
+                "regexp_replace(1, 'a', 'b')",
+                EvalError::ArgumentType {
+                    function: "regexp_replace".to_string(),
+                    position: 1,
+                    expected: "string",
+                    found: "int",
+                },
+            ),
+            (
+                "regexp_extract('a', '(a)', 2)",
+                EvalError::NoSuchGroup {
+                    pattern: "(a)".to_string(),
+                    group: 2,
+                },
+            ),
+            ("nope(1)", EvalError::UnknownFunction("nope".to_string())),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(eval(text), Err(expected), "{text}");
+        }
+        assert!(matches!(
+            eval("regexp_extract('a', '(', 0)"),
+            Err(EvalError::InvalidRegex { .. })
+        ));
+    }
+
+    #[test]
+    fn compiled_patterns_are_reused() {
+        let first = compile("a+b").unwrap();
+        let second = compile("a+b").unwrap();
+        assert_eq!(first.as_str(), second.as_str());
+        REGEX_CACHE.with(|cache| assert!(cache.borrow().contains_key("a+b")));
+    }
+}
@@ -0,0 +1,498 @@
+//! Loading CSV files into tables. The first record is a header; each
+//! following field is read with the literal grammar for its column's type.
+//!
+//! A table that does not exist yet is created from the header, with the
+//! types [`infer`](crate::infer) finds over the whole file. An existing
+//! table takes fields by position, one per input column.
+//!
+//! Rows that do not fit are rejected. Under [`ErrorPolicy::SkipErrors`]
+//! the rest still load, and the rejected rows can be kept for later, as
+//! CSV with [`rejects_csv`] or in a table with [`record_rejects`].
+//!
+//! [`import_reporting`] also reports how far it has got, every so many
+//! records or every so often, for files large enough to take a while.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::catalog::{Catalog, CatalogError, Column, ColumnType, Schema};
+use crate::csv::{self, CsvError};
+use crate::error::PResult;
+use crate::infer;
+use crate::literals::parsing::{boolean, float, int, list, null, point};
+use crate::literals::LiteralValue;
+
+/// What to do with a row that does not fit the table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Load nothing if any row fails.
+    #[default]
+    Strict,
+    /// Load the rows that fit and report the others.
+    SkipErrors,
+}
+
+/// A row that could not be loaded, with the line its record started on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+    /// The record's fields as read.
+    pub fields: Vec<String>,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// When an import reports its progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressInterval {
+    /// After every so many records.
+    Records(usize),
+    /// After the first record read once so long has passed since the last
+    /// report, or the start.
+    Time(Duration),
+}
+
+/// How far an import has got.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The records read so far, loaded or not.
+    pub records: usize,
+    /// The line the last of them started on.
+    pub line: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn records_per_second(&self) -> f64 {
+        self.records as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read {} records, to line {}, in {:.1}s ({:.0} rows/s)",
+            self.records,
+            self.line,
+            self.elapsed.as_secs_f64(),
+            self.records_per_second()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    pub rows: usize,
+    /// Rows left out under [`ErrorPolicy::SkipErrors`].
+    pub skipped: Vec<RowError>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    Csv(CsvError),
+    /// The file has no header record.
+    Empty,
+    /// Creating the table failed.
+    Catalog(CatalogError),
+    /// A row failed under [`ErrorPolicy::Strict`].
+    Row(RowError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Csv(e) => e.fmt(f),
+            ImportError::Empty => write!(f, "no header record"),
+            ImportError::Catalog(e) => e.fmt(f),
+            ImportError::Row(e) => write!(f, "{e}; nothing was imported"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<CsvError> for ImportError {
+    fn from(e: CsvError) -> ImportError {
+        ImportError::Csv(e)
+    }
+}
+
+impl From<CatalogError> for ImportError {
+    fn from(e: CatalogError) -> ImportError {
+        ImportError::Catalog(e)
+    }
+}
+
+/// Loads the CSV `text` into `table`, creating it if needed. Either every
+/// accepted row is inserted or, on error, none are.
+pub fn import(
+    catalog: &mut Catalog,
+    table: &str,
+    text: &str,
+    policy: ErrorPolicy,
+) -> Result<ImportReport, ImportError> {
+    import_reporting(
+        catalog,
+        table,
+        text,
+        policy,
+        ProgressInterval::Records(usize::MAX),
+        &mut |_| {},
+    )
+}
+
+/// [`import`], calling `report` as records are read, as `interval` says.
+pub fn import_reporting(
+    catalog: &mut Catalog,
+    table: &str,
+    text: &str,
+    policy: ErrorPolicy,
+    interval: ProgressInterval,
+    report: &mut dyn FnMut(&Progress),
+) -> Result<ImportReport, ImportError> {
+    let start = Instant::now();
+    let mut last_report = start;
+    let mut records = 0;
+    if catalog.table(table).is_none() {
+        catalog.create_table(table, inferred_schema(text)?)?;
+    }
+    let schema = catalog.table(table).expect("created above").schema();
+    let columns: Vec<&Column> = schema.input_columns().collect();
+
+    let mut rows = Vec::new();
+    let mut skipped = Vec::new();
+    for record in csv::records(text).skip(1) {
+        let record = record?;
+        records += 1;
+        let due = match interval {
+            ProgressInterval::Records(every) => records % every.max(1) == 0,
+            ProgressInterval::Time(every) => last_report.elapsed() >= every,
+        };
+        if due {
+            last_report = Instant::now();
+            report(&Progress {
+                records,
+                line: record.line,
+                elapsed: start.elapsed(),
+            });
+        }
+        match row(schema, table, &columns, &record.fields) {
+            Ok(values) => rows.push(values),
+            Err(message) => {
+                let error = RowError {
+                    line: record.line,
+                    message,
+                    fields: record.fields,
+                };
+                match policy {
+                    ErrorPolicy::Strict => return Err(ImportError::Row(error)),
+                    ErrorPolicy::SkipErrors => skipped.push(error),
+                }
+            }
+        }
+    }
+    let rows = catalog.insert(table, rows)?;
+    Ok(ImportReport { rows, skipped })
+}
+
+/// The columns of a table of rejected rows: the line, the error, and the
+/// record as a line of CSV.
+fn rejects_schema() -> Schema {
+    Schema {
+        columns: vec![
+            Column::new("line", ColumnType::Int),
+            Column::new("error", ColumnType::Text),
+            Column::new("record", ColumnType::Text),
+        ],
+    }
+}
+
+/// The rejected rows as CSV, with a header and a record per row: its
+/// line, its error and its record as a line of CSV.
+pub fn rejects_csv(rejected: &[RowError]) -> String {
+    let mut out = csv::write_record(&["line", "error", "record"]) + "\n";
+    for error in rejected {
+        out.push_str(&csv::write_record(&[
+            error.line.to_string(),
+            error.message.clone(),
+            csv::write_record(&error.fields),
+        ]));
+        out.push('\n');
+    }
+    out
+}
+
+/// Appends the rejected rows to `table`, creating it with columns `line`,
+/// `error` and `record` if it does not exist. An existing table must take
+/// those three values.
+pub fn record_rejects(
+    catalog: &mut Catalog,
+    table: &str,
+    rejected: &[RowError],
+) -> Result<usize, CatalogError> {
+    if catalog.table(table).is_none() {
+        catalog.create_table(table, rejects_schema())?;
+    }
+    let rows = rejected
+        .iter()
+        .map(|error| {
+            vec![
+                LiteralValue::Int(i64::try_from(error.line).unwrap_or(i64::MAX)),
+                LiteralValue::String(error.message.clone()),
+                LiteralValue::String(csv::write_record(&error.fields)),
+            ]
+        })
+        .collect();
+    catalog.insert(table, rows)
+}
+
+fn inferred_schema(text: &str) -> Result<Schema, ImportError> {
+    let reports = infer::infer(text, usize::MAX)?;
+    if reports.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    let columns = reports
+        .into_iter()
+        .map(|report| {
+            Column::new(
+                &infer::identifier_for(&report.name),
+                report.ty.unwrap_or(ColumnType::Text),
+            )
+        })
+        .collect();
+    Ok(Schema { columns })
+}
+
+/// The values of one record, checked against the schema. Missing trailing
+/// fields are NULL.
+pub(crate) fn row(
+    schema: &Schema,
+    table: &str,
+    columns: &[&Column],
+    fields: &[String],
+) -> Result<Vec<LiteralValue>, String> {
+    if fields.len() > columns.len() {
+        return Err(format!(
+            "{} fields but table `{table}` takes {}",
+            fields.len(),
+            columns.len()
+        ));
+    }
+    let values = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let field = fields.get(i).map_or("", String::as_str);
+            field_value(field, &column.ty).ok_or_else(|| {
+                format!(
+                    "column `{}` is {} but got `{field}`",
+                    column.name, column.ty
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    schema
+        .complete_row(table, values.clone())
+        .map_err(|e| e.to_string())?;
+    Ok(values)
+}
+
+fn whole<O>(parsed: PResult<'_, O>) -> Option<O> {
+    match parsed {
+        Ok(("", value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Reads a field as a value of the given type. Empty fields and `null` are
+/// NULL; text is taken as it is, and inet addresses are written without
+/// the `INET` keyword or quotes. Points and lists are written as literals.
+fn field_value(field: &str, ty: &ColumnType) -> Option<LiteralValue> {
+    let trimmed = field.trim();
+    if trimmed.is_empty() || whole(null::apply_grammar(trimmed)).is_some() {
+        return Some(LiteralValue::Null);
+    }
+    match ty {
+        ColumnType::Int => whole(int::apply_grammar(trimmed)).map(LiteralValue::Int),
+        ColumnType::Float => whole(float::apply_grammar(trimmed))
+            .or_else(|| whole(int::apply_grammar(trimmed)).map(|v| v as f64))
+            .map(LiteralValue::Float),
+        ColumnType::Bool => whole(boolean::apply_grammar(trimmed)).map(LiteralValue::Bool),
+        ColumnType::Inet => trimmed.parse().ok().map(LiteralValue::Inet),
+        ColumnType::Point => whole(point::apply_grammar(trimmed)).map(LiteralValue::Point),
+        ColumnType::List => whole(list::apply_grammar(trimmed)).map(LiteralValue::List),
+        ColumnType::Text | ColumnType::Enum(_) => Some(LiteralValue::String(field.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::literals::Point;
+    use LiteralValue::{Bool, Float, Int, Null};
+
+    const PEOPLE: &str = "id,name,score,active\n\
+                          1,ann,3.5,true\n\
+                          2,bob,4,\n";
+
+    fn values(catalog: &Catalog, table: &str) -> Vec<Vec<LiteralValue>> {
+        let table = catalog.table(table).unwrap();
+        table.scan().map(|row| row.values().to_vec()).collect()
+    }
+
+    #[test]
+    fn creates_a_table_from_the_header() {
+        let mut catalog = Catalog::new();
+        let report = import(&mut catalog, "people", PEOPLE, ErrorPolicy::Strict).unwrap();
+        assert_eq!(report.rows, 2);
+        assert_eq!(
+            catalog.table("people").unwrap().schema().column_names(),
+            vec!["id", "name", "score", "active"]
+        );
+        assert_eq!(
+            values(&catalog, "people"),
+            vec![
+                vec![
+                    Int(1),
+                    LiteralValue::String("ann".into()),
+                    Float(3.5),
+                    Bool(true)
+                ],
+                vec![Int(2), LiteralValue::String("bob".into()), Float(4.0), Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_points_and_lists() {
+        let mut catalog = Catalog::new();
+        catalog
+            .create_table(
+                "t",
+                Schema {
+                    columns: vec![
+                        Column::new("at", ColumnType::Point),
+                        Column::new("tags", ColumnType::List),
+                    ],
+                },
+            )
+            .unwrap();
+        let text = "at,tags\n\"POINT(1.5, -2)\",\"LIST('a', 1)\"\n";
+        import(&mut catalog, "t", text, ErrorPolicy::Strict).unwrap();
+        assert_eq!(
+            values(&catalog, "t"),
+            vec![vec![
+                LiteralValue::Point(Point::new(1.5, -2.0).unwrap()),
+                LiteralValue::List(vec![LiteralValue::String("a".into()), Int(1)]),
+            ]]
+        );
+    }
+
+    #[test]
+    fn error_policies() {
+        let text = "a,b\n1,x\nnope,y\n3\n4,y,z\n";
+        let mut catalog = Catalog::new();
+        catalog
+            .create_table(
+                "t",
+                Schema {
+                    columns: vec![
+                        Column::new("a", ColumnType::Int),
+                        Column::new("b", ColumnType::Enum(vec!["x".into(), "y".into()])),
+                    ],
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            import(&mut catalog, "t", text, ErrorPolicy::Strict)
+                .unwrap_err()
+                .to_string(),
+            "line 3: column `a` is INT but got `nope`; nothing was imported"
+        );
+        assert!(catalog.table("t").unwrap().is_empty());
+
+        let report = import(&mut catalog, "t", text, ErrorPolicy::SkipErrors).unwrap();
+        assert_eq!(report.rows, 2);
+        let skipped: Vec<String> = report.skipped.iter().map(RowError::to_string).collect();
+        assert_eq!(
+            skipped,
+            vec![
+                "line 3: column `a` is INT but got `nope`",
+                "line 5: 3 fields but table `t` takes 2",
+            ]
+        );
+        assert_eq!(values(&catalog, "t")[1], vec![Int(3), Null]);
+
+        assert_eq!(
+            rejects_csv(&report.skipped),
+            "line,error,record\n\
+             3,column `a` is INT but got `nope`,\"nope,y\"\n\
+             5,3 fields but table `t` takes 2,\"4,y,z\"\n"
+        );
+        assert_eq!(
+            record_rejects(&mut catalog, "rejects", &report.skipped),
+            Ok(2)
+        );
+        assert_eq!(
+            values(&catalog, "rejects")[0],
+            vec![
+                Int(3),
+                LiteralValue::String("column `a` is INT but got `nope`".into()),
+                LiteralValue::String("nope,y".into()),
+            ]
+        );
+
+        let bad_label = import(&mut catalog, "t", "a,b\n5,w\n", ErrorPolicy::Strict);
+        assert_eq!(
+            bad_label.unwrap_err().to_string(),
+            "line 2: column `b` is ENUM(\"x\", \"y\") but got string \"w\"; nothing was imported"
+        );
+    }
+
+    #[test]
+    fn reports_progress() {
+        let text = "a\n1\n2\n\"3\n\"\n4\n5\n";
+        let mut reports = Vec::new();
+        let mut catalog = Catalog::new();
+        let report = import_reporting(
+            &mut catalog,
+            "t",
+            text,
+            ErrorPolicy::SkipErrors,
+            ProgressInterval::Records(2),
+            &mut |progress| reports.push((progress.records, progress.line)),
+        )
+        .unwrap();
+        assert_eq!(report.rows, 5);
+        assert_eq!(reports, vec![(2, 3), (4, 6)]);
+
+        let mut count = 0;
+        import_reporting(
+            &mut Catalog::new(),
+            "t",
+            text,
+            ErrorPolicy::SkipErrors,
+            ProgressInterval::Time(Duration::ZERO),
+            &mut |_| count += 1,
+        )
+        .unwrap();
+        assert_eq!(count, 5);
+
+        let progress = Progress {
+            records: 3000,
+            line: 3001,
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(
+            progress.to_string(),
+            "read 3000 records, to line 3001, in 1.5s (2000 rows/s)"
+        );
+    }
+}
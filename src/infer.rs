@@ -0,0 +1,183 @@
+//! Schema inference for CSV files: the first record names the columns and
+//! the following ones are classified with the literal grammars.
+
+use crate::catalog::ColumnType;
+use crate::csv::{self, CsvError};
+use crate::error::PResult;
+use crate::literals::parsing::{boolean, float, int, null};
+use crate::literals::Inet;
+
+/// What was learned about one column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnReport {
+    pub name: String,
+    /// `None` when every sampled value was empty or `null`.
+    pub ty: Option<ColumnType>,
+    pub nulls: usize,
+    /// Up to three distinct non-null values, in order of appearance.
+    pub samples: Vec<String>,
+}
+
+const SAMPLE_VALUES: usize = 3;
+
+/// Reads the header and at most `sample_rows` data rows. Rows shorter than
+/// the header count their missing fields as nulls.
+pub fn infer(text: &str, sample_rows: usize) -> Result<Vec<ColumnReport>, CsvError> {
+    let mut records = csv::records(text);
+    let header = match records.next() {
+        Some(header) => header?,
+        None => return Ok(Vec::new()),
+    };
+    let mut reports: Vec<ColumnReport> = header
+        .fields
+        .into_iter()
+        .map(|name| ColumnReport {
+            name,
+            ty: None,
+            nulls: 0,
+            samples: Vec::new(),
+        })
+        .collect();
+    for record in records.take(sample_rows) {
+        let record = record?;
+        for (i, report) in reports.iter_mut().enumerate() {
+            let field = record.fields.get(i).map_or("", String::as_str);
+            match classify(field) {
+                None => report.nulls += 1,
+                Some(ty) => {
+                    report.ty = Some(report.ty.take().map_or(ty.clone(), |seen| widen(seen, ty)));
+                    if report.samples.len() < SAMPLE_VALUES
+                        && !report.samples.iter().any(|s| s == field)
+                    {
+                        report.samples.push(field.to_string());
+                    }
+                }
+            }
+        }
+    }
+    Ok(reports)
+}
+
+fn whole<O>(parsed: PResult<'_, O>) -> bool {
+    matches!(parsed, Ok(("", _)))
+}
+
+/// The narrowest type that reads the whole field, or `None` for a null.
+fn classify(field: &str) -> Option<ColumnType> {
+    let field = field.trim();
+    if field.is_empty() || whole(null::apply_grammar(field)) {
+        None
+    } else if whole(int::apply_grammar(field)) {
+        Some(ColumnType::Int)
+    } else if whole(float::apply_grammar(field)) {
+        Some(ColumnType::Float)
+    } else if whole(boolean::apply_grammar(field)) {
+        Some(ColumnType::Bool)
+    } else if field.parse::<Inet>().is_ok() {
+        Some(ColumnType::Inet)
+    } else {
+        Some(ColumnType::Text)
+    }
+}
+
+/// The type both values fit in: ints widen to floats, anything else mixed
+/// becomes text.
+pub(crate) fn widen(a: ColumnType, b: ColumnType) -> ColumnType {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (ColumnType::Int, ColumnType::Float) | (ColumnType::Float, ColumnType::Int) => {
+            ColumnType::Float
+        }
+        _ => ColumnType::Text,
+    }
+}
+
+/// Makes a header or file name usable as an unquoted identifier.
+pub fn identifier_for(name: &str) -> String {
+    let mut out: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// A table of the reports followed by a matching `CREATE TABLE` statement.
+/// Columns with no non-null values are declared as TEXT.
+pub fn render(table: &str, reports: &[ColumnReport]) -> String {
+    let mut out = String::from("column | type | nulls | samples\n");
+    for report in reports {
+        out.push_str(&format!(
+            "{} | {} | {} | {}\n",
+            report.name,
+            report
+                .ty
+                .as_ref()
+                .map_or("NULL".to_string(), ColumnType::to_string),
+            report.nulls,
+            report.samples.join(", ")
+        ));
+    }
+    let columns: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            let ty = report.ty.clone().unwrap_or(ColumnType::Text);
+            format!("{} {ty}", identifier_for(&report.name))
+        })
+        .collect();
+    out.push_str(&format!(
+        "\nCREATE TABLE {} ({})\n",
+        identifier_for(table),
+        columns.join(", ")
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEOPLE: &str = "id,name,score,active,note\n\
+                          1,ann,3.5,true,\n\
+                          2,bob,4,false,null\n\
+                          3,cy,,TRUE,\n\
+                          4,ann,n/a,false,\n";
+
+    #[test]
+    fn infers_types_nulls_and_samples() {
+        let reports = infer(PEOPLE, 100).unwrap();
+        let summary: Vec<(&str, Option<ColumnType>, usize)> = reports
+            .iter()
+            .map(|r| (r.name.as_str(), r.ty.clone(), r.nulls))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("id", Some(ColumnType::Int), 0),
+                ("name", Some(ColumnType::Text), 0),
+                ("score", Some(ColumnType::Text), 1),
+                ("active", Some(ColumnType::Bool), 0),
+                ("note", None, 4),
+            ]
+        );
+        assert_eq!(reports[1].samples, vec!["ann", "bob", "cy"]);
+    }
+
+    #[test]
+    fn ints_widen_to_float_and_sampling_is_bounded() {
+        let reports = infer("x\n1\n2.5\nword\n", 2).unwrap();
+        assert_eq!(reports[0].ty, Some(ColumnType::Float));
+        let reports = infer("ip\n10.0.0.1\n10.0.0.0/8\n::1\n", 10).unwrap();
+        assert_eq!(reports[0].ty, Some(ColumnType::Inet));
+    }
+
+    #[test]
+    fn renders_create_table() {
+        let reports = infer("Order ID,2nd value\n1,2.0\n", 10).unwrap();
+        let text = render("orders-2024", &reports);
+        assert!(text.ends_with("CREATE TABLE orders_2024 (Order_ID INT, _2nd_value FLOAT)\n"));
+    }
+}
@@ -0,0 +1,192 @@
+//! The `--output json` rendering of statement results: one JSON object per
+//! statement, on its own line.
+//!
+//! A query's result gives its `schema`, an object per column with its
+//! `name`, its `type` as a column would be declared (`null` when the query
+//! does not settle it) and whether it is `nullable`, then its `rows`.
+//!
+//! Values are written as `{"type": ..., "value": ...}` so that an int and a
+//! float with the same value stay apart. Floats that are not finite, which
+//! JSON cannot hold, are written as the strings `"NaN"`, `"inf"` and
+//! `"-inf"`, inet addresses as strings such as `"10.0.0.0/8"`, and points
+//! as `[lat, lon]`.
+
+use std::fmt::Write;
+
+use crate::error::ToyQlError;
+use crate::literals::LiteralValue;
+use crate::statements::Outcome;
+
+/// A statement that ran. Queries give their columns and rows; other
+/// statements say what they changed.
+pub fn outcome(index: usize, outcome: &Outcome) -> String {
+    let body = match outcome {
+        Outcome::Rows(result) => {
+            let columns: Vec<String> = result
+                .schema()
+                .iter()
+                .map(|column| {
+                    let ty = column
+                        .ty
+                        .map_or("null".to_string(), |ty| string(&ty.to_string()));
+                    format!(
+                        "{{\"name\":{},\"type\":{ty},\"nullable\":{}}}",
+                        string(column.name),
+                        column.nullable
+                    )
+                })
+                .collect();
+            let rows: Vec<String> = result
+                .rows
+                .iter()
+                .map(|row| {
+                    let values: Vec<String> = row.iter().map(value).collect();
+                    format!("[{}]", values.join(","))
+                })
+                .collect();
+            format!(
+                "\"schema\":[{}],\"rows\":[{}]",
+                columns.join(","),
+                rows.join(",")
+            )
+        }
+        Outcome::Explained(plan) => {
+            let lines: Vec<String> = plan.lines().map(string).collect();
+            format!("\"plan\":[{}]", lines.join(","))
+        }
+        Outcome::Created(name) => format!("\"created\":{}", string(name)),
+        Outcome::DefinedMacro(name) => format!("\"macro\":{}", string(name)),
+        Outcome::Inserted(count) => format!("\"inserted\":{count}"),
+        Outcome::Altered(name) => format!("\"altered\":{}", string(name)),
+        Outcome::Set(name) => format!("\"set\":{}", string(name)),
+    };
+    format!("{{\"statement\":{index},{body}}}")
+}
+
+/// A statement that failed. Parse errors also give where they happened and
+/// the text from there on that was left unparsed.
+pub fn error(index: usize, text: &str, error: &ToyQlError) -> String {
+    let mut body = String::new();
+    match error {
+        ToyQlError::Parse(e) => {
+            write!(
+                body,
+                "\"kind\":\"parse\",\"message\":{},\"line\":{},\"column\":{},\"remainder\":{}",
+                string(&e.message()),
+                e.line,
+                e.column,
+                string(&text[e.offset..])
+            )
+            .unwrap();
+        }
+        ToyQlError::Exec(e) => {
+            write!(
+                body,
+                "\"kind\":\"exec\",\"message\":{}",
+                string(&e.to_string())
+            )
+            .unwrap();
+        }
+        e => {
+            write!(
+                body,
+                "\"kind\":\"other\",\"message\":{}",
+                string(&e.to_string())
+            )
+            .unwrap();
+        }
+    }
+    format!("{{\"statement\":{index},\"error\":{{{body}}}}}")
+}
+
+fn value(value: &LiteralValue) -> String {
+    let json = match value {
+        LiteralValue::Float(v) if !v.is_finite() => string(&value.serialize()),
+        LiteralValue::Int(_) | LiteralValue::Float(_) | LiteralValue::Bool(_) => value.serialize(),
+        LiteralValue::String(v) => string(v),
+        LiteralValue::Inet(v) => string(&v.to_string()),
+        LiteralValue::Point(v) => format!("[{},{}]", v.lat(), v.lon()),
+        LiteralValue::List(items) => {
+            let items: Vec<String> = items.iter().map(self::value).collect();
+            format!("[{}]", items.join(","))
+        }
+        LiteralValue::Null => "null".to_string(),
+    };
+    format!("{{\"type\":\"{}\",\"value\":{json}}}", value.type_name())
+}
+
+/// A JSON string literal.
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::ColumnType;
+    use crate::statements::{ResultSet, ValueType};
+    use crate::table::Values;
+
+    #[test]
+    fn renders_rows_with_types() {
+        let result = ResultSet {
+            columns: vec!["a".to_string(), "b \"q\"".to_string()],
+            types: vec![ValueType {
+                ty: Some(ColumnType::Int),
+                nullable: false,
+            }],
+            rows: vec![
+                Values::from([LiteralValue::Int(1), LiteralValue::Float(f64::NAN)]),
+                Values::from([
+                    LiteralValue::String("x\ny\u{1}".to_string()),
+                    LiteralValue::Null,
+                ]),
+            ],
+        };
+        assert_eq!(
+            outcome(1, &Outcome::Rows(result)),
+            "{\"statement\":1,\"schema\":[\
+             {\"name\":\"a\",\"type\":\"INT\",\"nullable\":false},\
+             {\"name\":\"b \\\"q\\\"\",\"type\":null,\"nullable\":true}],\"rows\":[\
+             [{\"type\":\"int\",\"value\":1},{\"type\":\"float\",\"value\":\"NaN\"}],\
+             [{\"type\":\"string\",\"value\":\"x\\ny\\u0001\"},{\"type\":\"null\",\"value\":null}]]}"
+        );
+        assert_eq!(
+            outcome(2, &Outcome::Inserted(3)),
+            "{\"statement\":2,\"inserted\":3}"
+        );
+        assert_eq!(
+            outcome(
+                3,
+                &Outcome::Explained("Project: 1\n  Single row\n".to_string())
+            ),
+            "{\"statement\":3,\"plan\":[\"Project: 1\",\"  Single row\"]}"
+        );
+    }
+
+    #[test]
+    fn renders_errors() {
+        let text = "SELECT 1 FRM t";
+        let e = ToyQlError::from(crate::statements::parsing::parse(text).unwrap_err());
+        assert_eq!(
+            error(4, text, &e),
+            "{\"statement\":4,\"error\":{\"kind\":\"parse\",\
+             \"message\":\"expected end of input, found `FRM`\",\
+             \"line\":1,\"column\":10,\"remainder\":\"FRM t\"}}"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Splits query text into tokens, and the primitives grammars use to match
+//! them.
+//!
+//! Expression and statement grammars run over [`Tokens`] rather than
+//! characters, so whitespace, comments and keyword boundaries are dealt with
+//! once, here. Errors still point into the source text: a failed match
+//! reports the text from the offending token onwards.
+
+pub mod parsing;
+
+use std::ops::Range;
+
+use nom::error::{ContextError, ErrorKind, FromExternalError};
+use nom::{IResult, InputLength};
+
+use crate::error::{GrammarError, ParseError};
+use crate::literals::LiteralValue;
+
+/// Result type of every grammar over tokens.
+pub type TokenResult<'a, T> = IResult<Tokens<'a>, T, GrammarError<'a>>;
+
+/// Words that can never be identifiers unless quoted.
+pub const RESERVED: &[&str] = &[
+    "and", "or", "not", "true", "false", "null", "select", "from", "where", "limit", "as",
+    "create", "table", "insert", "into", "values", "alter", "is", "in", "like", "order", "by",
+    "offset", "group", "join", "inner", "on",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// A reserved word, lowercased.
+    Keyword(String),
+    /// A bare name, as written.
+    Identifier(String),
+    /// A name in backticks, with doubled backticks unescaped. It may be a
+    /// reserved word.
+    QuotedIdentifier(String),
+    Literal(LiteralValue),
+    /// An operator or punctuation, e.g. `<=` or `(`.
+    Symbol(&'static str),
+    /// A placeholder for a value bound at execution: `$n` is parameter `n`,
+    /// and each `?` is the parameter after the previous `?`, from 1.
+    Parameter(usize),
+    /// `@name`: a session variable.
+    Variable(String),
+    /// A `/*+ ... */` comment right after `SELECT`: a hint to the planner,
+    /// given as the text between the markers with surrounding whitespace
+    /// removed. Anywhere else such a comment is just a comment.
+    Hint(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range of the token in the source.
+    pub span: Range<usize>,
+}
+
+/// The input of token grammars: the tokens left to parse, and the source
+/// they came from.
+#[derive(Debug, Clone, Copy)]
+pub struct Tokens<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(source: &'a str, tokens: &'a [Token]) -> Tokens<'a> {
+        Tokens { source, tokens }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The source text from the next token onwards.
+    pub fn rest_source(&self) -> &'a str {
+        match self.tokens.first() {
+            Some(token) => &self.source[token.span.start..],
+            None => &self.source[self.source.len()..],
+        }
+    }
+
+    fn split_first(&self) -> Option<(&'a Token, Tokens<'a>)> {
+        let (first, rest) = self.tokens.split_first()?;
+        Some((
+            first,
+            Tokens {
+                source: self.source,
+                tokens: rest,
+            },
+        ))
+    }
+}
+
+impl InputLength for Tokens<'_> {
+    fn input_len(&self) -> usize {
+        self.tokens.len()
+    }
+}
+
+impl<'a> nom::error::ParseError<Tokens<'a>> for GrammarError<'a> {
+    fn from_error_kind(input: Tokens<'a>, kind: ErrorKind) -> Self {
+        nom::error::ParseError::from_error_kind(input.rest_source(), kind)
+    }
+
+    fn append(_input: Tokens<'a>, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        nom::error::ParseError::<&str>::or(self, other)
+    }
+}
+
+impl<'a> ContextError<Tokens<'a>> for GrammarError<'a> {
+    fn add_context(input: Tokens<'a>, ctx: &'static str, other: Self) -> Self {
+        ContextError::add_context(input.rest_source(), ctx, other)
+    }
+}
+
+impl<'a, E> FromExternalError<Tokens<'a>, E> for GrammarError<'a> {
+    fn from_external_error(input: Tokens<'a>, kind: ErrorKind, e: E) -> Self {
+        FromExternalError::from_external_error(input.rest_source(), kind, e)
+    }
+}
+
+/// Writes `name` so that it lexes back as the same identifier: bare when it
+/// can be, otherwise in backticks.
+pub fn quote_identifier(name: &str) -> String {
+    let bare = parsing::tokenize(name).is_ok_and(|tokens| {
+        matches!(
+            tokens.as_slice(),
+            [Token { kind: TokenKind::Identifier(_), span }] if *span == (0..name.len())
+        )
+    });
+    if bare {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+/// Tokenizes `source` and runs `grammar` over all of it: tokens left over
+/// are an error at the first of them.
+pub fn parse_all<T>(
+    source: &str,
+    grammar: impl for<'a> Fn(Tokens<'a>) -> TokenResult<'a, T>,
+) -> Result<T, ParseError> {
+    let tokens = parsing::tokenize(source)?;
+    match grammar(Tokens::new(source, &tokens)) {
+        Ok((rest, value)) if rest.is_empty() => Ok(value),
+        Ok((rest, _)) => Err(ParseError::at(
+            source,
+            rest.rest_source(),
+            vec!["end of input".to_string()],
+        )),
+        Err(e) => Err(ParseError::from_nom(source, e)),
+    }
+}
+
+/// Matches one token for which `f` gives a value; otherwise fails expecting
+/// what `expected` describes. The description is only built on failure, as
+/// building it for every attempt would cost more than matching.
+fn token<'a, O>(
+    expected: impl Fn() -> String,
+    f: impl Fn(&'a TokenKind) -> Option<O>,
+) -> impl Fn(Tokens<'a>) -> TokenResult<'a, O> {
+    move |input: Tokens<'a>| match input.split_first() {
+        Some((token, rest)) => match f(&token.kind) {
+            Some(value) => Ok((rest, value)),
+            None => Err(nom::Err::Error(GrammarError::expected(
+                input.rest_source(),
+                expected(),
+            ))),
+        },
+        None => Err(nom::Err::Error(GrammarError::expected(
+            input.rest_source(),
+            expected(),
+        ))),
+    }
+}
+
+/// Matches the reserved word `word`, given in lowercase.
+pub fn keyword<'a>(word: &'static str) -> impl Fn(Tokens<'a>) -> TokenResult<'a, ()> {
+    token(
+        move || format!("`{}`", word.to_ascii_uppercase()),
+        move |kind| matches!(kind, TokenKind::Keyword(k) if k == word).then_some(()),
+    )
+}
+
+/// Matches a bare, unreserved word case-insensitively, such as a type name.
+pub fn word<'a>(word: &'static str) -> impl Fn(Tokens<'a>) -> TokenResult<'a, ()> {
+    token(
+        move || format!("`{}`", word.to_ascii_uppercase()),
+        move |kind| {
+            matches!(kind, TokenKind::Identifier(name) if name.eq_ignore_ascii_case(word))
+                .then_some(())
+        },
+    )
+}
+
+/// Matches an operator or punctuation symbol.
+pub fn symbol<'a>(symbol: &'static str) -> impl Fn(Tokens<'a>) -> TokenResult<'a, ()> {
+    token(
+        move || format!("`{symbol}`"),
+        move |kind| (*kind == TokenKind::Symbol(symbol)).then_some(()),
+    )
+}
+
+/// The text of a hint, `/*+ <text> */`.
+pub fn hint(input: Tokens<'_>) -> TokenResult<'_, String> {
+    token(
+        || "hint".to_string(),
+        |kind| match kind {
+            TokenKind::Hint(text) => Some(text.clone()),
+            _ => None,
+        },
+    )(input)
+}
+
+/// A bare or quoted name.
+pub fn identifier(input: Tokens<'_>) -> TokenResult<'_, String> {
+    token(
+        || "identifier".to_string(),
+        |kind| match kind {
+            TokenKind::Identifier(name) | TokenKind::QuotedIdentifier(name) => Some(name.clone()),
+            _ => None,
+        },
+    )(input)
+}
+
+/// Any bare word, reserved or not, lowercased.
+pub fn bare_word(input: Tokens<'_>) -> TokenResult<'_, String> {
+    token(
+        || "word".to_string(),
+        |kind| match kind {
+            TokenKind::Identifier(word) | TokenKind::Keyword(word) => {
+                Some(word.to_ascii_lowercase())
+            }
+            _ => None,
+        },
+    )(input)
+}
+
+pub fn variable(input: Tokens<'_>) -> TokenResult<'_, String> {
+    token(
+        || "variable".to_string(),
+        |kind| match kind {
+            TokenKind::Variable(name) => Some(name.clone()),
+            _ => None,
+        },
+    )(input)
+}
+
+pub fn parameter(input: Tokens<'_>) -> TokenResult<'_, usize> {
+    token(
+        || "parameter".to_string(),
+        |kind| match kind {
+            TokenKind::Parameter(n) => Some(*n),
+            _ => None,
+        },
+    )(input)
+}
+
+pub fn literal(input: Tokens<'_>) -> TokenResult<'_, LiteralValue> {
+    token(
+        || "literal".to_string(),
+        |kind| match kind {
+            TokenKind::Literal(value) => Some(value.clone()),
+            _ => None,
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives_match_single_tokens() {
+        let source = "SELECT `select` ( Int";
+        let tokens = parsing::tokenize(source).unwrap();
+        let input = Tokens::new(source, &tokens);
+        let (input, ()) = keyword("select")(input).unwrap();
+        let (input, name) = identifier(input).unwrap();
+        assert_eq!(name, "select");
+        let (input, ()) = symbol("(")(input).unwrap();
+        let (input, ()) = word("int")(input).unwrap();
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn quotes_identifiers_only_when_needed() {
+        for (name, written) in [
+            ("a_1", "a_1"),
+            ("from", "`from`"),
+            ("inf", "`inf`"),
+            ("two words", "`two words`"),
+            ("a`b", "`a``b`"),
+            ("a--", "`a--`"),
+            (" a", "` a`"),
+            ("1a", "`1a`"),
+        ] {
+            assert_eq!(quote_identifier(name), written);
+            assert_eq!(parse_all(written, identifier), Ok(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn parse_all_reports_leftover_tokens() {
+        assert_eq!(parse_all(" a ", identifier), Ok("a".to_string()));
+        let error = parse_all("a (", identifier).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 3: expected end of input, found `(`"
+        );
+    }
+}
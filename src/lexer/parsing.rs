@@ -0,0 +1,420 @@
+//! nom grammar turning characters into tokens.
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while};
+use nom::character::complete::{char, digit0, multispace1, not_line_ending, satisfy};
+use nom::combinator::{map, recognize, value};
+use nom::multi::many0;
+use nom::sequence::pair;
+
+use super::{Token, TokenKind, RESERVED};
+use crate::error::{GrammarError, PResult, ParseError};
+use crate::literals::{self, LiteralValue};
+
+/// Operators and punctuation, longest first so that `<=` is not read as `<`.
+const SYMBOLS: &[&str] = &[
+    "<<", "<=", ">=", "<>", "!=", "||", "=", "<", ">", "+", "-", "*", "/", "%", "(", ")", ",", ";",
+    ".",
+];
+
+/// Splits `source` into tokens, dropping whitespace and comments.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, ParseError> {
+    lex(source, false)
+}
+
+/// The tokens of `source` up to and including its first `;`, or all of
+/// them if it has none. What follows the `;` is not read, so a script's
+/// statements can be lexed one at a time in time proportional to the
+/// script's length.
+pub fn tokenize_statement(source: &str) -> Result<Vec<Token>, ParseError> {
+    lex(source, true)
+}
+
+fn lex(source: &str, to_semicolon: bool) -> Result<Vec<Token>, ParseError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut input = source;
+    let mut question_marks = 0;
+    loop {
+        if tokens
+            .last()
+            .is_some_and(|t| matches!(&t.kind, TokenKind::Keyword(k) if k == "select"))
+        {
+            input = input.trim_start_matches([' ', '\t', '\r', '\n']);
+            if input.starts_with("/*+") {
+                let (rest, comment) = match block_comment(input) {
+                    Ok(parsed) => parsed,
+                    Err(e) => return Err(ParseError::from_nom(source, e)),
+                };
+                tokens.push(Token {
+                    kind: TokenKind::Hint(comment[3..comment.len() - 2].trim().to_string()),
+                    span: source.len() - input.len()..source.len() - rest.len(),
+                });
+                input = rest;
+                continue;
+            }
+        }
+        input = match trivia(input) {
+            Ok((rest, ())) => rest,
+            Err(e) => return Err(ParseError::from_nom(source, e)),
+        };
+        if input.is_empty() {
+            return Ok(tokens);
+        }
+        let after_operand = tokens.last().is_some_and(|t| ends_operand(&t.kind));
+        let (rest, mut kind) = match token(input, after_operand) {
+            Ok(parsed) => parsed,
+            Err(e) => return Err(ParseError::from_nom(source, e)),
+        };
+        if kind == TokenKind::Parameter(0) {
+            question_marks += 1;
+            kind = TokenKind::Parameter(question_marks);
+        }
+        let semicolon = kind == TokenKind::Symbol(";");
+        tokens.push(Token {
+            kind,
+            span: source.len() - input.len()..source.len() - rest.len(),
+        });
+        if semicolon && to_semicolon {
+            return Ok(tokens);
+        }
+        input = rest;
+    }
+}
+
+/// Whether a `-` after this token is subtraction rather than the sign of a
+/// number: `a-1` is `a - 1`, but `a * -1` multiplies by the literal `-1`.
+fn ends_operand(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Identifier(_)
+            | TokenKind::QuotedIdentifier(_)
+            | TokenKind::Literal(_)
+            | TokenKind::Parameter(_)
+            | TokenKind::Variable(_)
+            | TokenKind::Symbol(")")
+    )
+}
+
+/// Whitespace, `-- line` comments and `/* block */` comments.
+fn trivia(input: &str) -> PResult<'_, ()> {
+    value(
+        (),
+        many0(alt((
+            multispace1,
+            recognize(pair(tag("--"), not_line_ending)),
+            block_comment,
+        ))),
+    )(input)
+}
+
+fn block_comment(input: &str) -> PResult<'_, &str> {
+    let (body, _) = tag("/*")(input)?;
+    match body.find("*/") {
+        Some(end) => Ok((&body[end + 2..], &input[..end + 4])),
+        None => Err(nom::Err::Failure(GrammarError::expected(
+            &body[body.len()..],
+            "`*/`",
+        ))),
+    }
+}
+
+fn token(input: &str, after_operand: bool) -> PResult<'_, TokenKind> {
+    if after_operand && input.starts_with('-') {
+        return symbol(input);
+    }
+    if input.starts_with(['\'', '"']) {
+        return match literals::parsing::string::apply_grammar(input) {
+            Ok((rest, text)) => Ok((
+                rest,
+                TokenKind::Literal(LiteralValue::String(text.into_owned())),
+            )),
+            Err(nom::Err::Failure(e)) => Err(nom::Err::Failure(e)),
+            Err(_) => Err(nom::Err::Failure(GrammarError::expected(
+                &input[input.len()..],
+                "closing quote",
+            ))),
+        };
+    }
+    // Most tokens are words or symbols, which cannot be literals; trying
+    // every literal grammar on them first would make lexing several times
+    // slower.
+    if input.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        let (rest, kind) = word(input)?;
+        let lower = &input[..input.len() - rest.len()].to_ascii_lowercase();
+        if !literals::parsing::WORDS.contains(&lower.as_str()) {
+            return Ok((rest, kind));
+        }
+    } else if !input.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-') {
+        return alt((quoted_identifier, parameter, variable, symbol))(input)
+            .map_err(|e| unexpected(input, e));
+    }
+    alt((
+        map(literals::parsing::apply_grammar, TokenKind::Literal),
+        word,
+        quoted_identifier,
+        parameter,
+        variable,
+        symbol,
+    ))(input)
+    .map_err(|e| unexpected(input, e))
+}
+
+/// What each kind of token would have accepted is noise when none of them
+/// matched: the error is just that `input` was unexpected.
+fn unexpected<'a>(input: &'a str, e: nom::Err<GrammarError<'a>>) -> nom::Err<GrammarError<'a>> {
+    match e {
+        nom::Err::Error(_) => nom::Err::Error(GrammarError {
+            input,
+            expected: Vec::new(),
+            reason: None,
+        }),
+        e => e,
+    }
+}
+
+fn word(input: &str) -> PResult<'_, TokenKind> {
+    map(
+        recognize(pair(
+            satisfy(|c| c.is_ascii_alphabetic() || c == '_'),
+            take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        )),
+        |word: &str| {
+            let lower = word.to_ascii_lowercase();
+            if RESERVED.contains(&lower.as_str()) {
+                TokenKind::Keyword(lower)
+            } else {
+                TokenKind::Identifier(word.to_string())
+            }
+        },
+    )(input)
+}
+
+/// `@name`, where the name is written as a bare word, reserved or not.
+fn variable(input: &str) -> PResult<'_, TokenKind> {
+    let (rest, _) = char('@')(input)?;
+    match word(rest) {
+        Ok((rest, TokenKind::Keyword(name) | TokenKind::Identifier(name))) => {
+            Ok((rest, TokenKind::Variable(name)))
+        }
+        _ => Err(nom::Err::Failure(GrammarError::expected(
+            rest,
+            "variable name",
+        ))),
+    }
+}
+
+/// `$n` with `n` from 1, or `?`, which `tokenize` numbers.
+fn parameter(input: &str) -> PResult<'_, TokenKind> {
+    if let Some(rest) = input.strip_prefix('?') {
+        return Ok((rest, TokenKind::Parameter(0)));
+    }
+    let (rest, _) = char('$')(input)?;
+    let (after, digits) = digit0(rest)?;
+    match digits.parse() {
+        Ok(n) if n > 0 => Ok((after, TokenKind::Parameter(n))),
+        _ => Err(nom::Err::Failure(GrammarError::expected(
+            rest,
+            "parameter number",
+        ))),
+    }
+}
+
+/// `` `name` ``, where a doubled backtick stands for one.
+fn quoted_identifier(input: &str) -> PResult<'_, TokenKind> {
+    let (mut rest, _) = char('`')(input)?;
+    let mut name = String::new();
+    loop {
+        match rest.find('`') {
+            Some(i) if rest[i + 1..].starts_with('`') => {
+                name.push_str(&rest[..=i]);
+                rest = &rest[i + 2..];
+            }
+            Some(i) if i > 0 || !name.is_empty() => {
+                name.push_str(&rest[..i]);
+                return Ok((&rest[i + 1..], TokenKind::QuotedIdentifier(name)));
+            }
+            Some(_) => {
+                return Err(nom::Err::Failure(GrammarError::expected(
+                    &input[1..],
+                    "identifier",
+                )))
+            }
+            None => {
+                return Err(nom::Err::Failure(GrammarError::expected(
+                    &rest[rest.len()..],
+                    "closing backtick",
+                )))
+            }
+        }
+    }
+}
+
+fn symbol(input: &str) -> PResult<'_, TokenKind> {
+    match SYMBOLS.iter().find(|s| input.starts_with(**s)) {
+        Some(s) => Ok((&input[s.len()..], TokenKind::Symbol(s))),
+        None => Err(nom::Err::Error(GrammarError {
+            input,
+            expected: Vec::new(),
+            reason: None,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LiteralValue::{Int, Null};
+
+    use crate::literals::LiteralValue;
+    use crate::test_utils::check;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    fn ident(name: &str) -> TokenKind {
+        TokenKind::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn splits_words_literals_and_symbols() {
+        assert_eq!(
+            kinds("Select a<=1.5,`from` FROM nullable/* x */-- y\nWHERE null"),
+            vec![
+                TokenKind::Keyword("select".to_string()),
+                ident("a"),
+                TokenKind::Symbol("<="),
+                TokenKind::Literal(LiteralValue::Float(1.5)),
+                TokenKind::Symbol(","),
+                TokenKind::QuotedIdentifier("from".to_string()),
+                TokenKind::Keyword("from".to_string()),
+                ident("nullable"),
+                TokenKind::Keyword("where".to_string()),
+                TokenKind::Literal(Null),
+            ]
+        );
+        assert_eq!(
+            kinds("`a``b` 'it''s'")[0],
+            TokenKind::QuotedIdentifier("a`b".to_string())
+        );
+    }
+
+    #[test]
+    fn reads_hints_only_after_select() {
+        let select = TokenKind::Keyword("select".to_string());
+        assert_eq!(
+            kinds("SELECT\n/*+ Assert_Sorted */ 1 /*+ x */"),
+            vec![
+                select.clone(),
+                TokenKind::Hint("Assert_Sorted".to_string()),
+                TokenKind::Literal(Int(1)),
+            ]
+        );
+        assert_eq!(kinds("SELECT /* x */ /*+ y */"), vec![select]);
+        assert_eq!(
+            tokenize("SELECT /*+ x").unwrap_err().to_string(),
+            "line 1, column 13: expected `*/`, found end of input"
+        );
+    }
+
+    #[test]
+    fn minus_after_an_operand_is_an_operator() {
+        let minus = TokenKind::Symbol("-");
+        assert_eq!(
+            kinds("a-1"),
+            vec![ident("a"), minus.clone(), TokenKind::Literal(Int(1))]
+        );
+        assert_eq!(
+            kinds("(1)-1")[3..],
+            [minus.clone(), TokenKind::Literal(Int(1))]
+        );
+        assert_eq!(kinds("a * -1")[2], TokenKind::Literal(Int(-1)));
+        assert_eq!(
+            kinds("- 1"),
+            vec![minus.clone(), TokenKind::Literal(Int(1))]
+        );
+        assert_eq!(
+            kinds("@n-1"),
+            vec![
+                TokenKind::Variable("n".to_string()),
+                minus,
+                TokenKind::Literal(Int(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_cover_the_token_text() {
+        let source = "  abc  'x'";
+        let spans: Vec<_> = tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|t| &source[t.span])
+            .collect();
+        assert_eq!(spans, vec!["abc", "'x'"]);
+    }
+
+    #[test]
+    fn statement_tokens_stop_after_the_semicolon() {
+        let tokens = tokenize_statement("a ; b # c").unwrap();
+        let kinds: Vec<_> = tokens.into_iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![ident("a"), TokenKind::Symbol(";")]);
+        assert_eq!(tokenize_statement("a b").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn arbitrary_text_lexes_or_fails_in_bounds() {
+        check(
+            5000,
+            |g| g.string(),
+            |source| match tokenize(source) {
+                Ok(tokens) => {
+                    let mut end = 0;
+                    for token in tokens {
+                        if token.span.start < end || source.get(token.span.clone()).is_none() {
+                            return Err(format!("bad span {:?}", token.span));
+                        }
+                        end = token.span.end;
+                    }
+                    Ok(())
+                }
+                Err(e) if e.offset <= source.len() && source.is_char_boundary(e.offset) => Ok(()),
+                Err(e) => Err(format!("error at offset {}", e.offset)),
+            },
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for (source, message) in [
+            ("a # b", "line 1, column 3: unexpected `#`"),
+            ("@1", "line 1, column 2: expected variable name, found `1`"),
+            (
+                "'abc",
+                "line 1, column 5: expected closing quote, found end of input",
+            ),
+            (
+                r"'a\qb'",
+                "line 1, column 4: expected escape sequence, found `qb`",
+            ),
+            (
+                "`abc",
+                "line 1, column 5: expected closing backtick, found end of input",
+            ),
+            (
+                "1 /* x",
+                "line 1, column 7: expected `*/`, found end of input",
+            ),
+        ] {
+            assert_eq!(
+                tokenize(source).unwrap_err().to_string(),
+                message,
+                "{source}"
+            );
+        }
+    }
+}
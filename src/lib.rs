@@ -0,0 +1,1074 @@
+//! ToyQL: a small query language, built up from literal values.
+
+pub mod ast;
+pub mod catalog;
+pub mod csv;
+pub mod demo;
+pub mod encoding;
+pub mod error;
+pub mod eval;
+pub mod external;
+#[cfg(feature = "test-fixtures")]
+pub mod fixtures;
+pub mod functions;
+pub mod import;
+pub mod infer;
+pub mod json;
+pub mod lexer;
+pub mod lint;
+pub mod literals;
+pub mod macros;
+pub mod params;
+pub mod quote;
+pub mod repro;
+pub mod statements;
+pub mod stats;
+pub mod table;
+pub mod template;
+pub mod variables;
+
+// The fixtures export only the example data.
+#[cfg(any(test, feature = "test-fixtures"))]
+#[cfg_attr(not(test), allow(dead_code))]
+mod test_utils;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use error::{ParseError, ToyQlError};
+
+/// Default number of data rows `toyql infer` reads.
+const INFER_SAMPLE_ROWS: usize = 1000;
+
+/// Parses and executes the statements of each argument, printing what each
+/// one did. An argument, or a file of statements given with `-f PATH` or
+/// `--file PATH` (run before the arguments; `-` reads standard input,
+/// named `<stdin>` in errors), may hold several statements ended by `;`,
+/// with `--` and `/* */` comments between them. Tables created by one
+/// statement are visible to the later ones, and a failing statement does
+/// not stop the ones after it.
+///
+/// Leading `--stats json` prints a line of JSON per statement to stderr
+/// with timings and sizes (see [`stats::QueryStats`]); `--stats-file PATH`
+/// appends those lines to a file instead. Leading `--output json` prints
+/// each statement's result, or its error, as a line of JSON on stdout (see
+/// [`json`]): it sets the `output` setting, as `SET output = 'json'` does
+/// for the statements after it (see [`variables`]).
+///
+/// `--set NAME=VALUE` sets a session variable or setting before the first
+/// statement. The value is read as a literal if it is one, such as `3` or
+/// `'x'`, and as text otherwise, so `--set nulls=last` works.
+///
+/// `--param VALUE`, given once per placeholder, supplies a literal such as
+/// `42` or `'text'` for `$1`, `$2`, ... (or successive `?`s) in every
+/// statement; see [`statements::PreparedStatement`].
+///
+/// `--params FILE.json` runs all the statements once per parameter set in
+/// the file, with its names set as session variables for `@name` (see
+/// [`params`]); a `null` sets one to NULL. Each run starts with the
+/// variables as they were before the first, and the output of the runs
+/// follows one another.
+///
+/// `--encoding NAME` says how the files that `--file`, `--import` and
+/// `--params` give are encoded: `utf-8`, the default, `latin-1`, `utf-16`,
+/// `utf-16le` or `utf-16be` (see [`encoding`]). A byte order mark at the
+/// start of a file is dropped, and a first line starting `#!` in a file of
+/// statements is a comment, so that the file can be run as a program.
+///
+/// `--import TABLE FILE.csv` loads a CSV file into a table before any
+/// statement runs, creating the table if needed (see [`import`]). It may
+/// be given more than once. `--on-error POLICY` says what a bad row does:
+/// `abort`, the default, loads nothing from the file and stops the run;
+/// `skip` loads the other rows and counts the bad ones on stderr; `log`
+/// also reports each bad row there. `--strict` and `--skip-errors` are
+/// the same as `--on-error abort` and `--on-error log`. `--rejects
+/// FILE.csv` writes the bad rows of every import to a file, with their
+/// line, error and record, and `--rejects-table NAME` appends them to a
+/// table. `--progress N` reports on stderr how many records each import
+/// has read, and how fast, every `N` records; `--progress Ns` does so
+/// every `N` seconds.
+///
+/// `--define NAME=VALUE` gives a value for `{{NAME}}` in the statements,
+/// which are then expanded before they are parsed, as is `{{env.NAME}}`
+/// (see [`template`]). `--templates` expands them without any `--define`,
+/// and `--show-expanded` prints the expanded statements instead of running
+/// them. Otherwise statements are taken as they are, so that `{{` can
+/// appear in them, as in a string literal.
+///
+/// `--resolve-only` prints, for each query, a row per name it uses with
+/// what the name refers to (see [`statements::resolve`]) instead of
+/// running it. Other statements still run, so that later queries see the
+/// tables, macros and variables they make.
+///
+/// `--check` parses the statements without running any, reporting every
+/// syntax error as `SOURCE:LINE:COLUMN: MESSAGE` on stderr; after an error,
+/// checking resumes at the next statement.
+///
+/// `toyql infer <file.csv> [--sample N] [--encoding NAME]` instead prints
+/// the schema inferred for a CSV file.
+///
+/// `toyql demo [OPTIONS] [STATEMENT...]` runs the statements against the
+/// sample tables of [`demo`], which last only for the run; with no
+/// statements, it lists some queries to try.
+///
+/// `toyql lint [--deny CODE] [--warn CODE] [--allow CODE] [-f PATH]
+/// [--encoding NAME] [STATEMENT...]` parses the statements, like `--check`,
+/// and reports what the rules of [`lint`] find in them as
+/// `SOURCE:LINE:COLUMN: LEVEL[CODE]: MESSAGE` on stderr. It exits with 7 if
+/// a denied rule found anything.
+///
+/// `toyql repro <file>` replays an input that crashed toyql, or might,
+/// with the extra checks described in [`repro`], printing a line per
+/// statement.
+///
+/// Returns the process exit code; see [`exit_code`].
+pub fn run_from_args<I: IntoIterator<Item = String>>(args: I) -> i32 {
+    run_with(args, &mut std::io::stdin())
+}
+
+/// [`run_from_args`], reading `stdin` where a path is `-`.
+pub fn run_with<I: IntoIterator<Item = String>>(args: I, stdin: &mut dyn Read) -> i32 {
+    let mut args = args.into_iter().peekable();
+    if args.peek().map(String::as_str) == Some("infer") {
+        args.next();
+        return match run_infer(args.collect()) {
+            Ok(()) => 0,
+            Err(e) => report(&e),
+        };
+    }
+    if args.peek().map(String::as_str) == Some("lint") {
+        args.next();
+        return match run_lint(args.collect(), stdin) {
+            Ok(status) => status,
+            Err(e) => report(&e),
+        };
+    }
+    if args.peek().map(String::as_str) == Some("repro") {
+        args.next();
+        return match run_repro(args.collect()) {
+            Ok(()) => 0,
+            Err(e) => report(&e),
+        };
+    }
+    let demo = args.peek().map(String::as_str) == Some("demo");
+    if demo {
+        args.next();
+    }
+    let Options {
+        stats: mut stats_sink,
+        variables,
+        imports,
+        import,
+        params,
+        parameter_sets,
+        check,
+        files,
+        encoding,
+        defines,
+        templates,
+        show_expanded,
+        resolve_only,
+    } = match options(&mut args, stdin) {
+        Ok(options) => options,
+        Err(e) => return report(&e),
+    };
+    let templates = templates || show_expanded || !defines.is_empty();
+    let sources = match sources(args.collect(), files, encoding, stdin).and_then(|sources| {
+        if templates {
+            expand(sources, &defines)
+        } else {
+            Ok(sources)
+        }
+    }) {
+        Ok(sources) => sources,
+        Err(e) => return report(&e),
+    };
+    if show_expanded {
+        for (_, text) in &sources {
+            println!("{text}");
+        }
+        return 0;
+    }
+    if check {
+        return run_check(&sources);
+    }
+    let mut catalog = catalog::Catalog::new();
+    *catalog.variables_mut() = variables;
+    if demo {
+        if let Err(e) = demo::load(&mut catalog) {
+            return report(&e);
+        }
+        if sources.is_empty() {
+            print_demo_queries();
+            return 0;
+        }
+    }
+    let mut rejected = Vec::new();
+    for (table, path) in imports {
+        match run_import(&mut catalog, &table, path, encoding, &import, stdin) {
+            Ok(skipped) => rejected.extend(skipped),
+            Err(e) => return report(&e),
+        }
+    }
+    if let Err(e) = save_rejects(&mut catalog, &import, &rejected) {
+        return report(&e);
+    }
+    let mut status = 0;
+    let mut index = 0;
+    for set in &parameter_sets.unwrap_or_else(|| vec![Vec::new()]) {
+        let before = catalog.variables().clone();
+        params::bind(set, catalog.variables_mut()).expect("checked when the file was read");
+        for (_, text) in &sources {
+            let mut script = statements::parsing::parse_script(text);
+            loop {
+                let (next, parse) = stats::timed(|| script.next());
+                let Some((span, parsed)) = next else {
+                    break;
+                };
+                index += 1;
+                let mut stats = stats::QueryStats {
+                    index,
+                    parse,
+                    bytes_read: span.len(),
+                    ..stats::QueryStats::default()
+                };
+                let result =
+                    parsed
+                        .map_err(ToyQlError::from)
+                        .and_then(|statement| match statement {
+                            statements::Statement::Select(_)
+                            | statements::Statement::Explain(..)
+                                if resolve_only =>
+                            {
+                                let names = statements::resolve(&statement, &catalog);
+                                Ok(statements::Outcome::Rows(names))
+                            }
+                            _ => run_statement(statement, &params, &mut catalog, &mut stats),
+                        });
+                let json_output = catalog.variables().json_output();
+                match result {
+                    Ok(outcome) => {
+                        let output = if json_output {
+                            json::outcome(index, &outcome) + "\n"
+                        } else {
+                            render_outcome(&outcome)
+                        };
+                        print!("{output}");
+                        stats.bytes_written = output.len();
+                    }
+                    Err(e) if json_output => {
+                        let output = json::error(index, text, &e) + "\n";
+                        print!("{output}");
+                        stats.bytes_written = output.len();
+                        status = exit_code(&e);
+                        stats.exit_code = status;
+                    }
+                    Err(e) => {
+                        status = report(&e);
+                        stats.exit_code = status;
+                    }
+                }
+                if let Some(sink) = &mut stats_sink {
+                    if let Err(e) = writeln!(sink, "{}", stats.to_json()) {
+                        eprintln!("cannot write stats: {e}");
+                        stats_sink = None;
+                    }
+                }
+            }
+        }
+        *catalog.variables_mut() = before;
+    }
+    status
+}
+
+/// The leading options of a statement run.
+#[derive(Default)]
+struct Options {
+    /// Where stats lines go, if anywhere.
+    stats: Option<Box<dyn Write>>,
+    /// Variables set by `--output` and `--set`.
+    variables: variables::Variables,
+    /// Tables to load, with the CSV file for each.
+    imports: Vec<(String, String)>,
+    import: ImportOptions,
+    /// Values for `$1`, `$2`, ... in every statement.
+    params: Vec<literals::LiteralValue>,
+    /// The session variables of each run of the statements; without
+    /// `--params`, they run once with none.
+    parameter_sets: Option<Vec<params::ParameterSet>>,
+    /// Only parse the statements, reporting every syntax error.
+    check: bool,
+    /// Files of statements, run or checked before the arguments.
+    files: Vec<String>,
+    /// How the files the options give are encoded.
+    encoding: encoding::Encoding,
+    /// Values for `{{name}}` in the statements.
+    defines: BTreeMap<String, String>,
+    /// Expand templates even without `--define`.
+    templates: bool,
+    /// Print the statements after expansion instead of running them.
+    show_expanded: bool,
+    /// Report what the names of queries refer to instead of running them.
+    resolve_only: bool,
+}
+
+/// Consumes the leading options, in any order, opening where the stats
+/// go.
+fn options(
+    args: &mut std::iter::Peekable<impl Iterator<Item = String>>,
+    stdin: &mut dyn Read,
+) -> Result<Options, ToyQlError> {
+    let mut options = Options::default();
+    // Read once `--encoding` is known, wherever it comes.
+    let mut params_file = None;
+    loop {
+        match args.peek().map(String::as_str) {
+            Some("--stats") => {
+                args.next();
+                match args.next().as_deref() {
+                    Some("json") => options.stats = Some(Box::new(std::io::stderr())),
+                    _ => {
+                        return Err(ToyQlError::Usage(
+                            "--stats expects a format (json)".to_string(),
+                        ))
+                    }
+                }
+            }
+            Some("--stats-file") => {
+                args.next();
+                let path = args
+                    .next()
+                    .ok_or_else(|| ToyQlError::Usage("--stats-file expects a path".to_string()))?;
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|source| ToyQlError::Io { path, source })?;
+                options.stats = Some(Box::new(file));
+            }
+            Some("--output") => {
+                args.next();
+                let format = match args.next().as_deref() {
+                    Some(format @ ("json" | "text")) => format.to_string(),
+                    _ => {
+                        return Err(ToyQlError::Usage(
+                            "--output expects a format (text or json)".to_string(),
+                        ))
+                    }
+                };
+                let set = options
+                    .variables
+                    .set("output", literals::LiteralValue::String(format));
+                set.expect("`output` takes both formats");
+            }
+            Some("--set") => {
+                args.next();
+                let usage = || ToyQlError::Usage("--set expects NAME=VALUE".to_string());
+                let (name, text) = args
+                    .next()
+                    .as_deref()
+                    .and_then(template::define)
+                    .ok_or_else(usage)?;
+                let value = ParseError::parse_all(text.trim(), literals::parsing::apply_grammar)
+                    .unwrap_or(literals::LiteralValue::String(text));
+                options
+                    .variables
+                    .set(&name, value)
+                    .map_err(|e| ToyQlError::Usage(format!("--set {name}: {e}")))?;
+            }
+            Some("--import") => {
+                args.next();
+                let usage = || ToyQlError::Usage("--import expects a table and a file".to_string());
+                let table = args.next().ok_or_else(usage)?;
+                let path = args.next().ok_or_else(usage)?;
+                options.imports.push((table, path));
+            }
+            Some("--param") => {
+                args.next();
+                let text = args.next().ok_or_else(|| {
+                    ToyQlError::Usage("--param expects a literal such as 42 or 'text'".to_string())
+                })?;
+                let value = ParseError::parse_all(text.trim(), literals::parsing::apply_grammar)
+                    .map_err(|e| ToyQlError::Usage(format!("--param {text}: {e}")))?;
+                options.params.push(value);
+            }
+            Some("--params") => {
+                args.next();
+                let path = args
+                    .next()
+                    .ok_or_else(|| ToyQlError::Usage("--params expects a path".to_string()))?;
+                params_file = Some(path);
+            }
+            Some("--define") => {
+                args.next();
+                let usage = || ToyQlError::Usage("--define expects NAME=VALUE".to_string());
+                let (name, value) = args
+                    .next()
+                    .as_deref()
+                    .and_then(template::define)
+                    .ok_or_else(usage)?;
+                options.defines.insert(name, value);
+            }
+            Some("--templates") => {
+                args.next();
+                options.templates = true;
+            }
+            Some("--show-expanded") => {
+                args.next();
+                options.show_expanded = true;
+            }
+            Some("--resolve-only") => {
+                args.next();
+                options.resolve_only = true;
+            }
+            Some("--check") => {
+                args.next();
+                options.check = true;
+            }
+            Some("-f" | "--file") => {
+                args.next();
+                let path = args
+                    .next()
+                    .ok_or_else(|| ToyQlError::Usage("--file expects a path".to_string()))?;
+                options.files.push(path);
+            }
+            Some("--encoding") => {
+                args.next();
+                options.encoding = args
+                    .next()
+                    .ok_or_else(|| {
+                        ToyQlError::Usage("--encoding expects an encoding name".to_string())
+                    })?
+                    .parse()?;
+            }
+            Some("--on-error") => {
+                args.next();
+                let (policy, log) = match args.next().as_deref() {
+                    Some("abort") => (import::ErrorPolicy::Strict, true),
+                    Some("skip") => (import::ErrorPolicy::SkipErrors, false),
+                    Some("log") => (import::ErrorPolicy::SkipErrors, true),
+                    _ => {
+                        return Err(ToyQlError::Usage(
+                            "--on-error expects a policy (abort, skip or log)".to_string(),
+                        ))
+                    }
+                };
+                options.import.policy = policy;
+                options.import.log = log;
+            }
+            Some("--strict") => {
+                args.next();
+                options.import.policy = import::ErrorPolicy::Strict;
+                options.import.log = true;
+            }
+            Some("--skip-errors") => {
+                args.next();
+                options.import.policy = import::ErrorPolicy::SkipErrors;
+                options.import.log = true;
+            }
+            Some("--rejects") => {
+                args.next();
+                let path = args
+                    .next()
+                    .ok_or_else(|| ToyQlError::Usage("--rejects expects a path".to_string()))?;
+                options.import.rejects = Some(path);
+            }
+            Some("--rejects-table") => {
+                args.next();
+                let table = args.next().ok_or_else(|| {
+                    ToyQlError::Usage("--rejects-table expects a table".to_string())
+                })?;
+                options.import.rejects_table = Some(table);
+            }
+            Some("--progress") => {
+                args.next();
+                let interval = args
+                    .next()
+                    .and_then(|interval| progress_interval(&interval));
+                options.import.progress = Some(interval.ok_or_else(|| {
+                    ToyQlError::Usage(
+                        "--progress expects a number of records, or of seconds as `5s`".to_string(),
+                    )
+                })?);
+            }
+            _ => {
+                if let Some(path) = params_file {
+                    options.parameter_sets = Some(parameter_sets(&path, options.encoding, stdin)?);
+                }
+                return Ok(options);
+            }
+        }
+    }
+}
+
+/// The parameter sets of a `--params` file, each checked against the
+/// settings it may set.
+fn parameter_sets(
+    path: &str,
+    encoding: encoding::Encoding,
+    stdin: &mut dyn Read,
+) -> Result<Vec<params::ParameterSet>, ToyQlError> {
+    let text = read_text(path, encoding, stdin)?;
+    let sets =
+        params::parse(&text).map_err(|e| ToyQlError::Usage(format!("--params {path}: {e}")))?;
+    for set in &sets {
+        params::bind(set, &mut variables::Variables::default())
+            .map_err(|e| ToyQlError::Usage(format!("--params {path}: {e}")))?;
+    }
+    Ok(sets)
+}
+
+/// What `--import` does with bad rows.
+struct ImportOptions {
+    policy: import::ErrorPolicy,
+    /// Report each skipped row on stderr, not just their number.
+    log: bool,
+    /// A CSV file to write the skipped rows to.
+    rejects: Option<String>,
+    /// A table to append the skipped rows to.
+    rejects_table: Option<String>,
+    /// When to report how far each import has got.
+    progress: Option<import::ProgressInterval>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            policy: import::ErrorPolicy::Strict,
+            log: true,
+            rejects: None,
+            rejects_table: None,
+            progress: None,
+        }
+    }
+}
+
+/// `1000` records or `5s`, as `--progress` takes.
+fn progress_interval(text: &str) -> Option<import::ProgressInterval> {
+    match text.strip_suffix('s') {
+        Some(seconds) => {
+            let seconds: f64 = seconds.parse().ok()?;
+            let seconds = std::time::Duration::try_from_secs_f64(seconds).ok()?;
+            Some(import::ProgressInterval::Time(seconds))
+        }
+        None => match text.parse().ok()? {
+            0 => None,
+            records => Some(import::ProgressInterval::Records(records)),
+        },
+    }
+}
+
+/// Loads a CSV file into a table, reporting skipped rows, progress as
+/// `--progress` asks and the number loaded on stderr. Returns the skipped
+/// rows.
+fn run_import(
+    catalog: &mut catalog::Catalog,
+    table: &str,
+    path: String,
+    encoding: encoding::Encoding,
+    options: &ImportOptions,
+    stdin: &mut dyn Read,
+) -> Result<Vec<import::RowError>, ToyQlError> {
+    let text = read_text(&path, encoding, stdin)?;
+    let path = source_name(&path).to_string();
+    let report = match options.progress {
+        Some(interval) => import::import_reporting(
+            catalog,
+            table,
+            &text,
+            options.policy,
+            interval,
+            &mut |progress| eprintln!("{path}: {progress}"),
+        ),
+        None => import::import(catalog, table, &text, options.policy),
+    }
+    .map_err(|source| ToyQlError::Import {
+        path: path.clone(),
+        source,
+    })?;
+    for message in import_messages(&path, table, &report, options.log) {
+        eprintln!("{message}");
+    }
+    Ok(report.skipped)
+}
+
+/// What an import reports on stderr: each skipped row when `log` is set,
+/// else how many were skipped, then how many rows were loaded.
+fn import_messages(
+    path: &str,
+    table: &str,
+    report: &import::ImportReport,
+    log: bool,
+) -> Vec<String> {
+    let mut messages = Vec::new();
+    if log {
+        for skipped in &report.skipped {
+            messages.push(format!("{path}: {skipped}; skipped"));
+        }
+    } else if !report.skipped.is_empty() {
+        messages.push(format!("{path}: skipped {} bad rows", report.skipped.len()));
+    }
+    messages.push(format!(
+        "imported {} rows into `{table}` from {path}",
+        report.rows
+    ));
+    messages
+}
+
+/// Keeps the rows skipped by every import where `--rejects` and
+/// `--rejects-table` say.
+fn save_rejects(
+    catalog: &mut catalog::Catalog,
+    options: &ImportOptions,
+    rejected: &[import::RowError],
+) -> Result<(), ToyQlError> {
+    if let Some(path) = &options.rejects {
+        std::fs::write(path, import::rejects_csv(rejected)).map_err(|source| ToyQlError::Io {
+            path: path.clone(),
+            source,
+        })?;
+    }
+    if let Some(table) = &options.rejects_table {
+        import::record_rejects(catalog, table, rejected).map_err(|e| ToyQlError::Exec(e.into()))?;
+    }
+    Ok(())
+}
+
+/// The process exit code for each kind of failure.
+pub fn exit_code(error: &ToyQlError) -> i32 {
+    match error {
+        ToyQlError::Usage(_) | ToyQlError::UnknownEncoding(_) => 2,
+        ToyQlError::Parse(_) | ToyQlError::Template { .. } => 3,
+        ToyQlError::Exec(_) => 4,
+        ToyQlError::Io { .. }
+        | ToyQlError::Decode { .. }
+        | ToyQlError::Csv { .. }
+        | ToyQlError::Import { .. } => 5,
+        ToyQlError::Repro { .. } => 6,
+    }
+}
+
+/// Prints the error to stderr and returns its exit code.
+fn report(error: &ToyQlError) -> i32 {
+    match error {
+        ToyQlError::Parse(e) => eprintln!("{}", e.render()),
+        e => eprintln!("{e}"),
+    }
+    exit_code(error)
+}
+
+/// Runs a parsed statement against the catalog with `params` for its
+/// placeholders, recording how long it took and how many rows it produced.
+pub fn run_statement(
+    statement: statements::Statement,
+    params: &[literals::LiteralValue],
+    catalog: &mut catalog::Catalog,
+    stats: &mut stats::QueryStats,
+) -> Result<statements::Outcome, ToyQlError> {
+    stats.fingerprint = Some(statements::PlanFingerprint::of(&statement));
+    let statement = statements::PreparedStatement::new(statement);
+    let (outcome, elapsed) = stats::timed(|| statement.execute(params, catalog));
+    stats.execute = elapsed;
+    let outcome = outcome?;
+    stats.rows = match &outcome {
+        statements::Outcome::Rows(result) => result.rows.len(),
+        statements::Outcome::Explained(_)
+        | statements::Outcome::Created(_)
+        | statements::Outcome::DefinedMacro(_)
+        | statements::Outcome::Altered(_)
+        | statements::Outcome::Set(_) => 0,
+        statements::Outcome::Inserted(count) => *count,
+    };
+    Ok(outcome)
+}
+
+/// Lists the sample tables and the queries to try on them.
+fn print_demo_queries() {
+    println!("Loaded the sample tables `cities` and `servers`. Try:");
+    for query in demo::QUERIES {
+        println!("  toyql demo {}", quote_argument(query));
+    }
+}
+
+/// `text` quoted for a POSIX shell.
+fn quote_argument(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Reads a file of text in `encoding`, or `stdin` for `-`.
+fn read_text(
+    path: &str,
+    encoding: encoding::Encoding,
+    stdin: &mut dyn Read,
+) -> Result<String, ToyQlError> {
+    let bytes = match path {
+        "-" => {
+            let mut bytes = Vec::new();
+            stdin.read_to_end(&mut bytes).map(|_| bytes)
+        }
+        _ => std::fs::read(path),
+    }
+    .map_err(|source| ToyQlError::Io {
+        path: source_name(path).to_string(),
+        source,
+    })?;
+    encoding::decode(&bytes, encoding).map_err(|source| ToyQlError::Decode {
+        path: source_name(path).to_string(),
+        source,
+    })
+}
+
+/// Makes a first line starting `#!`, as in `#!/usr/bin/env toyql -f`, a
+/// `--` comment, so that a file of statements can be run as a program.
+/// Offsets into the text are unchanged.
+fn comment_shebang(mut text: String) -> String {
+    if text.starts_with("#!") {
+        text.replace_range(..2, "--");
+    }
+    text
+}
+
+/// How errors name the file at `path`; `-` is standard input.
+fn source_name(path: &str) -> &str {
+    match path {
+        "-" => "<stdin>",
+        _ => path,
+    }
+}
+
+/// The text of each file, then of each argument, named as errors in them
+/// are reported.
+fn sources(
+    texts: Vec<String>,
+    files: Vec<String>,
+    encoding: encoding::Encoding,
+    stdin: &mut dyn Read,
+) -> Result<Vec<(String, String)>, ToyQlError> {
+    let mut sources = Vec::new();
+    for path in files {
+        let text = comment_shebang(read_text(&path, encoding, stdin)?);
+        sources.push((source_name(&path).to_string(), text));
+    }
+    sources.extend(
+        texts
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| (format!("argument {}", i + 1), text)),
+    );
+    Ok(sources)
+}
+
+/// Expands the templates in each source.
+fn expand(
+    sources: Vec<(String, String)>,
+    defines: &BTreeMap<String, String>,
+) -> Result<Vec<(String, String)>, ToyQlError> {
+    sources
+        .into_iter()
+        .map(|(name, text)| match template::expand(&text, defines) {
+            Ok(text) => Ok((name, text)),
+            Err(source) => Err(ToyQlError::Template { path: name, source }),
+        })
+        .collect()
+}
+
+/// Parses the statements of each source, printing each syntax error.
+/// Returns the exit code: that of a parse error if there was one.
+fn run_check(sources: &[(String, String)]) -> i32 {
+    let (mut statements, mut errors) = (0, 0);
+    for (name, text) in sources {
+        for (_, result) in statements::parsing::parse_script(text) {
+            statements += 1;
+            if let Err(e) = result {
+                errors += 1;
+                eprintln!("{name}:{}:{}: {}", e.line, e.column, e.message());
+            }
+        }
+    }
+    eprintln!("{errors} syntax errors in {statements} statements");
+    // As for a statement that fails to parse; see `exit_code`.
+    if errors > 0 {
+        3
+    } else {
+        0
+    }
+}
+
+fn run_lint(args: Vec<String>, stdin: &mut dyn Read) -> Result<i32, ToyQlError> {
+    let mut linter = lint::Linter::new();
+    let (mut texts, mut files) = (Vec::new(), Vec::new());
+    let mut encoding = encoding::Encoding::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let level = match arg.as_str() {
+            "--deny" => lint::Level::Deny,
+            "--warn" => lint::Level::Warn,
+            "--allow" => lint::Level::Allow,
+            "-f" | "--file" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| ToyQlError::Usage("--file expects a path".to_string()))?;
+                files.push(path);
+                continue;
+            }
+            "--encoding" => {
+                encoding = args
+                    .next()
+                    .ok_or_else(|| {
+                        ToyQlError::Usage("--encoding expects an encoding name".to_string())
+                    })?
+                    .parse()?;
+                continue;
+            }
+            _ => {
+                texts.push(arg);
+                continue;
+            }
+        };
+        let code = args
+            .next()
+            .ok_or_else(|| ToyQlError::Usage(format!("{arg} expects a rule code")))?;
+        if !linter.set_level(&code, level) {
+            return Err(ToyQlError::Usage(format!("no lint rule `{code}`")));
+        }
+    }
+    let (mut statements, mut errors, mut findings, mut denied) = (0, 0, 0, 0);
+    for (name, text) in &sources(texts, files, encoding, stdin)? {
+        for (span, result) in statements::parsing::parse_script(text) {
+            statements += 1;
+            let statement = match result {
+                Ok(statement) => statement,
+                Err(e) => {
+                    errors += 1;
+                    eprintln!("{name}:{}:{}: {}", e.line, e.column, e.message());
+                    continue;
+                }
+            };
+            // Findings are placed at the statement's first token.
+            let tokens = lexer::parsing::tokenize(&text[span.clone()]).unwrap_or_default();
+            let start = span.start + tokens.first().map_or(0, |token| token.span.start);
+            let (line, column) = error::line_column(text, start);
+            for finding in linter.lint(&statement) {
+                findings += 1;
+                if finding.level == lint::Level::Deny {
+                    denied += 1;
+                }
+                eprintln!(
+                    "{name}:{line}:{column}: {}[{}]: {}",
+                    finding.level, finding.code, finding.message
+                );
+            }
+        }
+    }
+    eprintln!("{findings} findings and {errors} syntax errors in {statements} statements");
+    // A syntax error is reported as for `--check`; see `run_check`.
+    Ok(match (errors, denied) {
+        (0, 0) => 0,
+        (0, _) => 7,
+        _ => 3,
+    })
+}
+
+fn run_infer(args: Vec<String>) -> Result<(), ToyQlError> {
+    const USAGE: &str = "usage: toyql infer <file.csv> [--sample N] [--encoding NAME]";
+    let usage = |message: &str| ToyQlError::Usage(message.to_string());
+    let mut path = None;
+    let mut sample_rows = INFER_SAMPLE_ROWS;
+    let mut encoding = encoding::Encoding::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sample" => {
+                sample_rows = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| usage("--sample expects a row count"))?;
+            }
+            "--encoding" => {
+                encoding = args
+                    .next()
+                    .ok_or_else(|| usage("--encoding expects an encoding name"))?
+                    .parse()?;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(usage(USAGE)),
+        }
+    }
+    let path = path.ok_or_else(|| usage(USAGE))?;
+    let bytes = std::fs::read(&path).map_err(|source| ToyQlError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let text = encoding::decode(&bytes, encoding).map_err(|source| ToyQlError::Decode {
+        path: path.clone(),
+        source,
+    })?;
+    let reports = infer::infer(&text, sample_rows).map_err(|source| ToyQlError::Csv {
+        path: path.clone(),
+        source,
+    })?;
+    let table = std::path::Path::new(&path)
+        .file_stem()
+        .map_or("t".into(), |stem| stem.to_string_lossy());
+    print!("{}", infer::render(&table, &reports));
+    Ok(())
+}
+
+fn run_repro(args: Vec<String>) -> Result<(), ToyQlError> {
+    let [path] = args.as_slice() else {
+        return Err(ToyQlError::Usage("usage: toyql repro <file>".to_string()));
+    };
+    let bytes = std::fs::read(path).map_err(|source| ToyQlError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let lines =
+        repro::replay(&String::from_utf8_lossy(&bytes)).map_err(|source| ToyQlError::Repro {
+            path: path.clone(),
+            source,
+        })?;
+    for line in lines {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// What the CLI prints for a statement. Queries give a header line of
+/// column names, then one line per row, with values in their serialized
+/// form; EXPLAIN gives the plan tree.
+fn render_outcome(outcome: &statements::Outcome) -> String {
+    match outcome {
+        statements::Outcome::Rows(result) => {
+            let mut out = result.columns.join(" | ") + "\n";
+            for row in &result.rows {
+                let values: Vec<String> = row.iter().map(|value| value.serialize()).collect();
+                out.push_str(&values.join(" | "));
+                out.push('\n');
+            }
+            out
+        }
+        statements::Outcome::Explained(plan) => plan.clone(),
+        statements::Outcome::Created(name) => format!("CREATE TABLE {name}\n"),
+        statements::Outcome::DefinedMacro(name) => format!("CREATE MACRO {name}\n"),
+        statements::Outcome::Inserted(count) => format!("INSERT {count}\n"),
+        statements::Outcome::Altered(name) => format!("ALTER TABLE {name}\n"),
+        statements::Outcome::Set(name) => format!("SET {name}\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(args: &[&str]) -> i32 {
+        run_from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn status_with_stdin(args: &[&str], stdin: &str) -> i32 {
+        run_with(
+            args.iter().map(|arg| arg.to_string()),
+            &mut stdin.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn strict_undoes_skip_errors() {
+        for args in [
+            &["--skip-errors", "--strict"][..],
+            &["--on-error", "skip", "--strict"],
+        ] {
+            let mut iter = args.iter().map(|arg| arg.to_string()).peekable();
+            let import = options(&mut iter, &mut std::io::empty()).unwrap().import;
+            assert_eq!(import.policy, import::ErrorPolicy::Strict, "{args:?}");
+            assert!(import.log, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn skip_errors_is_on_error_log() {
+        let messages = |args: &[&str]| {
+            let mut iter = args.iter().map(|arg| arg.to_string()).peekable();
+            let options = options(&mut iter, &mut std::io::empty()).unwrap().import;
+            let mut catalog = catalog::Catalog::new();
+            let columns = vec![catalog::Column::new("id", catalog::ColumnType::Int)];
+            catalog
+                .create_table("t", catalog::Schema { columns })
+                .unwrap();
+            let report =
+                import::import(&mut catalog, "t", "id\n1\nx\n3\n4,5\n", options.policy).unwrap();
+            import_messages("t.csv", "t", &report, options.log)
+        };
+        let logged = messages(&["--skip-errors"]);
+        assert_eq!(logged, messages(&["--on-error", "log"]));
+        assert_eq!(logged.len(), 3);
+        assert_eq!(
+            messages(&["--on-error", "skip"]),
+            [
+                "t.csv: skipped 2 bad rows",
+                "imported 2 rows into `t` from t.csv"
+            ]
+        );
+    }
+
+    #[test]
+    fn progress_intervals() {
+        use import::ProgressInterval::{Records, Time};
+        use std::time::Duration;
+        for (text, expected) in [
+            ("1000", Some(Records(1000))),
+            ("5s", Some(Time(Duration::from_secs(5)))),
+            ("0.5s", Some(Time(Duration::from_millis(500)))),
+            ("0", None),
+            ("-1s", None),
+            ("fast", None),
+        ] {
+            assert_eq!(progress_interval(text), expected, "{text}");
+        }
+        let mut args = ["--progress", "2s", "SELECT 1"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .peekable();
+        let import = options(&mut args, &mut std::io::empty()).unwrap().import;
+        assert_eq!(import.progress, Some(Time(Duration::from_secs(2))));
+        assert_eq!(status(&["--progress", "x"]), 2);
+    }
+
+    #[test]
+    fn reads_statements_from_stdin() {
+        assert_eq!(
+            status_with_stdin(
+                &["--file", "-", "SELECT * FROM t"],
+                "CREATE TABLE t (a INT);"
+            ),
+            0
+        );
+        assert_eq!(
+            status_with_stdin(&["--file", "-", "SELECT * FROM t"], ""),
+            4
+        );
+        let files = vec!["-".to_string()];
+        let sources = sources(Vec::new(), files, Default::default(), &mut &b"SELEC 1"[..]).unwrap();
+        assert_eq!(sources, [("<stdin>".to_string(), "SELEC 1".to_string())]);
+    }
+
+    #[test]
+    fn f_is_short_for_file() {
+        let path = std::env::temp_dir().join(format!("toyql-{}-short.sql", std::process::id()));
+        std::fs::write(&path, "#!/usr/bin/env toyql -f\nCREATE TABLE t (a INT);\n").unwrap();
+        let path = path.to_str().unwrap();
+        assert_eq!(status(&["-f", path, "SELECT * FROM t"]), 0);
+        assert_eq!(status(&["lint", "-f", path]), 0);
+        assert_eq!(status(&["-f"]), 2);
+        // Only in a file is a shebang a comment.
+        assert_eq!(status(&["#!/usr/bin/env toyql -f\nSELECT 1"]), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn expands_templates_only_when_asked() {
+        assert_eq!(status(&["SELECT '{{y}}'"]), 0);
+        assert_eq!(status(&["--templates", "SELECT '{{y}}'"]), 3);
+        assert_eq!(status(&["--define", "x=1", "SELECT '{{y}}'"]), 3);
+        assert_eq!(status(&["--define", "y=1", "SELECT {{y}} + 1"]), 0);
+    }
+}
@@ -0,0 +1,338 @@
+//! Lint rules: checks on a statement's syntax tree for things that parse
+//! and run but are probably not what was meant, run by `toyql lint`.
+//!
+//! Each rule has a code, such as `L001`, and a level: allowed rules are not
+//! run, warnings are reported, and denied findings also fail the lint. The
+//! built-in rules warn by default. An embedder can register rules of its
+//! own alongside them.
+
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::catalog::{Column, ColumnType};
+use crate::literals::LiteralValue;
+use crate::statements::{visit, SelectItem, Statement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Allow => "allowed",
+            Level::Warn => "warning",
+            Level::Deny => "error",
+        })
+    }
+}
+
+/// Something a rule found in a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub code: String,
+    pub level: Level,
+    pub message: String,
+}
+
+type RuleFn = Box<dyn Fn(&Statement) -> Vec<String>>;
+
+struct Rule {
+    code: String,
+    summary: String,
+    level: Level,
+    check: RuleFn,
+}
+
+/// The rules to lint with, and the level of each.
+pub struct Linter {
+    rules: Vec<Rule>,
+}
+
+impl Linter {
+    /// A linter with the built-in rules.
+    pub fn new() -> Linter {
+        let mut linter = Linter { rules: Vec::new() };
+        linter.register(
+            "L001",
+            "SELECT * instead of a list of columns",
+            Level::Warn,
+            select_star,
+        );
+        linter.register(
+            "L002",
+            "join condition that uses no columns",
+            Level::Warn,
+            cross_join,
+        );
+        linter.register(
+            "L003",
+            "arithmetic or comparison mixing INT and FLOAT",
+            Level::Warn,
+            mixed_numbers,
+        );
+        linter
+    }
+
+    /// Adds a rule, which returns a message for each thing it finds in a
+    /// statement. A rule registered under a code already in use replaces
+    /// it.
+    pub fn register(
+        &mut self,
+        code: &str,
+        summary: &str,
+        level: Level,
+        check: impl Fn(&Statement) -> Vec<String> + 'static,
+    ) {
+        self.rules.retain(|rule| rule.code != code);
+        self.rules.push(Rule {
+            code: code.to_string(),
+            summary: summary.to_string(),
+            level,
+            check: Box::new(check),
+        });
+    }
+
+    /// Sets the level of the rule with `code`, returning whether there is
+    /// one.
+    pub fn set_level(&mut self, code: &str, level: Level) -> bool {
+        match self.rules.iter_mut().find(|rule| rule.code == code) {
+            Some(rule) => {
+                rule.level = level;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The code, summary and level of each rule, in registration order.
+    pub fn rules(&self) -> Vec<(&str, &str, Level)> {
+        self.rules
+            .iter()
+            .map(|rule| (rule.code.as_str(), rule.summary.as_str(), rule.level))
+            .collect()
+    }
+
+    /// What the rules that are not allowed find in `statement`.
+    pub fn lint(&self, statement: &Statement) -> Vec<Finding> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.level != Level::Allow)
+            .flat_map(|rule| {
+                (rule.check)(statement).into_iter().map(|message| Finding {
+                    code: rule.code.clone(),
+                    level: rule.level,
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Linter {
+        Linter::new()
+    }
+}
+
+impl fmt::Debug for Linter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.rules()).finish()
+    }
+}
+
+/// L001: a `*` item reads every column, including any added later, and
+/// hides which ones the query depends on.
+fn select_star(statement: &Statement) -> Vec<String> {
+    let (Statement::Select(select) | Statement::Explain(select, _)) = statement else {
+        return Vec::new();
+    };
+    if !select.items.contains(&SelectItem::Wildcard) {
+        return Vec::new();
+    }
+    vec!["`SELECT *` reads every column; list the ones the query needs".to_string()]
+}
+
+/// L002: a join condition that uses no columns, such as `ON true`, pairs
+/// every row of one table with every row of the other.
+fn cross_join(statement: &Statement) -> Vec<String> {
+    let (Statement::Select(select) | Statement::Explain(select, _)) = statement else {
+        return Vec::new();
+    };
+    let Some(join) = &select.join else {
+        return Vec::new();
+    };
+    if uses_columns(&join.on) {
+        return Vec::new();
+    }
+    vec![format!(
+        "`ON {}` uses no columns, so every row of `{}` is joined to every other row",
+        join.on.serialize(),
+        join.table
+    )]
+}
+
+fn uses_columns(expr: &Expr) -> bool {
+    matches!(expr, Expr::Identifier(_)) || expr.children().into_iter().any(uses_columns)
+}
+
+/// L003: an INT operand of arithmetic or a comparison with a FLOAT is
+/// converted to a float first, which is exact only up to 2^53, so
+/// `9007199254740993 = 9007199254740992.0` holds. Types are known for
+/// literals and, in CREATE TABLE, for the columns a generated column reads.
+fn mixed_numbers(statement: &Statement) -> Vec<String> {
+    let columns: &[Column] = match statement {
+        Statement::CreateTable { columns, .. } | Statement::CreateExternalTable { columns, .. } => {
+            columns
+        }
+        _ => &[],
+    };
+    let mut messages = Vec::new();
+    visit(statement, &mut |expr| {
+        mixed_operands(expr, columns, &mut messages)
+    });
+    messages
+}
+
+fn mixed_operands(expr: &Expr, columns: &[Column], messages: &mut Vec<String>) {
+    if let Expr::Binary { op, left, right } = expr {
+        let numeric = is_arithmetic(*op)
+            || matches!(
+                op,
+                BinaryOp::Eq
+                    | BinaryOp::NotEq
+                    | BinaryOp::Lt
+                    | BinaryOp::LtEq
+                    | BinaryOp::Gt
+                    | BinaryOp::GtEq
+            );
+        let types = (number_type(left, columns), number_type(right, columns));
+        if numeric
+            && matches!(
+                types,
+                (Some(ColumnType::Int), Some(ColumnType::Float))
+                    | (Some(ColumnType::Float), Some(ColumnType::Int))
+            )
+        {
+            messages.push(format!(
+                "`{}` mixes INT and FLOAT, so the INT is converted to a float",
+                expr.serialize()
+            ));
+        }
+    }
+    for child in expr.children() {
+        mixed_operands(child, columns, messages);
+    }
+}
+
+fn is_arithmetic(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod
+    )
+}
+
+/// INT or FLOAT, for an expression whose type is one of them and can be
+/// told from the text.
+fn number_type(expr: &Expr, columns: &[Column]) -> Option<ColumnType> {
+    match expr {
+        Expr::Literal(LiteralValue::Int(_)) => Some(ColumnType::Int),
+        Expr::Literal(LiteralValue::Float(_)) => Some(ColumnType::Float),
+        Expr::Identifier(name) => columns
+            .iter()
+            .find(|column| column.name == *name)
+            .map(|column| column.ty.clone())
+            .filter(|ty| matches!(ty, ColumnType::Int | ColumnType::Float)),
+        Expr::Unary {
+            op: UnaryOp::Neg | UnaryOp::Plus,
+            operand,
+        } => number_type(operand, columns),
+        Expr::Binary { op, left, right } if is_arithmetic(*op) => {
+            match (number_type(left, columns)?, number_type(right, columns)?) {
+                (ColumnType::Int, ColumnType::Int) => Some(ColumnType::Int),
+                _ => Some(ColumnType::Float),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::parsing::parse;
+
+    fn codes(linter: &Linter, text: &str) -> Vec<String> {
+        let statement = parse(text).unwrap();
+        linter
+            .lint(&statement)
+            .into_iter()
+            .map(|finding| format!("{} {}", finding.level, finding.code))
+            .collect()
+    }
+
+    #[test]
+    fn built_in_rules() {
+        let linter = Linter::new();
+        assert_eq!(codes(&linter, "SELECT * FROM t"), vec!["warning L001"]);
+        assert_eq!(
+            codes(&linter, "EXPLAIN SELECT * FROM a JOIN b ON 1 = 1"),
+            vec!["warning L001", "warning L002"]
+        );
+        assert!(codes(&linter, "SELECT x FROM a JOIN b ON x = y + 1").is_empty());
+        assert!(codes(&linter, "INSERT INTO t VALUES (1)").is_empty());
+        assert_eq!(
+            codes(&linter, "SELECT x FROM t WHERE x > 1 + 0.5"),
+            vec!["warning L003"]
+        );
+        assert_eq!(
+            codes(
+                &linter,
+                "CREATE TABLE t (a INT, b FLOAT AS (a * 2.0 + -1.5) VIRTUAL)"
+            ),
+            vec!["warning L003"]
+        );
+        assert!(codes(&linter, "SELECT x * 2, 1.5 / 2.0, 'a' = 1.0 FROM t").is_empty());
+        let finding = &linter.lint(&parse("INSERT INTO t VALUES (2 - 0.5)").unwrap())[0];
+        assert_eq!(
+            finding.message,
+            "`2 - 0.5` mixes INT and FLOAT, so the INT is converted to a float"
+        );
+        let finding = &linter.lint(&parse("SELECT x FROM a JOIN b ON true").unwrap())[0];
+        assert_eq!(
+            finding.message,
+            "`ON true` uses no columns, so every row of `b` is joined to every other row"
+        );
+    }
+
+    #[test]
+    fn levels_and_custom_rules() {
+        let mut linter = Linter::new();
+        assert!(linter.set_level("L001", Level::Allow));
+        assert!(linter.set_level("L002", Level::Deny));
+        assert!(!linter.set_level("L999", Level::Deny));
+        linter.register(
+            "X001",
+            "unbounded query",
+            Level::Warn,
+            |statement| match statement {
+                Statement::Select(select) if select.limit.is_none() => {
+                    vec!["no LIMIT".to_string()]
+                }
+                _ => Vec::new(),
+            },
+        );
+        assert_eq!(
+            codes(&linter, "SELECT * FROM a JOIN b ON true"),
+            vec!["error L002", "warning X001"]
+        );
+        assert_eq!(
+            codes(&linter, "SELECT * FROM t LIMIT 1"),
+            Vec::<String>::new()
+        );
+        assert_eq!(linter.rules()[3], ("X001", "unbounded query", Level::Warn));
+    }
+}
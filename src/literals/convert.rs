@@ -0,0 +1,148 @@
+//! Conversions from Rust values, so that embedders and tests can write
+//! `lit!(2.5)` or `(1, "x", None::<i64>).into_values()` instead of spelling
+//! out each variant.
+
+use super::{Inet, LiteralValue, Point};
+
+impl From<i64> for LiteralValue {
+    fn from(value: i64) -> LiteralValue {
+        LiteralValue::Int(value)
+    }
+}
+
+impl From<i32> for LiteralValue {
+    fn from(value: i32) -> LiteralValue {
+        LiteralValue::Int(value.into())
+    }
+}
+
+impl From<f64> for LiteralValue {
+    fn from(value: f64) -> LiteralValue {
+        LiteralValue::Float(value)
+    }
+}
+
+impl From<bool> for LiteralValue {
+    fn from(value: bool) -> LiteralValue {
+        LiteralValue::Bool(value)
+    }
+}
+
+impl From<&str> for LiteralValue {
+    fn from(value: &str) -> LiteralValue {
+        LiteralValue::String(value.to_string())
+    }
+}
+
+impl From<String> for LiteralValue {
+    fn from(value: String) -> LiteralValue {
+        LiteralValue::String(value)
+    }
+}
+
+impl From<Inet> for LiteralValue {
+    fn from(value: Inet) -> LiteralValue {
+        LiteralValue::Inet(value)
+    }
+}
+
+impl From<Point> for LiteralValue {
+    fn from(value: Point) -> LiteralValue {
+        LiteralValue::Point(value)
+    }
+}
+
+/// `None` is NULL.
+impl<T: Into<LiteralValue>> From<Option<T>> for LiteralValue {
+    fn from(value: Option<T>) -> LiteralValue {
+        value.map_or(LiteralValue::Null, Into::into)
+    }
+}
+
+/// A literal value from a Rust value, or `lit!(null)` for NULL.
+#[macro_export]
+macro_rules! lit {
+    (null) => {
+        $crate::literals::LiteralValue::Null
+    };
+    ($value:expr) => {
+        $crate::literals::LiteralValue::from($value)
+    };
+}
+
+/// The values of a row, as taken by
+/// [`Catalog::insert`](crate::catalog::Catalog::insert): implemented for
+/// tuples of up to eight values and for arrays.
+pub trait IntoValues {
+    fn into_values(self) -> Vec<LiteralValue>;
+}
+
+impl<T: Into<LiteralValue>, const N: usize> IntoValues for [T; N] {
+    fn into_values(self) -> Vec<LiteralValue> {
+        self.into_iter().map(Into::into).collect()
+    }
+}
+
+macro_rules! tuple_into_values {
+    ($($name:ident),+) => {
+        impl<$($name: Into<LiteralValue>),+> IntoValues for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<LiteralValue> {
+                let ($($name,)+) = self;
+                vec![$($name.into()),+]
+            }
+        }
+    };
+}
+
+tuple_into_values!(A);
+tuple_into_values!(A, B);
+tuple_into_values!(A, B, C);
+tuple_into_values!(A, B, C, D);
+tuple_into_values!(A, B, C, D, E);
+tuple_into_values!(A, B, C, D, E, F);
+tuple_into_values!(A, B, C, D, E, F, G);
+tuple_into_values!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Catalog, Column, ColumnType, Schema};
+    use LiteralValue::{Bool, Float, Int, Null, String as Str};
+
+    #[test]
+    fn converts_rust_values() {
+        assert_eq!(lit!(42), Int(42));
+        assert_eq!(lit!(7_i64), Int(7));
+        assert_eq!(lit!(2.5), Float(2.5));
+        assert_eq!(lit!("x"), Str("x".to_string()));
+        assert_eq!(lit!(true), Bool(true));
+        assert_eq!(lit!(null), Null);
+        assert_eq!(lit!(None::<i64>), Null);
+        assert_eq!(lit!(Some("y")), Str("y".to_string()));
+        assert_eq!(
+            lit!(Point::new(1.0, 2.0).unwrap()),
+            LiteralValue::Point(Point::new(1.0, 2.0).unwrap())
+        );
+    }
+
+    #[test]
+    fn tuples_and_arrays_become_rows() {
+        assert_eq!(
+            (1, 2.5, "x", None::<bool>).into_values(),
+            vec![Int(1), Float(2.5), Str("x".to_string()), Null]
+        );
+        assert_eq!([1, 2].into_values(), vec![Int(1), Int(2)]);
+
+        let mut catalog = Catalog::new();
+        let schema = Schema {
+            columns: vec![
+                Column::new("name", ColumnType::Text),
+                Column::new("n", ColumnType::Int),
+            ],
+        };
+        catalog.create_table("t", schema).unwrap();
+        let rows = vec![("a", 1).into_values(), ("b", None::<i64>).into_values()];
+        assert_eq!(catalog.insert("t", rows), Ok(2));
+    }
+}
@@ -0,0 +1,102 @@
+//! Hashable keys for literal values.
+//!
+//! [`LiteralValue::hash_key`] gives equal keys exactly to the values that
+//! [`LiteralValue::sort_key`] ranks as equal, so grouping by key puts
+//! together what sorting would: `1`, `1.0` and `1e0` form one group, as do
+//! `0.0` and `-0.0`, every NaN, and every NULL.
+
+use super::{Inet, LiteralValue};
+
+/// See [`LiteralValue::hash_key`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Null,
+    Bool(bool),
+    /// Ints, and floats with an integral value that fits in an `i64`.
+    Int(i64),
+    /// The bits of any other finite or infinite float.
+    Float(u64),
+    Nan,
+    String(String),
+    Inet(Inet),
+    Point(u64, u64),
+    List(Vec<HashKey>),
+}
+
+impl LiteralValue {
+    /// A key with `Hash` and `Eq` that agrees with the equality of
+    /// `sort_key`.
+    pub fn hash_key(&self) -> HashKey {
+        match self {
+            LiteralValue::Null => HashKey::Null,
+            LiteralValue::Bool(v) => HashKey::Bool(*v),
+            LiteralValue::Int(v) => HashKey::Int(*v),
+            LiteralValue::Float(v) => float_key(*v),
+            LiteralValue::String(v) => HashKey::String(v.clone()),
+            LiteralValue::Inet(v) => HashKey::Inet(*v),
+            LiteralValue::Point(v) => HashKey::Point(v.lat().to_bits(), v.lon().to_bits()),
+            LiteralValue::List(items) => {
+                HashKey::List(items.iter().map(LiteralValue::hash_key).collect())
+            }
+        }
+    }
+}
+
+fn float_key(v: f64) -> HashKey {
+    const TWO_POW_63: f64 = 9_223_372_036_854_775_808.0;
+    if v.is_nan() {
+        HashKey::Nan
+    } else if v.trunc() == v && (-TWO_POW_63..TWO_POW_63).contains(&v) {
+        // Also maps `-0.0` to `Int(0)`.
+        HashKey::Int(v as i64)
+    } else {
+        HashKey::Float(v.to_bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::example_literals;
+
+    #[test]
+    fn agrees_with_sort_key() {
+        let mut values = example_literals();
+        values.extend([
+            LiteralValue::Float(-0.0),
+            LiteralValue::Float(f64::NAN),
+            LiteralValue::Float(-f64::NAN),
+            LiteralValue::Float(9_007_199_254_740_993.0),
+            LiteralValue::Int(9_007_199_254_740_993),
+            LiteralValue::Float(-9_223_372_036_854_775_808.0),
+            LiteralValue::Int(i64::MIN),
+            LiteralValue::Float(9_223_372_036_854_775_808.0),
+            LiteralValue::Int(i64::MAX),
+        ]);
+        for a in &values {
+            for b in &values {
+                assert_eq!(
+                    a.hash_key() == b.hash_key(),
+                    a.sort_key() == b.sort_key(),
+                    "{a:?} and {b:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_group_by_value() {
+        assert_eq!(
+            LiteralValue::Float(2.0).hash_key(),
+            LiteralValue::Int(2).hash_key()
+        );
+        assert_eq!(
+            LiteralValue::Float(-0.0).hash_key(),
+            LiteralValue::Float(0.0).hash_key()
+        );
+        assert_ne!(
+            LiteralValue::Float(2.5).hash_key(),
+            LiteralValue::Int(2).hash_key()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! IP addresses with a network prefix length, as in `192.168.0.1/24`.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IPv4 or IPv6 address and the length of its network prefix. Ordered
+/// by family (IPv4 first), then address, then prefix length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Inet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Inet {
+    /// `None` when `prefix` is longer than the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Inet> {
+        (prefix <= max_prefix(addr)).then_some(Inet { addr, prefix })
+    }
+
+    pub fn addr(self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(self) -> u8 {
+        self.prefix
+    }
+
+    /// The network: the address with the bits after the prefix cleared.
+    pub fn network(self) -> Inet {
+        let bits = masked(self.addr, self.prefix);
+        let addr = match self.addr {
+            IpAddr::V4(_) => IpAddr::from((bits as u32).to_be_bytes()),
+            IpAddr::V6(_) => IpAddr::from(bits.to_be_bytes()),
+        };
+        Inet { addr, ..self }
+    }
+
+    /// Whether `other` lies inside this network and is a smaller network,
+    /// or a single address, itself: the `<<` operator with the operands
+    /// swapped.
+    pub fn strictly_contains(self, other: Inet) -> bool {
+        self.addr.is_ipv4() == other.addr.is_ipv4()
+            && self.prefix < other.prefix
+            && masked(self.addr, self.prefix) == masked(other.addr, self.prefix)
+    }
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// The address's bits with all but the first `prefix` cleared.
+fn masked(addr: IpAddr, prefix: u8) -> u128 {
+    let (bits, width) = match addr {
+        IpAddr::V4(a) => (u128::from(u32::from(a)), 32),
+        IpAddr::V6(a) => (u128::from(a), 128),
+    };
+    let host_bits = width - u32::from(prefix);
+    if host_bits >= 128 {
+        0
+    } else {
+        bits >> host_bits << host_bits
+    }
+}
+
+/// The prefix length is left out when it covers the whole address.
+impl fmt::Display for Inet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix == max_prefix(self.addr) {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidInet(pub String);
+
+impl fmt::Display for InvalidInet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid inet address `{}`", self.0)
+    }
+}
+
+impl std::error::Error for InvalidInet {}
+
+/// `address[/prefix]`; without a prefix the whole address is the network.
+impl FromStr for Inet {
+    type Err = InvalidInet;
+
+    fn from_str(text: &str) -> Result<Inet, InvalidInet> {
+        let invalid = || InvalidInet(text.to_string());
+        let (addr, prefix) = match text.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (text, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let prefix = match prefix {
+            None => max_prefix(addr),
+            Some(p) if !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()) => {
+                p.parse().map_err(|_| invalid())?
+            }
+            Some(_) => return Err(invalid()),
+        };
+        Inet::new(addr, prefix).ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inet(text: &str) -> Inet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_displays() {
+        for (text, shown) in [
+            ("192.168.0.1/24", "192.168.0.1/24"),
+            ("10.0.0.1", "10.0.0.1"),
+            ("10.0.0.1/32", "10.0.0.1"),
+            ("0.0.0.0/0", "0.0.0.0/0"),
+            ("2001:DB8::1/64", "2001:db8::1/64"),
+        ] {
+            assert_eq!(inet(text).to_string(), shown);
+        }
+        for text in [
+            "",
+            "10.0.0.1/33",
+            "10.0.0.1/",
+            "10.0.0.1/+8",
+            "10.0.0",
+            "::1/129",
+        ] {
+            assert!(text.parse::<Inet>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn networks_and_containment() {
+        assert_eq!(inet("192.168.7.9/20").network(), inet("192.168.0.0/20"));
+        assert_eq!(inet("10.1.2.3/0").network(), inet("0.0.0.0/0"));
+        assert_eq!(inet("2001:db8::ff/120").network(), inet("2001:db8::/120"));
+
+        let net = inet("192.168.0.0/16");
+        assert!(net.strictly_contains(inet("192.168.4.1")));
+        assert!(net.strictly_contains(inet("192.168.4.0/24")));
+        assert!(!net.strictly_contains(net));
+        assert!(!net.strictly_contains(inet("192.169.0.1")));
+        assert!(!net.strictly_contains(inet("::ffff:192.168.0.1")));
+        assert!(inet("::/0").strictly_contains(inet("::1")));
+    }
+}
@@ -0,0 +1,238 @@
+//! Literal values: the constants that can be written directly in a query.
+
+mod convert;
+mod hashing;
+mod inet;
+mod ordering;
+pub mod parsing;
+mod point;
+#[cfg(feature = "serde")]
+mod serde_impls;
+
+pub use convert::IntoValues;
+pub use hashing::HashKey;
+pub use inet::{Inet, InvalidInet};
+pub use ordering::SortKey;
+pub use point::Point;
+
+/// What [`LiteralValue::normalize`] does with a float zero whose sign bit
+/// is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NegativeZero {
+    /// `-0.0` stays apart from `0.0`.
+    #[default]
+    Keep,
+    /// `-0.0` becomes `0.0`.
+    Fold,
+}
+
+/// A single constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiteralValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Inet(Inet),
+    Point(Point),
+    /// A list of values that are neither lists nor NULL.
+    List(Vec<LiteralValue>),
+    Null,
+}
+
+impl LiteralValue {
+    /// The name used for this value's type in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LiteralValue::Int(_) => "int",
+            LiteralValue::Float(_) => "float",
+            LiteralValue::String(_) => "string",
+            LiteralValue::Bool(_) => "bool",
+            LiteralValue::Inet(_) => "inet",
+            LiteralValue::Point(_) => "point",
+            LiteralValue::List(_) => "list",
+            LiteralValue::Null => "null",
+        }
+    }
+
+    /// Renders the value in a form that `parsing::apply_grammar` reads back
+    /// as an identical value.
+    pub fn serialize(&self) -> String {
+        match self {
+            LiteralValue::Int(v) => parsing::int::serialize(*v),
+            LiteralValue::Float(v) => parsing::float::serialize(*v),
+            LiteralValue::String(v) => parsing::string::serialize(v),
+            LiteralValue::Bool(v) => parsing::boolean::serialize(*v),
+            LiteralValue::Inet(v) => parsing::inet::serialize(*v),
+            LiteralValue::Point(v) => parsing::point::serialize(*v),
+            LiteralValue::List(v) => parsing::list::serialize(v),
+            LiteralValue::Null => parsing::null::serialize(),
+        }
+    }
+
+    /// The value in canonical form: every NaN becomes the same NaN, and a
+    /// negative zero is kept or folded as `negative_zero` says, in floats
+    /// in the coordinates of points and in the items of lists. Other values
+    /// are already canonical. Rows are normalized before they are stored.
+    pub fn normalize(self, negative_zero: NegativeZero) -> LiteralValue {
+        match self {
+            LiteralValue::Float(v) => LiteralValue::Float(canonical(v, negative_zero)),
+            LiteralValue::Point(v) => LiteralValue::Point(
+                Point::new(
+                    canonical(v.lat(), negative_zero),
+                    canonical(v.lon(), negative_zero),
+                )
+                .expect("a zero's sign does not change its range"),
+            ),
+            LiteralValue::List(items) => LiteralValue::List(
+                items
+                    .into_iter()
+                    .map(|item| item.normalize(negative_zero))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    /// Structural identity: same variant and same value. Floats are
+    /// compared bit-for-bit once [`normalize`](Self::normalize)d, so that
+    /// every `NaN` is identical to every other and `0.0` is not identical
+    /// to `-0.0`. `Null` is identical to `Null`; this is not SQL equality,
+    /// under which comparing with NULL yields NULL.
+    pub fn identical(&self, other: &LiteralValue) -> bool {
+        let bits = |v: f64| canonical(v, NegativeZero::Keep).to_bits();
+        match (self, other) {
+            (LiteralValue::Int(a), LiteralValue::Int(b)) => a == b,
+            (LiteralValue::Float(a), LiteralValue::Float(b)) => bits(*a) == bits(*b),
+            (LiteralValue::String(a), LiteralValue::String(b)) => a == b,
+            (LiteralValue::Bool(a), LiteralValue::Bool(b)) => a == b,
+            (LiteralValue::Inet(a), LiteralValue::Inet(b)) => a == b,
+            (LiteralValue::Point(a), LiteralValue::Point(b)) => {
+                bits(a.lat()) == bits(b.lat()) && bits(a.lon()) == bits(b.lon())
+            }
+            (LiteralValue::List(a), LiteralValue::List(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.identical(b))
+            }
+            (LiteralValue::Null, LiteralValue::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+/// `v` with its NaN or negative zero made canonical.
+fn canonical(v: f64, negative_zero: NegativeZero) -> f64 {
+    if v.is_nan() {
+        f64::NAN
+    } else if v == 0.0 && negative_zero == NegativeZero::Fold {
+        0.0
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{check, example_literals};
+
+    #[test]
+    fn round_trip() {
+        for value in example_literals() {
+            let text = value.serialize();
+            let (rest, parsed) = parsing::apply_grammar(&text).unwrap();
+            assert_eq!(rest, "", "leftover input for {text}");
+            assert!(parsed.identical(&value), "{text} parsed as {parsed:?}");
+        }
+    }
+
+    #[test]
+    fn words_list_every_word_a_literal_starts_with() {
+        check(
+            2000,
+            |g| g.literal(),
+            |value| {
+                let text = value.serialize().to_ascii_lowercase();
+                let word = text.trim_start_matches('-');
+                let end = word
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(word.len());
+                match word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    true if !parsing::WORDS.contains(&&word[..end]) => {
+                        Err(format!("{text} starts with a word not in WORDS"))
+                    }
+                    _ => Ok(()),
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn arbitrary_values_round_trip() {
+        check(
+            3000,
+            |g| g.literal(),
+            |value| {
+                let text = value.serialize();
+                match parsing::apply_grammar(&text) {
+                    Ok(("", parsed)) if parsed.identical(value) => Ok(()),
+                    other => Err(format!("{text} read back as {other:?}")),
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn arbitrary_text_never_panics() {
+        check(
+            5000,
+            |g| {
+                if g.below(2) == 0 {
+                    g.string()
+                } else {
+                    g.numeric_text()
+                }
+            },
+            |text| {
+                let _ = parsing::apply_grammar(text);
+                let _ = parsing::parse(text);
+                Ok(())
+            },
+        );
+    }
+
+    #[test]
+    fn identical_distinguishes_variants() {
+        assert!(!LiteralValue::Int(1).identical(&LiteralValue::Float(1.0)));
+        assert!(LiteralValue::Float(f64::NAN).identical(&LiteralValue::Float(-f64::NAN)));
+        assert!(!LiteralValue::Float(0.0).identical(&LiteralValue::Float(-0.0)));
+        assert!(LiteralValue::Null.identical(&LiteralValue::Null));
+        assert!(!LiteralValue::Bool(false).identical(&LiteralValue::Null));
+    }
+
+    #[test]
+    fn normalizes_nans_and_negative_zeros() {
+        let normalize = |v: f64, zero| match LiteralValue::Float(v).normalize(zero) {
+            LiteralValue::Float(v) => v.to_bits(),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(normalize(-f64::NAN, NegativeZero::Keep), f64::NAN.to_bits());
+        assert_eq!(normalize(-0.0, NegativeZero::Keep), (-0.0f64).to_bits());
+        assert_eq!(normalize(-0.0, NegativeZero::Fold), 0.0f64.to_bits());
+        assert_eq!(normalize(-1.5, NegativeZero::Fold), (-1.5f64).to_bits());
+        let point = LiteralValue::Point(Point::new(-0.0, 10.0).unwrap());
+        assert!(point
+            .normalize(NegativeZero::Fold)
+            .identical(&LiteralValue::Point(Point::new(0.0, 10.0).unwrap())));
+    }
+
+    #[test]
+    fn keywords_ignore_case_but_need_word_boundary() {
+        assert_eq!(
+            parsing::apply_grammar("TRUE"),
+            Ok(("", LiteralValue::Bool(true)))
+        );
+        assert_eq!(parsing::apply_grammar("Null"), Ok(("", LiteralValue::Null)));
+        assert!(parsing::apply_grammar("nullable").is_err());
+        assert!(parsing::apply_grammar("false_start").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+//! Ordering of literal values.
+//!
+//! [`LiteralValue::compare`] is the partial order used by comparison
+//! operators: numbers compare exactly across int and float, strings by code
+//! point, bools with `false` first, inet addresses IPv4 first and then by
+//! address and prefix length, and anything else (points, lists, mixed
+//! types, NULL, NaN) is unordered.
+//!
+//! [`LiteralValue::sort_key`] extends it to a total order for sorting and
+//! indexing: NULL first, then bools, then numbers, then strings, then inet
+//! addresses, then points by latitude and longitude, then lists item by
+//! item, a shorter list before any it begins. Among the numbers, NaN sorts
+//! after `inf`, and `-0.0`, `0.0` and `0` are equal.
+
+use std::cmp::Ordering;
+
+use super::LiteralValue;
+
+impl LiteralValue {
+    /// `None` when the values are of types that do not compare, when either
+    /// is NULL, or when either is NaN.
+    pub fn compare(&self, other: &LiteralValue) -> Option<Ordering> {
+        use LiteralValue::{Bool, Float, Int};
+        match (self, other) {
+            (Int(a), Int(b)) => Some(a.cmp(b)),
+            (Int(a), Float(b)) => compare_int_float(*a, *b),
+            (Float(a), Int(b)) => compare_int_float(*b, *a).map(Ordering::reverse),
+            (Float(a), Float(b)) => a.partial_cmp(b),
+            (LiteralValue::String(a), LiteralValue::String(b)) => Some(a.cmp(b)),
+            (Bool(a), Bool(b)) => Some(a.cmp(b)),
+            (LiteralValue::Inet(a), LiteralValue::Inet(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
+    /// Whether `compare` can order the two types, NaN aside.
+    pub fn comparable_with(&self, other: &LiteralValue) -> bool {
+        use LiteralValue::{Bool, Float, Int};
+        matches!(
+            (self, other),
+            (Int(_) | Float(_), Int(_) | Float(_))
+                | (LiteralValue::String(_), LiteralValue::String(_))
+                | (Bool(_), Bool(_))
+                | (LiteralValue::Inet(_), LiteralValue::Inet(_))
+        )
+    }
+
+    /// A key whose `Ord` is total and agrees with `compare` wherever that
+    /// gives an answer.
+    pub fn sort_key(&self) -> SortKey<'_> {
+        SortKey(self)
+    }
+}
+
+/// See [`LiteralValue::sort_key`].
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey<'a>(&'a LiteralValue);
+
+impl SortKey<'_> {
+    /// Position of the value's group in the total order.
+    fn rank(&self) -> u8 {
+        match self.0 {
+            LiteralValue::Null => 0,
+            LiteralValue::Bool(_) => 1,
+            LiteralValue::Float(v) if v.is_nan() => 3,
+            LiteralValue::Int(_) | LiteralValue::Float(_) => 2,
+            LiteralValue::String(_) => 4,
+            LiteralValue::Inet(_) => 5,
+            LiteralValue::Point(_) => 6,
+            LiteralValue::List(_) => 7,
+        }
+    }
+}
+
+impl Ord for SortKey<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| match (self.0, other.0) {
+                (LiteralValue::Point(a), LiteralValue::Point(b)) => a
+                    .lat()
+                    .total_cmp(&b.lat())
+                    .then(a.lon().total_cmp(&b.lon())),
+                (LiteralValue::List(a), LiteralValue::List(b)) => a
+                    .iter()
+                    .map(LiteralValue::sort_key)
+                    .cmp(b.iter().map(LiteralValue::sort_key)),
+                (a, b) => a.compare(b).unwrap_or(Ordering::Equal),
+            })
+    }
+}
+
+impl PartialOrd for SortKey<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortKey<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey<'_> {}
+
+/// Exact comparison; converting the int to `f64` would round large values.
+fn compare_int_float(int: i64, float: f64) -> Option<Ordering> {
+    const TWO_POW_63: f64 = 9_223_372_036_854_775_808.0;
+    if float.is_nan() {
+        return None;
+    }
+    if float >= TWO_POW_63 {
+        return Some(Ordering::Less);
+    }
+    if float < -TWO_POW_63 {
+        return Some(Ordering::Greater);
+    }
+    let whole = float.trunc();
+    Some(int.cmp(&(whole as i64)).then_with(|| {
+        if float > whole {
+            Ordering::Less
+        } else if float < whole {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::example_literals;
+    use LiteralValue::{Bool, Float, Int, Null};
+
+    #[test]
+    fn compares_across_numeric_types_exactly() {
+        assert_eq!(Int(1).compare(&Float(1.0)), Some(Ordering::Equal));
+        assert_eq!(Int(1).compare(&Float(1.5)), Some(Ordering::Less));
+        assert_eq!(
+            Int(i64::MAX).compare(&Float(i64::MAX as f64)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(Float(-0.5).compare(&Int(-1)), Some(Ordering::Greater));
+        assert_eq!(Float(f64::NAN).compare(&Int(0)), None);
+        assert_eq!(Int(1).compare(&Bool(true)), None);
+        assert_eq!(Null.compare(&Null), None);
+    }
+
+    #[test]
+    fn sort_key_orders_groups() {
+        let mut values = [
+            LiteralValue::String("a".to_string()),
+            Float(f64::NAN),
+            Float(f64::INFINITY),
+            Int(2),
+            Float(-1.5),
+            Bool(true),
+            Bool(false),
+            Null,
+        ];
+        values.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        let serialized: Vec<String> = values.iter().map(LiteralValue::serialize).collect();
+        assert_eq!(
+            serialized,
+            vec!["null", "false", "true", "-1.5", "2", "inf", "NaN", "\"a\""]
+        );
+    }
+
+    /// Checks the order laws over every pair and triple of example values.
+    #[test]
+    fn orders_are_consistent() {
+        let values = example_literals();
+        for a in &values {
+            for b in &values {
+                let key = a.sort_key().cmp(&b.sort_key());
+                assert_eq!(
+                    key,
+                    b.sort_key().cmp(&a.sort_key()).reverse(),
+                    "{a:?} {b:?}"
+                );
+                assert_eq!(
+                    a.compare(b),
+                    b.compare(a).map(Ordering::reverse),
+                    "{a:?} {b:?}"
+                );
+                if let Some(ordering) = a.compare(b) {
+                    assert_eq!(ordering, key, "{a:?} {b:?}");
+                }
+                for c in &values {
+                    if key != Ordering::Greater && b.sort_key() <= c.sort_key() {
+                        assert!(a.sort_key() <= c.sort_key(), "{a:?} {b:?} {c:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! `true` and `false`, in any letter case.
+
+use crate::error::PResult;
+use nom::branch::alt;
+use nom::combinator::value;
+
+use super::keyword;
+
+pub fn apply_grammar(input: &str) -> PResult<'_, bool> {
+    alt((value(true, keyword("true")), value(false, keyword("false"))))(input)
+}
+
+pub fn serialize(value: bool) -> String {
+    value.to_string()
+}
@@ -0,0 +1,148 @@
+//! 64-bit floats, in the forms Rust's own parser accepts short of a `+`
+//! sign: a decimal point, an exponent or both (`1.5`, `.5`, `2.`, `1e10`,
+//! `-2.5E-3`), with digits grouped by underscores as in ints (`1_000.5`).
+//! The non-finite values are `inf`, `infinity` and `NaN`, in any letter
+//! case, the first two with an optional `-`.
+//!
+//! Floats serialize in Rust's `Debug` form, which always reads back as the
+//! same float: it has a decimal point or an exponent, and is the shortest
+//! such text for the value. Every NaN is written `NaN`, whatever its sign
+//! and payload, and reads back as [`f64::NAN`].
+
+use crate::error::PResult;
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{char, one_of, satisfy};
+use nom::combinator::{map_res, not, opt, recognize, value};
+use nom::sequence::{pair, preceded, terminated, tuple};
+
+use super::int::decimal;
+
+fn exponent(input: &str) -> PResult<'_, &str> {
+    recognize(tuple((one_of("eE"), opt(one_of("+-")), decimal)))(input)
+}
+
+fn finite(input: &str) -> PResult<'_, f64> {
+    map_res(
+        recognize(pair(
+            opt(char('-')),
+            alt((
+                recognize(tuple((decimal, char('.'), opt(decimal), opt(exponent)))),
+                recognize(tuple((preceded(char('.'), decimal), opt(exponent)))),
+                recognize(pair(decimal, exponent)),
+            )),
+        )),
+        |text: &str| text.replace('_', "").parse(),
+    )(input)
+}
+
+fn non_finite(input: &str) -> PResult<'_, f64> {
+    terminated(
+        alt((
+            value(f64::NAN, tag_no_case("nan")),
+            map_res(
+                recognize(pair(
+                    opt(char('-')),
+                    alt((tag_no_case("infinity"), tag_no_case("inf"))),
+                )),
+                str::parse,
+            ),
+        )),
+        // `info` or `NaNa` are identifiers, not floats.
+        not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+    )(input)
+}
+
+pub fn apply_grammar(input: &str) -> PResult<'_, f64> {
+    alt((finite, non_finite))(input)
+}
+
+pub fn serialize(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    format!("{value:?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{check, example_floats};
+
+    #[test]
+    fn needs_decimal_point_or_exponent() {
+        assert_eq!(apply_grammar("1.5e3"), Ok(("", 1500.0)));
+        assert_eq!(apply_grammar("2."), Ok(("", 2.0)));
+        assert_eq!(apply_grammar(".5"), Ok(("", 0.5)));
+        assert_eq!(apply_grammar("1e10"), Ok(("", 1e10)));
+        assert_eq!(apply_grammar("-2E-3"), Ok(("", -0.002)));
+        assert!(apply_grammar("15").is_err());
+        assert!(apply_grammar("1e").is_err());
+        assert_eq!(apply_grammar("1_000.25"), Ok(("", 1000.25)));
+    }
+
+    #[test]
+    fn non_finite_needs_word_boundary() {
+        assert_eq!(apply_grammar("-inf"), Ok(("", f64::NEG_INFINITY)));
+        assert_eq!(apply_grammar("Infinity"), Ok(("", f64::INFINITY)));
+        assert_eq!(apply_grammar("-INF"), Ok(("", f64::NEG_INFINITY)));
+        for nan in ["NaN", "nan", "NAN"] {
+            assert!(apply_grammar(nan).unwrap().1.is_nan());
+        }
+        assert!(apply_grammar("info").is_err());
+        assert!(apply_grammar("nano").is_err());
+    }
+
+    #[test]
+    fn serialization_reads_back_identically() {
+        assert_eq!(serialize(1e20), "1e20");
+        assert_eq!(serialize(-3.0), "-3.0");
+        assert_eq!(serialize(-f64::NAN), "NaN");
+        let mut values = example_floats();
+        values.extend([5e-324, 1e16, 123456789.0, 0.3, -1e-7]);
+        for value in values {
+            let text = serialize(value);
+            let (rest, parsed) = apply_grammar(&text).unwrap();
+            assert_eq!(rest, "", "leftover input for {text}");
+            assert_eq!(parsed.to_bits(), value.to_bits(), "{text}");
+        }
+    }
+
+    #[test]
+    fn agrees_with_rust() {
+        for text in [
+            "1.5",
+            "-0.0",
+            "1e10",
+            "1E+10",
+            ".5e-2",
+            "7.",
+            "1e999",
+            "-1e-999",
+            "inf",
+            "-Infinity",
+            "nan",
+        ] {
+            let expected: f64 = text.parse().unwrap();
+            let (rest, parsed) = apply_grammar(text).unwrap();
+            assert_eq!(rest, "", "leftover input for {text}");
+            assert_eq!(parsed.to_bits(), expected.to_bits(), "{text}");
+        }
+    }
+
+    #[test]
+    fn agrees_with_rust_on_arbitrary_text() {
+        check(
+            3000,
+            |g| g.numeric_text(),
+            |text| match (apply_grammar(text), text.parse::<f64>()) {
+                (Ok(("", ours)), Ok(rust))
+                    if ours.to_bits() != rust.to_bits() && !rust.is_nan() =>
+                {
+                    Err(format!("{ours:?} but Rust reads {rust:?}"))
+                }
+                _ => Ok(()),
+            },
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! `INET '<address>[/<prefix>]'`: the keyword, in any letter case, then a
+//! string literal holding the address.
+
+use nom::character::complete::multispace0;
+use nom::sequence::pair;
+
+use super::{keyword, string};
+use crate::error::{GrammarError, PResult};
+use crate::literals::Inet;
+
+pub fn apply_grammar(input: &str) -> PResult<'_, Inet> {
+    let (quoted, _) = pair(keyword("inet"), multispace0)(input)?;
+    let (rest, text) = string::apply_grammar(quoted)?;
+    match text.parse() {
+        Ok(inet) => Ok((rest, inet)),
+        Err(_) => Err(nom::Err::Failure(GrammarError::expected(
+            quoted,
+            "inet address",
+        ))),
+    }
+}
+
+pub fn serialize(value: Inet) -> String {
+    format!("INET {}", string::serialize(&value.to_string()))
+}
@@ -0,0 +1,200 @@
+//! Signed 64-bit integers, e.g. `42` or `-7`. Like Rust, they may also be
+//! written in hex, octal or binary (`0xFF`, `0o17`, `0b1010`), and digits
+//! may be grouped with underscores (`1_000_000`).
+
+use crate::error::{GrammarError, PResult};
+use nom::branch::alt;
+use nom::bytes::complete::{tag_no_case, take_while};
+use nom::character::complete::{char, satisfy};
+use nom::combinator::{cut, map_res, opt, recognize};
+use nom::error::context;
+use nom::sequence::{pair, preceded, tuple};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Decimal,
+    Hex,
+}
+
+impl Radix {
+    pub const ALL: [Radix; 4] = [Radix::Binary, Radix::Octal, Radix::Decimal, Radix::Hex];
+
+    pub fn base(self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+            Radix::Hex => 16,
+        }
+    }
+
+    /// What goes before the digits; empty for decimal.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Radix::Binary => "0b",
+            Radix::Octal => "0o",
+            Radix::Decimal => "",
+            Radix::Hex => "0x",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Radix::Binary => "binary digits",
+            Radix::Octal => "octal digits",
+            Radix::Decimal => "digits",
+            Radix::Hex => "hex digits",
+        }
+    }
+}
+
+/// A digit of `radix` followed by any mix of its digits and underscores.
+pub fn digits<'a>(radix: Radix) -> impl FnMut(&'a str) -> PResult<'a, &'a str> {
+    let is_digit = move |c: char| c.is_digit(radix.base());
+    recognize(pair(
+        satisfy(is_digit),
+        take_while(move |c| is_digit(c) || c == '_'),
+    ))
+}
+
+/// A run of decimal digits, possibly grouped with underscores. Shared with
+/// the float grammar.
+pub fn decimal(input: &str) -> PResult<'_, &str> {
+    digits(Radix::Decimal)(input)
+}
+
+/// A prefixed radix must be followed by its digits and nothing else that
+/// could continue a number: `0x`, `0xg` and `0b12` are errors rather than
+/// an int followed by something else.
+fn prefixed<'a>(radix: Radix) -> impl FnMut(&'a str) -> PResult<'a, (Radix, &'a str)> {
+    preceded(
+        tag_no_case(radix.prefix()),
+        cut(context(radix.label(), move |input| {
+            let (rest, digits) = digits(radix)(input)?;
+            if rest.starts_with(|c: char| c.is_alphanumeric()) {
+                return Err(nom::Err::Error(GrammarError::expected(rest, radix.label())));
+            }
+            Ok((rest, (radix, digits)))
+        })),
+    )
+}
+
+pub fn apply_grammar(input: &str) -> PResult<'_, i64> {
+    map_res(
+        tuple((
+            opt(char('-')),
+            alt((
+                prefixed(Radix::Hex),
+                prefixed(Radix::Octal),
+                prefixed(Radix::Binary),
+                |input| decimal(input).map(|(rest, digits)| (rest, (Radix::Decimal, digits))),
+            )),
+        )),
+        |(sign, (radix, digits))| {
+            let digits = format!("{}{}", sign.map_or("", |_| "-"), digits.replace('_', ""));
+            i64::from_str_radix(&digits, radix.base())
+        },
+    )(input)
+}
+
+/// The canonical decimal form.
+pub fn serialize(value: i64) -> String {
+    value.to_string()
+}
+
+/// `value` written in `radix`, with its prefix, e.g. `-0xff`.
+pub fn serialize_radix(value: i64, radix: Radix) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let magnitude = value.unsigned_abs();
+    let digits = match radix {
+        Radix::Binary => format!("{magnitude:b}"),
+        Radix::Octal => format!("{magnitude:o}"),
+        Radix::Decimal => magnitude.to_string(),
+        Radix::Hex => format!("{magnitude:x}"),
+    };
+    format!("{sign}{}{digits}", radix.prefix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{check, example_ints, example_radix_ints};
+
+    #[test]
+    fn parses_extremes() {
+        assert_eq!(apply_grammar("9223372036854775807"), Ok(("", i64::MAX)));
+        assert_eq!(apply_grammar("-9223372036854775808"), Ok(("", i64::MIN)));
+        assert!(apply_grammar("9223372036854775808").is_err());
+        assert_eq!(apply_grammar("-0x8000000000000000"), Ok(("", i64::MIN)));
+        assert!(apply_grammar("0x8000000000000000").is_err());
+    }
+
+    #[test]
+    fn stops_at_non_digit() {
+        assert_eq!(apply_grammar("12abc"), Ok(("abc", 12)));
+        assert!(apply_grammar("-").is_err());
+        assert!(apply_grammar("_1").is_err());
+    }
+
+    #[test]
+    fn parses_radix_examples() {
+        for (text, value) in example_radix_ints() {
+            assert_eq!(apply_grammar(text), Ok(("", value)), "{text}");
+        }
+    }
+
+    #[test]
+    fn prefix_needs_digits() {
+        for text in ["0x", "0xg", "0b12", "0o8", "0x_1"] {
+            assert!(
+                matches!(apply_grammar(text), Err(nom::Err::Failure(_))),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_digit_is_reported_where_it_is() {
+        let error = crate::literals::parsing::parse("0b102").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 5: expected binary digits, found `2`"
+        );
+    }
+
+    #[test]
+    fn round_trips_in_every_radix() {
+        for value in example_ints() {
+            for radix in Radix::ALL {
+                let text = serialize_radix(value, radix);
+                assert_eq!(apply_grammar(&text), Ok(("", value)), "{text}");
+            }
+        }
+        assert_eq!(serialize_radix(-255, Radix::Hex), "-0xff");
+        assert_eq!(serialize_radix(5, Radix::Binary), "0b101");
+    }
+
+    #[test]
+    fn agrees_with_rust_where_both_accept() {
+        check(
+            3000,
+            |g| g.numeric_text(),
+            |text| match (apply_grammar(text), text.parse::<i64>()) {
+                (Ok(("", ours)), Ok(rust)) if ours != rust => {
+                    Err(format!("{ours} but Rust reads {rust}"))
+                }
+                _ => Ok(()),
+            },
+        );
+        check(
+            1000,
+            |g| g.int(),
+            |&value| match apply_grammar(&serialize(value)) {
+                Ok(("", parsed)) if parsed == value => Ok(()),
+                other => Err(format!("read back as {other:?}")),
+            },
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! `LIST(<literal>, ...)`, with literals for the items. A list holds no
+//! lists and no NULLs, so that every list value can be written this way.
+//! Lists are built from text by the `SPLIT` function.
+
+use nom::character::complete::{char, multispace0};
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, preceded, tuple};
+
+use super::{item, keyword};
+use crate::error::PResult;
+use crate::literals::LiteralValue;
+
+pub fn apply_grammar(input: &str) -> PResult<'_, Vec<LiteralValue>> {
+    preceded(
+        tuple((keyword("list"), multispace0)),
+        delimited(
+            char('('),
+            delimited(
+                multispace0,
+                separated_list0(char(','), delimited(multispace0, item, multispace0)),
+                multispace0,
+            ),
+            char(')'),
+        ),
+    )(input)
+}
+
+pub fn serialize(items: &[LiteralValue]) -> String {
+    let items: Vec<String> = items.iter().map(LiteralValue::serialize).collect();
+    format!("LIST({})", items.join(", "))
+}
@@ -0,0 +1,73 @@
+//! nom grammars for literal values. Each submodule exposes an
+//! `apply_grammar` parser and a matching `serialize` function.
+//!
+//! These work on characters: the lexer uses them to produce literal tokens,
+//! and `infer` to classify CSV fields.
+
+pub mod boolean;
+pub mod float;
+pub mod inet;
+pub mod int;
+pub mod list;
+pub mod null;
+pub mod point;
+pub mod string;
+
+use crate::error::{GrammarError, PResult, ParseError};
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::satisfy;
+use nom::combinator::{map, not, value};
+use nom::error::context;
+use nom::sequence::terminated;
+
+use super::LiteralValue;
+
+/// The words a literal can start with, in lower case. Any other word is not
+/// a literal, which lets the lexer skip trying it as one.
+pub const WORDS: [&str; 9] = [
+    "true", "false", "null", "inet", "point", "list", "nan", "inf", "infinity",
+];
+
+/// Parses any literal. Floats are tried before ints so that `1.5` is not
+/// read as the int `1` followed by `.5`.
+pub fn apply_grammar(input: &str) -> PResult<'_, LiteralValue> {
+    alt((
+        item,
+        map(list::apply_grammar, LiteralValue::List),
+        value(LiteralValue::Null, null::apply_grammar),
+    ))(input)
+}
+
+/// A literal that a list can hold: anything but a list or NULL.
+fn item(input: &str) -> PResult<'_, LiteralValue> {
+    alt((
+        map(float::apply_grammar, LiteralValue::Float),
+        map(int::apply_grammar, LiteralValue::Int),
+        map(boolean::apply_grammar, LiteralValue::Bool),
+        map(inet::apply_grammar, LiteralValue::Inet),
+        map(point::apply_grammar, LiteralValue::Point),
+        map(string::apply_grammar, |text| {
+            LiteralValue::String(text.into_owned())
+        }),
+    ))(input)
+}
+
+/// Matches `word` case-insensitively, provided it is not just the start of a
+/// longer identifier (`nullable` is not `null`).
+pub fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> PResult<'a, &'a str> {
+    move |input| {
+        terminated(
+            tag_no_case(word),
+            not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+        )(input)
+        .map_err(|e: nom::Err<GrammarError>| {
+            e.map(|_| GrammarError::expected(input, format!("`{}`", word.to_ascii_uppercase())))
+        })
+    }
+}
+
+/// Parses a whole string as exactly one literal.
+pub fn parse(input: &str) -> Result<LiteralValue, ParseError> {
+    ParseError::parse_all(input, context("literal", apply_grammar))
+}
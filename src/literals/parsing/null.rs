@@ -0,0 +1,14 @@
+//! `null`, in any letter case.
+
+use crate::error::PResult;
+use nom::combinator::value;
+
+use super::keyword;
+
+pub fn apply_grammar(input: &str) -> PResult<'_, ()> {
+    value((), keyword("null"))(input)
+}
+
+pub fn serialize() -> String {
+    "null".to_string()
+}
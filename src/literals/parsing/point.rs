@@ -0,0 +1,43 @@
+//! `POINT(<lat>, <lon>)`, with number literals for the coordinates. A call
+//! such as `POINT(a, b)` with other arguments is not a literal; it reads as
+//! a call to the `POINT` function, which builds the same value.
+
+use nom::branch::alt;
+use nom::character::complete::{char, multispace0};
+use nom::combinator::{map, map_opt};
+use nom::sequence::{delimited, separated_pair, tuple};
+
+use super::{float, int, keyword};
+use crate::error::PResult;
+use crate::literals::Point;
+
+fn coordinate(input: &str) -> PResult<'_, f64> {
+    delimited(
+        multispace0,
+        alt((float::apply_grammar, map(int::apply_grammar, |v| v as f64))),
+        multispace0,
+    )(input)
+}
+
+pub fn apply_grammar(input: &str) -> PResult<'_, Point> {
+    map_opt(
+        tuple((
+            keyword("point"),
+            multispace0,
+            delimited(
+                char('('),
+                separated_pair(coordinate, char(','), coordinate),
+                char(')'),
+            ),
+        )),
+        |(_, _, (lat, lon))| Point::new(lat, lon),
+    )(input)
+}
+
+pub fn serialize(value: Point) -> String {
+    format!(
+        "POINT({}, {})",
+        float::serialize(value.lat()),
+        float::serialize(value.lon())
+    )
+}
@@ -0,0 +1,162 @@
+//! Quoted strings. Either quote character may be used. Inside, the escapes
+//! are Rust's: `\\`, `\"`, `\'`, `\n`, `\t`, `\r`, `\0`, `\xNN` for an
+//! ASCII character (`\x00` to `\x7F`), and `\u{N}` with one to six hex
+//! digits for any other character.
+//!
+//! A string without escapes is borrowed from the input; one with escapes is
+//! copied a run of plain text at a time, not a character at a time.
+
+use std::borrow::Cow;
+
+use crate::error::{GrammarError, PResult};
+use nom::branch::alt;
+use nom::bytes::complete::take_while_m_n;
+use nom::character::complete::char;
+use nom::combinator::{map_opt, value};
+use nom::sequence::{delimited, preceded};
+
+fn hex_digits<'a>(min: usize, max: usize) -> impl FnMut(&'a str) -> PResult<'a, u32> {
+    map_opt(
+        take_while_m_n(min, max, |c: char| c.is_ascii_hexdigit()),
+        |digits| u32::from_str_radix(digits, 16).ok(),
+    )
+}
+
+/// What follows a backslash. A malformed escape is a failure at the
+/// backslash's successor, not the end of the string.
+fn escape(input: &str) -> PResult<'_, char> {
+    alt((
+        value('\\', char('\\')),
+        value('"', char('"')),
+        value('\'', char('\'')),
+        value('\n', char('n')),
+        value('\t', char('t')),
+        value('\r', char('r')),
+        value('\0', char('0')),
+        map_opt(preceded(char('x'), hex_digits(2, 2)), |code| {
+            char::from_u32(code).filter(char::is_ascii)
+        }),
+        map_opt(
+            preceded(char('u'), delimited(char('{'), hex_digits(1, 6), char('}'))),
+            char::from_u32,
+        ),
+    ))(input)
+    .map_err(|_| nom::Err::Failure(GrammarError::expected(input, "escape sequence")))
+}
+
+/// The text between `quote`s, with its escapes replaced.
+fn quoted(input: &str, quote: char) -> PResult<'_, Cow<'_, str>> {
+    let (mut rest, _) = char(quote)(input)?;
+    let mut text = Cow::Borrowed("");
+    loop {
+        let Some(i) = rest.find([quote, '\\']) else {
+            return Err(nom::Err::Error(GrammarError::expected(
+                &rest[rest.len()..],
+                format!("`{quote}`"),
+            )));
+        };
+        let run = &rest[..i];
+        if rest[i..].starts_with(quote) {
+            match &mut text {
+                Cow::Borrowed(_) => text = Cow::Borrowed(run),
+                Cow::Owned(text) => text.push_str(run),
+            }
+            return Ok((&rest[i + 1..], text));
+        }
+        let (after, c) = escape(&rest[i + 1..])?;
+        let text = text.to_mut();
+        text.push_str(run);
+        text.push(c);
+        rest = after;
+    }
+}
+
+pub fn apply_grammar(input: &str) -> PResult<'_, Cow<'_, str>> {
+    alt((|i| quoted(i, '"'), |i| quoted(i, '\'')))(input)
+}
+
+/// Always emits the double-quoted form. Control characters are escaped, so
+/// the result is a single printable line.
+pub fn serialize(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::example_strings;
+
+    #[test]
+    fn handles_escapes_and_both_quotes() {
+        assert_eq!(
+            apply_grammar(r#""a\"b\\c\nd""#),
+            Ok(("", "a\"b\\c\nd".into()))
+        );
+        assert_eq!(apply_grammar(r"'it\'s'"), Ok(("", "it's".into())));
+        assert_eq!(apply_grammar("''"), Ok(("", "".into())));
+        assert_eq!(
+            apply_grammar(r"'\t\r\0\x41\x7f\u{e9}\u{1F600}'"),
+            Ok(("", "\t\r\0A\x7f\u{e9}\u{1F600}".into()))
+        );
+    }
+
+    #[test]
+    fn borrows_text_without_escapes() {
+        assert!(matches!(
+            apply_grammar("'plain' rest"),
+            Ok((" rest", Cow::Borrowed("plain")))
+        ));
+        assert!(matches!(apply_grammar(r"'a\nb'"), Ok(("", Cow::Owned(_)))));
+        let long = format!("'{}\\n{}'", "x".repeat(100_000), "y".repeat(100_000));
+        let (_, text) = apply_grammar(&long).unwrap();
+        assert_eq!(text.len(), 200_001);
+    }
+
+    #[test]
+    fn rejects_bad_escapes() {
+        for text in [
+            r"'\q'",
+            r"'\x4'",
+            r"'\x80'",
+            r"'\u{}'",
+            r"'\u41'",
+            r"'\u{d800}'",
+            r"'\u{1234567}'",
+        ] {
+            assert!(
+                matches!(apply_grammar(text), Err(nom::Err::Failure(_))),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unterminated() {
+        assert!(apply_grammar("\"abc").is_err());
+    }
+
+    #[test]
+    fn serialize_escapes_control_characters() {
+        assert_eq!(serialize("a\tb\u{7}\u{85}é"), r#""a\tb\u{7}\u{85}é""#);
+        for value in example_strings() {
+            let text = serialize(&value);
+            assert!(!text.chars().any(char::is_control), "{text:?}");
+            assert_eq!(apply_grammar(&text), Ok(("", value.into())));
+        }
+    }
+}
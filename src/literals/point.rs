@@ -0,0 +1,88 @@
+//! Points on the Earth's surface, given by latitude and longitude in
+//! degrees.
+
+/// Mean radius of the Earth, in metres.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A latitude in `[-90, 90]` and a longitude in `[-180, 180]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    lat: f64,
+    lon: f64,
+}
+
+impl Point {
+    /// `None` when either coordinate is out of range or not a number.
+    pub fn new(lat: f64, lon: f64) -> Option<Point> {
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon))
+            .then_some(Point { lat, lon })
+    }
+
+    pub fn lat(self) -> f64 {
+        self.lat
+    }
+
+    pub fn lon(self) -> f64 {
+        self.lon
+    }
+
+    /// Great-circle distance in metres, by the haversine formula on a
+    /// spherical Earth.
+    pub fn distance(self, other: Point) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let half_dlat = (lat2 - lat1) / 2.0;
+        let half_dlon = (other.lon - self.lon).to_radians() / 2.0;
+        let h = half_dlat.sin().powi(2) + lat1.cos() * lat2.cos() * half_dlon.sin().powi(2);
+        2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+    }
+
+    /// Whether the point lies in the box between latitudes `south` and
+    /// `north` and longitudes `west` and `east`, edges included. A box with
+    /// `west` greater than `east` crosses the antimeridian.
+    pub fn within_box(self, south: f64, west: f64, north: f64, east: f64) -> bool {
+        let lon_inside = if west <= east {
+            (west..=east).contains(&self.lon)
+        } else {
+            self.lon >= west || self.lon <= east
+        };
+        (south..=north).contains(&self.lat) && lon_inside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, lon: f64) -> Point {
+        Point::new(lat, lon).unwrap()
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        assert!(Point::new(90.5, 0.0).is_none());
+        assert!(Point::new(0.0, -180.5).is_none());
+        assert!(Point::new(f64::NAN, 0.0).is_none());
+        assert!(Point::new(-90.0, 180.0).is_some());
+    }
+
+    #[test]
+    fn distances() {
+        let paris = point(48.8566, 2.3522);
+        let london = point(51.5074, -0.1278);
+        let km = paris.distance(london) / 1000.0;
+        assert!((343.0..345.0).contains(&km), "{km}");
+        assert_eq!(paris.distance(paris), 0.0);
+        let antipodes = point(0.0, 0.0).distance(point(0.0, 180.0));
+        assert!((antipodes - std::f64::consts::PI * EARTH_RADIUS).abs() < 1e-6);
+    }
+
+    #[test]
+    fn boxes() {
+        let p = point(10.0, 20.0);
+        assert!(p.within_box(0.0, 0.0, 10.0, 20.0));
+        assert!(!p.within_box(11.0, 0.0, 20.0, 30.0));
+        assert!(point(0.0, 179.0).within_box(-1.0, 170.0, 1.0, -170.0));
+        assert!(point(0.0, -175.0).within_box(-1.0, 170.0, 1.0, -170.0));
+        assert!(!point(0.0, 0.0).within_box(-1.0, 170.0, 1.0, -170.0));
+    }
+}
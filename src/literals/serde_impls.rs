@@ -0,0 +1,123 @@
+//! `Serialize` and `Deserialize` for [`LiteralValue`], behind the `serde`
+//! feature.
+//!
+//! Values are externally tagged by variant, so an int and a float with the
+//! same value stay distinct: `{"Int": 1}` and `{"Float": 1.0}`. Formats
+//! such as JSON have no NaN or infinity, so floats that are not finite are
+//! written as the strings `"NaN"`, `"inf"` and `"-inf"` instead. Inet
+//! addresses are written as strings, `{"Inet": "10.0.0.0/8"}`, points as
+//! pairs, `{"Point": [48.85, 2.35]}`, and lists as arrays of values,
+//! `{"List": [{"String": "a"}]}`.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{LiteralValue, Point};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "LiteralValue")]
+enum Repr {
+    Int(i64),
+    Float(Float),
+    String(String),
+    Bool(bool),
+    Inet(String),
+    Point(f64, f64),
+    List(Vec<LiteralValue>),
+    Null,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Float {
+    Finite(f64),
+    NonFinite(String),
+}
+
+impl Serialize for LiteralValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            LiteralValue::Int(v) => Repr::Int(*v),
+            LiteralValue::Float(v) if v.is_finite() => Repr::Float(Float::Finite(*v)),
+            LiteralValue::Float(v) => Repr::Float(Float::NonFinite(format!("{v:?}"))),
+            LiteralValue::String(v) => Repr::String(v.clone()),
+            LiteralValue::Bool(v) => Repr::Bool(*v),
+            LiteralValue::Inet(v) => Repr::Inet(v.to_string()),
+            LiteralValue::Point(v) => Repr::Point(v.lat(), v.lon()),
+            LiteralValue::List(v) => Repr::List(v.clone()),
+            LiteralValue::Null => Repr::Null,
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LiteralValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Int(v) => LiteralValue::Int(v),
+            Repr::Float(Float::Finite(v)) => LiteralValue::Float(v),
+            Repr::Float(Float::NonFinite(text)) => match text.as_str() {
+                "NaN" => LiteralValue::Float(f64::NAN),
+                "inf" => LiteralValue::Float(f64::INFINITY),
+                "-inf" => LiteralValue::Float(f64::NEG_INFINITY),
+                _ => {
+                    return Err(D::Error::custom(format!(
+                        "expected a number, `NaN`, `inf` or `-inf`, found `{text}`"
+                    )))
+                }
+            },
+            Repr::String(v) => LiteralValue::String(v),
+            Repr::Bool(v) => LiteralValue::Bool(v),
+            Repr::Inet(text) => LiteralValue::Inet(text.parse().map_err(D::Error::custom)?),
+            Repr::Point(lat, lon) => {
+                LiteralValue::Point(Point::new(lat, lon).ok_or_else(|| {
+                    D::Error::custom(format!("point ({lat}, {lon}) is out of range"))
+                })?)
+            }
+            Repr::List(items) => {
+                if let Some(item) = items
+                    .iter()
+                    .find(|item| matches!(item, LiteralValue::List(_) | LiteralValue::Null))
+                {
+                    return Err(D::Error::custom(format!(
+                        "a list cannot hold a {}",
+                        item.type_name()
+                    )));
+                }
+                LiteralValue::List(items)
+            }
+            Repr::Null => LiteralValue::Null,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::example_literals;
+
+    #[test]
+    fn round_trips_through_json() {
+        for value in example_literals() {
+            let json = serde_json::to_string(&value).unwrap();
+            let back: LiteralValue = serde_json::from_str(&json).unwrap();
+            assert!(back.identical(&value), "{json}");
+        }
+    }
+
+    #[test]
+    fn keeps_ints_and_floats_apart() {
+        for (value, json) in [
+            (LiteralValue::Int(1), r#"{"Int":1}"#),
+            (LiteralValue::Float(1.0), r#"{"Float":1.0}"#),
+            (
+                LiteralValue::Float(f64::NEG_INFINITY),
+                r#"{"Float":"-inf"}"#,
+            ),
+            (LiteralValue::Null, r#""Null""#),
+        ] {
+            assert_eq!(serde_json::to_string(&value).unwrap(), json);
+        }
+        assert!(serde_json::from_str::<LiteralValue>(r#"{"Float":"nan"}"#).is_err());
+    }
+}
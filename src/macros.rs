@@ -0,0 +1,296 @@
+//! Macros: `CREATE MACRO add_tax(x) AS x * 1.2` names an expression with
+//! parameters, and a later call `add_tax(price)` is replaced by it, with
+//! each parameter replaced by its argument, before the statement is
+//! planned. Like session variables, macros live in the catalog, so they
+//! last as long as it does.
+//!
+//! A name in the body that is not a parameter is left as it is, so it can
+//! name a column of the table the calling query reads. A body may call
+//! macros defined before it; they are expanded when it is defined, so a
+//! macro cannot call itself. Macros cannot take the name of a built-in
+//! function or of another macro.
+//!
+//! Each use of a parameter copies its argument, so nested calls can expand
+//! to a tree exponentially larger than the text. A call may expand to at
+//! most [`MAX_EXPANSION`] nodes, and the expanded expression may be no
+//! deeper than a parsed one may be.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ast::parsing::MAX_DEPTH;
+use crate::ast::Expr;
+use crate::functions;
+
+/// How many nodes a single macro call may expand to.
+pub const MAX_EXPANSION: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroError {
+    /// A macro of that name exists already.
+    Exists(String),
+    /// The name is that of a built-in function.
+    Builtin(String),
+    DuplicateParameter {
+        name: String,
+        parameter: String,
+    },
+    /// A call gave the wrong number of arguments.
+    Arity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A call of the macro expanded to more than [`MAX_EXPANSION`] nodes.
+    TooLarge(String),
+    /// Expanding made the expression deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::Exists(name) => write!(f, "macro `{name}` already exists"),
+            MacroError::Builtin(name) => write!(f, "`{name}` is a built-in function"),
+            MacroError::DuplicateParameter { name, parameter } => {
+                write!(f, "macro `{name}` has parameter `{parameter}` twice")
+            }
+            MacroError::Arity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "macro `{name}` takes {expected} arguments but was given {found}"
+            ),
+            MacroError::TooLarge(name) => write!(
+                f,
+                "a call of macro `{name}` expands to more than {MAX_EXPANSION} nodes"
+            ),
+            MacroError::TooDeep => write!(
+                f,
+                "expanding macros nests the expression more than {MAX_DEPTH} deep"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MacroError {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Macro {
+    parameters: Vec<String>,
+    body: Expr,
+}
+
+/// The macros of a session, by lowercased name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Macros {
+    macros: BTreeMap<String, Macro>,
+}
+
+impl Macros {
+    /// Adds a macro, expanding the calls of other macros in its body.
+    pub fn define(
+        &mut self,
+        name: &str,
+        parameters: &[String],
+        body: &Expr,
+    ) -> Result<(), MacroError> {
+        let key = name.to_ascii_lowercase();
+        if functions::exists(&key) {
+            return Err(MacroError::Builtin(name.to_string()));
+        }
+        if self.macros.contains_key(&key) {
+            return Err(MacroError::Exists(name.to_string()));
+        }
+        for (i, parameter) in parameters.iter().enumerate() {
+            if parameters[..i].contains(parameter) {
+                return Err(MacroError::DuplicateParameter {
+                    name: name.to_string(),
+                    parameter: parameter.clone(),
+                });
+            }
+        }
+        let body = self.expand(body)?;
+        self.macros.insert(
+            key,
+            Macro {
+                parameters: parameters.to_vec(),
+                body,
+            },
+        );
+        Ok(())
+    }
+
+    /// Whether a macro is called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.macros.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Whether `expr` calls a macro.
+    pub fn used_by(&self, expr: &Expr) -> bool {
+        self.called(expr).is_some() || expr.children().into_iter().any(|e| self.used_by(e))
+    }
+
+    /// `expr` with each macro call replaced by the macro's body.
+    pub fn expand(&self, expr: &Expr) -> Result<Expr, MacroError> {
+        self.check(expr)?;
+        let expanded = self.expand_calls(expr)?;
+        if expanded.depth() > MAX_DEPTH {
+            return Err(MacroError::TooDeep);
+        }
+        Ok(expanded)
+    }
+
+    fn expand_calls(&self, expr: &Expr) -> Result<Expr, MacroError> {
+        expr.try_replace(&|e| self.expand_call(e))
+    }
+
+    /// The macro `expr` calls, if it is a macro call.
+    fn called(&self, expr: &Expr) -> Option<&Macro> {
+        match expr {
+            Expr::Call { name, .. } => self.macros.get(&name.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Checks that every macro call in `expr` has the right number of
+    /// arguments.
+    fn check(&self, expr: &Expr) -> Result<(), MacroError> {
+        if let (Some(called), Expr::Call { name, args }) = (self.called(expr), expr) {
+            if args.len() != called.parameters.len() {
+                return Err(MacroError::Arity {
+                    name: name.clone(),
+                    expected: called.parameters.len(),
+                    found: args.len(),
+                });
+            }
+        }
+        expr.children().into_iter().try_for_each(|e| self.check(e))
+    }
+
+    /// The body of the macro `expr` calls, with its parameters replaced by
+    /// the expanded arguments. An argument is only copied while the
+    /// expansion stays within [`MAX_EXPANSION`] nodes.
+    fn expand_call(&self, expr: &Expr) -> Result<Option<Expr>, MacroError> {
+        let Some(called) = self.called(expr) else {
+            return Ok(None);
+        };
+        let Expr::Call { name, args } = expr else {
+            unreachable!("`called` only finds calls")
+        };
+        let args = args
+            .iter()
+            .map(|arg| self.expand_calls(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sizes: Vec<usize> = args.iter().map(Expr::size).collect();
+        let nodes = Cell::new(called.body.size());
+        let expanded = called.body.try_replace(&|e| {
+            let Expr::Identifier(parameter) = e else {
+                return Ok(None);
+            };
+            let Some(i) = called.parameters.iter().position(|p| p == parameter) else {
+                return Ok(None);
+            };
+            nodes.set(nodes.get() - 1 + sizes[i]);
+            if nodes.get() > MAX_EXPANSION {
+                return Err(MacroError::TooLarge(name.clone()));
+            }
+            Ok(Some(args[i].clone()))
+        })?;
+        if expanded.depth() > MAX_DEPTH {
+            return Err(MacroError::TooDeep);
+        }
+        Ok(Some(expanded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parsing::apply_grammar;
+    use crate::lexer;
+
+    fn expr(text: &str) -> Expr {
+        lexer::parse_all(text, apply_grammar).unwrap()
+    }
+
+    #[test]
+    fn expands_calls_with_their_arguments() {
+        let mut macros = Macros::default();
+        macros
+            .define("add_tax", &["x".to_string()], &expr("x * 1.2"))
+            .unwrap();
+        macros
+            .define(
+                "gross",
+                &["x".to_string(), "fee".to_string()],
+                &expr("add_tax(x) + fee + rate"),
+            )
+            .unwrap();
+        assert_eq!(
+            macros
+                .expand(&expr("ADD_TAX(price) > gross(add_tax(1), 2)"))
+                .unwrap()
+                .serialize(),
+            "price * 1.2 > 1 * 1.2 * 1.2 + 2 + rate"
+        );
+        assert!(macros.used_by(&expr("1 + gross(1, 2)")));
+        assert!(!macros.used_by(&expr("upper(x)")));
+
+        assert_eq!(
+            macros
+                .expand(&expr("add_tax(1, 2)"))
+                .unwrap_err()
+                .to_string(),
+            "macro `add_tax` takes 1 arguments but was given 2"
+        );
+        assert_eq!(
+            macros.define("Add_Tax", &[], &expr("1")),
+            Err(MacroError::Exists("Add_Tax".to_string()))
+        );
+        assert_eq!(
+            macros.define("inet", &[], &expr("1")),
+            Err(MacroError::Builtin("inet".to_string()))
+        );
+        assert_eq!(
+            macros
+                .define("f", &["a".to_string(), "a".to_string()], &expr("a"))
+                .unwrap_err()
+                .to_string(),
+            "macro `f` has parameter `a` twice"
+        );
+    }
+
+    #[test]
+    fn limits_expansion() {
+        let mut macros = Macros::default();
+        macros
+            .define("d", &["x".to_string()], &expr("x + x"))
+            .unwrap();
+        let nested = |n| format!("{}1{}", "d(".repeat(n), ")".repeat(n));
+        assert_eq!(macros.expand(&expr(&nested(8))).unwrap().size(), 511);
+        assert_eq!(
+            macros.expand(&expr(&nested(32))).unwrap_err().to_string(),
+            "a call of macro `d` expands to more than 10000 nodes"
+        );
+
+        let chain = format!("x{}", " OR false".repeat(MAX_DEPTH / 2));
+        macros
+            .define("deep", &["x".to_string()], &expr(&chain))
+            .unwrap();
+        assert_eq!(
+            macros.expand(&expr("deep(deep(1))")),
+            Err(MacroError::TooDeep)
+        );
+        assert_eq!(
+            macros.expand(&expr(&format!(
+                "NOT {}",
+                "- ".repeat(MAX_DEPTH - 3) + "deep(1)"
+            ))),
+            Err(MacroError::TooDeep)
+        );
+    }
+}
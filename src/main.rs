@@ -1,3 +1,3 @@
 fn main() {
-    println!("Hello, world!");
+    std::process::exit(toyql::run_from_args(std::env::args().skip(1)));
 }
@@ -0,0 +1,235 @@
+//! Parameter files for `toyql --params FILE.json`: a JSON array of objects,
+//! each a set of named parameters. The statements run once per set, with
+//! each name set as a session variable (see [`variables`](crate::variables))
+//! so that statements read it as `@name`.
+//!
+//! Values must be scalars: strings, numbers, `true`, `false` or `null`. A
+//! number with a fraction or an exponent is a FLOAT, any other an INT, and
+//! `null` sets the variable to NULL.
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while_m_n};
+use nom::character::complete::{char, digit1, multispace0, one_of};
+use nom::combinator::{cut, map, map_opt, opt, recognize, value};
+use nom::error::context;
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+
+use crate::error::{GrammarError, PResult, ParseError};
+use crate::literals::LiteralValue;
+use crate::variables::{InvalidSetting, Variables};
+
+/// The names and values of one run, in the order the file gives them.
+pub type ParameterSet = Vec<(String, LiteralValue)>;
+
+/// Reads a parameter file.
+pub fn parse(text: &str) -> Result<Vec<ParameterSet>, ParseError> {
+    ParseError::parse_all(text, delimited(multispace0, sets, multispace0))
+}
+
+/// Sets each variable of `set`, checking the values of settings.
+pub fn bind(set: &ParameterSet, variables: &mut Variables) -> Result<(), InvalidSetting> {
+    for (name, value) in set {
+        variables.set(name, value.clone())?;
+    }
+    Ok(())
+}
+
+/// `f`, then any whitespace.
+fn spaced<'a, O>(
+    f: impl FnMut(&'a str) -> PResult<'a, O>,
+) -> impl FnMut(&'a str) -> PResult<'a, O> {
+    terminated(f, multispace0)
+}
+
+fn sets(input: &str) -> PResult<'_, Vec<ParameterSet>> {
+    context(
+        "an array of parameter sets",
+        delimited(
+            spaced(char('[')),
+            separated_list0(spaced(char(',')), spaced(set)),
+            char(']'),
+        ),
+    )(input)
+}
+
+fn set(input: &str) -> PResult<'_, ParameterSet> {
+    context(
+        "an object",
+        preceded(
+            spaced(char('{')),
+            cut(terminated(
+                separated_list0(
+                    spaced(char(',')),
+                    separated_pair(spaced(string), cut(spaced(char(':'))), cut(spaced(scalar))),
+                ),
+                char('}'),
+            )),
+        ),
+    )(input)
+}
+
+/// A number too big for an INT is an error of its own, not one of the
+/// alternatives.
+fn scalar(input: &str) -> PResult<'_, LiteralValue> {
+    alt((
+        number,
+        context(
+            "a string, number, boolean or null",
+            alt((
+                map(string, LiteralValue::String),
+                value(LiteralValue::Bool(true), tag("true")),
+                value(LiteralValue::Bool(false), tag("false")),
+                value(LiteralValue::Null, tag("null")),
+            )),
+        ),
+    ))(input)
+}
+
+fn number(input: &str) -> PResult<'_, LiteralValue> {
+    let (rest, text) = recognize(tuple((
+        opt(char('-')),
+        digit1,
+        opt(pair(char('.'), digit1)),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(input)?;
+    let number = if text.contains(['.', 'e', 'E']) {
+        text.parse().ok().map(LiteralValue::Float)
+    } else {
+        text.parse().ok().map(LiteralValue::Int)
+    };
+    match number {
+        Some(number) => Ok((rest, number)),
+        None => Err(nom::Err::Failure(GrammarError::expected(
+            input,
+            "an integer that fits in 64 bits",
+        ))),
+    }
+}
+
+/// A double-quoted string with JSON's escapes.
+fn string(input: &str) -> PResult<'_, String> {
+    let (mut rest, _) = char('"')(input)?;
+    let mut out = String::new();
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some('"') => return Ok((chars.as_str(), out)),
+            Some('\\') => {
+                let (after, c) = cut(escape)(chars.as_str())?;
+                out.push(c);
+                rest = after;
+            }
+            Some(c) if c >= ' ' => {
+                out.push(c);
+                rest = chars.as_str();
+            }
+            _ => return Err(nom::Err::Failure(GrammarError::expected(rest, "`\"`"))),
+        }
+    }
+}
+
+/// What follows a backslash.
+fn escape(input: &str) -> PResult<'_, char> {
+    context(
+        "an escape",
+        alt((
+            value('"', char('"')),
+            value('\\', char('\\')),
+            value('/', char('/')),
+            value('\u{8}', char('b')),
+            value('\u{c}', char('f')),
+            value('\n', char('n')),
+            value('\r', char('r')),
+            value('\t', char('t')),
+            preceded(char('u'), unicode),
+        )),
+    )(input)
+}
+
+fn hex4(input: &str) -> PResult<'_, u32> {
+    map_opt(
+        take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+        |digits| u32::from_str_radix(digits, 16).ok(),
+    )(input)
+}
+
+/// The four hex digits of a `\u` escape, and for a high surrogate the
+/// `\u` escape of the low one after it.
+fn unicode(input: &str) -> PResult<'_, char> {
+    alt((
+        map_opt(pair(hex4, preceded(tag("\\u"), hex4)), |(high, low)| {
+            let (0xD800..=0xDBFF, 0xDC00..=0xDFFF) = (high, low) else {
+                return None;
+            };
+            char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+        }),
+        map_opt(hex4, char::from_u32),
+    ))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LiteralValue::{Bool, Float, Int, Null, String as Str};
+
+    #[test]
+    fn reads_sets_of_scalars() {
+        let text = r#"
+            [
+              {"region": "north", "limit": 10, "ratio": 0.5e1},
+              {"region": "south \"\ud83d\ude00\"", "active": true, "skip": null},
+              {}
+            ]
+        "#;
+        assert_eq!(
+            parse(text),
+            Ok(vec![
+                vec![
+                    ("region".to_string(), Str("north".to_string())),
+                    ("limit".to_string(), Int(10)),
+                    ("ratio".to_string(), Float(5.0)),
+                ],
+                vec![
+                    ("region".to_string(), Str("south \"\u{1F600}\"".to_string())),
+                    ("active".to_string(), Bool(true)),
+                    ("skip".to_string(), Null),
+                ],
+                vec![],
+            ])
+        );
+    }
+
+    #[test]
+    fn binds_null_as_a_value() {
+        use crate::catalog::Catalog;
+        use crate::statements::{execute, parsing, Outcome};
+
+        let mut catalog = Catalog::new();
+        let sets = parse(r#"[{"s": null, "n": 1}]"#).unwrap();
+        bind(&sets[0], catalog.variables_mut()).unwrap();
+        let statement = parsing::parse("SELECT @s, @n").unwrap();
+        let Ok(Outcome::Rows(result)) = execute(&statement, &mut catalog) else {
+            panic!("SELECT @s failed");
+        };
+        assert_eq!(result.rows[0].as_ref(), [Null, Int(1)]);
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_scalar() {
+        let message = |text| parse(text).unwrap_err().message();
+        assert_eq!(
+            message(r#"[{"a": [1]}]"#),
+            "expected a string, number, boolean or null, found `[`"
+        );
+        assert_eq!(
+            message(r#"{"a": 1}"#),
+            "expected an array of parameter sets, found `{`"
+        );
+        assert_eq!(
+            message(r#"[{"a": 99999999999999999999}]"#),
+            "expected an integer that fits in 64 bits, found `99999999999999999999`"
+        );
+        assert_eq!(message(r#"[{"a": "\q"}]"#), "expected an escape, found `q`");
+    }
+}
@@ -0,0 +1,103 @@
+//! Quoting values and names for statements built as text.
+//!
+//! An embedder that has to write a statement out, rather than bind values
+//! into a [`PreparedStatement`](crate::statements::PreparedStatement), can
+//! splice in the output of these functions: each reads back as exactly one
+//! literal or identifier token, whatever the value or name holds.
+
+use crate::lexer;
+use crate::literals::LiteralValue;
+
+/// `value` written as a literal, as [`LiteralValue::serialize`] writes it.
+pub fn quote_literal(value: &LiteralValue) -> String {
+    value.serialize()
+}
+
+/// `name` written as an identifier: bare when it can be, otherwise in
+/// backticks, so that keywords and names with spaces or quotes stay names.
+/// No identifier is empty, so the empty name comes out as `` `` ``, which
+/// is a syntax error wherever it is put.
+pub fn quote_ident(name: &str) -> String {
+    lexer::quote_identifier(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Expr;
+    use crate::lexer::parsing::tokenize;
+    use crate::lexer::TokenKind;
+    use crate::statements::{parsing::parse, SelectItem, Statement};
+    use crate::test_utils::{check, example_literals};
+
+    fn token_kinds(text: &str) -> Vec<TokenKind> {
+        let tokens = tokenize(text).unwrap();
+        tokens.into_iter().map(|token| token.kind).collect()
+    }
+
+    /// Reads `text` back as `SELECT <literal> AS <name>`.
+    fn read_back(text: &str) -> (LiteralValue, String) {
+        let Ok(Statement::Select(select)) = parse(text) else {
+            panic!("{text} did not parse as a query");
+        };
+        match select.items.as_slice() {
+            [SelectItem::Expr {
+                expr: Expr::Literal(value),
+                alias: Some(alias),
+            }] => (value.clone(), alias.clone()),
+            items => panic!("{text} parsed as {items:?}"),
+        }
+    }
+
+    #[test]
+    fn quotes_keywords_and_odd_names() {
+        assert_eq!(quote_ident("name"), "name");
+        assert_eq!(quote_ident("select"), "`select`");
+        assert_eq!(quote_ident("x` FROM t; --"), "`x`` FROM t; --`");
+        assert_eq!(
+            quote_literal(&LiteralValue::String("'); DROP".to_string())),
+            "\"'); DROP\""
+        );
+        assert!(parse(&format!("SELECT 1 AS {}", quote_ident(""))).is_err());
+    }
+
+    /// Checks that `literal` and `ident` each lex as one token, and read
+    /// back as the value and name they quote, on their own and in a query.
+    fn reads_back(literal: &str, ident: &str, name: &str) -> Result<(), String> {
+        // Compared as text, since every NaN reads back as the same one.
+        let kinds = token_kinds(literal);
+        if !matches!(&kinds[..], [TokenKind::Literal(v)] if quote_literal(v) == literal) {
+            return Err(format!("{literal} lexed as {kinds:?}"));
+        }
+        let kinds = token_kinds(ident);
+        if !matches!(
+            &kinds[..],
+            [TokenKind::Identifier(n) | TokenKind::QuotedIdentifier(n)] if n == name
+        ) {
+            return Err(format!("{ident} lexed as {kinds:?}"));
+        }
+        let (value, alias) = read_back(&format!("SELECT {literal} AS {ident}"));
+        if quote_literal(&value) != literal || alias != name {
+            return Err(format!("read back as {value:?} AS {alias}"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn quoted_output_always_reads_back() {
+        for value in example_literals() {
+            reads_back(&quote_literal(&value), "x", "x").unwrap();
+        }
+        check(
+            2000,
+            |g| {
+                let name = match g.string() {
+                    name if name.is_empty() => "_".to_string(),
+                    name => name,
+                };
+                (g.literal(), name)
+            },
+            |(value, name)| reads_back(&quote_literal(value), &quote_ident(name), name),
+        );
+    }
+}
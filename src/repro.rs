@@ -0,0 +1,210 @@
+//! Replays inputs that crashed toyql, or might, checking invariants that a
+//! plain run does not.
+//!
+//! A reproduction is text holding statements separated by `;`, run in order
+//! against an empty catalog. For each statement:
+//! - every token lies within the source, on character boundaries, after the
+//!   token before it;
+//! - a parse error's line and column agree with its byte offset;
+//! - a statement that parses serializes to text that parses back and
+//!   serializes the same way again;
+//! - executing it, and displaying any error, does not panic.
+//!
+//! Text that does not tokenize at all is checked as a single statement.
+//! The files in `tests/crashes/` are replayed by this module's tests, so an
+//! input added there stays fixed. Most of them are edge cases written by
+//! hand; `stack_overflow.sql` is one that did crash toyql.
+
+use std::fmt;
+
+use crate::catalog::Catalog;
+use crate::error::{ParseError, ToyQlError};
+use crate::lexer::{parsing::tokenize, TokenKind};
+use crate::statements::{self, parsing::parse};
+
+/// An invariant that did not hold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// The statement it happened in, counted from 1.
+    pub statement: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "statement {}: {}", self.statement, self.message)
+    }
+}
+
+impl std::error::Error for Violation {}
+
+/// Runs each statement of `source`, returning a line per statement saying
+/// how it went.
+pub fn replay(source: &str) -> Result<Vec<String>, Violation> {
+    let mut catalog = Catalog::new();
+    let mut lines = Vec::new();
+    for (i, text) in split(source).into_iter().enumerate() {
+        let violation = |message: String| Violation {
+            statement: i + 1,
+            message,
+        };
+        check_tokens(text).map_err(violation)?;
+        let outcome = match parse(text) {
+            Err(e) => {
+                check_parse_error(text, &e).map_err(violation)?;
+                Err(ToyQlError::Parse(e))
+            }
+            Ok(statement) => {
+                check_round_trip(&statement).map_err(violation)?;
+                statements::execute(&statement, &mut catalog).map_err(ToyQlError::Exec)
+            }
+        };
+        lines.push(match outcome {
+            Ok(_) => format!("{}: ok", i + 1),
+            Err(e) => format!("{}: {e}", i + 1),
+        });
+    }
+    Ok(lines)
+}
+
+/// The statements of `source`, each with its `;`.
+fn split(source: &str) -> Vec<&str> {
+    let Ok(tokens) = tokenize(source) else {
+        return vec![source];
+    };
+    let mut statements = Vec::new();
+    let mut start = 0;
+    for token in &tokens {
+        if token.kind == TokenKind::Symbol(";") {
+            statements.push(&source[start..token.span.end]);
+            start = token.span.end;
+        }
+    }
+    if tokens.last().is_some_and(|t| t.span.end > start) {
+        statements.push(&source[start..]);
+    }
+    statements
+}
+
+fn check_tokens(text: &str) -> Result<(), String> {
+    let Ok(tokens) = tokenize(text) else {
+        return Ok(());
+    };
+    let mut end = 0;
+    for token in tokens {
+        let span = &token.span;
+        if span.start < end || span.end < span.start || span.end > text.len() {
+            return Err(format!(
+                "token {:?} at {span:?} is out of place",
+                token.kind
+            ));
+        }
+        if !text.is_char_boundary(span.start) || !text.is_char_boundary(span.end) {
+            return Err(format!(
+                "token {:?} at {span:?} splits a character",
+                token.kind
+            ));
+        }
+        end = span.end;
+    }
+    Ok(())
+}
+
+fn check_parse_error(text: &str, error: &ParseError) -> Result<(), String> {
+    if error.offset > text.len() || !text.is_char_boundary(error.offset) {
+        return Err(format!(
+            "parse error offset {} is not in the text",
+            error.offset
+        ));
+    }
+    let before = &text[..error.offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    if (error.line, error.column) != (line, column) {
+        return Err(format!(
+            "parse error at line {}, column {} but offset {} is line {line}, column {column}",
+            error.line, error.column, error.offset
+        ));
+    }
+    error.render();
+    Ok(())
+}
+
+fn check_round_trip(statement: &statements::Statement) -> Result<(), String> {
+    let text = statement.serialize();
+    let reparsed = parse(&text).map_err(|e| format!("`{text}` does not parse back: {e}"))?;
+    let again = reparsed.serialize();
+    if again != text {
+        return Err(format!("`{text}` serializes as `{again}` once reparsed"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn crash_corpus_still_passes() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/crashes");
+        let mut replayed = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "sql") {
+                let bytes = std::fs::read(&path).unwrap();
+                let source = String::from_utf8_lossy(&bytes);
+                if let Err(e) = replay(&source) {
+                    panic!("{}: {e}", path.display());
+                }
+                replayed += 1;
+            }
+        }
+        assert!(replayed > 0, "no inputs in {}", dir.display());
+    }
+
+    #[test]
+    fn refuses_what_overflowed_the_stack() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/crashes/stack_overflow.sql");
+        let lines = replay(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(lines.len(), 7);
+        for line in lines {
+            assert!(line.ends_with("expression nested too deeply"), "{line}");
+        }
+    }
+
+    #[test]
+    fn replays_statements_in_order() {
+        let lines =
+            replay("CREATE TABLE t (x INT); INSERT INTO t VALUES (1);\nSELECT y FROM t; SELECT (")
+                .unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "1: ok",
+                "2: ok",
+                "3: execution error: unknown identifier `y`",
+                "4: line 1, column 10: expected expression, found end of input",
+            ]
+        );
+        assert_eq!(replay("'open").unwrap().len(), 1);
+        assert!(replay("  -- nothing\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_broken_invariants() {
+        let error = ParseError {
+            offset: 3,
+            line: 1,
+            column: 3,
+            expected: vec![],
+            found: String::new(),
+            source_line: "a\nbc".to_string(),
+            reason: None,
+        };
+        assert_eq!(
+            check_parse_error("a\nbc", &error),
+            Err("parse error at line 1, column 3 but offset 3 is line 2, column 2".to_string())
+        );
+    }
+}
@@ -0,0 +1,692 @@
+//! Hash aggregation for SELECTs with GROUP BY or aggregate calls.
+//!
+//! Rows are put in groups by the values of the GROUP BY expressions, using
+//! [`LiteralValue::hash_key`], so numbers group by value across int and
+//! float and all NULLs (or all NaNs) form one group. Each group keeps an
+//! accumulator per aggregate call. Groups come out in the order their first
+//! row was seen; without GROUP BY there is exactly one group, even when no
+//! rows were read.
+//!
+//! - `COUNT(*)` counts rows; `COUNT(x)` counts non-null values of `x`.
+//! - `SUM(x)` adds numbers with the rules of `+`, so ints stay ints and
+//!   overflow is an error.
+//! - `AVG(x)` is the float mean.
+//! - `MIN(x)` and `MAX(x)` take values that compare with each other, and
+//!   order them like ORDER BY, so NaN is above every other number.
+//!
+//! Aggregates other than `COUNT` are NULL over no non-null values.
+//!
+//! Adding floats one by one rounds after each addition, so the result
+//! depends on the order the rows come in. Under the `deterministic_floats`
+//! setting, `SUM` over floats and `AVG` keep the exact sum instead, and
+//! round it once at the end ([`ExactSum`]), so that any order of the same
+//! values gives the same result, bit for bit. A `SUM` then adds its ints
+//! apart from its floats, in 128 bits, and adds them to the floats at the
+//! end; only a sum of ints alone can overflow, and only if its total
+//! does.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::execute::ExecError;
+use super::{OrderBy, SelectItem};
+use crate::ast::{BinaryOp, Expr};
+use crate::catalog::Schema;
+use crate::eval::{self, EvalError};
+use crate::functions;
+use crate::literals::{HashKey, LiteralValue};
+use crate::table::{Row, Values};
+
+/// The Aggregate step of a SELECT, with the select list and ORDER BY
+/// rewritten to read its output: each GROUP BY expression and aggregate
+/// call in them becomes the name of the column holding its value.
+pub struct Aggregation {
+    pub keys: Vec<Expr>,
+    pub aggregates: Vec<Expr>,
+    pub items: Vec<SelectItem>,
+    pub order_by: Vec<OrderBy>,
+}
+
+/// Plans the grouping of a SELECT; `None` when it neither groups nor
+/// aggregates. Names of output columns in `order_by` must already have been
+/// replaced by their expressions.
+pub fn plan(
+    items: &[SelectItem],
+    group_by: &[Expr],
+    order_by: &[OrderBy],
+) -> Result<Option<Aggregation>, ExecError> {
+    let mut aggregates = Vec::new();
+    for item in items {
+        if let SelectItem::Expr { expr, .. } = item {
+            collect_aggregates(expr, &mut aggregates);
+        }
+    }
+    for item in order_by {
+        collect_aggregates(&item.expr, &mut aggregates);
+    }
+    if aggregates.is_empty() && group_by.is_empty() {
+        return Ok(None);
+    }
+    for aggregate in &aggregates {
+        check_arity(aggregate)?;
+    }
+    for item in items {
+        match item {
+            SelectItem::Wildcard => return Err(ExecError::WildcardWithAggregates),
+            SelectItem::Expr { expr, .. } => check_grouped(expr, group_by)?,
+        }
+    }
+    for item in order_by {
+        check_grouped(&item.expr, group_by)?;
+    }
+    let output = |expr: &Expr| {
+        expr.replace(&|e| {
+            (group_by.contains(e) || aggregates.contains(&e))
+                .then(|| Expr::Identifier(e.serialize()))
+        })
+    };
+    let items = items
+        .iter()
+        .map(|item| match item {
+            SelectItem::Expr { expr, alias } => SelectItem::Expr {
+                expr: output(expr),
+                alias: alias.clone(),
+            },
+            SelectItem::Wildcard => unreachable!("rejected above"),
+        })
+        .collect();
+    let order_by = order_by
+        .iter()
+        .map(|item| OrderBy {
+            expr: output(&item.expr),
+            descending: item.descending,
+        })
+        .collect();
+    Ok(Some(Aggregation {
+        keys: group_by.to_vec(),
+        aggregates: aggregates.into_iter().cloned().collect(),
+        items,
+        order_by,
+    }))
+}
+
+/// Puts `rows` in groups by `keys` and computes `aggregates` over each,
+/// giving a row of `schema` per group: its keys, then its aggregates. With
+/// `exact`, float sums are exact until rounded once.
+pub fn run(
+    rows: impl Iterator<Item = Result<Row, ExecError>>,
+    keys: &[Expr],
+    aggregates: &[Expr],
+    schema: &Arc<Schema>,
+    exact: bool,
+) -> Result<Vec<Row>, ExecError> {
+    let mut grouping = Grouping {
+        keys,
+        aggregates,
+        exact,
+        positions: HashMap::new(),
+        groups: Vec::new(),
+    };
+    for row in rows {
+        grouping.add(&row?)?;
+    }
+    Ok(grouping.finish(schema)?)
+}
+
+/// The groups built so far.
+struct Grouping<'a> {
+    keys: &'a [Expr],
+    aggregates: &'a [Expr],
+    exact: bool,
+    positions: HashMap<Vec<HashKey>, usize>,
+    groups: Vec<Group>,
+}
+
+struct Group {
+    keys: Vec<LiteralValue>,
+    accumulators: Vec<Accumulator>,
+}
+
+impl Grouping<'_> {
+    /// Adds a row to its group.
+    fn add(&mut self, row: &Row) -> Result<(), ExecError> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| eval::evaluate(key, row))
+            .collect::<Result<Vec<_>, _>>()?;
+        let hash_keys = keys.iter().map(LiteralValue::hash_key).collect();
+        let position = match self.positions.get(&hash_keys) {
+            Some(&position) => position,
+            None => {
+                self.groups.push(self.new_group(keys));
+                self.positions.insert(hash_keys, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+        let group = &mut self.groups[position];
+        for (aggregate, accumulator) in self.aggregates.iter().zip(&mut group.accumulators) {
+            let Expr::Call { name, args } = aggregate else {
+                unreachable!("aggregates are calls");
+            };
+            let value = match args.first() {
+                Some(arg) => eval::evaluate(arg, row)?,
+                // `COUNT(*)` counts every row.
+                None => LiteralValue::Bool(true),
+            };
+            accumulator.add(name, value)?;
+        }
+        Ok(())
+    }
+
+    fn new_group(&self, keys: Vec<LiteralValue>) -> Group {
+        Group {
+            keys,
+            accumulators: self
+                .aggregates
+                .iter()
+                .map(|aggregate| match aggregate {
+                    Expr::Call { name, .. } => Accumulator::new(name, self.exact),
+                    _ => unreachable!("aggregates are calls"),
+                })
+                .collect(),
+        }
+    }
+
+    /// A row per group, in order of first appearance.
+    fn finish(mut self, schema: &Arc<Schema>) -> Result<Vec<Row>, EvalError> {
+        if self.groups.is_empty() && self.keys.is_empty() {
+            self.groups.push(self.new_group(Vec::new()));
+        }
+        self.groups
+            .into_iter()
+            .map(|group| {
+                let aggregates = group
+                    .accumulators
+                    .into_iter()
+                    .map(Accumulator::finish)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let values: Values = group.keys.into_iter().chain(aggregates).collect();
+                Ok(Row::new(Arc::clone(schema), values))
+            })
+            .collect()
+    }
+}
+
+fn is_aggregate(expr: &Expr) -> bool {
+    matches!(expr, Expr::Call { name, .. } if functions::is_aggregate(name))
+}
+
+/// The outermost aggregate calls in `expr`, without repeats.
+fn collect_aggregates<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+    if is_aggregate(expr) {
+        if !out.contains(&expr) {
+            out.push(expr);
+        }
+    } else {
+        for child in expr.children() {
+            collect_aggregates(child, out);
+        }
+    }
+}
+
+fn check_arity(aggregate: &Expr) -> Result<(), EvalError> {
+    let Expr::Call { name, args } = aggregate else {
+        return Ok(());
+    };
+    let count_rows = args.is_empty() && name.eq_ignore_ascii_case("count");
+    if args.len() == 1 || count_rows {
+        Ok(())
+    } else {
+        Err(EvalError::Arity {
+            function: name.clone(),
+            expected: 1,
+            found: args.len(),
+        })
+    }
+}
+
+/// Checks that every column `expr` uses outside aggregate calls is within
+/// a GROUP BY expression.
+fn check_grouped(expr: &Expr, keys: &[Expr]) -> Result<(), ExecError> {
+    if keys.contains(expr) || is_aggregate(expr) {
+        return Ok(());
+    }
+    match expr {
+        Expr::Identifier(name) => Err(ExecError::UngroupedColumn(name.clone())),
+        _ => expr
+            .children()
+            .into_iter()
+            .try_for_each(|child| check_grouped(child, keys)),
+    }
+}
+
+/// The running state of one aggregate call over one group.
+#[derive(Debug)]
+enum Accumulator {
+    Count(i64),
+    /// Adds each value with the rules of `+`.
+    Sum(LiteralValue),
+    /// With `exact`: the ints and the floats summed apart, each exactly.
+    ExactSum {
+        ints: Option<i128>,
+        floats: Option<ExactSum>,
+    },
+    Avg {
+        total: FloatSum,
+        count: u64,
+    },
+    Min(LiteralValue),
+    Max(LiteralValue),
+}
+
+impl Accumulator {
+    fn new(name: &str, exact: bool) -> Accumulator {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Accumulator::Count(0),
+            "sum" if exact => Accumulator::ExactSum {
+                ints: None,
+                floats: None,
+            },
+            "sum" => Accumulator::Sum(LiteralValue::Null),
+            "avg" => Accumulator::Avg {
+                total: match exact {
+                    true => FloatSum::Exact(ExactSum::default()),
+                    false => FloatSum::Plain(0.0),
+                },
+                count: 0,
+            },
+            "min" => Accumulator::Min(LiteralValue::Null),
+            "max" => Accumulator::Max(LiteralValue::Null),
+            _ => unreachable!("`{name}` is not an aggregate"),
+        }
+    }
+
+    /// Takes in one value; `name` is the function, for errors.
+    fn add(&mut self, name: &str, value: LiteralValue) -> Result<(), EvalError> {
+        if value == LiteralValue::Null {
+            return Ok(());
+        }
+        let number = match value {
+            LiteralValue::Int(v) => Some(v as f64),
+            LiteralValue::Float(v) => Some(v),
+            _ => None,
+        };
+        let not_a_number = |value: &LiteralValue| EvalError::ArgumentType {
+            function: name.to_string(),
+            position: 1,
+            expected: "a number",
+            found: value.type_name(),
+        };
+        let keep_lowest = matches!(self, Accumulator::Min(_));
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            Accumulator::Sum(total) => {
+                if number.is_none() {
+                    return Err(not_a_number(&value));
+                }
+                *total = match std::mem::replace(total, LiteralValue::Null) {
+                    LiteralValue::Null => value,
+                    previous => eval::binary(BinaryOp::Add, previous, value)?,
+                };
+            }
+            Accumulator::ExactSum { ints, floats } => match value {
+                LiteralValue::Int(v) => *ints = Some(ints.unwrap_or(0) + i128::from(v)),
+                LiteralValue::Float(v) => floats.get_or_insert_with(ExactSum::default).add(v),
+                _ => return Err(not_a_number(&value)),
+            },
+            Accumulator::Avg { total, count } => {
+                total.add(number.ok_or_else(|| not_a_number(&value))?);
+                *count += 1;
+            }
+            Accumulator::Min(best) | Accumulator::Max(best) => {
+                if *best == LiteralValue::Null {
+                    *best = value;
+                    return Ok(());
+                }
+                if !best.comparable_with(&value) {
+                    return Err(EvalError::ArgumentType {
+                        function: name.to_string(),
+                        position: 1,
+                        expected: best.type_name(),
+                        found: value.type_name(),
+                    });
+                }
+                let ordering = value.sort_key().cmp(&best.sort_key());
+                if (keep_lowest && ordering.is_lt()) || (!keep_lowest && ordering.is_gt()) {
+                    *best = value;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<LiteralValue, EvalError> {
+        Ok(match self {
+            Accumulator::Count(count) => LiteralValue::Int(count),
+            Accumulator::ExactSum {
+                ints: None,
+                floats: None,
+            } => LiteralValue::Null,
+            Accumulator::ExactSum {
+                ints: Some(ints),
+                floats: None,
+            } => LiteralValue::Int(
+                i64::try_from(ints).map_err(|_| EvalError::Overflow(BinaryOp::Add))?,
+            ),
+            Accumulator::ExactSum {
+                ints,
+                floats: Some(mut floats),
+            } => {
+                floats.add_int(ints.unwrap_or(0));
+                LiteralValue::Float(floats.total())
+            }
+            Accumulator::Avg { count: 0, .. } => LiteralValue::Null,
+            Accumulator::Avg { total, count } => LiteralValue::Float(total.total() / count as f64),
+            Accumulator::Sum(value) | Accumulator::Min(value) | Accumulator::Max(value) => value,
+        })
+    }
+}
+
+/// A running sum of floats, added one at a time or kept exact.
+#[derive(Debug)]
+enum FloatSum {
+    Plain(f64),
+    Exact(ExactSum),
+}
+
+impl FloatSum {
+    fn add(&mut self, value: f64) {
+        match self {
+            FloatSum::Plain(total) => *total += value,
+            FloatSum::Exact(sum) => sum.add(value),
+        }
+    }
+
+    fn total(&self) -> f64 {
+        match self {
+            FloatSum::Plain(total) => *total,
+            FloatSum::Exact(sum) => sum.total(),
+        }
+    }
+}
+
+/// The exact sum of finite floats, kept as one fixed-point integer in units
+/// of the smallest float, 2^-1074, wide enough for any finite float times
+/// 2^140, and rounded once when read. Adding is exact and does not depend
+/// on order, even where a partial sum would not fit in a float. Infinities
+/// and NaNs are added apart.
+#[derive(Debug)]
+struct ExactSum {
+    /// Two's complement, least significant limb first.
+    limbs: Box<[u64; EXACT_LIMBS]>,
+    /// The sum of the values that are not finite; 0 while there are none.
+    special: f64,
+}
+
+const EXACT_LIMBS: usize = 35;
+
+impl Default for ExactSum {
+    fn default() -> ExactSum {
+        ExactSum {
+            limbs: Box::new([0; EXACT_LIMBS]),
+            special: 0.0,
+        }
+    }
+}
+
+impl ExactSum {
+    /// Adds an int exactly.
+    fn add_int(&mut self, int: i128) {
+        self.add_scaled(int.unsigned_abs(), 1074, int < 0);
+    }
+
+    fn add(&mut self, x: f64) {
+        if !x.is_finite() {
+            self.special += x;
+            return;
+        }
+        let bits = x.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as usize;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, shift) = match exponent {
+            0 => (fraction, 0),
+            _ => (fraction | 1 << 52, exponent - 1),
+        };
+        self.add_scaled(mantissa as u128, shift, x < 0.0);
+    }
+
+    /// Adds or subtracts `magnitude` times 2^`shift` units.
+    fn add_scaled(&mut self, magnitude: u128, shift: usize, negative: bool) {
+        let (index, offset) = (shift / 64, shift % 64);
+        let (low, high) = (magnitude as u64, (magnitude >> 64) as u64);
+        let words = match offset {
+            0 => [low, high, 0],
+            _ => [
+                low << offset,
+                high << offset | low >> (64 - offset),
+                high >> (64 - offset),
+            ],
+        };
+        let mut carry = false;
+        for (i, limb) in self.limbs[index..].iter_mut().enumerate() {
+            let word = words.get(i).copied().unwrap_or(0);
+            if i >= words.len() && !carry {
+                break;
+            }
+            let (value, first) = match negative {
+                false => limb.overflowing_add(word),
+                true => limb.overflowing_sub(word),
+            };
+            let (value, second) = match negative {
+                false => value.overflowing_add(carry as u64),
+                true => value.overflowing_sub(carry as u64),
+            };
+            *limb = value;
+            carry = first || second;
+        }
+    }
+
+    /// The sum, correctly rounded, half to even.
+    fn total(&self) -> f64 {
+        if self.special != 0.0 || self.special.is_nan() {
+            return self.special;
+        }
+        let negative = (self.limbs[EXACT_LIMBS - 1] as i64) < 0;
+        let mut limbs = *self.limbs;
+        if negative {
+            let mut carry = true;
+            for limb in &mut limbs {
+                (*limb, carry) = (!*limb).overflowing_add(carry as u64);
+            }
+        }
+        let bit = |i: usize| limbs[i / 64] >> (i % 64) & 1 == 1;
+        let Some(top) = (0..EXACT_LIMBS * 64).rev().find(|&i| bit(i)) else {
+            return 0.0;
+        };
+        let magnitude = if top < 53 {
+            // Exact: a subnormal, or the smallest normals.
+            limbs[0] as f64 * f64::from_bits(1)
+        } else {
+            let mut mantissa = (top - 52..=top)
+                .rev()
+                .fold(0u64, |m, i| m << 1 | bit(i) as u64);
+            let mut top = top;
+            let half = bit(top - 53);
+            let below = (0..top - 53).any(bit);
+            if half && (below || mantissa & 1 == 1) {
+                mantissa += 1;
+                if mantissa == 1 << 53 {
+                    mantissa >>= 1;
+                    top += 1;
+                }
+            }
+            let exponent = top as u64 - 51;
+            if exponent >= 0x7ff {
+                f64::INFINITY
+            } else {
+                f64::from_bits(exponent << 52 | mantissa & ((1 << 52) - 1))
+            }
+        };
+        if negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LiteralValue::{Float, Int, Null};
+
+    fn fold(name: &str, values: Vec<LiteralValue>) -> Result<LiteralValue, EvalError> {
+        fold_with(name, values, false)
+    }
+
+    fn fold_with(
+        name: &str,
+        values: Vec<LiteralValue>,
+        exact: bool,
+    ) -> Result<LiteralValue, EvalError> {
+        let mut accumulator = Accumulator::new(name, exact);
+        for value in values {
+            accumulator.add(name, value)?;
+        }
+        accumulator.finish()
+    }
+
+    #[test]
+    fn accumulators() {
+        let string = || LiteralValue::String("a".to_string());
+        for (name, values, expected) in [
+            ("count", vec![Int(1), Null, Int(3)], Int(2)),
+            ("count", vec![], Int(0)),
+            ("sum", vec![Int(1), Null, Int(3)], Int(4)),
+            ("sum", vec![Int(1), Float(0.5)], Float(1.5)),
+            ("sum", vec![Null], Null),
+            ("avg", vec![Int(1), Int(2), Null], Float(1.5)),
+            ("avg", vec![], Null),
+            ("min", vec![Int(3), Float(2.5), Null, Int(4)], Float(2.5)),
+            ("max", vec![Float(f64::NAN), Int(4)], Float(f64::NAN)),
+            ("max", vec![string(), Null], string()),
+        ] {
+            let result = fold(name, values.clone()).unwrap();
+            assert!(
+                result.identical(&expected),
+                "{name}{values:?} gave {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn exact_sums_do_not_depend_on_order() {
+        let values = vec![
+            Float(1e100),
+            Float(0.1),
+            Int(3),
+            Float(-1e100),
+            Float(0.2),
+            Null,
+            Float(1e-30),
+        ];
+        let mut sums = Vec::new();
+        for rotation in 0..values.len() {
+            let mut rotated = values.clone();
+            rotated.rotate_left(rotation);
+            sums.push(fold_with("sum", rotated.clone(), true).unwrap());
+            assert!(fold_with("avg", rotated, true)
+                .unwrap()
+                .identical(&Float(3.3 / 6.0)));
+        }
+        assert!(sums.iter().all(|sum| sum.identical(&Float(3.3))));
+        // Added one at a time, the values before -1e100 are lost.
+        assert!(fold("sum", values).unwrap().identical(&Float(0.2)));
+
+        for (values, expected) in [
+            (vec![Int(1), Int(2)], Int(3)),
+            (vec![Int(1), Float(0.5), Int(1)], Float(2.5)),
+            (vec![Float(0.1); 10], Float(1.0)),
+            (vec![Float(f64::MAX), Float(f64::MAX)], Float(f64::INFINITY)),
+            (
+                vec![Float(f64::INFINITY), Float(1.0), Float(f64::NEG_INFINITY)],
+                Float(f64::NAN),
+            ),
+            (vec![], Null),
+        ] {
+            let result = fold_with("sum", values.clone(), true).unwrap();
+            assert!(result.identical(&expected), "{values:?} gave {result:?}");
+        }
+        assert!(fold_with("sum", vec![Int(i64::MAX), Int(1)], true).is_err());
+
+        // Ints are summed apart from floats, so overflow does not depend on
+        // whether a float comes first.
+        let values = vec![Int(i64::MAX), Int(1), Float(0.5)];
+        for rotation in 0..values.len() {
+            let mut rotated = values.clone();
+            rotated.rotate_left(rotation);
+            let result = fold_with("sum", rotated.clone(), true).unwrap();
+            assert!(
+                result.identical(&Float(i64::MAX as f64)),
+                "{rotated:?} gave {result:?}"
+            );
+        }
+        assert_eq!(
+            fold_with("sum", vec![Int(i64::MAX), Int(1), Int(-1)], true),
+            Ok(Int(i64::MAX))
+        );
+
+        // A partial sum past the largest float is kept exact, so only the
+        // total decides whether the sum overflows.
+        let (max, half_ulp) = (f64::MAX, 2f64.powi(970));
+        for (values, expected) in [
+            (vec![max, -max, max], max),
+            (vec![-max, max, -max], -max),
+            (vec![max, max, -max, -max, 5e-324], 5e-324),
+            (vec![-max, -max, max, max, -5e-324], -5e-324),
+            (vec![max, max, max, -max, -max], max),
+            (vec![max, max, -max, 1.0], max),
+            (vec![max, max, -max, half_ulp], f64::INFINITY),
+            (vec![-max, -max, max, -half_ulp], f64::NEG_INFINITY),
+            (vec![max, max, -max, half_ulp, -5e-324], max),
+            (vec![max, max, 1.0, -max], max),
+            (vec![max, max, -1.0], f64::INFINITY),
+            (vec![-max, -max, 1.0], f64::NEG_INFINITY),
+        ] {
+            for rotation in 0..values.len() {
+                let mut rotated: Vec<_> = values.iter().copied().map(Float).collect();
+                rotated.rotate_left(rotation);
+                let result = fold_with("sum", rotated.clone(), true).unwrap();
+                assert!(
+                    result.identical(&Float(expected)),
+                    "{rotated:?} gave {result:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accumulator_errors() {
+        for (name, values, message) in [
+            (
+                "sum",
+                vec![Int(i64::MAX), Int(1)],
+                "integer overflow in `+`",
+            ),
+            (
+                "SUM",
+                vec![LiteralValue::Bool(true)],
+                "argument 1 of `SUM` must be a number, found bool",
+            ),
+            (
+                "min",
+                vec![Int(1), LiteralValue::String("a".to_string())],
+                "argument 1 of `min` must be int, found string",
+            ),
+        ] {
+            let error = fold(name, values).unwrap_err();
+            assert_eq!(error.to_string(), message, "{name}");
+        }
+    }
+}
@@ -0,0 +1,1765 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+
+use super::plan::{self, Plan};
+use super::prepared::{resolve_macros, resolve_variables};
+use super::types::{self, ValueType};
+use super::{aggregate, join};
+use super::{ExplainFormat, OrderBy, Sample, Select, SelectItem, Statement};
+use crate::catalog::{Catalog, CatalogError, ColumnType, Schema};
+use crate::eval::{self, EvalError};
+use crate::external::CsvTable;
+use crate::literals::LiteralValue;
+use crate::macros::MacroError;
+use crate::table::{Row, Values};
+use crate::variables::{InvalidSetting, NullOrder};
+
+/// Rows produced by a query: named columns and the rows under them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    /// The type of each column, as the query determines it; see
+    /// [`types`](super::types).
+    pub types: Vec<ValueType>,
+    pub rows: Vec<Values>,
+}
+
+/// A column of a [`ResultSet`]: its name, the type of its values if known,
+/// and whether they may be NULL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultColumn<'a> {
+    pub name: &'a str,
+    pub ty: Option<&'a ColumnType>,
+    pub nullable: bool,
+}
+
+impl ResultSet {
+    /// The columns, with their types. A column without a type in `types`
+    /// is of unknown type, and may be NULL.
+    pub fn schema(&self) -> Vec<ResultColumn<'_>> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let ty = self.types.get(i);
+                ResultColumn {
+                    name,
+                    ty: ty.and_then(|ty| ty.ty.as_ref()),
+                    nullable: ty.is_none_or(|ty| ty.nullable),
+                }
+            })
+            .collect()
+    }
+}
+
+/// What a statement did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Rows(ResultSet),
+    /// The plan of an EXPLAINed query, as [`Plan`]'s `Display` shows it.
+    Explained(String),
+    Created(String),
+    /// The name of the macro a CREATE MACRO defined.
+    DefinedMacro(String),
+    Inserted(usize),
+    Altered(String),
+    /// The name of the variable a SET changed.
+    Set(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecError {
+    Catalog(CatalogError),
+    /// `*` was used without a FROM clause.
+    WildcardWithoutTable,
+    /// `*` was used in a SELECT with GROUP BY or aggregates.
+    WildcardWithAggregates,
+    /// A grouped SELECT used a column outside GROUP BY and aggregates.
+    UngroupedColumn(String),
+    /// A column name that both joined tables have was used unqualified, or
+    /// a column of a joined table is named like a qualified one.
+    AmbiguousColumn(String),
+    /// A table was joined to itself.
+    SelfJoin(String),
+    /// A prepared statement was given fewer values than its highest `$n`.
+    MissingParameter {
+        index: usize,
+        given: usize,
+    },
+    /// The ON condition of a join produced something other than a bool or
+    /// NULL.
+    NonBooleanJoin(&'static str),
+    /// The WHERE clause produced something other than a bool or NULL.
+    NonBooleanFilter(&'static str),
+    /// A rewrite turned a query into a statement that gives no rows.
+    NotAQuery,
+    /// Under the `ASSERT_SORTED` hint, ORDER BY gave a row with the first
+    /// keys before one with the second.
+    Unsorted {
+        before: Vec<LiteralValue>,
+        after: Vec<LiteralValue>,
+    },
+    InvalidSetting(InvalidSetting),
+    Macro(MacroError),
+    Eval(EvalError),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Catalog(e) => e.fmt(f),
+            ExecError::WildcardWithoutTable => write!(f, "`*` needs a FROM clause"),
+            ExecError::WildcardWithAggregates => {
+                write!(f, "`*` cannot be used with GROUP BY or aggregates")
+            }
+            ExecError::AmbiguousColumn(name) => write!(
+                f,
+                "column `{name}` is in both joined tables; write it as `<table>.{name}`"
+            ),
+            ExecError::SelfJoin(table) => write!(
+                f,
+                "table `{table}` is joined to itself, so its columns cannot be told apart"
+            ),
+            ExecError::MissingParameter { index, given } => write!(
+                f,
+                "statement uses ${index} but {given} parameter values were given"
+            ),
+            ExecError::NonBooleanJoin(found) => {
+                write!(f, "JOIN condition must be a bool, found {found}")
+            }
+            ExecError::UngroupedColumn(name) => write!(
+                f,
+                "column `{name}` must appear in GROUP BY or be used in an aggregate"
+            ),
+            ExecError::NonBooleanFilter(found) => {
+                write!(f, "WHERE clause must be a bool, found {found}")
+            }
+            ExecError::NotAQuery => {
+                write!(
+                    f,
+                    "a rewrite turned the query into another kind of statement"
+                )
+            }
+            ExecError::Unsorted { before, after } => {
+                let keys = |values: &[LiteralValue]| {
+                    let values: Vec<String> = values.iter().map(LiteralValue::serialize).collect();
+                    values.join(", ")
+                };
+                write!(
+                    f,
+                    "ORDER BY gave keys ({}) before ({})",
+                    keys(before),
+                    keys(after)
+                )
+            }
+            ExecError::InvalidSetting(e) => e.fmt(f),
+            ExecError::Macro(e) => e.fmt(f),
+            ExecError::Eval(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+impl From<CatalogError> for ExecError {
+    fn from(e: CatalogError) -> ExecError {
+        ExecError::Catalog(e)
+    }
+}
+
+impl From<InvalidSetting> for ExecError {
+    fn from(e: InvalidSetting) -> ExecError {
+        ExecError::InvalidSetting(e)
+    }
+}
+
+impl From<MacroError> for ExecError {
+    fn from(e: MacroError) -> ExecError {
+        ExecError::Macro(e)
+    }
+}
+
+impl From<EvalError> for ExecError {
+    fn from(e: EvalError) -> ExecError {
+        ExecError::Eval(e)
+    }
+}
+
+/// Runs `statement` against `catalog`, after the catalog's rewrites and
+/// with its macros expanded and its session variables in place of their
+/// `@name`s.
+pub fn execute(statement: &Statement, catalog: &mut Catalog) -> Result<Outcome, ExecError> {
+    let (statement, _) = catalog.rewrites().apply(statement);
+    let statement = resolve_macros(&statement, catalog.macros())?;
+    let statement = resolve_variables(&statement, catalog.variables()).into_owned();
+    match &statement {
+        Statement::Select(select) => execute_select(select, catalog).map(Outcome::Rows),
+        Statement::Explain(select, format) => plan::plan(select, catalog).map(|plan| {
+            Outcome::Explained(match format {
+                ExplainFormat::Text => plan.to_string(),
+                ExplainFormat::Dot => plan.to_dot(),
+                ExplainFormat::Mermaid => plan.to_mermaid(),
+            })
+        }),
+        Statement::CreateTable { name, columns } => {
+            catalog.create_table(
+                name,
+                Schema {
+                    columns: columns.clone(),
+                },
+            )?;
+            Ok(Outcome::Created(name.clone()))
+        }
+        Statement::CreateExternalTable {
+            name,
+            columns,
+            location,
+        } => {
+            let table = CsvTable::open(location, columns).map_err(CatalogError::External)?;
+            catalog.add_table(name, Box::new(table))?;
+            Ok(Outcome::Created(name.clone()))
+        }
+        Statement::CreateMacro {
+            name,
+            parameters,
+            body,
+        } => {
+            catalog.macros_mut().define(name, parameters, body)?;
+            Ok(Outcome::DefinedMacro(name.clone()))
+        }
+        Statement::Insert { table, rows } => {
+            let rows = rows
+                .iter()
+                .map(|row| row.iter().map(|expr| eval::evaluate(expr, &())).collect())
+                .collect::<Result<Vec<Vec<_>>, _>>()?;
+            Ok(Outcome::Inserted(catalog.insert(table, rows)?))
+        }
+        Statement::AddEnumLabel {
+            table,
+            column,
+            label,
+        } => {
+            catalog.add_enum_label(table, column, label)?;
+            Ok(Outcome::Altered(table.clone()))
+        }
+        Statement::Set { name, value } => {
+            catalog.variables_mut().set(name, value.clone())?;
+            Ok(Outcome::Set(name.clone()))
+        }
+        Statement::Show(name) => {
+            let variables = catalog.variables();
+            let rows = match name {
+                Some(name) => {
+                    let value = variables
+                        .value(name)
+                        .ok_or_else(|| EvalError::UnsetVariable(name.clone()))?;
+                    vec![(name.as_str(), value)]
+                }
+                None => variables
+                    .iter()
+                    .map(|(name, value)| (name, value.clone()))
+                    .collect(),
+            };
+            Ok(Outcome::Rows(ResultSet {
+                columns: vec!["name".to_string(), "value".to_string()],
+                types: vec![ValueType::of(ColumnType::Text, false), ValueType::unknown()],
+                rows: rows
+                    .into_iter()
+                    .map(|(name, value)| {
+                        Values::from([LiteralValue::String(name.to_string()), value])
+                    })
+                    .collect(),
+            }))
+        }
+        Statement::ShowSettings => {
+            let text = |s: &str| LiteralValue::String(s.to_string());
+            let rows = catalog
+                .variables()
+                .settings()
+                .map(|(setting, value)| {
+                    Values::from([
+                        text(setting.name),
+                        value.map_or(LiteralValue::Null, text),
+                        text(&setting.values.join(", ")),
+                        text(setting.description),
+                    ])
+                })
+                .collect();
+            Ok(Outcome::Rows(ResultSet {
+                columns: ["name", "value", "values", "description"]
+                    .map(str::to_string)
+                    .to_vec(),
+                types: vec![
+                    ValueType::of(ColumnType::Text, false),
+                    ValueType::of(ColumnType::Text, true),
+                    ValueType::of(ColumnType::Text, false),
+                    ValueType::of(ColumnType::Text, false),
+                ],
+                rows,
+            }))
+        }
+    }
+}
+
+fn execute_select(select: &Select, catalog: &Catalog) -> Result<ResultSet, ExecError> {
+    let rows = run_query(select, catalog)?;
+    Ok(ResultSet {
+        columns: rows.columns.clone(),
+        types: rows.types.clone(),
+        rows: rows.collect::<Result<_, _>>()?,
+    })
+}
+
+/// The rows of a query, computed as they are read. Only a sort, a grouping
+/// or the inner side of a join holds more than a row at a time, so a query
+/// that streams a large table through filters and a LIMIT runs in bounded
+/// memory, and stops reading the table once the LIMIT is reached.
+pub struct RowIterator<'a> {
+    columns: Vec<String>,
+    types: Vec<ValueType>,
+    rows: Rows<'a>,
+}
+
+impl RowIterator<'_> {
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The type of each column, as in [`ResultSet::types`].
+    pub fn types(&self) -> &[ValueType] {
+        &self.types
+    }
+}
+
+impl Iterator for RowIterator<'_> {
+    type Item = Result<Values, ExecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        Some(row.map(|row| Values::clone(row.values())))
+    }
+}
+
+impl fmt::Debug for RowIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowIterator")
+            .field("columns", &self.columns)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Starts running a query, after the catalog's rewrites, without reading
+/// any rows yet.
+pub fn query<'a>(select: &Select, catalog: &'a Catalog) -> Result<RowIterator<'a>, ExecError> {
+    let statement = Statement::Select(select.clone());
+    let (statement, _) = catalog.rewrites().apply(&statement);
+    let statement = resolve_macros(&statement, catalog.macros())?;
+    match resolve_variables(&statement, catalog.variables()).as_ref() {
+        Statement::Select(select) => run_query(select, catalog),
+        _ => Err(ExecError::NotAQuery),
+    }
+}
+
+fn run_query<'a>(select: &Select, catalog: &'a Catalog) -> Result<RowIterator<'a>, ExecError> {
+    let plan = plan::plan(select, catalog)?;
+    Ok(RowIterator {
+        columns: plan.columns(),
+        types: types::plan_types(&plan),
+        rows: run(plan, catalog)?,
+    })
+}
+
+/// The rows a plan node produces, pulled one at a time.
+type Rows<'a> = Box<dyn Iterator<Item = Result<Row, ExecError>> + 'a>;
+
+/// Starts running `plan`. No rows are read until the first is pulled, and
+/// each node pulls from the ones under it only as many rows as it needs.
+fn run(plan: Plan, catalog: &Catalog) -> Result<Rows<'_>, ExecError> {
+    Ok(match plan {
+        Plan::SingleRow => Box::new(std::iter::once(Ok(Row::new(
+            Arc::default(),
+            Values::from([]),
+        )))),
+        Plan::Scan {
+            table: name,
+            sample,
+            version,
+            lookup,
+            ..
+        } => {
+            let table = catalog
+                .table(&name)
+                .ok_or_else(|| CatalogError::UnknownTable(name.clone()))?;
+            let rows = match lookup
+                .as_ref()
+                .and_then(|(column, value)| table.lookup(column, value))
+            {
+                Some(rows) => Box::new(rows.map(Ok)),
+                None => table.try_scan(),
+            };
+            let rows = match version {
+                Some(version) => Box::new(rows.take(catalog.rows_at_version(&name, version)?)),
+                None => rows,
+            };
+            let mut sampler = sample.as_ref().map(Sampler::new);
+            let rows = rows.filter(move |_| sampler.as_mut().is_none_or(Sampler::keep));
+            let schema = table.schema();
+            if schema.needs_decoding() {
+                Box::new(rows.map(|row| {
+                    let values = schema.decode(row?.values())?;
+                    Ok(Row::new(Arc::clone(schema), values))
+                }))
+            } else {
+                Box::new(rows.map(|row| Ok(row?)))
+            }
+        }
+        Plan::Join {
+            left,
+            right,
+            on,
+            keys,
+            schema,
+        } => {
+            let left = run(*left, catalog)?;
+            let right = run(*right, catalog)?;
+            deferred(move || join::run(left, right, on, keys, schema))
+        }
+        Plan::Filter { input, predicate } => {
+            Box::new(run(*input, catalog)?.filter_map(move |row| {
+                let row = match row {
+                    Ok(row) => row,
+                    Err(e) => return Some(Err(e)),
+                };
+                match eval::evaluate(&predicate, &row) {
+                    Ok(LiteralValue::Bool(true)) => Some(Ok(row)),
+                    Ok(LiteralValue::Bool(false) | LiteralValue::Null) => None,
+                    Ok(other) => Some(Err(ExecError::NonBooleanFilter(other.type_name()))),
+                    Err(e) => Some(Err(e.into())),
+                }
+            }))
+        }
+        Plan::Aggregate {
+            input,
+            keys,
+            aggregates,
+            schema,
+        } => {
+            let input = run(*input, catalog)?;
+            let exact = catalog.variables().deterministic_floats();
+            deferred(move || {
+                let rows = aggregate::run(input, &keys, &aggregates, &schema, exact)?;
+                Ok(rows.into_iter().map(Ok))
+            })
+        }
+        Plan::Sort { input, order, keep } => {
+            let input = run(*input, catalog)?;
+            let nulls = catalog.variables().null_order();
+            deferred(move || {
+                let mut sorter = TopK::new(&order, nulls, keep.unwrap_or(usize::MAX));
+                for row in input {
+                    let row = row?;
+                    let keys = order
+                        .iter()
+                        .map(|item| eval::evaluate_key(&item.expr, &row))
+                        .collect::<Result<_, _>>()?;
+                    sorter.push(keys, row);
+                }
+                let rows: Vec<Row> = sorter.into_sorted().collect();
+                Ok(rows.into_iter().map(Ok))
+            })
+        }
+        Plan::AssertSorted { input, order } => {
+            let nulls = catalog.variables().null_order();
+            let mut previous: Option<Vec<LiteralValue>> = None;
+            Box::new(run(*input, catalog)?.map(move |row| {
+                let row = row?;
+                let keys = order
+                    .iter()
+                    .map(|item| eval::evaluate_key(&item.expr, &row))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(previous) = previous.take() {
+                    if keys_order(&order, nulls, &previous, &keys).is_gt() {
+                        return Err(ExecError::Unsorted {
+                            before: previous,
+                            after: keys,
+                        });
+                    }
+                }
+                previous = Some(keys);
+                Ok(row)
+            }))
+        }
+        Plan::Project {
+            input,
+            items,
+            schema,
+        } => {
+            // `SELECT *` keeps rows as they are, so they can be shared, not
+            // copied, unless it leaves hidden columns out.
+            let hidden = input.hidden();
+            let pass_through =
+                matches!(items.as_slice(), [SelectItem::Wildcard]) && !hidden.contains(&true);
+            Box::new(run(*input, catalog)?.map(move |row| {
+                let row = row?;
+                let values = if pass_through {
+                    Values::clone(row.values())
+                } else {
+                    let mut values = Vec::with_capacity(schema.columns.len());
+                    for item in &items {
+                        match item {
+                            SelectItem::Wildcard => values.extend(
+                                row.values()
+                                    .iter()
+                                    .zip(&hidden)
+                                    .filter(|(_, hidden)| !**hidden)
+                                    .map(|(value, _)| value.clone()),
+                            ),
+                            SelectItem::Expr { expr, .. } => {
+                                values.push(eval::evaluate(expr, &row)?)
+                            }
+                        }
+                    }
+                    Values::from(values)
+                };
+                Ok(Row::new(Arc::clone(&schema), values))
+            }))
+        }
+        Plan::Limit {
+            input,
+            limit,
+            offset,
+        } => {
+            let mut skipped = 0;
+            let rows = run(*input, catalog)?.filter(move |row| {
+                let skip = row.is_ok() && skipped < offset;
+                skipped += u64::from(skip);
+                !skip
+            });
+            match limit {
+                Some(limit) => Box::new(rows.take(limit as usize)),
+                None => Box::new(rows),
+            }
+        }
+    })
+}
+
+/// Rows from `start`, which is only called when the first row is pulled: for
+/// nodes that must read their input before they can produce anything.
+fn deferred<'a, I>(start: impl FnOnce() -> Result<I, ExecError> + 'a) -> Rows<'a>
+where
+    I: Iterator<Item = Result<Row, ExecError>> + 'a,
+{
+    Box::new(std::iter::once_with(start).flat_map(|started| -> Rows<'a> {
+        match started {
+            Ok(rows) => Box::new(rows),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
+    }))
+}
+
+/// Keeps the first `capacity` rows in ORDER BY order among those pushed.
+/// With a LIMIT this holds only the rows that can still make it into the
+/// result, rather than the whole table: the heap's top is the last of
+/// them, and is dropped when a row that sorts earlier arrives.
+struct TopK<'a> {
+    order: &'a [OrderBy],
+    nulls: Option<NullOrder>,
+    capacity: usize,
+    heap: BinaryHeap<Sorted<'a>>,
+    pushed: usize,
+}
+
+/// A row with its sort keys. Rows with equal keys keep their scan order.
+struct Sorted<'a> {
+    order: &'a [OrderBy],
+    nulls: Option<NullOrder>,
+    keys: Vec<LiteralValue>,
+    position: usize,
+    row: Row,
+}
+
+impl<'a> TopK<'a> {
+    /// With `nulls` set, NULL keys sort first or last whichever way their
+    /// column sorts; otherwise NULL sorts before every other value.
+    fn new(order: &'a [OrderBy], nulls: Option<NullOrder>, capacity: usize) -> TopK<'a> {
+        TopK {
+            order,
+            nulls,
+            capacity,
+            heap: BinaryHeap::new(),
+            pushed: 0,
+        }
+    }
+
+    fn push(&mut self, keys: Vec<LiteralValue>, row: Row) {
+        self.heap.push(Sorted {
+            order: self.order,
+            nulls: self.nulls,
+            keys,
+            position: self.pushed,
+            row,
+        });
+        self.pushed += 1;
+        if self.heap.len() > self.capacity {
+            self.heap.pop();
+        }
+    }
+
+    fn into_sorted(self) -> impl Iterator<Item = Row> + 'a {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|sorted| sorted.row)
+    }
+}
+
+/// How rows whose ORDER BY keys are `a` and `b` compare, for
+/// `AssertSorted`. It is written apart from the ordering `Sort` uses, so
+/// that it can check it.
+fn keys_order(
+    order: &[OrderBy],
+    nulls: Option<NullOrder>,
+    a: &[LiteralValue],
+    b: &[LiteralValue],
+) -> Ordering {
+    use LiteralValue::Null;
+    for (item, (a, b)) in order.iter().zip(a.iter().zip(b)) {
+        let ordering = match (nulls, a, b) {
+            (Some(nulls), Null, b) if *b != Null => null_first(nulls),
+            (Some(nulls), a, Null) if *a != Null => null_first(nulls).reverse(),
+            _ if item.descending => b.sort_key().cmp(&a.sort_key()),
+            _ => a.sort_key().cmp(&b.sort_key()),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+impl Ord for Sorted<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .iter()
+            .zip(self.keys.iter().zip(&other.keys))
+            .map(|(item, (a, b))| {
+                match (self.nulls, a, b) {
+                    (Some(nulls), LiteralValue::Null, b) if *b != LiteralValue::Null => {
+                        return null_first(nulls);
+                    }
+                    (Some(nulls), a, LiteralValue::Null) if *a != LiteralValue::Null => {
+                        return null_first(nulls).reverse();
+                    }
+                    _ => {}
+                }
+                let ordering = a.sort_key().cmp(&b.sort_key());
+                if item.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| self.position.cmp(&other.position))
+    }
+}
+
+/// How a NULL key compares with a value under the `nulls` setting.
+fn null_first(nulls: NullOrder) -> Ordering {
+    match nulls {
+        NullOrder::First => Ordering::Less,
+        NullOrder::Last => Ordering::Greater,
+    }
+}
+
+impl PartialOrd for Sorted<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Sorted<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Sorted<'_> {}
+
+/// Decides which scanned rows a TABLESAMPLE keeps, one draw per row from a
+/// SplitMix64 sequence.
+struct Sampler {
+    fraction: f64,
+    state: u64,
+}
+
+impl Sampler {
+    fn new(sample: &Sample) -> Sampler {
+        let seed = sample
+            .seed
+            .unwrap_or_else(|| RandomState::new().build_hasher().finish());
+        Sampler {
+            fraction: sample.percent / 100.0,
+            state: seed,
+        }
+    }
+
+    fn keep(&mut self) -> bool {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // The top 53 bits as a float in [0, 1).
+        ((z >> 11) as f64 / (1u64 << 53) as f64) < self.fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Expr;
+    use crate::catalog::{Column, ColumnType};
+    use crate::statements::parsing::parse;
+    use crate::table::{MemoryTable, Table};
+    use crate::test_utils::check;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use LiteralValue::Int;
+
+    fn run_on(catalog: &mut Catalog, text: &str) -> Result<Outcome, ExecError> {
+        execute(&parse(text).unwrap(), catalog)
+    }
+
+    fn people() -> Catalog {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE people (name TEXT, age INT)",
+            "INSERT INTO people VALUES ('ann', 31), ('bob', 17), ('cy', null), ('dee', 45)",
+        ] {
+            run_on(&mut catalog, text).unwrap();
+        }
+        catalog
+    }
+
+    fn run(text: &str) -> Result<ResultSet, ExecError> {
+        match run_on(&mut people(), text)? {
+            Outcome::Rows(result) => Ok(result),
+            other => panic!("{text} gave {other:?}"),
+        }
+    }
+
+    fn names(result: &ResultSet) -> Vec<String> {
+        result.rows.iter().map(|row| row[0].serialize()).collect()
+    }
+
+    #[test]
+    fn select_without_from_yields_one_row() {
+        let result = run("SELECT 1 + 1, 'x' AS label").unwrap();
+        assert_eq!(result.columns, vec!["1 + 1", "label"]);
+        assert_eq!(
+            result.rows,
+            vec![Values::from([
+                Int(2),
+                LiteralValue::String("x".to_string())
+            ])]
+        );
+    }
+
+    #[test]
+    fn filter_skips_false_and_null() {
+        let result = run("SELECT name FROM people WHERE age >= 18").unwrap();
+        assert_eq!(names(&result), vec!["\"ann\"", "\"dee\""]);
+    }
+
+    #[test]
+    fn filter_predicates() {
+        for (filter, expected) in [
+            ("age IS NULL", vec!["\"cy\""]),
+            ("age IS NOT NULL AND age < 40", vec!["\"ann\"", "\"bob\""]),
+            ("name IN ('bob', 'dee', 'eve')", vec!["\"bob\"", "\"dee\""]),
+            ("age NOT IN (31, 17)", vec!["\"dee\""]),
+            (
+                "name LIKE '_e%' OR name LIKE '%n'",
+                vec!["\"ann\"", "\"dee\""],
+            ),
+            ("NOT name NOT LIKE 'c%'", vec!["\"cy\""]),
+        ] {
+            let result = run(&format!("SELECT name FROM people WHERE {filter}")).unwrap();
+            assert_eq!(names(&result), expected, "{filter}");
+        }
+    }
+
+    /// Answers lookups on `name`, counting them.
+    #[derive(Debug)]
+    struct Keyed {
+        rows: MemoryTable,
+        lookups: Rc<Cell<usize>>,
+    }
+
+    impl Table for Keyed {
+        fn schema(&self) -> &Arc<Schema> {
+            self.rows.schema()
+        }
+
+        fn set_schema(&mut self, schema: Schema) {
+            self.rows.set_schema(schema);
+        }
+
+        fn len(&self) -> usize {
+            self.rows.len()
+        }
+
+        fn append(&mut self, values: Values) {
+            self.rows.append(values);
+        }
+
+        fn scan(&self) -> Box<dyn Iterator<Item = Row> + '_> {
+            self.rows.scan()
+        }
+
+        fn get(&self, position: usize) -> Option<Row> {
+            self.rows.get(position)
+        }
+
+        fn lookup(
+            &self,
+            column: &str,
+            value: &LiteralValue,
+        ) -> Option<Box<dyn Iterator<Item = Row> + '_>> {
+            if column != "name" {
+                return None;
+            }
+            self.lookups.set(self.lookups.get() + 1);
+            let value = value.clone();
+            Some(Box::new(
+                self.rows
+                    .scan()
+                    .filter(move |row| row.get("name") == Some(&value)),
+            ))
+        }
+    }
+
+    #[test]
+    fn equality_filters_use_table_lookups() {
+        let lookups = Rc::new(Cell::new(0));
+        let keyed = Keyed {
+            rows: MemoryTable::new(Schema {
+                columns: vec![
+                    Column::new("name", ColumnType::Text),
+                    Column::new("age", ColumnType::Int),
+                ],
+            }),
+            lookups: Rc::clone(&lookups),
+        };
+        let mut catalog = Catalog::new();
+        catalog.add_table("k", Box::new(keyed)).unwrap();
+        run_on(
+            &mut catalog,
+            "INSERT INTO k VALUES ('ann', 31), ('bob', 17)",
+        )
+        .unwrap();
+
+        for (filter, looked_up, expected) in [
+            ("name = 'bob'", 1, vec![Int(17)]),
+            ("age > 20 AND 'ann' = name", 1, vec![Int(31)]),
+            ("name = 'bob' AND age > 20", 1, vec![]),
+            ("name = 'bob' OR age > 20", 0, vec![Int(31), Int(17)]),
+            ("age = 31", 0, vec![Int(31)]),
+        ] {
+            lookups.set(0);
+            let text = format!("SELECT age FROM k WHERE {filter}");
+            let Ok(Outcome::Rows(result)) = run_on(&mut catalog, &text) else {
+                panic!("expected rows");
+            };
+            let ages: Vec<LiteralValue> = result.rows.iter().map(|row| row[0].clone()).collect();
+            assert_eq!(ages, expected, "{filter}");
+            assert_eq!(lookups.get(), looked_up, "{filter}");
+        }
+    }
+
+    #[test]
+    fn memory_tables_look_up_their_first_column() {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE ids (id INT, name TEXT)",
+            "INSERT INTO ids VALUES (1, 'ann'), (2, 'bob'), (2, 'cy')",
+        ] {
+            run_on(&mut catalog, text).unwrap();
+        }
+        for (filter, lookup, expected) in [
+            ("id = 2", true, vec!["\"bob\"", "\"cy\""]),
+            ("2 = id AND name > 'c'", true, vec!["\"cy\""]),
+            // `=` compares an int with a float, which a key would not.
+            ("id = 2.0", false, vec!["\"bob\"", "\"cy\""]),
+            ("id = NULL", false, vec![]),
+            // Planned, but the table scans, as `name` is not its key.
+            ("name = 'ann'", true, vec!["\"ann\""]),
+        ] {
+            let text = format!("SELECT name FROM ids WHERE {filter}");
+            let Ok(Outcome::Explained(plan)) = run_on(&mut catalog, &format!("EXPLAIN {text}"))
+            else {
+                panic!("expected a plan");
+            };
+            assert_eq!(plan.contains("(lookup"), lookup, "{plan}");
+            assert_eq!(
+                rows_of(&mut catalog, &text).unwrap().1,
+                expected,
+                "{filter}"
+            );
+        }
+        assert_eq!(
+            run_on(&mut catalog, "SELECT name FROM ids WHERE id = 'x'")
+                .unwrap_err()
+                .to_string(),
+            "cannot apply `=` to int and string"
+        );
+    }
+
+    #[test]
+    fn explain_shows_the_plan_without_running_it() {
+        assert_eq!(
+            run_on(
+                &mut people(),
+                "EXPLAIN SELECT name FROM people WHERE age / 0 > 1 LIMIT 1"
+            ),
+            Ok(Outcome::Explained(
+                "Limit: 1\n  Project: name\n    Filter: age / 0 > 1\n      Scan: people\n"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            run_on(&mut people(), "EXPLAIN (FORMAT mermaid) SELECT 1"),
+            Ok(Outcome::Explained(
+                "flowchart BT\n  n0[\"Project: 1\"]\n  n1[\"Single row\"]\n  n1 --> n0\n"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            run_on(&mut people(), "EXPLAIN SELECT * FROM nobody"),
+            Err(ExecError::Catalog(CatalogError::UnknownTable(
+                "nobody".to_string()
+            )))
+        );
+    }
+
+    /// Counts the rows its scans yield.
+    #[derive(Debug)]
+    struct Counted {
+        rows: MemoryTable,
+        read: Rc<Cell<usize>>,
+    }
+
+    impl Table for Counted {
+        fn schema(&self) -> &Arc<Schema> {
+            self.rows.schema()
+        }
+
+        fn set_schema(&mut self, schema: Schema) {
+            self.rows.set_schema(schema);
+        }
+
+        fn len(&self) -> usize {
+            self.rows.len()
+        }
+
+        fn append(&mut self, values: Values) {
+            self.rows.append(values);
+        }
+
+        fn scan(&self) -> Box<dyn Iterator<Item = Row> + '_> {
+            let read = Rc::clone(&self.read);
+            Box::new(self.rows.scan().inspect(move |_| read.set(read.get() + 1)))
+        }
+
+        fn get(&self, position: usize) -> Option<Row> {
+            self.rows.get(position)
+        }
+    }
+
+    #[test]
+    fn rows_are_read_as_they_are_needed() {
+        let read = Rc::new(Cell::new(0));
+        let mut catalog = people();
+        let counted = Counted {
+            rows: MemoryTable::new(Schema {
+                columns: vec![Column::new("x", ColumnType::Int)],
+            }),
+            read: Rc::clone(&read),
+        };
+        catalog.add_table("n", Box::new(counted)).unwrap();
+        let values: Vec<String> = (0..1000).map(|x| format!("({x})")).collect();
+        run_on(
+            &mut catalog,
+            &format!("INSERT INTO n VALUES {}", values.join(", ")),
+        )
+        .unwrap();
+
+        for (text, rows, expected_reads) in [
+            ("SELECT x FROM n WHERE x % 2 = 0 LIMIT 3", 3, 5),
+            ("SELECT x FROM n LIMIT 2 OFFSET 10", 2, 12),
+            (
+                "SELECT x, name FROM n JOIN people ON x = age LIMIT 1",
+                1,
+                18,
+            ),
+            ("SELECT x FROM n ORDER BY x DESC LIMIT 1", 1, 1000),
+        ] {
+            read.set(0);
+            let Ok(Outcome::Rows(result)) = run_on(&mut catalog, text) else {
+                panic!("{text} failed");
+            };
+            assert_eq!(result.rows.len(), rows, "{text}");
+            assert_eq!(read.get(), expected_reads, "{text}");
+        }
+
+        read.set(0);
+        let Ok(Statement::Select(select)) = parse("SELECT x * 10 AS y FROM n WHERE x > 1") else {
+            panic!("expected a SELECT");
+        };
+        let mut rows = query(&select, &catalog).unwrap();
+        assert_eq!(rows.columns(), ["y"]);
+        assert_eq!(read.get(), 0);
+        assert_eq!(rows.next(), Some(Ok(Values::from([Int(20)]))));
+        assert_eq!(rows.next(), Some(Ok(Values::from([Int(30)]))));
+        assert_eq!(read.get(), 4);
+    }
+
+    #[test]
+    fn order_limit_and_offset() {
+        for (query, expected) in [
+            ("ORDER BY age", vec!["cy", "bob", "ann", "dee"]),
+            ("ORDER BY age DESC LIMIT 2", vec!["dee", "ann"]),
+            ("ORDER BY age LIMIT 2 OFFSET 1", vec!["bob", "ann"]),
+            ("ORDER BY age IS NULL", vec!["ann", "bob", "dee", "cy"]),
+            (
+                "ORDER BY age IS NULL DESC, name DESC",
+                vec!["cy", "dee", "bob", "ann"],
+            ),
+            ("ORDER BY twice DESC LIMIT 1", vec!["dee"]),
+            ("LIMIT 1 OFFSET 2", vec!["cy"]),
+            ("OFFSET 3", vec!["dee"]),
+            ("ORDER BY name LIMIT 0", vec![]),
+        ] {
+            let text = format!("SELECT name, age * 2 AS twice FROM people {query}");
+            let result = run(&text).unwrap();
+            let expected: Vec<String> = expected.iter().map(|n| format!("\"{n}\"")).collect();
+            assert_eq!(names(&result), expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn aggregates_and_grouping() {
+        let mut catalog = people();
+        run_on(
+            &mut catalog,
+            "INSERT INTO people VALUES ('ann', 9), ('bob', 17), ('eve', null)",
+        )
+        .unwrap();
+        for (query, expected) in [
+            (
+                "SELECT count(*), count(age), sum(age), min(age), max(name) FROM people",
+                vec!["7, 5, 119, 9, \"eve\""],
+            ),
+            ("SELECT avg(age) FROM people WHERE age > 40", vec!["45.0"]),
+            (
+                "SELECT count(*), sum(age), avg(age), max(age) FROM people WHERE age > 99",
+                vec!["0, null, null, null"],
+            ),
+            (
+                "SELECT name, count(*) AS n FROM people GROUP BY name",
+                vec![
+                    "\"ann\", 2",
+                    "\"bob\", 2",
+                    "\"cy\", 1",
+                    "\"dee\", 1",
+                    "\"eve\", 1",
+                ],
+            ),
+            (
+                "SELECT age, count(*) FROM people GROUP BY age ORDER BY age",
+                vec!["null, 2", "9, 1", "17, 2", "31, 1", "45, 1"],
+            ),
+            (
+                "SELECT age > 20 AS adult, sum(age) * 2 FROM people \
+                 GROUP BY age > 20 ORDER BY count(*) DESC, adult LIMIT 2",
+                vec!["false, 86", "null, null"],
+            ),
+            (
+                "SELECT name FROM people GROUP BY name ORDER BY max(age) DESC OFFSET 3",
+                vec!["\"cy\"", "\"eve\""],
+            ),
+        ] {
+            let Ok(Outcome::Rows(result)) = run_on(&mut catalog, query) else {
+                panic!("{query} failed");
+            };
+            let rows: Vec<String> = result
+                .rows
+                .iter()
+                .map(|row| {
+                    let values: Vec<String> = row.iter().map(LiteralValue::serialize).collect();
+                    values.join(", ")
+                })
+                .collect();
+            assert_eq!(rows, expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn aggregate_errors() {
+        for (text, message) in [
+            (
+                "SELECT name, count(*) FROM people",
+                "column `name` must appear in GROUP BY or be used in an aggregate",
+            ),
+            (
+                "SELECT age + 1 FROM people GROUP BY age ORDER BY name",
+                "column `name` must appear in GROUP BY or be used in an aggregate",
+            ),
+            (
+                "SELECT * FROM people GROUP BY name",
+                "`*` cannot be used with GROUP BY or aggregates",
+            ),
+            (
+                "SELECT name FROM people WHERE count(*) > 1",
+                "aggregate `count` is not allowed here",
+            ),
+            (
+                "SELECT sum(count(*)) FROM people",
+                "aggregate `count` is not allowed here",
+            ),
+            (
+                "SELECT max(age, 1) FROM people",
+                "`max` takes 1 arguments but 2 were given",
+            ),
+            (
+                "SELECT sum(name) FROM people",
+                "argument 1 of `sum` must be a number, found string",
+            ),
+        ] {
+            assert_eq!(run(text).unwrap_err().to_string(), message, "{text}");
+        }
+    }
+
+    #[test]
+    fn table_sample_is_repeatable_with_a_seed() {
+        let mut catalog = Catalog::new();
+        run_on(&mut catalog, "CREATE TABLE n (x INT)").unwrap();
+        let values: Vec<String> = (0..1000).map(|x| format!("({x})")).collect();
+        let insert = format!("INSERT INTO n VALUES {}", values.join(", "));
+        run_on(&mut catalog, &insert).unwrap();
+        let mut sample = |clause: &str| {
+            let text = format!("SELECT x FROM n TABLESAMPLE {clause} WHERE x % 2 = 0");
+            match run_on(&mut catalog, &text) {
+                Ok(Outcome::Rows(result)) => result.rows,
+                other => panic!("{text} gave {other:?}"),
+            }
+        };
+
+        let first = sample("(30 PERCENT) REPEATABLE (7)");
+        assert_eq!(sample("(30 PERCENT) REPEATABLE (7)"), first);
+        assert_ne!(sample("(30 PERCENT) REPEATABLE (8)"), first);
+        assert!((100..200).contains(&first.len()), "{} rows", first.len());
+        assert!(sample("(0 PERCENT)").is_empty());
+        assert_eq!(sample("(100 PERCENT)").len(), 500);
+    }
+
+    fn rows_of(catalog: &mut Catalog, text: &str) -> Result<(Vec<String>, Vec<String>), ExecError> {
+        let Outcome::Rows(result) = run_on(catalog, text)? else {
+            panic!("{text} gave no rows");
+        };
+        let rows = result
+            .rows
+            .iter()
+            .map(|row| {
+                let values: Vec<String> = row.iter().map(LiteralValue::serialize).collect();
+                values.join(", ")
+            })
+            .collect();
+        Ok((result.columns, rows))
+    }
+
+    #[test]
+    fn joins() {
+        let mut catalog = people();
+        for text in [
+            "CREATE TABLE pets (owner TEXT, pet TEXT, age INT)",
+            "INSERT INTO pets VALUES ('bob', 'rex', 3), ('ann', 'tom', 12), \
+             ('bob', 'kit', 1), (null, 'stray', 2)",
+        ] {
+            run_on(&mut catalog, text).unwrap();
+        }
+        for (query, expected) in [
+            (
+                "SELECT name, pet FROM people JOIN pets ON name = owner",
+                vec!["\"ann\", \"tom\"", "\"bob\", \"rex\"", "\"bob\", \"kit\""],
+            ),
+            (
+                "SELECT name, pet FROM people JOIN pets ON name <= owner AND owner <= name",
+                vec!["\"ann\", \"tom\"", "\"bob\", \"rex\"", "\"bob\", \"kit\""],
+            ),
+            (
+                "SELECT pet, people.age FROM people INNER JOIN pets \
+                 ON pets.owner = people.name AND pets.age > 1 WHERE people.age < 40",
+                vec!["\"tom\", 31", "\"rex\", 17"],
+            ),
+            (
+                "SELECT name, pet FROM people JOIN pets ON people.age < pets.age * 3",
+                vec!["\"ann\", \"tom\"", "\"bob\", \"tom\""],
+            ),
+            (
+                "SELECT owner, count(*) AS n, max(pets.age) FROM pets JOIN people \
+                 ON owner = name GROUP BY owner ORDER BY n DESC",
+                vec!["\"bob\", 2, 3", "\"ann\", 1, 12"],
+            ),
+        ] {
+            let (_, rows) = rows_of(&mut catalog, query).unwrap();
+            assert_eq!(rows, expected, "{query}");
+        }
+
+        let (columns, _) = rows_of(
+            &mut catalog,
+            "SELECT *, pets.age AS years FROM people JOIN pets ON true",
+        )
+        .unwrap();
+        assert_eq!(
+            columns,
+            vec!["name", "people.age", "owner", "pet", "pets.age", "years"]
+        );
+
+        for (text, message) in [
+            (
+                "SELECT age FROM people JOIN pets ON name = owner",
+                "column `age` is in both joined tables; write it as `<table>.age`",
+            ),
+            (
+                "SELECT 1 FROM people JOIN pets ON age > 1",
+                "column `age` is in both joined tables; write it as `<table>.age`",
+            ),
+            (
+                "SELECT 1 FROM people JOIN pets ON pets.age",
+                "JOIN condition must be a bool, found int",
+            ),
+            (
+                "SELECT 1 FROM people JOIN vets ON true",
+                "unknown table `vets`",
+            ),
+            (
+                "SELECT vets.age FROM people JOIN pets ON true",
+                "unknown identifier `vets.age`",
+            ),
+            (
+                "SELECT * FROM people JOIN people ON people.age = people.age",
+                "table `people` is joined to itself, so its columns cannot be told apart",
+            ),
+            (
+                "SELECT 1 FROM people JOIN pets ON name = pets.age",
+                "cannot apply `=` to string and int",
+            ),
+            (
+                "SELECT 1 FROM people JOIN pets ON people.age = owner AND true",
+                "cannot apply `=` to int and string",
+            ),
+        ] {
+            let error = rows_of(&mut catalog, text).unwrap_err();
+            assert_eq!(error.to_string(), message, "{text}");
+        }
+    }
+
+    #[test]
+    fn top_k_holds_only_the_limit() {
+        let order = [OrderBy {
+            expr: Expr::Identifier("x".to_string()),
+            descending: false,
+        }];
+        let schema = Arc::new(Schema {
+            columns: vec![Column::new("x", ColumnType::Int)],
+        });
+        let row = |x| Row::new(Arc::clone(&schema), Values::from([Int(x)]));
+        let mut top = TopK::new(&order, None, 2);
+        for x in [5, 3, 9, 1, 3] {
+            top.push(vec![Int(x)], row(x));
+            assert!(top.heap.len() <= 2);
+        }
+        let rows: Vec<Row> = top.into_sorted().collect();
+        assert_eq!(rows, vec![row(1), row(3)]);
+    }
+
+    #[test]
+    fn wildcard_and_limit() {
+        let result = run("SELECT *, age * 2 AS twice FROM people LIMIT 2").unwrap();
+        assert_eq!(result.columns, vec!["name", "age", "twice"]);
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[1][2], Int(34));
+        assert!(run("SELECT * FROM people LIMIT 0").unwrap().rows.is_empty());
+    }
+
+    #[test]
+    fn wildcard_filter_and_limit_share_stored_rows() {
+        let mut catalog = people();
+        let Ok(Outcome::Rows(result)) =
+            run_on(&mut catalog, "SELECT * FROM people WHERE age > 18 LIMIT 1")
+        else {
+            panic!("expected rows");
+        };
+        let stored = Values::clone(catalog.table("people").unwrap().get(0).unwrap().values());
+        assert!(Values::ptr_eq(&result.rows[0], &stored));
+
+        let Ok(Outcome::Rows(result)) = run_on(&mut catalog, "SELECT *, 1 FROM people") else {
+            panic!("expected rows");
+        };
+        assert!(!Values::ptr_eq(&result.rows[0], &stored));
+    }
+
+    #[test]
+    fn generated_columns_are_computed() {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE g (a INT, b INT AS (a * 2) STORED, c BOOL AS (a > 1))",
+            "INSERT INTO g VALUES (1), (2)",
+        ] {
+            run_on(&mut catalog, text).unwrap();
+        }
+        let Ok(Outcome::Rows(result)) = run_on(&mut catalog, "SELECT * FROM g WHERE c") else {
+            panic!("expected rows");
+        };
+        assert_eq!(result.columns, vec!["a", "b", "c"]);
+        assert_eq!(
+            result.rows,
+            vec![Values::from([Int(2), Int(4), LiteralValue::Bool(true)])]
+        );
+        assert_eq!(
+            run_on(&mut catalog, "INSERT INTO g VALUES (1, 2)")
+                .unwrap_err()
+                .to_string(),
+            "table `g` has 1 columns but 2 values were given"
+        );
+    }
+
+    #[test]
+    fn enum_columns() {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE e (id INT, status ENUM('open', 'closed'))",
+            "INSERT INTO e VALUES (1, 'open'), (2, 'closed')",
+        ] {
+            run_on(&mut catalog, text).unwrap();
+        }
+        assert_eq!(
+            run_on(&mut catalog, "INSERT INTO e VALUES (3, 'archived')")
+                .unwrap_err()
+                .to_string(),
+            "column `status` is ENUM(\"open\", \"closed\") but got string \"archived\""
+        );
+        assert_eq!(
+            run_on(
+                &mut catalog,
+                "ALTER TABLE e ALTER status ADD VALUE 'archived'"
+            ),
+            Ok(Outcome::Altered("e".to_string()))
+        );
+        run_on(&mut catalog, "INSERT INTO e VALUES (3, 'archived')").unwrap();
+        let stored = catalog.table("e").unwrap().get(2).unwrap();
+        assert_eq!(stored.values().as_ref(), [Int(3), Int(2)]);
+
+        let Ok(Outcome::Rows(result)) =
+            run_on(&mut catalog, "SELECT id FROM e WHERE status <> 'open'")
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            result.rows,
+            vec![Values::from([Int(2)]), Values::from([Int(3)])]
+        );
+        let Ok(Outcome::Rows(result)) = run_on(&mut catalog, "SELECT status FROM e LIMIT 1") else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            result.rows,
+            vec![Values::from([LiteralValue::String("open".to_string())])]
+        );
+
+        for (text, message) in [
+            (
+                "ALTER TABLE e ALTER status ADD VALUE 'open'",
+                "column `status` lists label 'open' twice",
+            ),
+            (
+                "ALTER TABLE e ALTER id ADD VALUE 'x'",
+                "column `id` is not an ENUM",
+            ),
+            (
+                "ALTER TABLE e ALTER nope ADD VALUE 'x'",
+                "table `e` has no column `nope`",
+            ),
+        ] {
+            assert_eq!(run_on(&mut catalog, text).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn enum_columns_order_by_declared_position() {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE tasks (id INT, level ENUM('low', 'mid', 'high'))",
+            "INSERT INTO tasks VALUES (1, 'mid'), (2, 'high'), (3, null), (4, 'low')",
+            "CREATE TABLE owners (task INT, name TEXT)",
+            "INSERT INTO owners VALUES (1, 'ann'), (2, 'bob'), (4, 'cy')",
+        ] {
+            run_on(&mut catalog, text).unwrap();
+        }
+        let levels = |rows: &[&str]| rows.iter().map(|row| row.to_string()).collect::<Vec<_>>();
+        for (query, expected) in [
+            (
+                "SELECT level FROM tasks ORDER BY level",
+                levels(&["null", "\"low\"", "\"mid\"", "\"high\""]),
+            ),
+            (
+                "SELECT level AS l FROM tasks ORDER BY l DESC LIMIT 2",
+                levels(&["\"high\"", "\"mid\""]),
+            ),
+            (
+                "SELECT id FROM tasks WHERE level > 'low' ORDER BY id",
+                levels(&["1", "2"]),
+            ),
+            (
+                "SELECT name FROM tasks JOIN owners ON id = task ORDER BY level",
+                levels(&["\"cy\"", "\"ann\"", "\"bob\""]),
+            ),
+        ] {
+            assert_eq!(rows_of(&mut catalog, query).unwrap().1, expected, "{query}");
+        }
+        assert_eq!(
+            run_on(&mut catalog, "SELECT id FROM tasks WHERE level < 'urgent'")
+                .unwrap_err()
+                .to_string(),
+            "'urgent' is not a label of the ENUM it is compared with"
+        );
+    }
+
+    #[test]
+    fn external_tables_read_their_file_on_each_scan() {
+        let path = std::env::temp_dir().join(format!("toyql-{}-ext.csv", std::process::id()));
+        std::fs::write(&path, "city,pop\nOslo,700000\nBergen,\n").unwrap();
+        let location = LiteralValue::String(path.to_string_lossy().into_owned()).serialize();
+        let mut catalog = Catalog::new();
+        assert_eq!(
+            run_on(
+                &mut catalog,
+                &format!("CREATE EXTERNAL TABLE c (pop FLOAT) USING csv LOCATION {location}")
+            ),
+            Ok(Outcome::Created("c".to_string()))
+        );
+        let (columns, rows) = rows_of(
+            &mut catalog,
+            "SELECT city, pop FROM c WHERE pop IS NOT NULL",
+        )
+        .unwrap();
+        assert_eq!(columns, vec!["city", "pop"]);
+        assert_eq!(rows, vec!["\"Oslo\", 700000.0"]);
+        assert_eq!(
+            run_on(&mut catalog, "INSERT INTO c VALUES ('Rome', 1.0)")
+                .unwrap_err()
+                .to_string(),
+            "table `c` is read-only"
+        );
+
+        std::fs::write(&path, "city,pop\nOslo,700000\nBergen,many\n").unwrap();
+        assert_eq!(
+            run_on(&mut catalog, "SELECT count(*) FROM c")
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "{} line 3: column `pop` is FLOAT but got `many`",
+                path.display()
+            )
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn wildcards_leave_out_hidden_columns() {
+        let dir = std::env::temp_dir().join(format!("toyql-{}-hidden", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.csv"), "id\n1\n").unwrap();
+        let location = LiteralValue::String(dir.to_string_lossy().into_owned()).serialize();
+        let mut catalog = Catalog::new();
+        run_on(
+            &mut catalog,
+            &format!("CREATE EXTERNAL TABLE parts USING csv LOCATION {location}"),
+        )
+        .unwrap();
+        let (columns, rows) = rows_of(&mut catalog, "SELECT * FROM parts").unwrap();
+        assert_eq!(
+            (columns, rows),
+            (vec!["id".to_string()], vec!["1".to_string()])
+        );
+        let (columns, rows) = rows_of(
+            &mut catalog,
+            "SELECT *, _filename LIKE '%a.csv' AS named FROM parts WHERE _filename IS NOT NULL",
+        )
+        .unwrap();
+        assert_eq!(columns, vec!["id", "named"]);
+        assert_eq!(rows, vec!["1, true"]);
+        let result = match run_on(&mut catalog, "SELECT * FROM parts").unwrap() {
+            Outcome::Rows(result) => result,
+            other => panic!("gave {other:?}"),
+        };
+        assert_eq!(result.types.len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reads_tables_as_of_a_version() {
+        let mut catalog = people();
+        run_on(&mut catalog, "INSERT INTO people VALUES ('eve', 22)").unwrap();
+        let all = ["\"ann\"", "\"bob\"", "\"cy\"", "\"dee\"", "\"eve\""];
+        for (version, expected) in [(0, &all[..0]), (1, &all[..4]), (2, &all[..])] {
+            let text = format!("SELECT name FROM people FOR VERSION AS OF {version}");
+            let (_, rows) = rows_of(&mut catalog, &text).unwrap();
+            assert_eq!(rows, expected, "{text}");
+        }
+        // The version limits the rows before the filter, and in place of
+        // the lookup the filter would otherwise use.
+        let (_, rows) = rows_of(
+            &mut catalog,
+            "SELECT count(*) FROM people FOR VERSION AS OF 1 WHERE name = 'eve'",
+        )
+        .unwrap();
+        assert_eq!(rows, vec!["0"]);
+        assert_eq!(
+            run_on(&mut catalog, "SELECT * FROM people FOR VERSION AS OF 3")
+                .unwrap_err()
+                .to_string(),
+            "table `people` has no version 3; its latest is 2"
+        );
+    }
+
+    #[test]
+    fn session_variables() {
+        let mut catalog = people();
+        assert_eq!(
+            run_on(&mut catalog, "SET min_age = 18"),
+            Ok(Outcome::Set("min_age".to_string()))
+        );
+        let (columns, rows) = rows_of(
+            &mut catalog,
+            "SELECT name, @min_age FROM people WHERE age >= @min_age ORDER BY age",
+        )
+        .unwrap();
+        assert_eq!(columns, vec!["name", "@min_age"]);
+        assert_eq!(rows, vec!["\"ann\", 18", "\"dee\", 18"]);
+
+        run_on(&mut catalog, "SET nulls TO 'last'").unwrap();
+        let (_, rows) = rows_of(&mut catalog, "SELECT name FROM people ORDER BY age").unwrap();
+        assert_eq!(rows, vec!["\"bob\"", "\"ann\"", "\"dee\"", "\"cy\""]);
+        run_on(&mut catalog, "SET nulls = First").unwrap();
+        let (_, rows) = rows_of(
+            &mut catalog,
+            "SELECT name FROM people ORDER BY age DESC LIMIT 2",
+        )
+        .unwrap();
+        assert_eq!(rows, vec!["\"cy\"", "\"dee\""]);
+
+        let (columns, rows) = rows_of(&mut catalog, "SHOW ALL").unwrap();
+        assert_eq!(columns, vec!["name", "value"]);
+        assert_eq!(rows, vec!["\"min_age\", 18", "\"nulls\", \"first\""]);
+        let (columns, rows) = rows_of(&mut catalog, "SHOW SETTINGS").unwrap();
+        assert_eq!(columns, vec!["name", "value", "values", "description"]);
+        assert_eq!(
+            rows,
+            vec![
+                "\"deterministic_floats\", \"off\", \"on, off\", \
+                 \"whether float SUM and AVG round once, whatever the order of rows\"",
+                "\"negative_zero\", \"keep\", \"keep, fold\", \
+                 \"whether a float -0.0 is stored as it is or as 0.0\"",
+                "\"nulls\", \"first\", \"first, last\", \
+                 \"where ORDER BY puts NULLs; unset, they sort before other values\"",
+                "\"output\", \"text\", \"text, json\", \
+                 \"how the CLI prints what statements did\"",
+            ]
+        );
+        run_on(&mut catalog, "SET min_age = NULL").unwrap();
+        let (_, rows) = rows_of(&mut catalog, "SHOW min_age").unwrap();
+        assert_eq!(rows, vec!["\"min_age\", null"]);
+        let (_, rows) = rows_of(&mut catalog, "SELECT @min_age, @min_age IS NULL").unwrap();
+        assert_eq!(rows, vec!["null, true"]);
+        let (_, rows) = rows_of(&mut catalog, "SHOW output").unwrap();
+        assert_eq!(rows, vec!["\"output\", \"text\""]);
+
+        for text in ["SELECT @max_age", "SHOW max_age"] {
+            assert_eq!(
+                run_on(&mut catalog, text).unwrap_err().to_string(),
+                "variable @max_age is not set",
+                "{text}"
+            );
+        }
+        assert_eq!(
+            run_on(&mut catalog, "SET nulls = 'middle'")
+                .unwrap_err()
+                .to_string(),
+            "setting `nulls` must be one of 'first', 'last'"
+        );
+    }
+
+    #[test]
+    fn macros_expand_where_they_are_called() {
+        let mut catalog = people();
+        assert_eq!(
+            run_on(&mut catalog, "CREATE MACRO older(x, years) AS x + years"),
+            Ok(Outcome::DefinedMacro("older".to_string()))
+        );
+        run_on(&mut catalog, "CREATE MACRO adult() AS age >= @min_age").unwrap();
+        run_on(&mut catalog, "SET min_age = 18").unwrap();
+        let (columns, rows) = rows_of(
+            &mut catalog,
+            "SELECT name, older(age, 2) FROM people WHERE adult() ORDER BY older(age, 1)",
+        )
+        .unwrap();
+        assert_eq!(columns, vec!["name", "older(age, 2)"]);
+        assert_eq!(rows, vec!["\"ann\", 33", "\"dee\", 47"]);
+
+        for (text, message) in [
+            (
+                "SELECT older(1)",
+                "macro `older` takes 2 arguments but was given 1",
+            ),
+            ("CREATE MACRO OLDER(x) AS x", "macro `OLDER` already exists"),
+            (
+                "CREATE MACRO count(x) AS x",
+                "`count` is a built-in function",
+            ),
+        ] {
+            assert_eq!(run_on(&mut catalog, text).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn create_and_insert_outcomes() {
+        let mut catalog = Catalog::new();
+        assert_eq!(
+            run_on(&mut catalog, "CREATE TABLE t (x FLOAT)"),
+            Ok(Outcome::Created("t".to_string()))
+        );
+        assert_eq!(
+            run_on(&mut catalog, "INSERT INTO t VALUES (1), (2.5), (-(3) * 2)"),
+            Ok(Outcome::Inserted(3))
+        );
+        assert_eq!(
+            run_on(&mut catalog, "INSERT INTO t VALUES ('a')")
+                .unwrap_err()
+                .to_string(),
+            "column `x` is FLOAT but got string \"a\""
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            run("SELECT * FROM nobody"),
+            Err(ExecError::Catalog(CatalogError::UnknownTable(
+                "nobody".to_string()
+            )))
+        );
+        assert_eq!(run("SELECT *"), Err(ExecError::WildcardWithoutTable));
+        assert_eq!(
+            run("SELECT name FROM people WHERE age"),
+            Err(ExecError::NonBooleanFilter("int"))
+        );
+        assert_eq!(
+            run("SELECT height FROM people"),
+            Err(ExecError::Eval(EvalError::UnknownIdentifier(
+                "height".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn sorted_results_are_in_order() {
+        check(
+            300,
+            |g| {
+                let rows: Vec<String> = (0..g.below(g.size + 1))
+                    .map(|_| {
+                        let int = match g.below(4) {
+                            0 => LiteralValue::Null,
+                            _ => Int(g.int() % 8),
+                        };
+                        let float = match g.below(4) {
+                            0 => LiteralValue::Null,
+                            _ => LiteralValue::Float(g.float()),
+                        };
+                        let text = match g.below(4) {
+                            0 => LiteralValue::Null,
+                            _ => LiteralValue::String(g.pick(&["a", "b", "B", ""]).to_string()),
+                        };
+                        format!(
+                            "({}, {}, {})",
+                            int.serialize(),
+                            float.serialize(),
+                            text.serialize()
+                        )
+                    })
+                    .collect();
+                let order: Vec<String> = (0..g.below(3) + 1)
+                    .map(|_| {
+                        let key = g.pick(&["i", "f", "s", "i + f", "-i", "s || s"]);
+                        format!("{key}{}", g.pick(&["", " DESC"]))
+                    })
+                    .collect();
+                let limit = match g.below(3) {
+                    0 => format!(" LIMIT {}", g.below(g.size + 1)),
+                    _ => String::new(),
+                };
+                (
+                    rows,
+                    g.pick(&["NULL", "'first'", "'last'"]),
+                    format!(
+                        "SELECT /*+ ASSERT_SORTED */ i, f, s FROM t ORDER BY {}{limit}",
+                        order.join(", ")
+                    ),
+                )
+            },
+            |(rows, nulls, query)| {
+                let mut catalog = Catalog::new();
+                run_on(&mut catalog, "CREATE TABLE t (i INT, f FLOAT, s TEXT)").unwrap();
+                if !rows.is_empty() {
+                    let insert = format!("INSERT INTO t VALUES {}", rows.join(", "));
+                    run_on(&mut catalog, &insert).unwrap();
+                }
+                run_on(&mut catalog, &format!("SET nulls = {nulls}")).unwrap();
+                match run_on(&mut catalog, query) {
+                    Ok(Outcome::Rows(_)) => Ok(()),
+                    other => Err(format!("{other:?}")),
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn asserting_sorted_catches_rows_out_of_order() {
+        let catalog = people();
+        let plan = Plan::AssertSorted {
+            input: Box::new(Plan::Scan {
+                table: "people".to_string(),
+                schema: Arc::clone(catalog.table("people").unwrap().schema()),
+                sample: None,
+                version: None,
+                lookup: None,
+            }),
+            order: vec![OrderBy {
+                expr: Expr::Identifier("age".to_string()),
+                descending: false,
+            }],
+        };
+        let error = super::run(plan, &catalog)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "ORDER BY gave keys (31) before (17)");
+    }
+}
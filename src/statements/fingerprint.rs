@@ -0,0 +1,120 @@
+//! Fingerprints: a stable hash shared by every run of a statement with
+//! different values, to group statements by in stats and telemetry.
+//!
+//! A statement is normalized before it is hashed: each literal and each
+//! `$n` becomes the same placeholder, as do the counts of LIMIT and OFFSET,
+//! the numbers of TABLESAMPLE and the version of FOR VERSION AS OF. Names,
+//! operators and the shape of the statement are kept, so `SELECT x FROM t
+//! WHERE x > 5` and `select x from t where x > $1` share a fingerprint, but
+//! `... WHERE y > 5` does not.
+//!
+//! The hash is 64-bit FNV-1a over the normalized statement's text, so the
+//! same statement has the same fingerprint across runs, platforms and
+//! versions of Rust.
+
+use std::fmt;
+
+use super::prepared::map;
+use super::{Sample, Statement};
+use crate::ast::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlanFingerprint(u64);
+
+impl PlanFingerprint {
+    pub fn of(statement: &Statement) -> PlanFingerprint {
+        PlanFingerprint(fnv1a(normalize(statement).serialize().as_bytes()))
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+/// Sixteen hex digits.
+impl fmt::Display for PlanFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The statement with its values replaced by placeholders.
+fn normalize(statement: &Statement) -> Statement {
+    let mut statement = map(statement, &|expr| {
+        expr.replace(&|e| match e {
+            Expr::Literal(_) | Expr::Parameter(_) => Some(Expr::Parameter(0)),
+            _ => None,
+        })
+    });
+    if let Statement::Select(select) | Statement::Explain(select, _) = &mut statement {
+        for count in [&mut select.limit, &mut select.offset]
+            .into_iter()
+            .flatten()
+        {
+            *count = 0;
+        }
+        select.version = select.version.map(|_| 0);
+        if let Some(sample) = &mut select.sample {
+            *sample = Sample {
+                percent: 0.0,
+                seed: sample.seed.map(|_| 0),
+            };
+        }
+    }
+    statement
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::parsing::parse;
+
+    fn fingerprint(text: &str) -> PlanFingerprint {
+        PlanFingerprint::of(&parse(text).unwrap())
+    }
+
+    #[test]
+    fn ignores_values_but_not_shape() {
+        let base = fingerprint("SELECT x, 'a' FROM t WHERE x > 5 LIMIT 10");
+        for same in [
+            "select x, 'b' from t where x > 6.5 limit 20",
+            "SELECT x, $2 FROM t WHERE x > ? LIMIT 1",
+        ] {
+            assert_eq!(fingerprint(same), base, "{same}");
+        }
+        assert_eq!(
+            fingerprint("SELECT * FROM t FOR VERSION AS OF 1"),
+            fingerprint("SELECT * FROM t FOR VERSION AS OF 7")
+        );
+        for different in [
+            "SELECT x, 'a' FROM t WHERE y > 5 LIMIT 10",
+            "SELECT x, 'a' FROM t WHERE x >= 5 LIMIT 10",
+            "SELECT x, 'a' FROM t WHERE x > 5",
+            "SELECT x, 'a' AS a FROM t WHERE x > 5 LIMIT 10",
+            "EXPLAIN SELECT x, 'a' FROM t WHERE x > 5 LIMIT 10",
+        ] {
+            assert_ne!(fingerprint(different), base, "{different}");
+        }
+        assert_eq!(
+            fingerprint("INSERT INTO t VALUES (1, 'a'), (2, 'b')"),
+            fingerprint("INSERT INTO t VALUES (3, NULL), (4, 'c')")
+        );
+    }
+
+    #[test]
+    fn is_stable() {
+        // A change here breaks every fingerprint recorded so far.
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            fingerprint("SELECT 1").to_string(),
+            format!("{:016x}", fnv1a(b"SELECT $0"))
+        );
+    }
+}
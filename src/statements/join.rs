@@ -0,0 +1,460 @@
+//! Inner joins: `FROM a JOIN b ON <expr>`.
+//!
+//! The join produces rows whose columns are those of `a` followed by those of
+//! `b`. A column name that both tables have is called `a.x` and `b.x` there;
+//! any other keeps its own name. Names in the query are rewritten to match,
+//! so `a.y` and `y` both reach a column `y` that only `a` has, while a bare
+//! `x` is ambiguous. A table cannot be joined to itself, as there are no
+//! aliases to tell its two sides apart, and neither table may have a column
+//! whose name is spelled like a qualified one, such as a column `a.x` when
+//! `a` has a column `x`.
+//!
+//! When ON is an equality between an expression over `a` and one over `b`,
+//! at its top or under AND, the rows of `b` are put in a hash table on their
+//! side of it and each row of `a` looks up its matches there (a hash join).
+//! Otherwise every pair of rows is tried (a nested-loop join). Either way the
+//! whole ON condition decides which pairs are kept, and pairs come out in the
+//! order of `a`, then of `b`. A hash join fails as the nested-loop join
+//! would where the two sides' keys cannot be compared, as for an int and a
+//! string. Only the rows of `b` are held in memory; those
+//! of `a` are read as joined rows are needed.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::execute::ExecError;
+use super::plan::Plan;
+use super::{Sample, Select, SelectItem};
+use crate::ast::{BinaryOp, Expr};
+use crate::catalog::{Column, Schema};
+use crate::eval;
+use crate::literals::{HashKey, LiteralValue};
+use crate::table::{Row, Table, Values};
+
+/// The two tables of a join and the names their columns have once joined.
+struct Names<'a> {
+    left: Side<'a>,
+    right: Side<'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Which {
+    Left,
+    Right,
+}
+
+struct Side<'a> {
+    table: &'a str,
+    columns: Vec<String>,
+    /// The name of each column in the joined table.
+    joined: Vec<String>,
+}
+
+impl<'a> Names<'a> {
+    fn new(
+        left: (&'a str, Vec<String>),
+        right: (&'a str, Vec<String>),
+    ) -> Result<Names<'a>, ExecError> {
+        if left.0 == right.0 {
+            return Err(ExecError::SelfJoin(left.0.to_string()));
+        }
+        for (table, columns) in [&left, &right] {
+            for column in columns {
+                let qualified = format!("{table}.{column}");
+                if left.1.contains(&qualified) || right.1.contains(&qualified) {
+                    return Err(ExecError::AmbiguousColumn(qualified));
+                }
+            }
+        }
+        let side = |(table, columns): (&'a str, Vec<String>), other: &[String]| {
+            let joined = columns
+                .iter()
+                .map(|column| {
+                    if other.contains(column) {
+                        format!("{table}.{column}")
+                    } else {
+                        column.clone()
+                    }
+                })
+                .collect();
+            Side {
+                table,
+                columns,
+                joined,
+            }
+        };
+        let (left_columns, right_columns) = (left.1.clone(), right.1.clone());
+        Ok(Names {
+            left: side(left, &right_columns),
+            right: side(right, &left_columns),
+        })
+    }
+
+    /// The joined-table column that `name`, as written in the query, refers
+    /// to; `None` when it is not a column of either table.
+    fn resolve(&self, name: &str) -> Result<Option<&str>, ExecError> {
+        fn find<'s>(side: &'s Side<'_>, column: &str) -> Option<&'s str> {
+            side.columns
+                .iter()
+                .position(|c| c == column)
+                .map(|i| side.joined[i].as_str())
+        }
+        if let Some((table, column)) = name.split_once('.') {
+            for side in [&self.left, &self.right] {
+                if side.table == table {
+                    if let Some(joined) = find(side, column) {
+                        return Ok(Some(joined));
+                    }
+                }
+            }
+        }
+        match (find(&self.left, name), find(&self.right, name)) {
+            (Some(_), Some(_)) => Err(ExecError::AmbiguousColumn(name.to_string())),
+            (left, right) => Ok(left.or(right)),
+        }
+    }
+
+    /// `expr` with its names rewritten to the joined table's, leaving the
+    /// names in `keep` alone.
+    fn rewrite(&self, expr: &Expr, keep: &[&str]) -> Result<Expr, ExecError> {
+        let mut renames = HashMap::new();
+        for name in expr.identifiers() {
+            if keep.contains(&name) {
+                continue;
+            }
+            if let Some(joined) = self.resolve(name)? {
+                renames.insert(name.to_string(), joined.to_string());
+            }
+        }
+        Ok(expr.replace(&|e| match e {
+            Expr::Identifier(name) => renames.get(name).cloned().map(Expr::Identifier),
+            _ => None,
+        }))
+    }
+
+    /// `expr`, written with joined names of one side's columns, with the
+    /// names those columns have in their own table.
+    fn local(&self, expr: &Expr, which: Which) -> Expr {
+        let side = match which {
+            Which::Left => &self.left,
+            Which::Right => &self.right,
+        };
+        expr.replace(&|e| match e {
+            Expr::Identifier(name) => side
+                .joined
+                .iter()
+                .position(|j| j == name)
+                .map(|i| Expr::Identifier(side.columns[i].clone())),
+            _ => None,
+        })
+    }
+
+    /// Which table all the columns `expr` uses belong to, if it uses any.
+    fn side_of(&self, expr: &Expr) -> Option<Which> {
+        let names = expr.identifiers();
+        let all_in = |side: &Side<'_>| {
+            !names.is_empty()
+                && names
+                    .iter()
+                    .all(|name| side.joined.iter().any(|j| j == name))
+        };
+        if all_in(&self.left) {
+            Some(Which::Left)
+        } else if all_in(&self.right) {
+            Some(Which::Right)
+        } else {
+            None
+        }
+    }
+}
+
+/// Plans the join of the FROM table `left` with the JOIN table `right`.
+/// Returns the join node and `select` rewritten to run over its rows.
+pub fn plan(
+    select: &Select,
+    left: &dyn Table,
+    right: &dyn Table,
+) -> Result<(Plan, Select), ExecError> {
+    let (Some(left_name), Some(join)) = (&select.from, &select.join) else {
+        unreachable!("a join has two tables");
+    };
+    let names = Names::new(
+        (left_name, left.schema().column_names()),
+        (&join.table, right.schema().column_names()),
+    )?;
+    let rewritten = rewrite(select, &names)?;
+    let on = names.rewrite(&join.on, &[])?;
+    let keys = equality(&on, &names).map(|(left_key, right_key)| {
+        (
+            names.local(left_key, Which::Left),
+            names.local(right_key, Which::Right),
+        )
+    });
+
+    let column = |column: &Column, name: &String| Column {
+        hidden: column.hidden,
+        ..Column::new(name, column.ty.clone())
+    };
+    let columns = left
+        .schema()
+        .columns
+        .iter()
+        .zip(&names.left.joined)
+        .chain(right.schema().columns.iter().zip(&names.right.joined))
+        .map(|(c, name)| column(c, name))
+        .collect();
+    let scan = |table: &String, schema: &Arc<Schema>, sample: &Option<Sample>, version| {
+        Box::new(Plan::Scan {
+            table: table.clone(),
+            schema: Arc::clone(schema),
+            sample: sample.clone(),
+            version,
+            lookup: None,
+        })
+    };
+    let plan = Plan::Join {
+        left: scan(left_name, left.schema(), &select.sample, select.version),
+        right: scan(&join.table, right.schema(), &None, None),
+        on,
+        keys,
+        schema: Arc::new(Schema { columns }),
+    };
+    Ok((plan, rewritten))
+}
+
+/// Pairs each of the `left` rows with the `right` rows for which `on` is
+/// true, giving rows of `schema`. The right rows are read first and kept;
+/// the left ones are read as the joined rows are pulled. With `keys`, only
+/// right rows whose key equals the left row's are tried.
+pub fn run<'a>(
+    left: impl Iterator<Item = Result<Row, ExecError>> + 'a,
+    right: impl Iterator<Item = Result<Row, ExecError>>,
+    on: Expr,
+    keys: Option<(Expr, Expr)>,
+    schema: Arc<Schema>,
+) -> Result<impl Iterator<Item = Result<Row, ExecError>> + 'a, ExecError> {
+    let right = right.collect::<Result<Vec<_>, _>>()?;
+    let candidates = match keys {
+        Some((left_key, right_key)) => {
+            let mut buckets: HashMap<HashKey, Vec<Row>> = HashMap::new();
+            let mut kinds: Vec<(LiteralValue, Row)> = Vec::new();
+            for row in right {
+                let key = eval::evaluate(&right_key, &row)?;
+                // NULL equals nothing, so it can never match.
+                if key == LiteralValue::Null {
+                    continue;
+                }
+                if !kinds.iter().any(|(kind, _)| same_kind(kind, &key)) {
+                    kinds.push((key.clone(), row.clone()));
+                }
+                buckets.entry(key.hash_key()).or_default().push(row);
+            }
+            Candidates::Hashed {
+                left_key,
+                buckets,
+                kinds,
+            }
+        }
+        None => Candidates::All(right),
+    };
+    Ok(left.flat_map(move |left| {
+        let mut joined = Vec::new();
+        if let Err(e) = candidates.pair(left, &on, &schema, &mut joined) {
+            joined.push(Err(e));
+        }
+        joined
+    }))
+}
+
+/// The right rows, arranged for finding those a left row may pair with.
+enum Candidates {
+    All(Vec<Row>),
+    Hashed {
+        left_key: Expr,
+        buckets: HashMap<HashKey, Vec<Row>>,
+        /// The first right row with a key of each kind, with its key.
+        kinds: Vec<(LiteralValue, Row)>,
+    },
+}
+
+/// Whether `a` and `b` are both ints or floats, or of the same type.
+fn same_kind(a: &LiteralValue, b: &LiteralValue) -> bool {
+    a.comparable_with(b) || a.type_name() == b.type_name()
+}
+
+/// The row `left` and `right` make together.
+fn joined(left: &Row, right: &Row, schema: &Arc<Schema>) -> Row {
+    let values: Values = left
+        .values()
+        .iter()
+        .chain(right.values().iter())
+        .cloned()
+        .collect();
+    Row::new(Arc::clone(schema), values)
+}
+
+impl Candidates {
+    /// Pushes each joined row `left` makes onto `out`.
+    fn pair(
+        &self,
+        left: Result<Row, ExecError>,
+        on: &Expr,
+        schema: &Arc<Schema>,
+        out: &mut Vec<Result<Row, ExecError>>,
+    ) -> Result<(), ExecError> {
+        let left = left?;
+        let rights = match self {
+            Candidates::All(rows) => rows.as_slice(),
+            Candidates::Hashed {
+                left_key,
+                buckets,
+                kinds,
+            } => {
+                let key = eval::evaluate(left_key, &left)?;
+                // A key that cannot be compared with the left one fails the
+                // ON condition in a nested-loop join; evaluating it on one
+                // such pair fails the same way.
+                if key != LiteralValue::Null {
+                    for (_, right) in kinds.iter().filter(|(kind, _)| !key.comparable_with(kind)) {
+                        eval::evaluate(on, &joined(&left, right, schema))?;
+                    }
+                }
+                buckets.get(&key.hash_key()).map_or(&[][..], Vec::as_slice)
+            }
+        };
+        for right in rights {
+            let row = joined(&left, right, schema);
+            match eval::evaluate(on, &row)? {
+                LiteralValue::Bool(true) => out.push(Ok(row)),
+                LiteralValue::Bool(false) | LiteralValue::Null => {}
+                other => return Err(ExecError::NonBooleanJoin(other.type_name())),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `select` with names rewritten for the joined table. ORDER BY may also
+/// name output columns, which are left alone.
+fn rewrite(select: &Select, names: &Names<'_>) -> Result<Select, ExecError> {
+    let mut aliases = Vec::new();
+    let mut items = Vec::new();
+    for item in &select.items {
+        items.push(match item {
+            SelectItem::Wildcard => SelectItem::Wildcard,
+            SelectItem::Expr { expr, alias } => {
+                if let Some(alias) = alias {
+                    aliases.push(alias.as_str());
+                }
+                SelectItem::Expr {
+                    expr: names.rewrite(expr, &[])?,
+                    alias: alias.clone(),
+                }
+            }
+        });
+    }
+    let exprs = |exprs: &[Expr]| -> Result<Vec<Expr>, ExecError> {
+        exprs.iter().map(|e| names.rewrite(e, &[])).collect()
+    };
+    let mut order_by = select.order_by.clone();
+    for item in &mut order_by {
+        item.expr = names.rewrite(&item.expr, &aliases)?;
+    }
+    Ok(Select {
+        assert_sorted: select.assert_sorted,
+        items,
+        from: select.from.clone(),
+        sample: None,
+        version: None,
+        join: None,
+        filter: select
+            .filter
+            .as_ref()
+            .map(|filter| names.rewrite(filter, &[]))
+            .transpose()?,
+        group_by: exprs(&select.group_by)?,
+        order_by,
+        limit: select.limit,
+        offset: select.offset,
+    })
+}
+
+/// An equality in `on`, at its top or under AND, between an expression over
+/// the left table and one over the right, as (left, right).
+fn equality<'e>(on: &'e Expr, names: &Names<'_>) -> Option<(&'e Expr, &'e Expr)> {
+    match on {
+        Expr::Binary {
+            op: BinaryOp::Eq,
+            left,
+            right,
+        } => match (names.side_of(left), names.side_of(right)) {
+            (Some(Which::Left), Some(Which::Right)) => Some((left, right)),
+            (Some(Which::Right), Some(Which::Left)) => Some((right, left)),
+            _ => None,
+        },
+        Expr::Binary {
+            op: BinaryOp::And,
+            left,
+            right,
+        } => equality(left, names).or_else(|| equality(right, names)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parsing::parse;
+
+    fn names() -> Names<'static> {
+        let columns = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        Names::new(("a", columns(&["id", "x"])), ("b", columns(&["id", "y"]))).unwrap()
+    }
+
+    #[test]
+    fn resolves_and_qualifies_names() {
+        let names = names();
+        assert_eq!(names.left.joined, vec!["a.id", "x"]);
+        assert_eq!(names.resolve("a.x"), Ok(Some("x")));
+        assert_eq!(names.resolve("y"), Ok(Some("y")));
+        assert_eq!(names.resolve("b.id"), Ok(Some("b.id")));
+        assert_eq!(names.resolve("z"), Ok(None));
+        assert_eq!(
+            names.resolve("id"),
+            Err(ExecError::AmbiguousColumn("id".to_string()))
+        );
+    }
+
+    #[test]
+    fn refuses_names_it_cannot_tell_apart() {
+        let columns = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            Names::new(("a", columns(&["x"])), ("a", columns(&["x"]))).err(),
+            Some(ExecError::SelfJoin("a".to_string()))
+        );
+        for (left, right) in [(&["x", "a.x"][..], &["x"][..]), (&["x"], &["a.x"])] {
+            assert_eq!(
+                Names::new(("a", columns(left)), ("b", columns(right))).err(),
+                Some(ExecError::AmbiguousColumn("a.x".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn finds_hash_join_keys() {
+        let names = names();
+        for (on, expected) in [
+            ("a.id = b.id", Some(("a.id", "b.id"))),
+            ("x > 1 AND y + 1 = x * 2", Some(("x * 2", "y + 1"))),
+            ("x = x + 1", None),
+            ("x < y", None),
+            ("x = 1", None),
+            ("x = y OR true", None),
+        ] {
+            let on = names.rewrite(&parse(on).unwrap(), &[]).unwrap();
+            let keys = equality(&on, &names).map(|(l, r)| (l.serialize(), r.serialize()));
+            let expected = expected.map(|(l, r)| (l.to_string(), r.to_string()));
+            assert_eq!(keys, expected, "{}", on.serialize());
+        }
+    }
+}
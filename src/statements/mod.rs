@@ -0,0 +1,716 @@
+//! Statements: the top-level units of a query.
+
+mod aggregate;
+mod execute;
+mod fingerprint;
+mod join;
+pub mod parsing;
+mod plan;
+mod prepared;
+mod resolve;
+mod rewrite;
+pub(crate) mod types;
+
+pub use execute::{execute, query, ExecError, Outcome, ResultColumn, ResultSet, RowIterator};
+pub use fingerprint::PlanFingerprint;
+pub use plan::{plan, Plan};
+pub(crate) use prepared::visit;
+pub use prepared::PreparedStatement;
+pub use resolve::resolve;
+pub use rewrite::{Applied, Rewrites};
+pub use types::ValueType;
+
+use crate::ast::Expr;
+use crate::catalog::Column;
+use crate::lexer::quote_identifier;
+use crate::literals::LiteralValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select(Select),
+    /// `EXPLAIN [(FORMAT <format>)] <select>`: shows how the query would
+    /// run, without running it.
+    Explain(Select, ExplainFormat),
+    /// `CREATE TABLE <name> (<column> <type> [AS (<expr>) STORED|VIRTUAL], ...)`
+    CreateTable {
+        name: String,
+        columns: Vec<Column>,
+    },
+    /// `CREATE EXTERNAL TABLE <name> [(<column> <type>, ...)] USING csv
+    /// LOCATION '<path>'`: a read-only table over a CSV file, or over the
+    /// files of a directory or `*` pattern, with the listed columns' types
+    /// in place of inferred ones; see
+    /// [`external`](crate::external).
+    CreateExternalTable {
+        name: String,
+        columns: Vec<Column>,
+        location: String,
+    },
+    /// `CREATE MACRO <name>([<parameter>, ...]) AS <expr>`: names an
+    /// expression for later statements to call; see
+    /// [`macros`](crate::macros).
+    CreateMacro {
+        name: String,
+        parameters: Vec<String>,
+        body: Expr,
+    },
+    /// `INSERT INTO <table> VALUES (<expr>, ...), ...`
+    Insert {
+        table: String,
+        rows: Vec<Vec<Expr>>,
+    },
+    /// `ALTER TABLE <table> ALTER [COLUMN] <column> ADD VALUE '<label>'`:
+    /// adds a label to an ENUM column.
+    AddEnumLabel {
+        table: String,
+        column: String,
+        label: String,
+    },
+    /// `SET [@]<name> = <literal>` (or `TO`): sets a session variable; see
+    /// [`crate::variables`]. For a setting, a bare word stands for a
+    /// string literal of it in lower case.
+    Set {
+        name: String,
+        value: LiteralValue,
+    },
+    /// `SHOW [@]<name>`, or `SHOW ALL` (`None`): the values of session
+    /// variables.
+    Show(Option<String>),
+    /// `SHOW SETTINGS`: every setting, with the value in effect; see
+    /// [`crate::variables::SETTINGS`].
+    ShowSettings,
+}
+
+fn serialize_columns(columns: &[Column]) -> String {
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let mut column = format!("{} {}", quote_identifier(&c.name), c.ty);
+            if let Some(generated) = &c.generated {
+                column.push_str(&format!(
+                    " AS ({}) {}",
+                    generated.expr.serialize(),
+                    generated.kind.name()
+                ));
+            }
+            column
+        })
+        .collect();
+    columns.join(", ")
+}
+
+/// How EXPLAIN shows a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExplainFormat {
+    /// A line per node, with its inputs indented under it.
+    #[default]
+    Text,
+    /// A Graphviz `digraph`.
+    Dot,
+    /// A Mermaid flowchart.
+    Mermaid,
+}
+
+impl ExplainFormat {
+    pub const ALL: [ExplainFormat; 3] = [
+        ExplainFormat::Text,
+        ExplainFormat::Dot,
+        ExplainFormat::Mermaid,
+    ];
+
+    /// The name written after FORMAT.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExplainFormat::Text => "text",
+            ExplainFormat::Dot => "dot",
+            ExplainFormat::Mermaid => "mermaid",
+        }
+    }
+}
+
+/// `SELECT [/*+ ASSERT_SORTED */] <items> [FROM <table> [<sample>] [FOR VERSION AS OF <n>] [<join>]]
+/// [WHERE <expr>] [GROUP BY <expr>, ...] [ORDER BY <order>, ...] [LIMIT <n>]
+/// [OFFSET <n>]`
+///
+/// With a version, the FROM table is read as it was at that version; see
+/// [`Catalog`](crate::catalog::Catalog).
+///
+/// The `ASSERT_SORTED` hint is for tests, and only read in debug builds:
+/// it checks that the rows ORDER BY produces really are in order,
+/// recomputing their keys, and fails the query if they are not. Any other
+/// hint is ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub assert_sorted: bool,
+    pub items: Vec<SelectItem>,
+    pub from: Option<String>,
+    pub sample: Option<Sample>,
+    pub version: Option<u64>,
+    pub join: Option<Join>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// `[INNER] JOIN <table> ON <expr>`: pairs each row of the FROM table with
+/// each row of `table` for which `on` is true. A column in both tables must
+/// be written `<table>.<column>`; other columns may be written either way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub table: String,
+    pub on: Expr,
+}
+
+/// `TABLESAMPLE (<percent> PERCENT) [REPEATABLE (<seed>)]`: each row of the
+/// table is kept with probability `percent / 100`, before WHERE. The same
+/// seed over the same table keeps the same rows; without one, every query
+/// draws afresh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub percent: f64,
+    pub seed: Option<u64>,
+}
+
+/// `<expr> [ASC | DESC]`. Names in the expression refer to output columns
+/// first, then to columns of the source table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    /// `*`: every column of the source table.
+    Wildcard,
+    Expr {
+        expr: Expr,
+        alias: Option<String>,
+    },
+}
+
+impl Statement {
+    /// Renders the statement so that `parsing::parse` rebuilds it.
+    pub fn serialize(&self) -> String {
+        match self {
+            Statement::Select(select) => select.serialize(),
+            Statement::Explain(select, ExplainFormat::Text) => {
+                format!("EXPLAIN {}", select.serialize())
+            }
+            Statement::Explain(select, format) => {
+                format!("EXPLAIN (FORMAT {}) {}", format.name(), select.serialize())
+            }
+            Statement::CreateTable { name, columns } => format!(
+                "CREATE TABLE {} ({})",
+                quote_identifier(name),
+                serialize_columns(columns)
+            ),
+            Statement::CreateExternalTable {
+                name,
+                columns,
+                location,
+            } => {
+                let mut out = format!("CREATE EXTERNAL TABLE {}", quote_identifier(name));
+                if !columns.is_empty() {
+                    out.push_str(&format!(" ({})", serialize_columns(columns)));
+                }
+                out.push_str(&format!(
+                    " USING csv LOCATION {}",
+                    LiteralValue::String(location.clone()).serialize()
+                ));
+                out
+            }
+            Statement::CreateMacro {
+                name,
+                parameters,
+                body,
+            } => {
+                let parameters: Vec<String> =
+                    parameters.iter().map(|p| quote_identifier(p)).collect();
+                format!(
+                    "CREATE MACRO {}({}) AS {}",
+                    quote_identifier(name),
+                    parameters.join(", "),
+                    body.serialize()
+                )
+            }
+            Statement::Insert { table, rows } => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        let values: Vec<String> = row.iter().map(Expr::serialize).collect();
+                        format!("({})", values.join(", "))
+                    })
+                    .collect();
+                format!(
+                    "INSERT INTO {} VALUES {}",
+                    quote_identifier(table),
+                    rows.join(", ")
+                )
+            }
+            Statement::AddEnumLabel {
+                table,
+                column,
+                label,
+            } => format!(
+                "ALTER TABLE {} ALTER COLUMN {} ADD VALUE {}",
+                quote_identifier(table),
+                quote_identifier(column),
+                LiteralValue::String(label.clone()).serialize()
+            ),
+            Statement::Set { name, value } => {
+                format!("SET {} = {}", quote_identifier(name), value.serialize())
+            }
+            // A variable called `all` or `settings` needs quoting to not
+            // mean the statement of that name.
+            Statement::Show(Some(name))
+                if name.eq_ignore_ascii_case("all") || name.eq_ignore_ascii_case("settings") =>
+            {
+                format!("SHOW `{name}`")
+            }
+            Statement::Show(Some(name)) => format!("SHOW {}", quote_identifier(name)),
+            Statement::Show(None) => "SHOW ALL".to_string(),
+            Statement::ShowSettings => "SHOW SETTINGS".to_string(),
+        }
+    }
+}
+
+impl Select {
+    pub fn serialize(&self) -> String {
+        let items: Vec<String> = self.items.iter().map(SelectItem::serialize).collect();
+        let hint = if self.assert_sorted {
+            "/*+ ASSERT_SORTED */ "
+        } else {
+            ""
+        };
+        let mut out = format!("SELECT {hint}{}", items.join(", "));
+        if let Some(table) = &self.from {
+            out.push_str(&format!(" FROM {}", quote_identifier(table)));
+        }
+        if let Some(sample) = &self.sample {
+            out.push_str(&format!(" {}", sample.serialize()));
+        }
+        if let Some(version) = self.version {
+            out.push_str(&format!(" FOR VERSION AS OF {version}"));
+        }
+        if let Some(join) = &self.join {
+            out.push_str(&format!(
+                " JOIN {} ON {}",
+                quote_identifier(&join.table),
+                join.on.serialize()
+            ));
+        }
+        if let Some(filter) = &self.filter {
+            out.push_str(&format!(" WHERE {}", filter.serialize()));
+        }
+        if !self.group_by.is_empty() {
+            let keys: Vec<String> = self.group_by.iter().map(Expr::serialize).collect();
+            out.push_str(&format!(" GROUP BY {}", keys.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let order: Vec<String> = self.order_by.iter().map(OrderBy::serialize).collect();
+            out.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            out.push_str(&format!(" LIMIT {limit}"));
+        }
+        if let Some(offset) = self.offset {
+            out.push_str(&format!(" OFFSET {offset}"));
+        }
+        out
+    }
+}
+
+impl SelectItem {
+    pub fn serialize(&self) -> String {
+        match self {
+            SelectItem::Wildcard => "*".to_string(),
+            SelectItem::Expr { expr, alias: None } => expr.serialize(),
+            SelectItem::Expr {
+                expr,
+                alias: Some(alias),
+            } => format!("{} AS {}", expr.serialize(), quote_identifier(alias)),
+        }
+    }
+}
+
+impl OrderBy {
+    pub fn serialize(&self) -> String {
+        let direction = if self.descending { " DESC" } else { "" };
+        format!("{}{direction}", self.expr.serialize())
+    }
+}
+
+impl Sample {
+    pub fn serialize(&self) -> String {
+        let mut out = format!(
+            "TABLESAMPLE ({} PERCENT)",
+            LiteralValue::Float(self.percent).serialize()
+        );
+        if let Some(seed) = self.seed {
+            out.push_str(&format!(" REPEATABLE ({seed})"));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOp;
+    use crate::literals::LiteralValue;
+    use crate::test_utils::check;
+
+    fn parse(text: &str) -> Statement {
+        parsing::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_full_select() {
+        assert_eq!(
+            parse("select a, b + 1 as c from t where a > 2 limit 10;"),
+            Statement::Select(Select {
+                assert_sorted: false,
+                items: vec![
+                    SelectItem::Expr {
+                        expr: Expr::Identifier("a".to_string()),
+                        alias: None,
+                    },
+                    SelectItem::Expr {
+                        expr: Expr::binary(
+                            BinaryOp::Add,
+                            Expr::Identifier("b".to_string()),
+                            Expr::Literal(LiteralValue::Int(1))
+                        ),
+                        alias: Some("c".to_string()),
+                    },
+                ],
+                from: Some("t".to_string()),
+                sample: None,
+                version: None,
+                join: None,
+                filter: Some(Expr::binary(
+                    BinaryOp::Gt,
+                    Expr::Identifier("a".to_string()),
+                    Expr::Literal(LiteralValue::Int(2))
+                )),
+                group_by: vec![],
+                order_by: vec![],
+                limit: Some(10),
+                offset: None,
+            })
+        );
+    }
+
+    #[test]
+    fn skips_unknown_hints() {
+        assert_eq!(parse("SELECT /*+ x */ 1"), parse("SELECT 1"));
+        assert_eq!(
+            parse("SELECT /*+ NO_INDEX(t) */ a FROM t"),
+            parse("SELECT a FROM t")
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for text in [
+            "SELECT 1",
+            "SELECT *",
+            "SELECT * FROM t",
+            "SELECT a AS x, b * 2 FROM t WHERE a = 'x' OR NOT b LIMIT 0",
+            "SELECT -(1) AS neg LIMIT 5",
+            "SELECT a FROM t ORDER BY a DESC, b + 1 LIMIT 3 OFFSET 2",
+            "select a from t order by a asc offset 1",
+            "SELECT /*+ assert_sorted */ a FROM t ORDER BY a DESC",
+            "SELECT a FROM t ORDER BY a LIKE 'x%' DESC",
+            "SELECT a, count(*), SUM(b + 1) FROM t WHERE b > 0 GROUP BY a, c ORDER BY count(*) DESC",
+            "select max(a) from t group by a % 2 limit 1",
+            "SELECT * FROM t TABLESAMPLE (10 PERCENT) WHERE a > 1",
+            "select a from t tablesample (12.5 percent) repeatable (42) limit 3",
+            "SELECT a.id, b.x FROM a JOIN b ON a.id = b.a_id WHERE x > 1",
+            "select * from a inner join `b c` on id < `b c`.id and true",
+            "SELECT * FROM a TABLESAMPLE (50 PERCENT) JOIN b ON f(a.x)",
+            "SELECT * FROM t FOR VERSION AS OF 3 WHERE a > 1",
+            "select * from a tablesample (5 percent) for version as of 0 join b on a.x = b.x",
+            "CREATE TABLE t (a INT, b FLOAT, c TEXT, d BOOL)",
+            "CREATE TABLE `order` (`from` INT, `two words` TEXT)",
+            "CREATE TABLE g (a INT, b INT AS (a * 2) STORED, c BOOL AS (a > 1) VIRTUAL)",
+            "CREATE TABLE n (ip INET, at POINT, tags LIST)",
+            "CREATE EXTERNAL TABLE e USING csv LOCATION 'data/e.csv'",
+            "create external table `e f` (a INT, `b c` FLOAT) using CSV location \"x\\\"y.csv\"",
+            "SELECT ip FROM n WHERE ip << INET '10.0.0.0/8' AND masklen(ip) < 32",
+            "CREATE TABLE e (s ENUM('open', 'it\\'s closed'))",
+            "alter table e alter s add value 'new'",
+            "ALTER TABLE `e f` ALTER COLUMN `s t` ADD VALUE \"x\"",
+            "SELECT `select` AS `as`, f(`x y`) FROM `from` -- trailing comment",
+            "INSERT INTO /* block */ `t` VALUES (1)",
+            "INSERT INTO t VALUES (?, $2 - 1), (?, ?)",
+            "EXPLAIN SELECT a, count(*) FROM t WHERE b GROUP BY a ORDER BY a LIMIT 1",
+            "explain select * from a join b on a.x = b.x",
+            "EXPLAIN (FORMAT dot) SELECT a FROM t WHERE a > 1",
+            "explain (format MERMAID) select 1",
+            "EXPLAIN (FORMAT text) SELECT 1",
+            "INSERT INTO t VALUES (1, 2.5, 'x', true), (-(1), null, 'a' || 'b', NOT false)",
+            "SET nulls = 'last'",
+            "set @limit to -3",
+            "SET `all` = NULL",
+            "SET deterministic_floats = ON",
+            "SHOW nulls",
+            "show all",
+            "SHOW `all`",
+            "show settings",
+            "SHOW `Settings`",
+            "SELECT a FROM t WHERE a > @low LIMIT 1",
+            "CREATE MACRO add_tax(x) AS x * 1.2",
+            "create macro `pi`() as 3.14",
+            "CREATE MACRO between_(x, `low`, high) AS x >= `low` AND x <= high",
+        ] {
+            let statement = parse(text);
+            assert_eq!(parse(&statement.serialize()), statement, "{text}");
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        for (text, column, message) in [
+            ("SELEC 1", 1, "expected statement, found `SELEC`"),
+            (
+                "SELECT",
+                7,
+                "expected `*` or expression, found end of input",
+            ),
+            (
+                "SELECT 1,",
+                10,
+                "expected `*` or expression, found end of input",
+            ),
+            (
+                "SELECT 1 FROM",
+                14,
+                "expected table name, found end of input",
+            ),
+            (
+                "SELECT 1 FROM where",
+                15,
+                "expected table name, found `where`",
+            ),
+            ("SELECT (1 + 2", 14, "expected `)`, found end of input"),
+            (
+                "EXPLAIN (FORMAT json) SELECT 1",
+                17,
+                "expected text, dot or mermaid, found `json`",
+            ),
+            ("SELECT 1 +", 11, "expected expression, found end of input"),
+            ("SELECT f(1, 2", 14, "expected `)`, found end of input"),
+            ("SELECT 1 AS", 12, "expected alias, found end of input"),
+            ("SELECT 1 IS 1", 13, "expected `NULL`, found `1`"),
+            (
+                "SELECT 1 IN (1,",
+                16,
+                "expected expression, found end of input",
+            ),
+            ("SELECT 1 LIMIT x", 16, "expected row count, found `x`"),
+            ("SELECT 1 ORDER a", 16, "expected `BY`, found `a`"),
+            (
+                "SELECT 1 ORDER BY",
+                18,
+                "expected expression, found end of input",
+            ),
+            ("SELECT 1 GROUP a", 16, "expected `BY`, found `a`"),
+            (
+                "SELECT 1 GROUP BY",
+                18,
+                "expected expression, found end of input",
+            ),
+            (
+                "SELECT 1 FROM t TABLESAMPLE (101 PERCENT)",
+                30,
+                "expected percentage from 0 to 100, found `101`",
+            ),
+            (
+                "SELECT 1 FROM t TABLESAMPLE (5)",
+                31,
+                "expected `PERCENT`, found `)`",
+            ),
+            (
+                "SELECT 1 FROM t TABLESAMPLE (5 PERCENT) REPEATABLE (x)",
+                53,
+                "expected seed, found `x`",
+            ),
+            (
+                "SELECT 1 FROM a JOIN b",
+                23,
+                "expected `ON`, found end of input",
+            ),
+            ("SELECT 1 FROM a INNER b", 23, "expected `JOIN`, found `b`"),
+            ("SELECT t. FROM t", 11, "expected identifier, found `FROM`"),
+            ("SELECT $0", 9, "expected parameter number, found `0`"),
+            (
+                "SELECT $",
+                9,
+                "expected parameter number, found end of input",
+            ),
+            ("SELECT 1 OFFSET x", 17, "expected row count, found `x`"),
+            (
+                "EXPLAIN INSERT INTO t",
+                9,
+                "expected `SELECT`, found `INSERT`",
+            ),
+            ("SET @tag = Hello", 12, "expected literal, found `Hello`"),
+            ("SELECT 1 FRM t", 10, "expected end of input, found `FRM`"),
+            (
+                "CREATE TABLE t (a DATE)",
+                19,
+                "expected column type, found `DATE`",
+            ),
+            (
+                "CREATE TABLE t (a INT b TEXT)",
+                23,
+                "expected `)`, found `b`",
+            ),
+            (
+                "CREATE t (a INT)",
+                8,
+                "expected `TABLE`, `EXTERNAL` or `MACRO`, found `t`",
+            ),
+            (
+                "CREATE TABLE t (s ENUM(1))",
+                24,
+                "expected label, found `1`",
+            ),
+            ("INSERT INTO t VALUES (1), 2", 27, "expected `(`, found `2`"),
+        ] {
+            let error = parsing::parse(text).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("line 1, column {column}: {message}"),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn arbitrary_scripts_never_panic() {
+        const PIECES: &[&str] = &[
+            "SELECT",
+            "EXPLAIN",
+            "FROM",
+            "t",
+            "JOIN",
+            "ON",
+            "WHERE",
+            "GROUP BY",
+            "ORDER BY",
+            "LIMIT",
+            "INSERT INTO",
+            "VALUES",
+            "CREATE TABLE",
+            "INT",
+            "x",
+            "count(*)",
+            "=",
+            "+",
+            "-",
+            "*",
+            "1",
+            "2.5",
+            "'a'",
+            "(",
+            ")",
+            ",",
+            ";",
+            "--",
+            "/*",
+            "*/",
+            "`",
+            "\n",
+        ];
+        check(
+            1000,
+            |g| {
+                let pieces = g.below(g.size + 1);
+                (0..pieces)
+                    .map(|_| match g.below(4) {
+                        0 => g.string(),
+                        _ => g.pick(PIECES).to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            },
+            |source| {
+                let mut end = 0;
+                for (span, result) in parsing::parse_script(source) {
+                    if span.start < end || span.end > source.len() {
+                        return Err(format!("bad span {span:?}"));
+                    }
+                    end = span.end;
+                    if let Err(e) = result {
+                        if !span.contains(&e.offset) && e.offset != span.end {
+                            return Err(format!("error at {} outside {span:?}", e.offset));
+                        }
+                    }
+                }
+                Ok(())
+            },
+        );
+    }
+
+    #[test]
+    fn parses_scripts_and_recovers_from_errors() {
+        let source = "SELECT 1;\nSELEC 2;\n;SELECT (;\nSELECT 'x' # ; SELECT 3 -- done\n";
+        let results: Vec<String> = parsing::parse_script(source)
+            .map(|(_, result)| match result {
+                Ok(statement) => statement.serialize(),
+                Err(e) => e.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                "SELECT 1",
+                "line 2, column 1: expected statement, found `SELEC`",
+                "line 3, column 10: expected expression, found `;`",
+                "line 4, column 12: unexpected `#`",
+                "SELECT 3",
+            ]
+        );
+        let spans: Vec<_> = parsing::parse_script(source)
+            .map(|(span, _)| span)
+            .collect();
+        assert_eq!(spans, vec![0..9, 9..18, 20..29, 29..44, 44..source.len()]);
+        assert_eq!(
+            parsing::parse_script(" ; /* nothing */ -- here\n").count(),
+            0
+        );
+
+        // A shebang line is not a comment here, only in a loaded file.
+        let source = "#!/usr/bin/env toyql -f\nSELECT 1;\n#!x";
+        let results: Vec<String> = parsing::parse_script(source)
+            .map(|(_, result)| match result {
+                Ok(statement) => statement.serialize(),
+                Err(e) => e.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                "line 1, column 1: unexpected `#`",
+                "line 3, column 1: unexpected `#`"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for text in [
+            "SELECT",
+            "SELECT 1 FROM",
+            "SELECT 1 LIMIT -1",
+            "SELECT 1 LIMIT x",
+        ] {
+            assert!(parsing::parse(text).is_err(), "{text} parsed");
+        }
+    }
+}
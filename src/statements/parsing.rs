@@ -0,0 +1,540 @@
+//! nom grammar for statements, over tokens.
+//!
+//! Once a statement's leading keyword has matched, the rest is parsed under
+//! `cut`, so an error is reported where the statement went wrong rather
+//! than at its start.
+
+use std::ops::Range;
+
+use nom::branch::alt;
+use nom::combinator::{cut, map, map_opt, opt, value};
+use nom::error::context;
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+
+use super::{ExplainFormat, Join, OrderBy, Sample, Select, SelectItem, Statement};
+use crate::ast;
+use crate::catalog::{Column, ColumnType, Generated, GeneratedKind};
+use crate::error::ParseError;
+use crate::lexer::parsing::{tokenize, tokenize_statement};
+use crate::lexer::{
+    self, bare_word, hint, identifier, keyword, literal, symbol, word, TokenKind, TokenResult,
+    Tokens,
+};
+use crate::literals::LiteralValue;
+use crate::variables;
+
+/// Parses one statement with an optional trailing `;`.
+pub fn apply_grammar(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    terminated(
+        context(
+            "statement",
+            alt((
+                map(select, Statement::Select),
+                explain,
+                create,
+                insert,
+                alter_table,
+                set,
+                show,
+            )),
+        ),
+        opt(symbol(";")),
+    )(input)
+}
+
+/// Parses a whole string as exactly one statement.
+pub fn parse(input: &str) -> Result<Statement, ParseError> {
+    lexer::parse_all(input, apply_grammar)
+}
+
+/// Parses each statement of `source`, where statements end with `;` (the
+/// last may leave it out) and `--` and `/* */` comments may come anywhere
+/// between tokens. After a syntax error, parsing resumes after the next `;`,
+/// so one mistake does not hide those after it. Errors are located in the
+/// whole of `source`.
+///
+/// Statements are parsed as they are pulled, so each can be run before the
+/// next is read.
+pub fn parse_script(source: &str) -> Script<'_> {
+    Script { source, start: 0 }
+}
+
+/// The statements of a script; see [`parse_script`].
+#[derive(Debug, Clone)]
+pub struct Script<'a> {
+    source: &'a str,
+    /// Where the next statement, or the space before it, starts.
+    start: usize,
+}
+
+impl Iterator for Script<'_> {
+    /// The span of the statement's text in the source, with its `;` and any
+    /// space and comments before it, and the statement.
+    type Item = (Range<usize>, Result<Statement, ParseError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let source = self.source;
+        while self.start < source.len() {
+            let start = self.start;
+            let rest = &source[start..];
+            let (tokens, lex_error) = match tokenize_statement(rest) {
+                Ok(tokens) => (tokens, None),
+                // The tokens before the bad one can still end a statement.
+                Err(e) => (tokenize(&rest[..e.offset]).unwrap_or_default(), Some(e)),
+            };
+            let semicolon = tokens.iter().find(|t| t.kind == TokenKind::Symbol(";"));
+            let end = match (semicolon, lex_error) {
+                (Some(semicolon), _) if semicolon.span.start == tokens[0].span.start => {
+                    // An empty statement.
+                    self.start += semicolon.span.end;
+                    continue;
+                }
+                (Some(semicolon), _) => semicolon.span.end,
+                (None, Some(e)) => {
+                    let resume = rest[e.offset..]
+                        .find(';')
+                        .map_or(rest.len(), |i| e.offset + i + 1);
+                    self.start += resume;
+                    return Some((start..self.start, Err(e.relocated(source, start))));
+                }
+                (None, None) if tokens.is_empty() => break,
+                (None, None) => rest.len(),
+            };
+            self.start += end;
+            let result = parse(&rest[..end]).map_err(|e| e.relocated(source, start));
+            return Some((start..self.start, result));
+        }
+        self.start = source.len();
+        None
+    }
+}
+
+/// `EXPLAIN [(FORMAT text|dot|mermaid)] <select>`
+fn explain(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    map(
+        preceded(
+            word("explain"),
+            cut(pair(
+                opt(delimited(
+                    symbol("("),
+                    preceded(word("format"), cut(explain_format)),
+                    cut(symbol(")")),
+                )),
+                select,
+            )),
+        ),
+        |(format, select)| Statement::Explain(select, format.unwrap_or_default()),
+    )(input)
+}
+
+fn explain_format(input: Tokens<'_>) -> TokenResult<'_, ExplainFormat> {
+    context(
+        "text, dot or mermaid",
+        map_opt(identifier, |name| {
+            ExplainFormat::ALL
+                .into_iter()
+                .find(|format| name.eq_ignore_ascii_case(format.name()))
+        }),
+    )(input)
+}
+
+/// An identifier, labelled with what it names.
+fn name<'a>(what: &'static str) -> impl FnMut(Tokens<'a>) -> TokenResult<'a, String> {
+    context(what, identifier)
+}
+
+/// `(item, item, ...)` with at least one item.
+fn parenthesized<'a, O>(
+    item: impl FnMut(Tokens<'a>) -> TokenResult<'a, O>,
+) -> impl FnMut(Tokens<'a>) -> TokenResult<'a, Vec<O>> {
+    delimited(
+        symbol("("),
+        cut(separated_list1(symbol(","), cut(item))),
+        cut(symbol(")")),
+    )
+}
+
+fn create(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    preceded(
+        keyword("create"),
+        cut(alt((
+            map(
+                preceded(
+                    keyword("table"),
+                    cut(pair(name("table name"), parenthesized(column))),
+                ),
+                |(name, columns)| Statement::CreateTable { name, columns },
+            ),
+            external_table,
+            create_macro,
+        ))),
+    )(input)
+}
+
+/// `MACRO <name>([<parameter>, ...]) AS <expr>`, after CREATE.
+fn create_macro(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    map(
+        preceded(
+            word("macro"),
+            cut(tuple((
+                name("macro name"),
+                alt((
+                    value(Vec::new(), pair(symbol("("), symbol(")"))),
+                    parenthesized(name("parameter name")),
+                )),
+                preceded(keyword("as"), ast::parsing::apply_grammar),
+            ))),
+        ),
+        |(name, parameters, body)| Statement::CreateMacro {
+            name,
+            parameters,
+            body,
+        },
+    )(input)
+}
+
+/// `EXTERNAL TABLE <name> [(<column>, ...)] USING csv LOCATION '<path>'`,
+/// after CREATE.
+fn external_table(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    map(
+        preceded(
+            pair(word("external"), cut(keyword("table"))),
+            cut(tuple((
+                name("table name"),
+                opt(parenthesized(column)),
+                preceded(word("using"), word("csv")),
+                preceded(word("location"), context("file path", string)),
+            ))),
+        ),
+        |(name, columns, (), location)| Statement::CreateExternalTable {
+            name,
+            columns: columns.unwrap_or_default(),
+            location,
+        },
+    )(input)
+}
+
+/// `<name> <type> [AS (<expr>) [STORED | VIRTUAL]]`; generated columns are
+/// virtual unless marked otherwise.
+fn column(input: Tokens<'_>) -> TokenResult<'_, Column> {
+    map(
+        tuple((
+            name("column name"),
+            column_type,
+            opt(preceded(
+                keyword("as"),
+                cut(pair(
+                    delimited(symbol("("), ast::parsing::apply_grammar, symbol(")")),
+                    opt(alt((
+                        value(GeneratedKind::Stored, word("stored")),
+                        value(GeneratedKind::Virtual, word("virtual")),
+                    ))),
+                )),
+            )),
+        )),
+        |(name, ty, generated)| Column {
+            name,
+            ty,
+            generated: generated.map(|(expr, kind)| Generated {
+                expr,
+                kind: kind.unwrap_or(GeneratedKind::Virtual),
+            }),
+            hidden: false,
+        },
+    )(input)
+}
+
+fn column_type(input: Tokens<'_>) -> TokenResult<'_, ColumnType> {
+    context(
+        "column type",
+        alt((
+            value(ColumnType::Int, alt((word("integer"), word("int")))),
+            value(
+                ColumnType::Float,
+                alt((word("float"), word("real"), word("double"))),
+            ),
+            value(
+                ColumnType::Text,
+                alt((word("text"), word("varchar"), word("string"))),
+            ),
+            value(ColumnType::Bool, alt((word("boolean"), word("bool")))),
+            value(ColumnType::Inet, word("inet")),
+            value(ColumnType::Point, word("point")),
+            value(ColumnType::List, word("list")),
+            map(
+                preceded(word("enum"), cut(parenthesized(label))),
+                ColumnType::Enum,
+            ),
+        )),
+    )(input)
+}
+
+/// An ENUM label: a string literal.
+fn label(input: Tokens<'_>) -> TokenResult<'_, String> {
+    context("label", string)(input)
+}
+
+fn string(input: Tokens<'_>) -> TokenResult<'_, String> {
+    map_opt(literal, |value| match value {
+        LiteralValue::String(text) => Some(text),
+        _ => None,
+    })(input)
+}
+
+fn alter_table(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    map(
+        preceded(
+            keyword("alter"),
+            cut(tuple((
+                preceded(keyword("table"), name("table name")),
+                preceded(
+                    pair(keyword("alter"), opt(word("column"))),
+                    name("column name"),
+                ),
+                preceded(pair(word("add"), word("value")), label),
+            ))),
+        ),
+        |(table, column, label)| Statement::AddEnumLabel {
+            table,
+            column,
+            label,
+        },
+    )(input)
+}
+
+/// `SET [@]<name> = <literal>`, or with `TO` for `=`. For a setting, a
+/// bare word is taken as a string, lowercased, as in
+/// `SET deterministic_floats = ON`; any other variable takes only a
+/// literal, so that its value is kept as written.
+fn set(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    preceded(word("set"), cut(set_variable))(input)
+}
+
+fn set_variable(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    let (input, name) = terminated(variable_name, alt((symbol("="), word("to"))))(input)?;
+    let (input, value) = if variables::setting(&name).is_some() {
+        context(
+            "literal",
+            alt((signed_literal, map(bare_word, LiteralValue::String))),
+        )(input)?
+    } else {
+        context("literal", signed_literal)(input)?
+    };
+    Ok((input, Statement::Set { name, value }))
+}
+
+/// A literal, or a `-` and a number where the lexer has read the minus as
+/// an operator, as it does after a word such as `TO`.
+fn signed_literal(input: Tokens<'_>) -> TokenResult<'_, LiteralValue> {
+    alt((
+        literal,
+        preceded(
+            symbol("-"),
+            map_opt(literal, |value| match value {
+                LiteralValue::Int(n) => n.checked_neg().map(LiteralValue::Int),
+                LiteralValue::Float(x) => Some(LiteralValue::Float(-x)),
+                _ => None,
+            }),
+        ),
+    ))(input)
+}
+
+/// `SHOW [@]<name>`, `SHOW ALL` or `SHOW SETTINGS`.
+fn show(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    preceded(
+        word("show"),
+        cut(alt((
+            value(Statement::Show(None), word("all")),
+            value(Statement::ShowSettings, word("settings")),
+            map(variable_name, |name| Statement::Show(Some(name))),
+        ))),
+    )(input)
+}
+
+/// A session variable's name, with or without its `@`.
+fn variable_name(input: Tokens<'_>) -> TokenResult<'_, String> {
+    alt((lexer::variable, name("variable name")))(input)
+}
+
+fn insert(input: Tokens<'_>) -> TokenResult<'_, Statement> {
+    map(
+        preceded(
+            keyword("insert"),
+            cut(pair(
+                preceded(keyword("into"), name("table name")),
+                preceded(
+                    keyword("values"),
+                    separated_list1(symbol(","), cut(parenthesized(ast::parsing::apply_grammar))),
+                ),
+            )),
+        ),
+        |(table, rows)| Statement::Insert { table, rows },
+    )(input)
+}
+
+fn select(input: Tokens<'_>) -> TokenResult<'_, Select> {
+    map(
+        preceded(
+            keyword("select"),
+            cut(tuple((
+                assert_sorted,
+                separated_list1(symbol(","), cut(select_item)),
+                opt(preceded(
+                    keyword("from"),
+                    cut(tuple((
+                        name("table name"),
+                        opt(sample),
+                        opt(version),
+                        opt(join),
+                    ))),
+                )),
+                opt(preceded(keyword("where"), cut(ast::parsing::apply_grammar))),
+                opt(preceded(
+                    pair(keyword("group"), cut(keyword("by"))),
+                    cut(separated_list1(
+                        symbol(","),
+                        cut(ast::parsing::apply_grammar),
+                    )),
+                )),
+                opt(preceded(
+                    pair(keyword("order"), cut(keyword("by"))),
+                    cut(separated_list1(symbol(","), cut(order_by))),
+                )),
+                opt(preceded(keyword("limit"), cut(row_count))),
+                opt(preceded(keyword("offset"), cut(row_count))),
+            ))),
+        ),
+        |(assert_sorted, items, from, filter, group_by, order_by, limit, offset)| Select {
+            assert_sorted,
+            items,
+            sample: from.as_ref().and_then(|(_, sample, _, _)| sample.clone()),
+            version: from.as_ref().and_then(|(_, _, version, _)| *version),
+            join: from.as_ref().and_then(|(_, _, _, join)| join.clone()),
+            from: from.map(|(table, _, _, _)| table),
+            filter,
+            group_by: group_by.unwrap_or_default(),
+            order_by: order_by.unwrap_or_default(),
+            limit,
+            offset,
+        },
+    )(input)
+}
+
+/// Whether `/*+ ASSERT_SORTED */` follows SELECT in a debug build. Other
+/// hints, and this one in a release build, are skipped like comments.
+fn assert_sorted(input: Tokens<'_>) -> TokenResult<'_, bool> {
+    let (rest, text) = opt(hint)(input)?;
+    let asserted = text.is_some_and(|text| text.eq_ignore_ascii_case("assert_sorted"));
+    Ok((rest, asserted && cfg!(debug_assertions)))
+}
+
+/// `<expr> [ASC | DESC]`
+fn order_by(input: Tokens<'_>) -> TokenResult<'_, OrderBy> {
+    map(
+        pair(
+            ast::parsing::apply_grammar,
+            opt(alt((value(false, word("asc")), value(true, word("desc"))))),
+        ),
+        |(expr, descending)| OrderBy {
+            expr,
+            descending: descending.unwrap_or(false),
+        },
+    )(input)
+}
+
+/// `[INNER] JOIN <table> ON <expr>`
+fn join(input: Tokens<'_>) -> TokenResult<'_, Join> {
+    map(
+        preceded(
+            alt((
+                value((), pair(keyword("inner"), cut(keyword("join")))),
+                keyword("join"),
+            )),
+            cut(pair(
+                name("table name"),
+                preceded(keyword("on"), cut(ast::parsing::apply_grammar)),
+            )),
+        ),
+        |(table, on)| Join { table, on },
+    )(input)
+}
+
+/// `TABLESAMPLE (<percent> PERCENT) [REPEATABLE (<seed>)]`
+fn sample(input: Tokens<'_>) -> TokenResult<'_, Sample> {
+    map(
+        preceded(
+            word("tablesample"),
+            cut(pair(
+                delimited(
+                    symbol("("),
+                    terminated(percentage, word("percent")),
+                    symbol(")"),
+                ),
+                opt(preceded(
+                    word("repeatable"),
+                    cut(delimited(symbol("("), seed, symbol(")"))),
+                )),
+            )),
+        ),
+        |(percent, seed)| Sample { percent, seed },
+    )(input)
+}
+
+/// `FOR VERSION AS OF <n>`
+fn version(input: Tokens<'_>) -> TokenResult<'_, u64> {
+    preceded(
+        pair(word("for"), cut(word("version"))),
+        cut(preceded(
+            pair(keyword("as"), word("of")),
+            context("version number", row_count),
+        )),
+    )(input)
+}
+
+fn percentage(input: Tokens<'_>) -> TokenResult<'_, f64> {
+    context(
+        "percentage from 0 to 100",
+        map_opt(literal, |value| {
+            let percent = match value {
+                LiteralValue::Int(n) => n as f64,
+                LiteralValue::Float(n) => n,
+                _ => return None,
+            };
+            (0.0..=100.0).contains(&percent).then_some(percent)
+        }),
+    )(input)
+}
+
+fn seed(input: Tokens<'_>) -> TokenResult<'_, u64> {
+    context(
+        "seed",
+        map_opt(literal, |value| match value {
+            LiteralValue::Int(n) => u64::try_from(n).ok(),
+            _ => None,
+        }),
+    )(input)
+}
+
+fn row_count(input: Tokens<'_>) -> TokenResult<'_, u64> {
+    context(
+        "row count",
+        map_opt(literal, |value| match value {
+            LiteralValue::Int(n) => u64::try_from(n).ok(),
+            _ => None,
+        }),
+    )(input)
+}
+
+fn select_item(input: Tokens<'_>) -> TokenResult<'_, SelectItem> {
+    alt((
+        value(SelectItem::Wildcard, symbol("*")),
+        map(
+            pair(
+                ast::parsing::apply_grammar,
+                opt(preceded(keyword("as"), cut(name("alias")))),
+            ),
+            |(expr, alias)| SelectItem::Expr { expr, alias },
+        ),
+    ))(input)
+}
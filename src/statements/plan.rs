@@ -0,0 +1,625 @@
+//! Logical plans: the steps a SELECT is carried out in.
+//!
+//! A SELECT is planned into a tree of nodes, each reading the rows of the
+//! nodes under it. From the bottom up:
+//! - `Scan` reads a table, or `Single row` stands in for a missing FROM;
+//! - `Hash join` or `Nested loop join` pairs the rows of two scans;
+//! - `Filter` keeps the rows WHERE is true for;
+//! - `Aggregate` turns them into one row per group;
+//! - `Sort` orders them, keeping only what LIMIT and OFFSET can reach;
+//! - `Assert sorted`, under the `ASSERT_SORTED` hint of debug builds,
+//!   checks that they came out in order;
+//! - `Project` computes the select list;
+//! - `Limit` skips the OFFSET rows and stops after LIMIT more.
+//!
+//! Names are resolved while planning: columns of a join get their joined
+//! names, ORDER BY aliases are replaced by the expressions they stand for,
+//! and above an `Aggregate`, GROUP BY expressions and aggregate calls become
+//! names of its output columns. Each node can then evaluate its expressions
+//! against the rows it reads.
+//!
+//! `EXPLAIN <select>` shows the plan, one node per line, with the nodes a
+//! node reads indented under it. `EXPLAIN (FORMAT dot)` and `(FORMAT
+//! mermaid)` show the same nodes as a graph for Graphviz or Mermaid, with
+//! an edge from each node to the one that reads it.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use super::execute::ExecError;
+use super::{aggregate, join, OrderBy, Sample, Select, SelectItem};
+use crate::ast::{BinaryOp, Expr};
+use crate::catalog::{Catalog, CatalogError, Column, ColumnType, Schema};
+use crate::lexer::quote_identifier;
+use crate::literals::LiteralValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Plan {
+    /// One row with no columns.
+    SingleRow,
+    /// Every row of `table`, or a sample of them. With a `version`, the
+    /// rows it had then. With a `lookup`, a table that keeps a key on the
+    /// column reads only the rows with that value; any other scans them
+    /// all.
+    Scan {
+        table: String,
+        schema: Arc<Schema>,
+        sample: Option<Sample>,
+        version: Option<u64>,
+        lookup: Option<(String, LiteralValue)>,
+    },
+    /// Pairs of a `left` row and a `right` row for which `on` is true. With
+    /// `keys`, an expression over each side whose values must be equal, the
+    /// right rows are found through a hash table.
+    Join {
+        left: Box<Plan>,
+        right: Box<Plan>,
+        on: Expr,
+        keys: Option<(Expr, Expr)>,
+        schema: Arc<Schema>,
+    },
+    Filter {
+        input: Box<Plan>,
+        predicate: Expr,
+    },
+    /// A row per group, with a column per GROUP BY expression and then one
+    /// per aggregate call, each named by its expression.
+    Aggregate {
+        input: Box<Plan>,
+        keys: Vec<Expr>,
+        aggregates: Vec<Expr>,
+        schema: Arc<Schema>,
+    },
+    /// The rows in `order`, only the first `keep` of them when set.
+    Sort {
+        input: Box<Plan>,
+        order: Vec<OrderBy>,
+        keep: Option<usize>,
+    },
+    /// The rows unchanged, after checking that each comes no earlier in
+    /// `order` than the row before it. The keys are evaluated again from
+    /// each row and compared here, not by `Sort`'s code, so that a sort
+    /// that puts rows out of order is caught: a row out of order fails the
+    /// query with `ExecError::Unsorted`. Planned only for the
+    /// `ASSERT_SORTED` hint, which is read in debug builds alone.
+    AssertSorted {
+        input: Box<Plan>,
+        order: Vec<OrderBy>,
+    },
+    Project {
+        input: Box<Plan>,
+        items: Vec<SelectItem>,
+        schema: Arc<Schema>,
+    },
+    Limit {
+        input: Box<Plan>,
+        limit: Option<u64>,
+        offset: u64,
+    },
+}
+
+impl Plan {
+    /// The names of the columns the node produces.
+    pub fn columns(&self) -> Vec<String> {
+        match self {
+            Plan::SingleRow => Vec::new(),
+            Plan::Scan { schema, .. }
+            | Plan::Join { schema, .. }
+            | Plan::Aggregate { schema, .. }
+            | Plan::Project { schema, .. } => schema.column_names(),
+            Plan::Filter { input, .. }
+            | Plan::Sort { input, .. }
+            | Plan::AssertSorted { input, .. }
+            | Plan::Limit { input, .. } => input.columns(),
+        }
+    }
+
+    /// Whether each of [`Plan::columns`] is hidden, left out of `*`.
+    pub fn hidden(&self) -> Vec<bool> {
+        match self {
+            Plan::Scan { schema, .. }
+            | Plan::Join { schema, .. }
+            | Plan::Aggregate { schema, .. }
+            | Plan::Project { schema, .. } => schema.columns.iter().map(|c| c.hidden).collect(),
+            Plan::SingleRow => Vec::new(),
+            Plan::Filter { input, .. }
+            | Plan::Sort { input, .. }
+            | Plan::AssertSorted { input, .. }
+            | Plan::Limit { input, .. } => input.hidden(),
+        }
+    }
+
+    /// The nodes this one reads from.
+    pub fn inputs(&self) -> Vec<&Plan> {
+        match self {
+            Plan::SingleRow | Plan::Scan { .. } => Vec::new(),
+            Plan::Join { left, right, .. } => vec![left, right],
+            Plan::Filter { input, .. }
+            | Plan::Aggregate { input, .. }
+            | Plan::Sort { input, .. }
+            | Plan::AssertSorted { input, .. }
+            | Plan::Project { input, .. }
+            | Plan::Limit { input, .. } => vec![input],
+        }
+    }
+
+    /// The node's line in EXPLAIN output.
+    fn describe(&self) -> String {
+        let list = |exprs: &[Expr]| {
+            let exprs: Vec<String> = exprs.iter().map(Expr::serialize).collect();
+            exprs.join(", ")
+        };
+        match self {
+            Plan::SingleRow => "Single row".to_string(),
+            Plan::Scan {
+                table,
+                sample,
+                version,
+                lookup,
+                ..
+            } => {
+                let mut out = format!("Scan: {}", quote_identifier(table));
+                if let Some(sample) = sample {
+                    out.push_str(&format!(" {}", sample.serialize()));
+                }
+                if let Some(version) = version {
+                    out.push_str(&format!(" FOR VERSION AS OF {version}"));
+                }
+                if let Some((column, value)) = lookup {
+                    out.push_str(&format!(
+                        " (lookup {} = {})",
+                        quote_identifier(column),
+                        value.serialize()
+                    ));
+                }
+                out
+            }
+            Plan::Join { on, keys, .. } => {
+                let kind = if keys.is_some() {
+                    "Hash join"
+                } else {
+                    "Nested loop join"
+                };
+                format!("{kind}: {}", on.serialize())
+            }
+            Plan::Filter { predicate, .. } => format!("Filter: {}", predicate.serialize()),
+            Plan::Aggregate {
+                keys, aggregates, ..
+            } => {
+                let mut parts = Vec::new();
+                if !aggregates.is_empty() {
+                    parts.push(list(aggregates));
+                }
+                if !keys.is_empty() {
+                    parts.push(format!("GROUP BY {}", list(keys)));
+                }
+                format!("Aggregate: {}", parts.join(" "))
+            }
+            Plan::Sort { order, keep, .. } => {
+                let order: Vec<String> = order.iter().map(OrderBy::serialize).collect();
+                let mut out = format!("Sort: {}", order.join(", "));
+                if let Some(keep) = keep {
+                    out.push_str(&format!(" (top {keep})"));
+                }
+                out
+            }
+            Plan::AssertSorted { order, .. } => {
+                let order: Vec<String> = order.iter().map(OrderBy::serialize).collect();
+                format!("Assert sorted: {}", order.join(", "))
+            }
+            Plan::Project { items, .. } => {
+                let items: Vec<String> = items.iter().map(SelectItem::serialize).collect();
+                format!("Project: {}", items.join(", "))
+            }
+            Plan::Limit { limit, offset, .. } => {
+                let mut out = match limit {
+                    Some(limit) => format!("Limit: {limit}"),
+                    None => "Limit: all".to_string(),
+                };
+                if *offset > 0 {
+                    out.push_str(&format!(", skip {offset}"));
+                }
+                out
+            }
+        }
+    }
+
+    /// The plan as a Graphviz `digraph`.
+    pub fn to_dot(&self) -> String {
+        let mut out = "digraph plan {\n  rankdir=BT;\n  node [shape=box];\n".to_string();
+        for (id, label, reader) in self.nodes() {
+            let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("  n{id} [label=\"{label}\"];\n"));
+            if let Some(reader) = reader {
+                out.push_str(&format!("  n{id} -> n{reader};\n"));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The plan as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = "flowchart BT\n".to_string();
+        for (id, label, reader) in self.nodes() {
+            // Mermaid has no escape for a quote inside a quoted label, only
+            // this entity.
+            let label = label.replace('"', "#quot;");
+            out.push_str(&format!("  n{id}[\"{label}\"]\n"));
+            if let Some(reader) = reader {
+                out.push_str(&format!("  n{id} --> n{reader}\n"));
+            }
+        }
+        out
+    }
+
+    /// Each node, top first, with a number, its description and the number
+    /// of the node that reads it.
+    fn nodes(&self) -> Vec<(usize, String, Option<usize>)> {
+        let mut nodes = Vec::new();
+        let mut pending = vec![(self, None)];
+        while let Some((node, reader)) = pending.pop() {
+            let id = nodes.len();
+            nodes.push((id, node.describe(), reader));
+            pending.extend(
+                node.inputs()
+                    .into_iter()
+                    .rev()
+                    .map(|input| (input, Some(id))),
+            );
+        }
+        nodes
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self.describe(), indent = depth * 2)?;
+        self.inputs()
+            .into_iter()
+            .try_for_each(|input| input.write(f, depth + 1))
+    }
+}
+
+/// The plan as EXPLAIN shows it: a line per node, each ending in a newline.
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Plans `select` over the tables of `catalog`. The plan's output columns
+/// are the query's result columns.
+pub fn plan(select: &Select, catalog: &Catalog) -> Result<Plan, ExecError> {
+    let table = |name: &String| {
+        catalog
+            .table(name)
+            .ok_or_else(|| CatalogError::UnknownTable(name.clone()))
+    };
+    let (mut plan, rewritten) = match (&select.from, &select.join) {
+        (Some(from), Some(join)) => {
+            let (plan, rewritten) = join::plan(select, table(from)?, table(&join.table)?)?;
+            (plan, Cow::Owned(rewritten))
+        }
+        (Some(from), None) => {
+            // A sample is drawn from a full scan, so that the seed picks the
+            // same rows whatever the filter is. A lookup cannot tell which
+            // version a row arrived in.
+            let schema = table(from)?.schema();
+            let lookup = select
+                .filter
+                .as_ref()
+                .filter(|_| select.sample.is_none() && select.version.is_none())
+                .and_then(|filter| point_lookup(filter, schema));
+            let scan = Plan::Scan {
+                table: from.clone(),
+                schema: Arc::clone(schema),
+                sample: select.sample.clone(),
+                version: select.version,
+                lookup,
+            };
+            (scan, Cow::Borrowed(select))
+        }
+        (None, _) => (Plan::SingleRow, Cow::Borrowed(select)),
+    };
+    // Output columns are named as the query wrote them.
+    let visible: Vec<String> = plan
+        .columns()
+        .into_iter()
+        .zip(plan.hidden())
+        .filter(|(_, hidden)| !hidden)
+        .map(|(name, _)| name)
+        .collect();
+    let columns = output_columns(select, &visible)?;
+
+    if let Some(filter) = &rewritten.filter {
+        plan = Plan::Filter {
+            input: Box::new(plan),
+            predicate: filter.clone(),
+        };
+    }
+    let order_by = resolve_aliases(&rewritten.order_by, &select.items, &rewritten.items);
+    let (items, order_by) = match aggregate::plan(&rewritten.items, &rewritten.group_by, &order_by)?
+    {
+        Some(aggregation) => {
+            let names = aggregation
+                .keys
+                .iter()
+                .chain(&aggregation.aggregates)
+                .map(Expr::serialize);
+            plan = Plan::Aggregate {
+                input: Box::new(plan),
+                schema: computed(names),
+                keys: aggregation.keys,
+                aggregates: aggregation.aggregates,
+            };
+            (aggregation.items, aggregation.order_by)
+        }
+        None => (rewritten.items.clone(), order_by),
+    };
+    let offset = rewritten.offset.unwrap_or(0);
+    if !order_by.is_empty() {
+        plan = Plan::Sort {
+            input: Box::new(plan),
+            order: order_by.clone(),
+            keep: rewritten.limit.map(|n| n.saturating_add(offset) as usize),
+        };
+        if select.assert_sorted {
+            plan = Plan::AssertSorted {
+                input: Box::new(plan),
+                order: order_by,
+            };
+        }
+    }
+    plan = Plan::Project {
+        input: Box::new(plan),
+        items,
+        schema: computed(columns),
+    };
+    if rewritten.limit.is_some() || offset > 0 {
+        plan = Plan::Limit {
+            input: Box::new(plan),
+            limit: rewritten.limit,
+            offset,
+        };
+    }
+    Ok(plan)
+}
+
+/// A schema for columns the query computes. Their values may be of any
+/// type; the type given here is never read.
+fn computed(names: impl IntoIterator<Item = String>) -> Arc<Schema> {
+    let columns = names
+        .into_iter()
+        .map(|name| Column::new(&name, ColumnType::Text))
+        .collect();
+    Arc::new(Schema { columns })
+}
+
+/// The name of the output column an expression item gives.
+fn item_name(expr: &Expr, alias: &Option<String>) -> String {
+    match (alias, expr) {
+        (Some(alias), _) => alias.clone(),
+        (None, Expr::Identifier(name)) => name.clone(),
+        (None, expr) => expr.serialize(),
+    }
+}
+
+fn output_columns(select: &Select, source: &[String]) -> Result<Vec<String>, ExecError> {
+    let mut columns = Vec::new();
+    for item in &select.items {
+        match item {
+            SelectItem::Wildcard if select.from.is_none() => {
+                return Err(ExecError::WildcardWithoutTable)
+            }
+            SelectItem::Wildcard => columns.extend_from_slice(source),
+            SelectItem::Expr { expr, alias } => columns.push(item_name(expr, alias)),
+        }
+    }
+    Ok(columns)
+}
+
+/// `order_by` with each name of an output column replaced by the expression
+/// it stands for, so that it can be evaluated before the select list is.
+/// Output columns are named as in `written`, and computed as in `items`.
+fn resolve_aliases(
+    order_by: &[OrderBy],
+    written: &[SelectItem],
+    items: &[SelectItem],
+) -> Vec<OrderBy> {
+    let outputs: Vec<(String, &Expr)> = written
+        .iter()
+        .zip(items)
+        .filter_map(|pair| match pair {
+            (SelectItem::Expr { expr, alias }, SelectItem::Expr { expr: computed, .. }) => {
+                Some((item_name(expr, alias), computed))
+            }
+            _ => None,
+        })
+        .collect();
+    order_by
+        .iter()
+        .map(|item| OrderBy {
+            expr: item.expr.replace(&|e| match e {
+                Expr::Identifier(name) => outputs
+                    .iter()
+                    .find(|(output, _)| output == name)
+                    .map(|(_, expr)| (*expr).clone()),
+                _ => None,
+            }),
+            descending: item.descending,
+        })
+        .collect()
+}
+
+/// A `column = literal` test that every row passing `filter` must meet,
+/// which a table may answer from a key instead of a scan. Found at the top
+/// of the filter or under `AND`. Only a literal that the column's type
+/// takes as it is, or widened, and that is not NULL, is looked up, and it
+/// is given as the column holds it; for any other, `=` may convert or
+/// fail where a key would not, so the table is scanned.
+fn point_lookup(filter: &Expr, schema: &Schema) -> Option<(String, LiteralValue)> {
+    match filter {
+        Expr::Binary {
+            op: BinaryOp::Eq,
+            left,
+            right,
+        } => match (left.as_ref(), right.as_ref()) {
+            (Expr::Identifier(column), Expr::Literal(value))
+            | (Expr::Literal(value), Expr::Identifier(column)) => {
+                let ty = &schema.columns[schema.index_of(column)?].ty;
+                match ty.coerce(value.clone()) {
+                    Ok(LiteralValue::Null) | Err(_) => None,
+                    Ok(value) => Some((column.clone(), value)),
+                }
+            }
+            _ => None,
+        },
+        Expr::Binary {
+            op: BinaryOp::And,
+            left,
+            right,
+        } => point_lookup(left, schema).or_else(|| point_lookup(right, schema)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::{execute, parsing::parse, Statement};
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE people (name TEXT, age INT)",
+            "CREATE TABLE pets (owner TEXT, pet TEXT, age INT)",
+        ] {
+            execute(&parse(text).unwrap(), &mut catalog).unwrap();
+        }
+        catalog
+    }
+
+    fn explain(text: &str) -> Result<String, ExecError> {
+        let Ok(Statement::Select(select)) = parse(text) else {
+            panic!("{text} is not a SELECT");
+        };
+        plan(&select, &catalog()).map(|plan| plan.to_string())
+    }
+
+    #[test]
+    fn plans_each_clause_as_a_node() {
+        for (text, expected) in [
+            ("SELECT 1", "Project: 1\n  Single row\n"),
+            (
+                "SELECT name, age * 2 AS twice FROM people WHERE age > 18 \
+                 ORDER BY twice DESC LIMIT 2 OFFSET 1",
+                "Limit: 2, skip 1\n\
+                 \x20 Project: name, age * 2 AS twice\n\
+                 \x20   Sort: age * 2 DESC (top 3)\n\
+                 \x20     Filter: age > 18\n\
+                 \x20       Scan: people\n",
+            ),
+            (
+                "SELECT * FROM people WHERE name = 'bob' AND age > 1 OFFSET 2",
+                "Limit: all, skip 2\n\
+                 \x20 Project: *\n\
+                 \x20   Filter: name = \"bob\" AND age > 1\n\
+                 \x20     Scan: people (lookup name = \"bob\")\n",
+            ),
+            (
+                "SELECT name, count(*) AS n FROM people TABLESAMPLE (50 PERCENT) \
+                 GROUP BY name ORDER BY n",
+                "Project: name, `count(*)` AS n\n\
+                 \x20 Sort: `count(*)`\n\
+                 \x20   Aggregate: count(*) GROUP BY name\n\
+                 \x20     Scan: people TABLESAMPLE (50.0 PERCENT)\n",
+            ),
+            (
+                "SELECT * FROM people FOR VERSION AS OF 1 WHERE name = 'bob'",
+                "Project: *\n\
+                 \x20 Filter: name = \"bob\"\n\
+                 \x20   Scan: people FOR VERSION AS OF 1\n",
+            ),
+            (
+                "SELECT pet FROM people JOIN pets ON name = owner AND pets.age < 3",
+                "Project: pet\n\
+                 \x20 Hash join: name = owner AND pets.age < 3\n\
+                 \x20   Scan: people\n\
+                 \x20   Scan: pets\n",
+            ),
+            (
+                "SELECT pet FROM people JOIN pets ON people.age > pets.age",
+                "Project: pet\n\
+                 \x20 Nested loop join: people.age > pets.age\n\
+                 \x20   Scan: people\n\
+                 \x20   Scan: pets\n",
+            ),
+        ] {
+            assert_eq!(explain(text).unwrap(), expected, "{text}");
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn plans_the_assert_sorted_hint() {
+        assert_eq!(
+            explain("SELECT /*+ ASSERT_SORTED */ name FROM people ORDER BY age, name DESC")
+                .unwrap(),
+            "Project: name\n\
+             \x20 Assert sorted: age, name DESC\n\
+             \x20   Sort: age, name DESC\n\
+             \x20     Scan: people\n"
+        );
+    }
+
+    #[test]
+    fn renders_graphs() {
+        let Ok(Statement::Select(select)) =
+            parse("SELECT pet FROM people JOIN pets ON name = owner WHERE pet <> 'rex'")
+        else {
+            panic!("not a SELECT");
+        };
+        let plan = plan(&select, &catalog()).unwrap();
+        assert_eq!(
+            plan.to_dot(),
+            "digraph plan {\n  rankdir=BT;\n  node [shape=box];\n\
+             \x20 n0 [label=\"Project: pet\"];\n\
+             \x20 n1 [label=\"Filter: pet <> \\\"rex\\\"\"];\n\
+             \x20 n1 -> n0;\n\
+             \x20 n2 [label=\"Hash join: name = owner\"];\n\
+             \x20 n2 -> n1;\n\
+             \x20 n3 [label=\"Scan: people\"];\n\
+             \x20 n3 -> n2;\n\
+             \x20 n4 [label=\"Scan: pets\"];\n\
+             \x20 n4 -> n2;\n\
+             }\n"
+        );
+        assert_eq!(
+            plan.to_mermaid(),
+            "flowchart BT\n\
+             \x20 n0[\"Project: pet\"]\n\
+             \x20 n1[\"Filter: pet <> #quot;rex#quot;\"]\n\
+             \x20 n1 --> n0\n\
+             \x20 n2[\"Hash join: name = owner\"]\n\
+             \x20 n2 --> n1\n\
+             \x20 n3[\"Scan: people\"]\n\
+             \x20 n3 --> n2\n\
+             \x20 n4[\"Scan: pets\"]\n\
+             \x20 n4 --> n2\n"
+        );
+    }
+
+    #[test]
+    fn planning_errors() {
+        for (text, message) in [
+            ("SELECT * FROM nobody", "unknown table `nobody`"),
+            ("SELECT *", "`*` needs a FROM clause"),
+            (
+                "SELECT name FROM people GROUP BY age",
+                "column `name` must appear in GROUP BY or be used in an aggregate",
+            ),
+        ] {
+            assert_eq!(explain(text).unwrap_err().to_string(), message, "{text}");
+        }
+    }
+}
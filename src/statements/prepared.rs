@@ -0,0 +1,268 @@
+//! Prepared statements: parsed once, then run any number of times with
+//! values for their `$n` and `?` placeholders.
+//!
+//! Values are bound into a copy of the parsed statement, never spliced into
+//! query text, so a string value cannot change the statement's shape.
+
+use std::borrow::Cow;
+
+use super::execute::{execute, ExecError, Outcome};
+use super::parsing::parse;
+use super::{SelectItem, Statement};
+use crate::ast::Expr;
+use crate::catalog::Catalog;
+use crate::error::ParseError;
+use crate::literals::LiteralValue;
+use crate::macros::{MacroError, Macros};
+use crate::variables::Variables;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreparedStatement {
+    statement: Statement,
+    parameters: usize,
+}
+
+impl PreparedStatement {
+    pub fn new(statement: Statement) -> PreparedStatement {
+        let mut parameters = 0;
+        visit(&statement, &mut |expr| {
+            parameters = parameters.max(highest_parameter(expr));
+        });
+        PreparedStatement {
+            statement,
+            parameters,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<PreparedStatement, ParseError> {
+        parse(text).map(PreparedStatement::new)
+    }
+
+    pub fn statement(&self) -> &Statement {
+        &self.statement
+    }
+
+    /// The highest `$n` used: the number of values the statement needs.
+    /// Any values beyond it are ignored.
+    pub fn parameter_count(&self) -> usize {
+        self.parameters
+    }
+
+    /// The statement with `values[n - 1]` in place of each `$n`.
+    pub fn bind(&self, values: &[LiteralValue]) -> Result<Statement, ExecError> {
+        if values.len() < self.parameters {
+            return Err(ExecError::MissingParameter {
+                index: self.parameters,
+                given: values.len(),
+            });
+        }
+        if self.parameters == 0 {
+            return Ok(self.statement.clone());
+        }
+        Ok(substitute(&self.statement, &|e| match e {
+            Expr::Parameter(n) => Some(Expr::Literal(values[n - 1].clone())),
+            _ => None,
+        }))
+    }
+
+    pub fn execute(
+        &self,
+        values: &[LiteralValue],
+        catalog: &mut Catalog,
+    ) -> Result<Outcome, ExecError> {
+        execute(&self.bind(values)?, catalog)
+    }
+}
+
+fn highest_parameter(expr: &Expr) -> usize {
+    match expr {
+        Expr::Parameter(n) => *n,
+        _ => expr
+            .children()
+            .into_iter()
+            .map(highest_parameter)
+            .max()
+            .unwrap_or(0),
+    }
+}
+
+/// The statement with each `@name` replaced by the variable's value. Unset
+/// variables are left for evaluation to report.
+pub(super) fn resolve_variables<'a>(
+    statement: &'a Statement,
+    variables: &Variables,
+) -> Cow<'a, Statement> {
+    let mut uses_variables = false;
+    visit(statement, &mut |expr| {
+        uses_variables |= uses_variable(expr);
+    });
+    if !uses_variables {
+        return Cow::Borrowed(statement);
+    }
+    Cow::Owned(substitute(statement, &|e| match e {
+        Expr::Variable(name) => variables.value(name).map(Expr::Literal),
+        _ => None,
+    }))
+}
+
+/// The statement with each macro call replaced by the macro's body.
+pub(super) fn resolve_macros<'a>(
+    statement: &'a Statement,
+    macros: &Macros,
+) -> Result<Cow<'a, Statement>, MacroError> {
+    let mut result = Ok(false);
+    visit(statement, &mut |expr| {
+        if let Ok(uses_macros) = &mut result {
+            match macros.expand(expr) {
+                Ok(_) => *uses_macros |= macros.used_by(expr),
+                Err(e) => result = Err(e),
+            }
+        }
+    });
+    if !result? {
+        return Ok(Cow::Borrowed(statement));
+    }
+    Ok(Cow::Owned(substitute(statement, &|e| {
+        macros
+            .used_by(e)
+            .then(|| macros.expand(e).expect("checked above"))
+    })))
+}
+
+fn uses_variable(expr: &Expr) -> bool {
+    matches!(expr, Expr::Variable(_)) || expr.children().into_iter().any(uses_variable)
+}
+
+/// A copy of the statement in which each subexpression that `f` maps to
+/// `Some` is replaced, as by [`Expr::replace`]. A select item that changes
+/// keeps the name it was written with as its output column's name.
+fn substitute(statement: &Statement, f: &impl Fn(&Expr) -> Option<Expr>) -> Statement {
+    let mut statement = statement.clone();
+    if let Statement::Select(select) | Statement::Explain(select, _) = &mut statement {
+        for item in &mut select.items {
+            if let SelectItem::Expr { expr, alias } = item {
+                if alias.is_none() && expr.replace(f) != *expr {
+                    *alias = Some(expr.serialize());
+                }
+            }
+        }
+    }
+    map(&statement, &|expr| expr.replace(f))
+}
+
+/// Calls `f` on each top-level expression of the statement.
+pub(crate) fn visit(statement: &Statement, f: &mut impl FnMut(&Expr)) {
+    match statement {
+        Statement::Select(select) | Statement::Explain(select, _) => {
+            for item in &select.items {
+                if let SelectItem::Expr { expr, .. } = item {
+                    f(expr);
+                }
+            }
+            select.join.iter().for_each(|join| f(&join.on));
+            select.filter.iter().for_each(&mut *f);
+            select.group_by.iter().for_each(&mut *f);
+            select.order_by.iter().for_each(|item| f(&item.expr));
+        }
+        Statement::CreateTable { columns, .. } | Statement::CreateExternalTable { columns, .. } => {
+            for column in columns {
+                column.generated.iter().for_each(|g| f(&g.expr));
+            }
+        }
+        Statement::Insert { rows, .. } => rows.iter().flatten().for_each(f),
+        // A macro's body is expanded where it is called.
+        Statement::CreateMacro { .. }
+        | Statement::AddEnumLabel { .. }
+        | Statement::Set { .. }
+        | Statement::Show(_)
+        | Statement::ShowSettings => {}
+    }
+}
+
+/// A copy of the statement with `f` applied to each top-level expression.
+pub(super) fn map(statement: &Statement, f: &impl Fn(&Expr) -> Expr) -> Statement {
+    let mut statement = statement.clone();
+    match &mut statement {
+        Statement::Select(select) | Statement::Explain(select, _) => {
+            for item in &mut select.items {
+                if let SelectItem::Expr { expr, .. } = item {
+                    *expr = f(expr);
+                }
+            }
+            if let Some(join) = &mut select.join {
+                join.on = f(&join.on);
+            }
+            if let Some(filter) = &mut select.filter {
+                *filter = f(filter);
+            }
+            for key in &mut select.group_by {
+                *key = f(key);
+            }
+            for item in &mut select.order_by {
+                item.expr = f(&item.expr);
+            }
+        }
+        Statement::CreateTable { columns, .. } | Statement::CreateExternalTable { columns, .. } => {
+            for generated in columns.iter_mut().filter_map(|c| c.generated.as_mut()) {
+                generated.expr = f(&generated.expr);
+            }
+        }
+        Statement::Insert { rows, .. } => {
+            for expr in rows.iter_mut().flatten() {
+                *expr = f(expr);
+            }
+        }
+        // A macro's body is expanded where it is called.
+        Statement::CreateMacro { .. }
+        | Statement::AddEnumLabel { .. }
+        | Statement::Set { .. }
+        | Statement::Show(_)
+        | Statement::ShowSettings => {}
+    }
+    statement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LiteralValue::{Int, String as Str};
+
+    #[test]
+    fn numbers_placeholders_and_binds_values() {
+        let prepared = PreparedStatement::parse("SELECT ? + ?, $1 * 10 WHERE ? > $2").unwrap();
+        assert_eq!(prepared.parameter_count(), 3);
+        assert_eq!(
+            prepared.statement().serialize(),
+            "SELECT $1 + $2, $1 * 10 WHERE $3 > $2"
+        );
+        let bound = prepared.bind(&[Int(1), Int(2), Int(3), Int(4)]).unwrap();
+        assert_eq!(
+            bound.serialize(),
+            "SELECT 1 + 2 AS `$1 + $2`, 1 * 10 AS `$1 * 10` WHERE 3 > 2"
+        );
+        assert_eq!(
+            prepared.bind(&[Int(1)]).unwrap_err().to_string(),
+            "statement uses $3 but 1 parameter values were given"
+        );
+    }
+
+    #[test]
+    fn runs_again_with_new_values() {
+        let mut catalog = Catalog::new();
+        let create = PreparedStatement::parse("CREATE TABLE t (name TEXT, n INT)").unwrap();
+        create.execute(&[], &mut catalog).unwrap();
+        let insert = PreparedStatement::parse("INSERT INTO t VALUES ($1, $2)").unwrap();
+        for (name, n) in [("a", 1), ("b'); DROP", 2)] {
+            let values = [Str(name.to_string()), Int(n)];
+            assert_eq!(
+                insert.execute(&values, &mut catalog),
+                Ok(Outcome::Inserted(1))
+            );
+        }
+        let select = PreparedStatement::parse("SELECT name FROM t WHERE n = ?").unwrap();
+        let Ok(Outcome::Rows(result)) = select.execute(&[Int(2)], &mut catalog) else {
+            panic!("expected rows");
+        };
+        assert_eq!(result.rows[0][0], Str("b'); DROP".to_string()));
+    }
+}
@@ -0,0 +1,254 @@
+//! Name resolution as a report: what each name in a query refers to, by
+//! the rules planning uses (see [`plan`](super::plan)), for debugging
+//! queries whose names do not mean what was meant.
+//!
+//! Each row gives a name where it is written (its scope: `FROM`, `JOIN`,
+//! `ON`, `item N` of the select list, `WHERE`, `GROUP BY` or `ORDER BY`),
+//! its kind and what it refers to:
+//!
+//! - `table`: a table of the catalog;
+//! - `column`: a column of a FROM or JOIN table, as `<table>.<column>`;
+//! - `alias`: in ORDER BY, an output column, as the select item naming it;
+//! - `function`, `aggregate` or `macro`: a call;
+//! - `variable`: a session variable, with its value if it is set;
+//! - `wildcard`: `*`, with the tables it stands for;
+//! - `ambiguous`: a column of both joined tables, with both;
+//! - `unknown`: nothing, so running the query would fail.
+//!
+//! Names are reported as written, before macros are expanded.
+
+use super::execute::ResultSet;
+use super::types::ValueType;
+use super::{Select, SelectItem, Statement};
+use crate::ast::Expr;
+use crate::catalog::{Catalog, ColumnType};
+use crate::functions;
+use crate::literals::LiteralValue;
+use crate::table::Values;
+
+/// The names of `statement`, if it is a query, as rows of `scope`, `name`,
+/// `kind` and `target`. Other statements have none.
+pub fn resolve(statement: &Statement, catalog: &Catalog) -> ResultSet {
+    let mut resolver = Resolver {
+        catalog,
+        tables: Vec::new(),
+        rows: Vec::new(),
+    };
+    if let Statement::Select(select) | Statement::Explain(select, _) = statement {
+        resolver.select(select);
+    }
+    ResultSet {
+        columns: ["scope", "name", "kind", "target"]
+            .map(str::to_string)
+            .to_vec(),
+        types: vec![
+            ValueType::of(ColumnType::Text, false),
+            ValueType::of(ColumnType::Text, false),
+            ValueType::of(ColumnType::Text, false),
+            ValueType::of(ColumnType::Text, true),
+        ],
+        rows: resolver.rows,
+    }
+}
+
+struct Resolver<'a> {
+    catalog: &'a Catalog,
+    /// The FROM and JOIN tables that exist, with their columns.
+    tables: Vec<(String, Vec<String>)>,
+    rows: Vec<Values>,
+}
+
+impl Resolver<'_> {
+    fn push(&mut self, scope: &str, name: &str, kind: &str, target: Option<String>) {
+        let text = |s: &str| LiteralValue::String(s.to_string());
+        self.rows.push(Values::from([
+            text(scope),
+            text(name),
+            text(kind),
+            target.map_or(LiteralValue::Null, LiteralValue::String),
+        ]));
+    }
+
+    fn select(&mut self, select: &Select) {
+        let sources = select
+            .from
+            .iter()
+            .map(|from| ("FROM", from))
+            .chain(select.join.iter().map(|join| ("JOIN", &join.table)));
+        for (scope, name) in sources {
+            match self.catalog.table(name) {
+                Some(table) => {
+                    let columns = table.schema().columns.iter();
+                    let columns = columns.map(|c| c.name.clone()).collect();
+                    self.tables.push((name.clone(), columns));
+                    self.push(scope, name, "table", Some(name.clone()));
+                }
+                None => self.push(scope, name, "unknown", None),
+            }
+        }
+        if let Some(join) = &select.join {
+            self.expr("ON", &join.on, &[]);
+        }
+        let mut outputs = Vec::new();
+        for (i, item) in select.items.iter().enumerate() {
+            let scope = format!("item {}", i + 1);
+            match item {
+                SelectItem::Wildcard => {
+                    let tables: Vec<&str> = self.tables.iter().map(|(t, _)| t.as_str()).collect();
+                    if select.from.is_some() {
+                        self.push(&scope, "*", "wildcard", Some(tables.join(", ")));
+                    } else {
+                        self.push(&scope, "*", "unknown", None);
+                    }
+                }
+                SelectItem::Expr { expr, alias } => {
+                    self.expr(&scope, expr, &[]);
+                    let name = match (alias, expr) {
+                        (Some(alias), _) => alias.clone(),
+                        (None, Expr::Identifier(name)) => name.clone(),
+                        (None, expr) => expr.serialize(),
+                    };
+                    outputs.push((name, scope));
+                }
+            }
+        }
+        if let Some(filter) = &select.filter {
+            self.expr("WHERE", filter, &[]);
+        }
+        for key in &select.group_by {
+            self.expr("GROUP BY", key, &[]);
+        }
+        for item in &select.order_by {
+            self.expr("ORDER BY", &item.expr, &outputs);
+        }
+    }
+
+    /// Reports the names in `expr`. A name in `aliases` is an output
+    /// column, named by the select item given with it.
+    fn expr(&mut self, scope: &str, expr: &Expr, aliases: &[(String, String)]) {
+        match expr {
+            Expr::Identifier(name) => {
+                if let Some((_, item)) = aliases.iter().find(|(output, _)| output == name) {
+                    self.push(scope, name, "alias", Some(item.clone()));
+                    return;
+                }
+                let (kind, target) = self.column(name);
+                self.push(scope, name, kind, target);
+            }
+            Expr::Variable(name) => {
+                let value = self.catalog.variables().get(name);
+                self.push(
+                    scope,
+                    &format!("@{name}"),
+                    "variable",
+                    value.map(LiteralValue::serialize),
+                );
+            }
+            Expr::Call { name, args } => {
+                let kind = if functions::is_aggregate(name) {
+                    "aggregate"
+                } else if functions::exists(name) {
+                    "function"
+                } else if self.catalog.macros().contains(name) {
+                    "macro"
+                } else {
+                    "unknown"
+                };
+                self.push(scope, name, kind, None);
+                for arg in args {
+                    self.expr(scope, arg, aliases);
+                }
+            }
+            _ => {
+                for child in expr.children() {
+                    self.expr(scope, child, aliases);
+                }
+            }
+        }
+    }
+
+    /// What a column name refers to, as the join's names resolve it: a
+    /// `<table>.<column>` name picks the table, and a bare name must be a
+    /// column of only one of them.
+    fn column(&self, name: &str) -> (&'static str, Option<String>) {
+        if let Some((table, column)) = name.split_once('.') {
+            let found = self
+                .tables
+                .iter()
+                .any(|(t, columns)| t == table && columns.iter().any(|c| c == column));
+            if found {
+                return ("column", Some(name.to_string()));
+            }
+        }
+        let found: Vec<String> = self
+            .tables
+            .iter()
+            .filter(|(_, columns)| columns.iter().any(|c| c == name))
+            .map(|(table, _)| format!("{table}.{name}"))
+            .collect();
+        match found.len() {
+            0 => ("unknown", None),
+            1 => ("column", found.into_iter().next()),
+            _ => ("ambiguous", Some(found.join(", "))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::execute;
+    use crate::statements::parsing::parse;
+
+    #[test]
+    fn reports_what_each_name_refers_to() {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE a (id INT, x INT)",
+            "CREATE TABLE b (id INT, a_id INT)",
+            "CREATE MACRO double(v) AS v * 2",
+            "SET low = 1",
+        ] {
+            execute(&parse(text).unwrap(), &mut catalog).unwrap();
+        }
+        let statement = parse(
+            "SELECT a.id, double(x) AS d, count(*), nope(id) FROM a JOIN b ON a.id = a_id \
+             WHERE x > @low GROUP BY a.id, x ORDER BY d, y",
+        )
+        .unwrap();
+        let rows: Vec<String> = resolve(&statement, &catalog)
+            .rows
+            .iter()
+            .map(|row| {
+                let values: Vec<String> = row.iter().map(LiteralValue::serialize).collect();
+                values.join(" | ")
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "\"FROM\" | \"a\" | \"table\" | \"a\"",
+                "\"JOIN\" | \"b\" | \"table\" | \"b\"",
+                "\"ON\" | \"a.id\" | \"column\" | \"a.id\"",
+                "\"ON\" | \"a_id\" | \"column\" | \"b.a_id\"",
+                "\"item 1\" | \"a.id\" | \"column\" | \"a.id\"",
+                "\"item 2\" | \"double\" | \"macro\" | null",
+                "\"item 2\" | \"x\" | \"column\" | \"a.x\"",
+                "\"item 3\" | \"count\" | \"aggregate\" | null",
+                "\"item 4\" | \"nope\" | \"unknown\" | null",
+                "\"item 4\" | \"id\" | \"ambiguous\" | \"a.id, b.id\"",
+                "\"WHERE\" | \"x\" | \"column\" | \"a.x\"",
+                "\"WHERE\" | \"@low\" | \"variable\" | \"1\"",
+                "\"GROUP BY\" | \"a.id\" | \"column\" | \"a.id\"",
+                "\"GROUP BY\" | \"x\" | \"column\" | \"a.x\"",
+                "\"ORDER BY\" | \"d\" | \"alias\" | \"item 2\"",
+                "\"ORDER BY\" | \"y\" | \"unknown\" | null",
+            ]
+        );
+        assert!(
+            resolve(&parse("INSERT INTO a VALUES (1, 2)").unwrap(), &catalog)
+                .rows
+                .is_empty()
+        );
+    }
+}
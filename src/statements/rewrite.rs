@@ -0,0 +1,215 @@
+//! Statement rewrites registered by an embedder, applied by
+//! [`execute`](super::execute) before a statement is planned or run: to
+//! add a tenant's filter to every query of a shared table, say, or to move
+//! statements from a legacy table name to a new one.
+//!
+//! A rewrite is a function from a statement to the statement to run instead,
+//! or `None` when it has nothing to change. Rewrites run in ascending order
+//! of the number they were registered with, and in registration order among
+//! equal numbers; each sees the output of the ones before it. An EXPLAINed
+//! query is rewritten as the query itself would be.
+//!
+//! Every rewrite that changes a statement is reported to the tracers, with
+//! the statement before and after it.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use super::Statement;
+
+/// One rewrite's change to a statement, as its tracers see it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Applied {
+    /// The name the rewrite was registered under.
+    pub rewrite: String,
+    pub before: Statement,
+    pub after: Statement,
+}
+
+type RewriteFn = Box<dyn Fn(&Statement) -> Option<Statement>>;
+type TraceFn = Box<dyn Fn(&Applied)>;
+
+struct Rewrite {
+    name: String,
+    order: i32,
+    f: RewriteFn,
+}
+
+/// The rewrites and tracers registered with a catalog.
+#[derive(Default)]
+pub struct Rewrites {
+    /// Kept in the order they run.
+    rewrites: Vec<Rewrite>,
+    tracers: Vec<TraceFn>,
+}
+
+impl Rewrites {
+    /// Adds a rewrite that runs after those with a lower `order`, and after
+    /// those already registered with the same one. A rewrite registered
+    /// under a name already in use replaces it.
+    pub fn register(
+        &mut self,
+        name: &str,
+        order: i32,
+        f: impl Fn(&Statement) -> Option<Statement> + 'static,
+    ) {
+        self.remove(name);
+        let position = self.rewrites.partition_point(|r| r.order <= order);
+        self.rewrites.insert(
+            position,
+            Rewrite {
+                name: name.to_string(),
+                order,
+                f: Box::new(f),
+            },
+        );
+    }
+
+    /// Removes the named rewrite, returning whether there was one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.rewrites.len();
+        self.rewrites.retain(|r| r.name != name);
+        self.rewrites.len() < before
+    }
+
+    /// The names of the rewrites, in the order they run.
+    pub fn names(&self) -> Vec<&str> {
+        self.rewrites.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    /// Calls `f` with each change a rewrite makes from now on.
+    pub fn trace(&mut self, f: impl Fn(&Applied) + 'static) {
+        self.tracers.push(Box::new(f));
+    }
+
+    /// `statement` after every rewrite, and the changes they made.
+    pub fn apply<'a>(&self, statement: &'a Statement) -> (Cow<'a, Statement>, Vec<Applied>) {
+        let mut current = Cow::Borrowed(statement);
+        let mut applied = Vec::new();
+        for rewrite in &self.rewrites {
+            let Some(after) = self.run(rewrite, &current) else {
+                continue;
+            };
+            if after == *current {
+                continue;
+            }
+            let change = Applied {
+                rewrite: rewrite.name.clone(),
+                before: current.into_owned(),
+                after: after.clone(),
+            };
+            self.tracers.iter().for_each(|tracer| tracer(&change));
+            applied.push(change);
+            current = Cow::Owned(after);
+        }
+        (current, applied)
+    }
+
+    fn run(&self, rewrite: &Rewrite, statement: &Statement) -> Option<Statement> {
+        match statement {
+            Statement::Explain(select, format) => {
+                match (rewrite.f)(&Statement::Select(select.clone()))? {
+                    Statement::Select(select) => Some(Statement::Explain(select, *format)),
+                    other => Some(other),
+                }
+            }
+            statement => (rewrite.f)(statement),
+        }
+    }
+}
+
+impl fmt::Debug for Rewrites {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rewrites")
+            .field("rewrites", &self.names())
+            .field("tracers", &self.tracers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::statements::{execute, parsing::parse, Outcome};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Renames table `from` to `to` in SELECTs and INSERTs.
+    fn rename(from: &'static str, to: &'static str) -> impl Fn(&Statement) -> Option<Statement> {
+        move |statement| {
+            let mut statement = statement.clone();
+            let table = match &mut statement {
+                Statement::Select(select) => select.from.as_mut()?,
+                Statement::Insert { table, .. } => table,
+                _ => return None,
+            };
+            if table != from {
+                return None;
+            }
+            *table = to.to_string();
+            Some(statement)
+        }
+    }
+
+    #[test]
+    fn runs_in_order_and_traces_changes() {
+        let mut rewrites = Rewrites::default();
+        rewrites.register("b_to_c", 2, rename("b", "c"));
+        rewrites.register("a_to_b", 1, rename("a", "b"));
+        rewrites.register("unused", 1, rename("z", "y"));
+        assert_eq!(rewrites.names(), vec!["a_to_b", "unused", "b_to_c"]);
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let traced = Rc::clone(&seen);
+        rewrites.trace(move |applied| {
+            traced.borrow_mut().push(format!(
+                "{}: {} => {}",
+                applied.rewrite,
+                applied.before.serialize(),
+                applied.after.serialize()
+            ));
+        });
+        let statement = parse("SELECT x FROM a").unwrap();
+        let (rewritten, applied) = rewrites.apply(&statement);
+        assert_eq!(rewritten.serialize(), "SELECT x FROM c");
+        assert_eq!(applied.len(), 2);
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                "a_to_b: SELECT x FROM a => SELECT x FROM b",
+                "b_to_c: SELECT x FROM b => SELECT x FROM c",
+            ]
+        );
+
+        rewrites.register("b_to_c", 0, rename("b", "d"));
+        assert_eq!(rewrites.names(), vec!["b_to_c", "a_to_b", "unused"]);
+        assert!(rewrites.remove("unused"));
+        assert!(!rewrites.remove("unused"));
+        let (rewritten, _) = rewrites.apply(&statement);
+        assert_eq!(rewritten.serialize(), "SELECT x FROM b");
+    }
+
+    #[test]
+    fn execute_rewrites_before_planning() {
+        let mut catalog = Catalog::new();
+        catalog
+            .rewrites_mut()
+            .register("legacy_names", 0, rename("old", "new"));
+        for text in [
+            "CREATE TABLE new (x INT)",
+            "INSERT INTO old VALUES (1), (2)",
+        ] {
+            execute(&parse(text).unwrap(), &mut catalog).unwrap();
+        }
+        let Ok(Outcome::Rows(result)) = execute(&parse("SELECT x FROM old").unwrap(), &mut catalog)
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(
+            execute(&parse("EXPLAIN SELECT x FROM old").unwrap(), &mut catalog),
+            Ok(Outcome::Explained("Project: x\n  Scan: new\n".to_string()))
+        );
+    }
+}
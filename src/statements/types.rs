@@ -0,0 +1,286 @@
+//! The types of a query's output columns, worked out from the plan before
+//! any row is read.
+//!
+//! A table column has its declared type, and may hold NULL. A computed
+//! column has the type its expression gives, by the rules evaluation
+//! follows: INT arithmetic stays INT, arithmetic with a FLOAT is FLOAT,
+//! comparisons and tests are BOOL, and so on. It may be NULL when any of
+//! its operands may be, except that `IS NULL` and `COUNT` never are, and
+//! the other aggregates and `REGEXP_EXTRACT` always may be.
+//!
+//! A type is unknown when the expression does not settle it, as for the
+//! NULL literal, an unknown name or arithmetic over a column of unknown
+//! type.
+
+use super::plan::Plan;
+use super::SelectItem;
+use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::catalog::{ColumnType, Schema};
+use crate::literals::LiteralValue;
+
+/// What is known of the values of a column before they are read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueType {
+    /// `None` when unknown.
+    pub ty: Option<ColumnType>,
+    /// Whether the column may hold NULL.
+    pub nullable: bool,
+}
+
+impl ValueType {
+    /// Nothing known: any type, and maybe NULL.
+    pub fn unknown() -> ValueType {
+        ValueType {
+            ty: None,
+            nullable: true,
+        }
+    }
+
+    pub(super) fn of(ty: ColumnType, nullable: bool) -> ValueType {
+        ValueType {
+            ty: Some(ty),
+            nullable,
+        }
+    }
+}
+
+/// The types of the columns `plan` produces.
+pub fn plan_types(plan: &Plan) -> Vec<ValueType> {
+    let input = |input: &Plan| -> Vec<(String, ValueType)> {
+        input.columns().into_iter().zip(plan_types(input)).collect()
+    };
+    match plan {
+        Plan::SingleRow => Vec::new(),
+        Plan::Scan { schema, .. } | Plan::Join { schema, .. } => table_types(schema),
+        Plan::Filter { input, .. }
+        | Plan::Sort { input, .. }
+        | Plan::AssertSorted { input, .. }
+        | Plan::Limit { input, .. } => plan_types(input),
+        Plan::Aggregate {
+            input: from,
+            keys,
+            aggregates,
+            ..
+        } => {
+            let columns = input(from);
+            keys.iter()
+                .chain(aggregates)
+                .map(|expr| expr_type(expr, &columns))
+                .collect()
+        }
+        Plan::Project {
+            input: from, items, ..
+        } => {
+            let columns = input(from);
+            let hidden = from.hidden();
+            items
+                .iter()
+                .flat_map(|item| match item {
+                    SelectItem::Wildcard => columns
+                        .iter()
+                        .zip(&hidden)
+                        .filter(|(_, hidden)| !**hidden)
+                        .map(|((_, ty), _)| ty.clone())
+                        .collect(),
+                    SelectItem::Expr { expr, .. } => vec![expr_type(expr, &columns)],
+                })
+                .collect()
+        }
+    }
+}
+
+/// The type of `expr` over a row of `schema`, as for a generated column.
+pub(crate) fn expr_type_in(expr: &Expr, schema: &Schema) -> ValueType {
+    let columns: Vec<(String, ValueType)> = schema
+        .column_names()
+        .into_iter()
+        .zip(table_types(schema))
+        .collect();
+    expr_type(expr, &columns)
+}
+
+fn table_types(schema: &Schema) -> Vec<ValueType> {
+    schema
+        .columns
+        .iter()
+        .map(|column| ValueType::of(column.ty.clone(), true))
+        .collect()
+}
+
+/// The type of `expr` over rows of `columns`.
+fn expr_type(expr: &Expr, columns: &[(String, ValueType)]) -> ValueType {
+    let of = |expr: &Expr| expr_type(expr, columns);
+    match expr {
+        Expr::Literal(value) => literal_type(value),
+        Expr::Identifier(name) => columns
+            .iter()
+            .find(|(column, _)| column == name)
+            .map_or_else(ValueType::unknown, |(_, ty)| ty.clone()),
+        Expr::Parameter(_) | Expr::Variable(_) => ValueType::unknown(),
+        Expr::Unary { op, operand } => {
+            let operand = of(operand);
+            let ty = match (op, operand.ty) {
+                (UnaryOp::Not, _) => Some(ColumnType::Bool),
+                (_, ty @ Some(ColumnType::Int | ColumnType::Float)) => ty,
+                _ => None,
+            };
+            ValueType {
+                ty,
+                nullable: operand.nullable,
+            }
+        }
+        Expr::Binary { op, left, right } => {
+            let (left, right) = (of(left), of(right));
+            let ty = match op {
+                BinaryOp::Concat => Some(ColumnType::Text),
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+                    match (left.ty, right.ty) {
+                        (Some(ColumnType::Int), Some(ColumnType::Int)) => Some(ColumnType::Int),
+                        (
+                            Some(ColumnType::Int | ColumnType::Float),
+                            Some(ColumnType::Int | ColumnType::Float),
+                        ) => Some(ColumnType::Float),
+                        _ => None,
+                    }
+                }
+                _ => Some(ColumnType::Bool),
+            };
+            ValueType {
+                ty,
+                nullable: left.nullable || right.nullable,
+            }
+        }
+        Expr::Call { name, args } => {
+            let nullable = args.iter().any(|arg| of(arg).nullable);
+            let first = || args.first().map_or_else(ValueType::unknown, of).ty;
+            match name.to_ascii_lowercase().as_str() {
+                "count" => ValueType::of(ColumnType::Int, false),
+                "sum" | "min" | "max" => ValueType {
+                    ty: first(),
+                    nullable: true,
+                },
+                "avg" => ValueType::of(ColumnType::Float, true),
+                "regexp_extract" => ValueType::of(ColumnType::Text, true),
+                "regexp_replace" | "host" => ValueType::of(ColumnType::Text, nullable),
+                "inet" | "network" => ValueType::of(ColumnType::Inet, nullable),
+                "masklen" => ValueType::of(ColumnType::Int, nullable),
+                "split" => ValueType::of(ColumnType::List, nullable),
+                "point" => ValueType::of(ColumnType::Point, nullable),
+                "st_distance" => ValueType::of(ColumnType::Float, nullable),
+                "st_within_box" => ValueType::of(ColumnType::Bool, nullable),
+                _ => ValueType { ty: None, nullable },
+            }
+        }
+        Expr::IsNull { .. } => ValueType::of(ColumnType::Bool, false),
+        Expr::InList { operand, list, .. } => ValueType::of(
+            ColumnType::Bool,
+            std::iter::once(&**operand)
+                .chain(list)
+                .any(|expr| of(expr).nullable),
+        ),
+        Expr::Like {
+            operand, pattern, ..
+        } => ValueType::of(
+            ColumnType::Bool,
+            of(operand).nullable || of(pattern).nullable,
+        ),
+    }
+}
+
+fn literal_type(value: &LiteralValue) -> ValueType {
+    let ty = match value {
+        LiteralValue::Int(_) => ColumnType::Int,
+        LiteralValue::Float(_) => ColumnType::Float,
+        LiteralValue::String(_) => ColumnType::Text,
+        LiteralValue::Bool(_) => ColumnType::Bool,
+        LiteralValue::Inet(_) => ColumnType::Inet,
+        LiteralValue::Point(_) => ColumnType::Point,
+        LiteralValue::List(_) => ColumnType::List,
+        LiteralValue::Null => return ValueType::unknown(),
+    };
+    ValueType::of(ty, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::statements::parsing::parse;
+    use crate::statements::{execute, plan, Statement};
+
+    fn types(catalog: &Catalog, text: &str) -> Vec<String> {
+        let Statement::Select(select) = parse(text).unwrap() else {
+            panic!("not a query: {text}")
+        };
+        plan(&select, catalog)
+            .map(|plan| plan_types(&plan))
+            .unwrap()
+            .iter()
+            .map(|value| {
+                let ty = value
+                    .ty
+                    .as_ref()
+                    .map_or("?".to_string(), ColumnType::to_string);
+                if value.nullable {
+                    ty
+                } else {
+                    ty + " NOT NULL"
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn types_output_columns() {
+        let mut catalog = Catalog::new();
+        for text in [
+            "CREATE TABLE t (id INT, name TEXT, score FLOAT)",
+            "CREATE TABLE u (t_id INT, kind ENUM('a', 'b'))",
+        ] {
+            execute(&parse(text).unwrap(), &mut catalog).unwrap();
+        }
+        assert_eq!(
+            types(
+                &catalog,
+                "SELECT id, -id, id % 2, id / 2.0, 1 + 2, name || 'x', score > 1, \
+                 name IS NULL, 'a', NULL, inet('10.0.0.1'), point(1, 2), split(name, ','), nope(1) \
+                 FROM t ORDER BY id LIMIT 1"
+            ),
+            vec![
+                "INT",
+                "INT",
+                "INT",
+                "FLOAT",
+                "INT NOT NULL",
+                "TEXT",
+                "BOOL",
+                "BOOL NOT NULL",
+                "TEXT NOT NULL",
+                "?",
+                "INET NOT NULL",
+                "POINT NOT NULL",
+                "LIST",
+                "? NOT NULL",
+            ]
+        );
+        assert_eq!(
+            types(
+                &catalog,
+                "SELECT name, count(*), sum(id), avg(id), max(name) FROM t GROUP BY name"
+            ),
+            vec!["TEXT", "INT NOT NULL", "INT", "FLOAT", "TEXT"]
+        );
+        assert_eq!(
+            types(&catalog, "SELECT kind FROM u"),
+            vec!["ENUM(\"a\", \"b\")"]
+        );
+        // A join keeps ENUM columns' types, so they still order by position.
+        assert_eq!(
+            types(
+                &catalog,
+                "SELECT *, kind IN ('a') FROM t JOIN u ON id = t_id"
+            ),
+            vec!["INT", "TEXT", "FLOAT", "INT", "ENUM(\"a\", \"b\")", "BOOL"]
+        );
+    }
+}
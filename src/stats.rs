@@ -0,0 +1,87 @@
+//! Per-statement resource usage, reported by `--stats json`.
+//!
+//! Each statement gives one JSON object on its own line, so the output can be
+//! appended to a file and read back as JSON Lines.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::statements::PlanFingerprint;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryStats {
+    /// Position of the statement among all those run, from 1.
+    pub index: usize,
+    /// Time spent parsing, and executing if parsing succeeded.
+    pub parse: Duration,
+    pub execute: Duration,
+    /// Rows returned by a query or written by an INSERT.
+    pub rows: usize,
+    /// Bytes of statement text read and of output printed.
+    pub bytes_read: usize,
+    pub bytes_written: usize,
+    /// The exit code the statement's failure maps to, or 0.
+    pub exit_code: i32,
+    /// Shared by runs of the statement with other values; see
+    /// [`PlanFingerprint`]. None if the statement did not parse.
+    pub fingerprint: Option<PlanFingerprint>,
+}
+
+impl QueryStats {
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        let fields = [
+            ("statement", self.index.to_string()),
+            ("parse_us", self.parse.as_micros().to_string()),
+            ("execute_us", self.execute.as_micros().to_string()),
+            ("rows", self.rows.to_string()),
+            ("bytes_read", self.bytes_read.to_string()),
+            ("bytes_written", self.bytes_written.to_string()),
+            ("exit_code", self.exit_code.to_string()),
+            (
+                "fingerprint",
+                self.fingerprint
+                    .map_or("null".to_string(), |f| format!("\"{f}\"")),
+            ),
+        ];
+        for (i, (name, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, "\"{name}\":{value}").unwrap();
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// Runs `f`, returning its result and how long it took.
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let started = Instant::now();
+    let result = f();
+    (result, started.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_one_json_object() {
+        let stats = QueryStats {
+            index: 2,
+            parse: Duration::from_micros(15),
+            execute: Duration::from_millis(3),
+            rows: 4,
+            bytes_read: 20,
+            bytes_written: 31,
+            exit_code: 0,
+            fingerprint: None,
+        };
+        assert_eq!(
+            stats.to_json(),
+            "{\"statement\":2,\"parse_us\":15,\"execute_us\":3000,\"rows\":4,\
+             \"bytes_read\":20,\"bytes_written\":31,\"exit_code\":0,\"fingerprint\":null}"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Tables as the executor sees them: a schema and rows that can be
+//! appended, scanned and fetched by position.
+//!
+//! The executor only uses the [`Table`] trait, so a table may keep its rows
+//! anywhere. [`MemoryTable`] keeps them in a `Vec`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::catalog::{CatalogError, ColumnType, GeneratedKind, Schema};
+use crate::eval::Bindings;
+use crate::literals::{HashKey, LiteralValue};
+
+/// The values of a row, shared by reference count so that passing a row
+/// through a query never copies them.
+pub type Values = Arc<[LiteralValue]>;
+
+/// A row together with the schema naming its values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    schema: Arc<Schema>,
+    values: Values,
+}
+
+impl Row {
+    /// `values` must have one entry per column of `schema`.
+    pub fn new(schema: Arc<Schema>, values: Values) -> Row {
+        debug_assert_eq!(schema.columns.len(), values.len());
+        Row { schema, values }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn values(&self) -> &Values {
+        &self.values
+    }
+
+    /// The value in the named column.
+    pub fn get(&self, column: &str) -> Option<&LiteralValue> {
+        Some(&self.values[self.schema.index_of(column)?])
+    }
+}
+
+/// A row serializes as a map from column name to value, in column order.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Row {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for (column, value) in self.schema.columns.iter().zip(self.values.iter()) {
+            map.serialize_entry(&column.name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Bindings for Row {
+    fn lookup(&self, name: &str) -> Option<LiteralValue> {
+        self.get(name).cloned()
+    }
+
+    fn labels(&self, name: &str) -> Option<&[String]> {
+        match &self.schema.columns[self.schema.index_of(name)?].ty {
+            ColumnType::Enum(labels) => Some(labels),
+            _ => None,
+        }
+    }
+}
+
+pub trait Table: fmt::Debug {
+    fn schema(&self) -> &Arc<Schema>;
+
+    /// Replaces the schema. The new one must read the stored rows the same
+    /// way as the old; `Catalog::add_enum_label` only ever extends it.
+    fn set_schema(&mut self, schema: Schema);
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether rows cannot be added. `Catalog::insert` refuses to insert
+    /// into a read-only table, and never calls its `append`.
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    /// Adds a row at the end. The values must already fit the schema;
+    /// `Catalog::insert` checks them before calling this.
+    fn append(&mut self, values: Values);
+
+    /// Every row, in insertion order.
+    fn scan(&self) -> Box<dyn Iterator<Item = Row> + '_>;
+
+    /// Every row, or an error for a row that cannot be read, as for a
+    /// table that reads a file on each scan. Queries scan through this; a
+    /// table whose rows cannot fail to read need not implement it.
+    fn try_scan(&self) -> Box<dyn Iterator<Item = Result<Row, CatalogError>> + '_> {
+        Box::new(self.scan().map(Ok))
+    }
+
+    /// The row at `position` in insertion order.
+    fn get(&self, position: usize) -> Option<Row>;
+
+    /// The rows whose `column` equals `value`, for a table that can find
+    /// them without reading every row, such as by a key. `None` means the
+    /// caller has to scan. The rows may still be filtered further by the
+    /// caller, so a table may give more rows than match, but never fewer.
+    ///
+    /// The executor only asks with a `value` of the column's own type, as
+    /// stored (an INT for a FLOAT column is already widened), and never
+    /// NULL, so matching by [`LiteralValue::hash_key`] finds every row that
+    /// `=` would.
+    fn lookup(
+        &self,
+        _column: &str,
+        _value: &LiteralValue,
+    ) -> Option<Box<dyn Iterator<Item = Row> + '_>> {
+        None
+    }
+}
+
+/// A table held entirely in memory. Its first column is its key: rows are
+/// indexed by its value, so that [`Table::lookup`] on it reads only the
+/// rows that have it, unless it is an ENUM or virtual column, whose stored
+/// values are not the ones queries compare.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTable {
+    schema: Arc<Schema>,
+    rows: Vec<Values>,
+    /// The positions of the rows with each value of the first column.
+    index: HashMap<HashKey, Vec<usize>>,
+}
+
+impl MemoryTable {
+    pub fn new(schema: Schema) -> MemoryTable {
+        MemoryTable {
+            schema: Arc::new(schema),
+            ..MemoryTable::default()
+        }
+    }
+
+    fn row(&self, values: &Values) -> Row {
+        Row::new(Arc::clone(&self.schema), Values::clone(values))
+    }
+
+    /// Whether lookups on `column` can use the index.
+    fn is_key(&self, column: &str) -> bool {
+        self.schema.columns.first().is_some_and(|key| {
+            key.name == column
+                && !matches!(key.ty, ColumnType::Enum(_))
+                && key
+                    .generated
+                    .as_ref()
+                    .is_none_or(|g| g.kind == GeneratedKind::Stored)
+        })
+    }
+}
+
+impl Table for MemoryTable {
+    fn schema(&self) -> &Arc<Schema> {
+        &self.schema
+    }
+
+    fn set_schema(&mut self, schema: Schema) {
+        self.schema = Arc::new(schema);
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn append(&mut self, values: Values) {
+        if let Some(key) = values.first() {
+            let positions = self.index.entry(key.hash_key()).or_default();
+            positions.push(self.rows.len());
+        }
+        self.rows.push(values);
+    }
+
+    fn scan(&self) -> Box<dyn Iterator<Item = Row> + '_> {
+        Box::new(self.rows.iter().map(|values| self.row(values)))
+    }
+
+    fn get(&self, position: usize) -> Option<Row> {
+        self.rows.get(position).map(|values| self.row(values))
+    }
+
+    fn lookup(
+        &self,
+        column: &str,
+        value: &LiteralValue,
+    ) -> Option<Box<dyn Iterator<Item = Row> + '_>> {
+        if !self.is_key(column) {
+            return None;
+        }
+        let positions = self
+            .index
+            .get(&value.hash_key())
+            .map_or(&[][..], Vec::as_slice);
+        Some(Box::new(
+            positions
+                .iter()
+                .map(|&position| self.row(&self.rows[position])),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Column, ColumnType};
+    use LiteralValue::{Float, Int, Null};
+
+    #[test]
+    fn append_scan_and_get() {
+        let mut table = MemoryTable::new(Schema {
+            columns: vec![
+                Column::new("a", ColumnType::Int),
+                Column::new("b", ColumnType::Int),
+            ],
+        });
+        assert!(table.is_empty());
+        table.append(Values::from([Int(1), Null]));
+        table.append(Values::from([Int(2), Int(3)]));
+
+        let firsts: Vec<_> = table.scan().map(|row| row.get("a").cloned()).collect();
+        assert_eq!(firsts, vec![Some(Int(1)), Some(Int(2))]);
+
+        let row = table.get(1).unwrap();
+        assert_eq!(row.lookup("b"), Some(Int(3)));
+        assert_eq!(row.get("c"), None);
+        assert!(table.get(2).is_none());
+        assert!(Arc::ptr_eq(row.values(), table.get(1).unwrap().values()));
+    }
+
+    #[test]
+    fn lookups_use_the_first_column() {
+        let mut table = MemoryTable::new(Schema {
+            columns: vec![
+                Column::new("k", ColumnType::Float),
+                Column::new("v", ColumnType::Int),
+            ],
+        });
+        for (k, v) in [(1.0, 1), (2.5, 2), (-0.0, 3), (1.0, 4)] {
+            table.append(Values::from([Float(k), Int(v)]));
+        }
+        let found = |key: LiteralValue| -> Vec<_> {
+            let rows = table.lookup("k", &key).unwrap();
+            rows.map(|row| row.get("v").cloned().unwrap()).collect()
+        };
+        assert_eq!(found(Float(1.0)), vec![Int(1), Int(4)]);
+        assert_eq!(found(Int(1)), vec![Int(1), Int(4)]);
+        assert_eq!(found(Float(0.0)), vec![Int(3)]);
+        assert_eq!(found(Float(7.0)), vec![]);
+        assert!(table.lookup("v", &Int(1)).is_none());
+
+        let labels = ColumnType::Enum(vec!["a".into()]);
+        let mut table = MemoryTable::new(Schema {
+            columns: vec![Column::new("e", labels)],
+        });
+        table.append(Values::from([Int(0)]));
+        assert!(table
+            .lookup("e", &LiteralValue::String("a".into()))
+            .is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rows_serialize_as_maps() {
+        let mut table = MemoryTable::new(Schema {
+            columns: vec![
+                Column::new("a", ColumnType::Int),
+                Column::new("b", ColumnType::Float),
+            ],
+        });
+        table.append(Values::from([Int(1), LiteralValue::Float(f64::NAN)]));
+        assert_eq!(
+            serde_json::to_string(&table.get(0).unwrap()).unwrap(),
+            r#"{"a":{"Int":1},"b":{"Float":"NaN"}}"#
+        );
+    }
+}
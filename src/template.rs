@@ -0,0 +1,136 @@
+//! Templates in statement text, expanded before parsing: `{{name}}` is
+//! replaced with the value given by `--define name=value`, and
+//! `{{env.NAME}}` with the environment variable `NAME`. Spaces inside the
+//! braces are ignored. `\{{` stands for a literal `{{`. The CLI expands
+//! templates only when it is given `--define` or `--templates`.
+//!
+//! The values are pasted in as they are, not quoted: a text value meant as
+//! a string literal must be written inside quotes, as in `'{{region}}'`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::line_column;
+
+/// A template that cannot be expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Expands every `{{...}}` in `text` with `defines` and the environment.
+pub fn expand(text: &str, defines: &BTreeMap<String, String>) -> Result<String, TemplateError> {
+    expand_with(text, defines, |name| std::env::var(name).ok())
+}
+
+/// [`expand`], reading the environment through `env`.
+fn expand_with(
+    text: &str,
+    defines: &BTreeMap<String, String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<String, TemplateError> {
+    let error = |offset: usize, message: String| {
+        let (line, column) = line_column(text, offset);
+        TemplateError {
+            line,
+            column,
+            message,
+        }
+    };
+    let mut out = String::with_capacity(text.len());
+    let mut rest = 0;
+    while let Some(found) = text[rest..].find("{{") {
+        let start = rest + found;
+        if text[..start].ends_with('\\') {
+            out.push_str(&text[rest..start - 1]);
+            out.push_str("{{");
+            rest = start + 2;
+            continue;
+        }
+        out.push_str(&text[rest..start]);
+        let Some(length) = text[start + 2..].find("}}") else {
+            return Err(error(start, "`{{` without `}}`".to_string()));
+        };
+        let name = text[start + 2..start + 2 + length].trim();
+        let value = match name.strip_prefix("env.") {
+            Some(variable) => env(variable).ok_or_else(|| {
+                error(
+                    start,
+                    format!("environment variable `{variable}` is not set"),
+                )
+            })?,
+            None => defines
+                .get(name)
+                .cloned()
+                .ok_or_else(|| error(start, format!("`{name}` is not defined")))?,
+        };
+        out.push_str(&value);
+        rest = start + 2 + length + 2;
+    }
+    out.push_str(&text[rest..]);
+    Ok(out)
+}
+
+/// Reads a `--define` argument, `name=value`.
+pub fn define(argument: &str) -> Option<(String, String)> {
+    let (name, value) = argument.split_once('=')?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| (name.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_defines_and_environment() {
+        let defines = BTreeMap::from([("region".to_string(), "north".to_string())]);
+        let env = |name: &str| (name == "LIMIT").then(|| "10".to_string());
+        assert_eq!(
+            expand_with(
+                "SELECT * FROM sales WHERE region = '{{ region }}' LIMIT {{env.LIMIT}}; \
+                 SELECT '\\{{region}}'",
+                &defines,
+                env
+            ),
+            Ok("SELECT * FROM sales WHERE region = 'north' LIMIT 10; SELECT '{{region}}'".into())
+        );
+        assert_eq!(
+            expand_with("SELECT 1;\nSELECT {{nope}}", &defines, env)
+                .unwrap_err()
+                .to_string(),
+            "line 2, column 8: `nope` is not defined"
+        );
+        assert_eq!(
+            expand_with("SELECT {{env.HOME}}", &defines, env)
+                .unwrap_err()
+                .to_string(),
+            "line 1, column 8: environment variable `HOME` is not set"
+        );
+        assert_eq!(
+            expand_with("SELECT {{region", &defines, env)
+                .unwrap_err()
+                .to_string(),
+            "line 1, column 8: `{{` without `}}`"
+        );
+        assert_eq!(
+            define("region=north=east"),
+            Some(("region".into(), "north=east".into()))
+        );
+        assert_eq!(define("=x"), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Example data shared by the round-trip tests, and generators of
+//! arbitrary data for the property tests.
+
+use std::fmt::Debug;
+use std::net::IpAddr;
+
+use crate::literals::{Inet, LiteralValue, Point};
+
+pub fn example_ints() -> Vec<i64> {
+    vec![0, 1, -1, 42, -1000, 1_000_000_007, i64::MAX, i64::MIN]
+}
+
+/// Ints written with a radix prefix or digit grouping, and their values.
+pub fn example_radix_ints() -> Vec<(&'static str, i64)> {
+    vec![
+        ("0xFF", 255),
+        ("0Xff", 255),
+        ("-0x10", -16),
+        ("0o17", 15),
+        ("0b1010", 10),
+        ("1_000_000", 1_000_000),
+        ("0xdead_beef", 0xdead_beef),
+        ("0b1111_0000", 0b1111_0000),
+        ("0x7fff_ffff_ffff_ffff", i64::MAX),
+        ("007", 7),
+    ]
+}
+
+pub fn example_floats() -> Vec<f64> {
+    vec![
+        0.0,
+        -0.0,
+        1.0,
+        -1.5,
+        0.1,
+        std::f64::consts::PI,
+        1e20,
+        -2.5e-10,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+    ]
+}
+
+pub fn example_strings() -> Vec<String> {
+    [
+        "",
+        "plain",
+        "with space",
+        "quote\"inside",
+        "it's",
+        "back\\slash",
+        "two\nlines",
+        "tab\tand\r\nreturn",
+        "nul\0byte",
+        "\u{7}bell \u{1b}[0m escape \u{7f}\u{85}",
+        "ünïcödé ✓",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Every example above, plus some inet addresses, points and lists, the
+/// booleans and null, as literal values.
+pub fn example_literals() -> Vec<LiteralValue> {
+    let mut values: Vec<LiteralValue> = Vec::new();
+    values.extend(example_ints().into_iter().map(LiteralValue::Int));
+    values.extend(example_floats().into_iter().map(LiteralValue::Float));
+    values.extend(example_strings().into_iter().map(LiteralValue::String));
+    values.extend(
+        ["10.0.0.1", "192.168.0.0/16", "::1", "2001:db8::/32"]
+            .map(|text| LiteralValue::Inet(text.parse().unwrap())),
+    );
+    values.extend(
+        [(0.0, 0.0), (-33.8688, 151.2093), (90.0, -180.0), (1.5, 0.0)]
+            .map(|(lat, lon)| LiteralValue::Point(Point::new(lat, lon).unwrap())),
+    );
+    values.extend([
+        LiteralValue::List(vec![]),
+        LiteralValue::List(vec![
+            LiteralValue::Int(-1),
+            LiteralValue::String("a, b)".to_string()),
+            LiteralValue::Float(f64::NAN),
+            LiteralValue::Point(Point::new(1.5, 0.0).unwrap()),
+        ]),
+        LiteralValue::Bool(true),
+        LiteralValue::Bool(false),
+        LiteralValue::Null,
+    ]);
+    values
+}
+
+/// Characters that mean something to one grammar or another, which
+/// arbitrary text is mostly made of.
+const TRICKY: &[char] = &[
+    '\'', '"', '`', '\\', ';', '-', '/', '*', '#', '$', '?', '(', ')', ',', '.', ' ', '\n', '\t',
+    '\r', '\0', '\u{7f}', '\u{85}', 'é', '✓', 'a', 'Z', '_', '0', '9',
+];
+
+/// What numbers are written with, in any of the literal grammars.
+const NUMERIC: &[char] = &[
+    '0', '1', '7', '9', '.', '_', '-', '+', 'e', 'E', 'x', 'o', 'b', 'f', 'F', 'i', 'n', 'N', 'a',
+    't', 'y', ' ',
+];
+
+/// A seeded source of arbitrary values. Everything it makes is bounded by
+/// `size`, and smaller sizes make simpler values: short text, small ints,
+/// floats with few digits. [`check`] relies on that to shrink a failure.
+pub struct Generator {
+    state: u64,
+    pub size: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64, size: usize) -> Generator {
+        // xorshift must not start from zero.
+        Generator {
+            state: seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1,
+            size,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    pub fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+
+    /// An int of up to `size` bits; from `size` 64 on, any int.
+    pub fn int(&mut self) -> i64 {
+        let bits = self.size.min(64) as u32;
+        let magnitude = self.next_u64().checked_shr(64 - bits).unwrap_or(0);
+        magnitude as i64 * if self.below(2) == 0 { 1 } else { -1 }
+    }
+
+    /// A float with an int's worth of digits, scaled by a power of two;
+    /// from `size` 64 on, also any bit pattern, NaNs included.
+    pub fn float(&mut self) -> f64 {
+        if self.size >= 64 && self.below(4) == 0 {
+            return f64::from_bits(self.next_u64());
+        }
+        let scale = self.below(2 * self.size + 1) as i32 - self.size as i32;
+        self.int() as f64 * 2f64.powi(scale)
+    }
+
+    /// Up to `size` characters, mostly from `alphabet` but sometimes any
+    /// character at all.
+    pub fn text(&mut self, alphabet: &[char]) -> String {
+        (0..self.below(self.size + 1))
+            .map(|_| match self.below(8) {
+                0 => char::from_u32(self.next_u64() as u32 % 0x11_0000).unwrap_or('\u{fffd}'),
+                _ => self.pick(alphabet),
+            })
+            .collect()
+    }
+
+    /// Text made mostly of characters the grammars treat specially.
+    pub fn string(&mut self) -> String {
+        self.text(TRICKY)
+    }
+
+    /// Text that looks more or less like a number.
+    pub fn numeric_text(&mut self) -> String {
+        self.text(NUMERIC)
+    }
+
+    pub fn literal(&mut self) -> LiteralValue {
+        match self.below(9) {
+            0 => LiteralValue::Int(self.int()),
+            1 => LiteralValue::Float(self.float()),
+            2 => LiteralValue::String(self.string()),
+            3 => LiteralValue::Bool(self.below(2) == 0),
+            4 => LiteralValue::Inet(self.inet()),
+            5 => {
+                let lat = self.float().clamp(-90.0, 90.0);
+                let lon = self.float().clamp(-180.0, 180.0);
+                Point::new(lat, lon).map_or(LiteralValue::Null, LiteralValue::Point)
+            }
+            6 => {
+                let items = self.below(4);
+                LiteralValue::List((0..items).map(|_| self.item()).collect())
+            }
+            _ => LiteralValue::Null,
+        }
+    }
+
+    /// A literal a list can hold: neither a list nor NULL.
+    fn item(&mut self) -> LiteralValue {
+        loop {
+            match self.literal() {
+                LiteralValue::List(_) | LiteralValue::Null => {}
+                item => return item,
+            }
+        }
+    }
+
+    fn inet(&mut self) -> Inet {
+        let (address, max_prefix) = if self.below(2) == 0 {
+            (IpAddr::from((self.next_u64() as u32).to_be_bytes()), 32)
+        } else {
+            let high = u128::from(self.next_u64()) << 64;
+            (
+                IpAddr::from((high | u128::from(self.next_u64())).to_be_bytes()),
+                128,
+            )
+        };
+        let prefix = (self.below(2) == 0).then(|| self.below(max_prefix + 1));
+        let text = match prefix {
+            Some(prefix) => format!("{address}/{prefix}"),
+            None => address.to_string(),
+        };
+        text.parse().unwrap()
+    }
+}
+
+/// Checks `property` against `cases` generated values, at sizes growing
+/// from 0 so that simple cases come first. On failure it looks for a
+/// smaller one by generating again from the failing seed at each smaller
+/// size, then panics with the smallest failing value and its message.
+pub fn check<T: Debug>(
+    cases: u64,
+    generate: impl Fn(&mut Generator) -> T,
+    property: impl Fn(&T) -> Result<(), String>,
+) {
+    const MAX_SIZE: usize = 80;
+    for seed in 0..cases {
+        let size = (seed as usize * (MAX_SIZE + 1) / cases as usize).min(MAX_SIZE);
+        let value = generate(&mut Generator::new(seed, size));
+        let Err(message) = property(&value) else {
+            continue;
+        };
+        let (value, message) = (0..size)
+            .find_map(|size| {
+                let value = generate(&mut Generator::new(seed, size));
+                property(&value).err().map(|message| (value, message))
+            })
+            .unwrap_or((value, message));
+        panic!("property failed for {value:?} (seed {seed}): {message}");
+    }
+}
@@ -0,0 +1,245 @@
+//! Session variables: values set with `SET name = <literal>`, read back
+//! with `SHOW name` or `SHOW ALL` and in expressions as `@name`. They live
+//! in the catalog, so they last as long as it does: for the rest of a CLI
+//! run. A variable set to NULL holds NULL; one never set cannot be read.
+//!
+//! A few names are settings, which change how statements run and take
+//! only certain values; [`SETTINGS`] lists them, and `SHOW SETTINGS`
+//! shows each with the value in effect. A setting can always be read, as
+//! its default or NULL while unset, and setting it to NULL unsets it:
+//!
+//! - `deterministic_floats`: `'on'` or `'off'`, whether SUM and AVG over
+//!   floats keep the exact sum and round it once, so that they give the
+//!   same result whatever order the rows come in.
+//! - `negative_zero`: `'keep'` or `'fold'`, whether a float `-0.0` is
+//!   stored as it is or as `0.0`; see [`LiteralValue::normalize`].
+//! - `nulls`: `'first'` or `'last'`, where ORDER BY puts NULLs, whichever
+//!   way it sorts. Unset, NULL sorts before every other value, so first
+//!   in ascending order and last in descending.
+//! - `output`: `'text'` or `'json'`, how the CLI prints what statements
+//!   did. `--output` sets it before the first statement, as `--set` does
+//!   for any variable.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::literals::{LiteralValue, NegativeZero};
+
+/// A variable that changes how statements run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting {
+    pub name: &'static str,
+    /// The strings it may be set to.
+    pub values: &'static [&'static str],
+    /// What it is while unset, if that is one of `values`.
+    pub default: Option<&'static str>,
+    pub description: &'static str,
+}
+
+/// Every setting, by name.
+pub const SETTINGS: [Setting; 4] = [
+    Setting {
+        name: "deterministic_floats",
+        values: &["on", "off"],
+        default: Some("off"),
+        description: "whether float SUM and AVG round once, whatever the order of rows",
+    },
+    Setting {
+        name: "negative_zero",
+        values: &["keep", "fold"],
+        default: Some("keep"),
+        description: "whether a float -0.0 is stored as it is or as 0.0",
+    },
+    Setting {
+        name: "nulls",
+        values: &["first", "last"],
+        default: None,
+        description: "where ORDER BY puts NULLs; unset, they sort before other values",
+    },
+    Setting {
+        name: "output",
+        values: &["text", "json"],
+        default: Some("text"),
+        description: "how the CLI prints what statements did",
+    },
+];
+
+/// The setting called `name`, if there is one.
+pub fn setting(name: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.name == name)
+}
+
+/// A value a setting does not take.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSetting {
+    pub name: String,
+    pub allowed: &'static [&'static str],
+}
+
+impl fmt::Display for InvalidSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let allowed: Vec<String> = self.allowed.iter().map(|v| format!("'{v}'")).collect();
+        write!(
+            f,
+            "setting `{}` must be one of {}",
+            self.name,
+            allowed.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for InvalidSetting {}
+
+/// Where ORDER BY puts NULLs; see the `nulls` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullOrder {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Variables {
+    values: BTreeMap<String, LiteralValue>,
+}
+
+impl Variables {
+    pub fn get(&self, name: &str) -> Option<&LiteralValue> {
+        self.values.get(name)
+    }
+
+    /// What `@name` reads: the variable's value, or for a setting that is
+    /// not set, its default or NULL. `None` for any other name not set.
+    pub fn value(&self, name: &str) -> Option<LiteralValue> {
+        if let Some(value) = self.values.get(name) {
+            return Some(value.clone());
+        }
+        let default = setting(name)?.default;
+        Some(default.map_or(LiteralValue::Null, |value| {
+            LiteralValue::String(value.to_string())
+        }))
+    }
+
+    /// Sets a variable, to NULL as to any other value, or unsets a setting
+    /// given NULL. A setting must be given one of the strings it takes.
+    pub fn set(&mut self, name: &str, value: LiteralValue) -> Result<(), InvalidSetting> {
+        if let Some(setting) = setting(name) {
+            if value == LiteralValue::Null {
+                self.values.remove(name);
+                return Ok(());
+            }
+            if !matches!(&value, LiteralValue::String(s) if setting.values.contains(&s.as_str())) {
+                return Err(InvalidSetting {
+                    name: name.to_string(),
+                    allowed: setting.values,
+                });
+            }
+        }
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Every variable that is set, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LiteralValue)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn null_order(&self) -> Option<NullOrder> {
+        match self.setting("nulls")? {
+            "first" => Some(NullOrder::First),
+            _ => Some(NullOrder::Last),
+        }
+    }
+
+    /// Whether float sums are exact until rounded once; see the
+    /// `deterministic_floats` setting.
+    pub fn deterministic_floats(&self) -> bool {
+        self.setting("deterministic_floats") == Some("on")
+    }
+
+    /// How stored rows write a float `-0.0`; see the `negative_zero`
+    /// setting.
+    pub fn negative_zero(&self) -> NegativeZero {
+        match self.setting("negative_zero") {
+            Some("fold") => NegativeZero::Fold,
+            _ => NegativeZero::Keep,
+        }
+    }
+
+    /// Each setting with the value in effect: the one set, or else its
+    /// default.
+    pub fn settings(&self) -> impl Iterator<Item = (&'static Setting, Option<&str>)> + '_ {
+        SETTINGS
+            .iter()
+            .map(|setting| (setting, self.setting(setting.name).or(setting.default)))
+    }
+
+    /// Whether the `output` setting asks for JSON.
+    pub fn json_output(&self) -> bool {
+        self.setting("output") == Some("json")
+    }
+
+    /// The value of a setting, which `set` has checked is a string.
+    fn setting(&self, name: &str) -> Option<&str> {
+        match self.values.get(name)? {
+            LiteralValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LiteralValue::{Int, Null, String as Str};
+
+    #[test]
+    fn sets_unsets_and_checks_settings() {
+        let mut variables = Variables::default();
+        variables.set("limit", Int(3)).unwrap();
+        variables.set("nulls", Str("last".to_string())).unwrap();
+        assert_eq!(variables.get("limit"), Some(&Int(3)));
+        assert_eq!(variables.null_order(), Some(NullOrder::Last));
+        assert!(!variables.json_output());
+
+        let error = variables.set("output", Str("xml".to_string())).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "setting `output` must be one of 'text', 'json'"
+        );
+        assert!(variables.set("nulls", Int(1)).is_err());
+
+        variables.set("nulls", Null).unwrap();
+        assert_eq!(variables.null_order(), None);
+        assert_eq!(variables.value("nulls"), Some(Null));
+        variables.set("limit", Null).unwrap();
+        assert_eq!(variables.value("limit"), Some(Null));
+        assert_eq!(variables.value("offset"), None);
+        let names: Vec<&str> = variables.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["limit"]);
+
+        variables.set("output", Str("json".to_string())).unwrap();
+        let effective: Vec<(&str, Option<&str>)> = variables
+            .settings()
+            .map(|(setting, value)| (setting.name, value))
+            .collect();
+        assert_eq!(
+            effective,
+            vec![
+                ("deterministic_floats", Some("off")),
+                ("negative_zero", Some("keep")),
+                ("nulls", None),
+                ("output", Some("json"))
+            ]
+        );
+        variables.set("output", Null).unwrap();
+        assert_eq!(variables.settings().nth(3).unwrap().1, Some("text"));
+        assert_eq!(variables.value("output"), Some(Str("text".to_string())));
+        assert_eq!(variables.negative_zero(), NegativeZero::Keep);
+        variables
+            .set("negative_zero", Str("fold".to_string()))
+            .unwrap();
+        assert_eq!(variables.negative_zero(), NegativeZero::Fold);
+    }
+}
@@ -0,0 +1,3 @@
+SELECT ((((((((((((((((((((((((((((((((1))))))))))))))))))))))))))))))));
+SELECT - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - 1;
+SELECT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT true;
@@ -0,0 +1 @@
+SELECT 9223372036854775808
\ No newline at end of file
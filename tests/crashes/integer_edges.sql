@@ -0,0 +1,4 @@
+SELECT 9223372036854775807 + 1;
+SELECT -(-9223372036854775807 - 1);
+SELECT (-9223372036854775807 - 1) / -1;
+SELECT 1 % 0;
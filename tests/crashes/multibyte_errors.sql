@@ -0,0 +1,3 @@
+SELECT 'é' + ;
+SELECT 'ü' || 'ß' + 1;
+SELECT 'ü' LIKE '_';
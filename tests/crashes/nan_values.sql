@@ -0,0 +1,4 @@
+SELECT NaN, -inf;
+CREATE TABLE t (x FLOAT);
+INSERT INTO t VALUES (NaN), (-0.0), (0.0), (NaN);
+SELECT x, count(*) FROM t GROUP BY x ORDER BY x;
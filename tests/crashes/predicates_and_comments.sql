@@ -0,0 +1,3 @@
+SELECT 'a' LIKE '%%_%';
+SELECT 1 IN ();
+SELECT 1 /* a */ + /**/ 2 -- end
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT é
\ No newline at end of file